@@ -0,0 +1,171 @@
+use std::ops::Range;
+
+use crate::ir::{Function, GeneratorStatement, Statement};
+
+pub type BlockId = usize;
+
+#[derive(Debug)]
+pub struct BasicBlock {
+    /// Indices into the function body.
+    pub instructions: Range<usize>,
+    /// For conditional jumps the jump target comes before the fall through.
+    pub successors: Vec<BlockId>,
+    pub predecessors: Vec<BlockId>,
+    /// Where an exception thrown inside the block goes. It is also the last
+    /// successor, so values reaching the handler get phis like any other.
+    pub handler: Option<BlockId>,
+}
+
+#[derive(Debug)]
+pub struct ControlFlowGraph {
+    pub blocks: Vec<BasicBlock>,
+    block_of: Vec<BlockId>,
+}
+
+impl ControlFlowGraph {
+    pub const ENTRY: BlockId = 0;
+
+    pub fn new(function: &Function) -> Self {
+        let body = &function.body;
+
+        let mut leaders = vec![false; body.len()];
+        if let Some(first) = leaders.first_mut() {
+            *first = true;
+        }
+
+        for (index, instruction) in body.iter().enumerate() {
+            let targets = Self::targets(function, index);
+            let ends_block = !targets.is_empty() || !instruction.statement.falls_through();
+
+            for target in targets {
+                leaders[target] = true;
+            }
+            if ends_block && index + 1 < body.len() {
+                leaders[index + 1] = true;
+            }
+        }
+
+        // Protected ranges start and end on block boundaries.
+        for handler in &function.exception_handlers {
+            let boundaries = [handler.start, handler.end, handler.target];
+            for index in boundaries
+                .iter()
+                .filter_map(|offset| function.instruction_at(*offset))
+            {
+                leaders[index] = true;
+            }
+        }
+
+        let mut block_of = Vec::with_capacity(body.len());
+        let mut blocks: Vec<BasicBlock> = Vec::new();
+
+        for (index, is_leader) in leaders.iter().enumerate() {
+            if *is_leader {
+                blocks.push(BasicBlock {
+                    instructions: index..index,
+                    successors: Vec::new(),
+                    predecessors: Vec::new(),
+                    handler: None,
+                });
+            }
+            let block = blocks.len() - 1;
+            blocks[block].instructions.end = index + 1;
+            block_of.push(block);
+        }
+
+        for block in 0..blocks.len() {
+            let last = blocks[block].instructions.end - 1;
+
+            let mut successors: Vec<BlockId> = Self::targets(function, last)
+                .into_iter()
+                .map(|target| block_of[target])
+                .collect();
+
+            // A block that yields is resumed at its SaveGenerator target.
+            for index in blocks[block].instructions.clone() {
+                if let Statement::Generator(GeneratorStatement::Save { offset }) =
+                    body[index].statement
+                {
+                    successors.extend(function.jump_target(index, offset).map(|t| block_of[t]));
+                }
+            }
+
+            if body[last].statement.falls_through() && last + 1 < body.len() {
+                successors.push(block_of[last + 1]);
+            }
+
+            let start = body[blocks[block].instructions.start].offset;
+            let handler = function
+                .exception_handlers
+                .iter()
+                .find(|handler| handler.start <= start && start < handler.end)
+                .and_then(|handler| function.instruction_at(handler.target))
+                .map(|target| block_of[target]);
+            successors.extend(handler);
+            blocks[block].handler = handler;
+
+            let mut unique = Vec::with_capacity(successors.len());
+            for successor in successors {
+                if !unique.contains(&successor) {
+                    unique.push(successor);
+                }
+            }
+
+            for successor in &unique {
+                blocks[*successor].predecessors.push(block);
+            }
+            blocks[block].successors = unique;
+        }
+
+        Self { blocks, block_of }
+    }
+
+    /// Explicit branch targets of the instruction at `index`, as body indices.
+    fn targets(function: &Function, index: usize) -> Vec<usize> {
+        match &function.body[index].statement {
+            Statement::Jump(jump) => function
+                .jump_target(index, jump.offset)
+                .into_iter()
+                .collect(),
+            Statement::Switch(switch) => switch
+                .offsets
+                .iter()
+                .chain(std::iter::once(&switch.default))
+                .filter_map(|offset| function.jump_target(index, *offset))
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    pub fn block_of(&self, instruction: usize) -> BlockId {
+        self.block_of[instruction]
+    }
+
+    /// Blocks reachable from the entry, in reverse postorder.
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        if self.blocks.is_empty() {
+            return vec![];
+        }
+
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = Vec::with_capacity(self.blocks.len());
+        let mut stack = vec![(Self::ENTRY, 0)];
+        visited[Self::ENTRY] = true;
+
+        while let Some((block, next)) = stack.pop() {
+            match self.blocks[block].successors.get(next) {
+                Some(&successor) => {
+                    stack.push((block, next + 1));
+                    if !visited[successor] {
+                        visited[successor] = true;
+                        stack.push((successor, 0));
+                    }
+                }
+                None => postorder.push(block),
+            }
+        }
+
+        postorder.reverse();
+        postorder
+    }
+}
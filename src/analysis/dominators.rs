@@ -0,0 +1,177 @@
+use super::{BlockId, ControlFlowGraph};
+
+/// Dominator tree, computed with the Cooper, Harvey and Kennedy algorithm.
+#[derive(Debug)]
+pub struct Dominators {
+    immediate: Vec<Option<BlockId>>,
+    children: Vec<Vec<BlockId>>,
+    /// Reverse postorder position of each block, `None` if unreachable.
+    order: Vec<Option<usize>>,
+}
+
+impl Dominators {
+    pub fn new(cfg: &ControlFlowGraph) -> Self {
+        let predecessors: Vec<&[BlockId]> = cfg
+            .blocks
+            .iter()
+            .map(|block| block.predecessors.as_slice())
+            .collect();
+        Self::from_edges(&cfg.reverse_postorder(), &predecessors)
+    }
+
+    /// The dominator tree of any graph, given its blocks reachable from the
+    /// entry in reverse postorder and the predecessors of every block.
+    pub fn from_edges(reverse_postorder: &[BlockId], predecessors: &[&[BlockId]]) -> Self {
+        let count = predecessors.len();
+        let mut order = vec![None; count];
+        for (position, block) in reverse_postorder.iter().enumerate() {
+            order[*block] = Some(position);
+        }
+
+        let mut immediate: Vec<Option<BlockId>> = vec![None; count];
+        if let Some(&entry) = reverse_postorder.first() {
+            immediate[entry] = Some(entry);
+        }
+
+        let intersect = |immediate: &[Option<BlockId>], mut left: BlockId, mut right: BlockId| {
+            while left != right {
+                while order[left] > order[right] {
+                    left = immediate[left].unwrap();
+                }
+                while order[right] > order[left] {
+                    right = immediate[right].unwrap();
+                }
+            }
+            left
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for &block in reverse_postorder.iter().skip(1) {
+                let mut new_immediate = None;
+                for &predecessor in predecessors[block] {
+                    if immediate[predecessor].is_none() {
+                        continue;
+                    }
+                    new_immediate = Some(match new_immediate {
+                        None => predecessor,
+                        Some(current) => intersect(&immediate, predecessor, current),
+                    });
+                }
+
+                if new_immediate.is_some() && immediate[block] != new_immediate {
+                    immediate[block] = new_immediate;
+                    changed = true;
+                }
+            }
+        }
+
+        if let Some(&entry) = reverse_postorder.first() {
+            immediate[entry] = None;
+        }
+
+        let mut children = vec![Vec::new(); count];
+        for &block in reverse_postorder {
+            if let Some(parent) = immediate[block] {
+                children[parent].push(block);
+            }
+        }
+
+        Self {
+            immediate,
+            children,
+            order,
+        }
+    }
+
+    pub fn immediate_dominator(&self, block: BlockId) -> Option<BlockId> {
+        self.immediate[block]
+    }
+
+    pub fn children(&self, block: BlockId) -> &[BlockId] {
+        &self.children[block]
+    }
+
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.order[block].is_some()
+    }
+
+    pub fn dominates(&self, dominator: BlockId, mut block: BlockId) -> bool {
+        if !self.is_reachable(block) {
+            return false;
+        }
+        loop {
+            if block == dominator {
+                return true;
+            }
+            match self.immediate[block] {
+                Some(parent) => block = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// Dominance frontier of every block.
+    pub fn frontiers(&self, cfg: &ControlFlowGraph) -> Vec<Vec<BlockId>> {
+        let mut frontiers = vec![Vec::new(); cfg.blocks.len()];
+
+        for (block, basic_block) in cfg.blocks.iter().enumerate() {
+            if basic_block.predecessors.len() < 2 || !self.is_reachable(block) {
+                continue;
+            }
+
+            for &predecessor in &basic_block.predecessors {
+                if !self.is_reachable(predecessor) {
+                    continue;
+                }
+
+                let mut runner = predecessor;
+                while Some(runner) != self.immediate[block] {
+                    if !frontiers[runner].contains(&block) {
+                        frontiers[runner].push(block);
+                    }
+                    match self.immediate[runner] {
+                        Some(parent) => runner = parent,
+                        None => break,
+                    }
+                }
+            }
+        }
+
+        frontiers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn irreducible_loop_is_dominated_by_its_common_entry() {
+        // 0 branches into both 1 and 2, which jump to each other, so
+        // neither header dominates the other.
+        let predecessors: [&[BlockId]; 4] = [&[], &[0, 2], &[0, 1], &[1, 2]];
+        let dominators = Dominators::from_edges(&[0, 1, 2, 3], &predecessors);
+
+        for block in 1..4 {
+            assert_eq!(dominators.immediate_dominator(block), Some(0));
+        }
+        assert_eq!(dominators.immediate_dominator(0), None);
+        assert_eq!(dominators.children(0), &[1, 2, 3]);
+        assert!(!dominators.dominates(1, 2));
+        assert!(!dominators.dominates(2, 1));
+        assert!(dominators.dominates(0, 3));
+    }
+
+    #[test]
+    fn unreachable_blocks_have_no_dominator() {
+        let predecessors: [&[BlockId]; 3] = [&[], &[0], &[2]];
+        let dominators = Dominators::from_edges(&[0, 1], &predecessors);
+
+        assert!(!dominators.is_reachable(2));
+        assert!(!dominators.dominates(0, 2));
+        assert_eq!(dominators.immediate_dominator(2), None);
+    }
+}
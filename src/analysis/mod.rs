@@ -0,0 +1,7 @@
+mod cfg;
+mod dominators;
+mod ssa;
+
+pub use cfg::*;
+pub use dominators::*;
+pub use ssa::*;
@@ -0,0 +1,487 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::ir::{Function, Register};
+
+use super::{BlockId, ControlFlowGraph, Dominators};
+
+pub type ValueId = usize;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Definition {
+    /// Read before any write, so whatever the register held on entry.
+    Entry,
+    Instruction(usize),
+    Phi(BlockId),
+}
+
+#[derive(Debug)]
+pub struct Value {
+    pub register: u32,
+    pub definition: Definition,
+}
+
+#[derive(Debug, Clone)]
+pub struct Phi {
+    pub value: ValueId,
+    /// One argument per reachable predecessor of the block, except that a
+    /// handler takes one for every value a register holds in a block it
+    /// protects.
+    pub arguments: Vec<(BlockId, ValueId)>,
+}
+
+/// Static single assignment form of a function. The instructions are left
+/// untouched; instead every register read and write is mapped to a value.
+#[derive(Debug)]
+pub struct SsaForm {
+    pub values: Vec<Value>,
+    pub phis: Vec<Vec<Phi>>,
+    uses: HashMap<(usize, u32), ValueId>,
+    defs: HashMap<(usize, u32), ValueId>,
+    entry_values: HashMap<u32, ValueId>,
+}
+
+impl SsaForm {
+    pub fn new(function: &Function, cfg: &ControlFlowGraph, dominators: &Dominators) -> Self {
+        let mut ssa = Self {
+            values: Vec::new(),
+            phis: (0..cfg.blocks.len()).map(|_| Vec::new()).collect(),
+            uses: HashMap::new(),
+            defs: HashMap::new(),
+            entry_values: HashMap::new(),
+        };
+
+        if cfg.blocks.is_empty() {
+            return ssa;
+        }
+
+        ssa.insert_phis(function, cfg, dominators);
+        ssa.rename(function, cfg, dominators);
+        ssa
+    }
+
+    /// Pruned phi placement: a phi is only added where the register is live.
+    fn insert_phis(
+        &mut self,
+        function: &Function,
+        cfg: &ControlFlowGraph,
+        dominators: &Dominators,
+    ) {
+        let live_in = Self::live_in(function, cfg);
+        let frontiers = dominators.frontiers(cfg);
+
+        let mut def_blocks: HashMap<u32, Vec<BlockId>> = HashMap::new();
+        for (block, basic_block) in cfg.blocks.iter().enumerate() {
+            if !dominators.is_reachable(block) {
+                continue;
+            }
+            for index in basic_block.instructions.clone() {
+                for register in function.body[index].statement.defs() {
+                    let blocks = def_blocks.entry(register.index()).or_default();
+                    if blocks.last() != Some(&block) {
+                        blocks.push(block);
+                    }
+                }
+            }
+        }
+
+        let mut registers: Vec<_> = def_blocks.keys().copied().collect();
+        registers.sort_unstable();
+
+        for register in registers {
+            let mut has_phi = HashSet::new();
+            let mut worklist = def_blocks[&register].clone();
+
+            while let Some(block) = worklist.pop() {
+                // The handler sees the register's values from inside the
+                // block, so it needs a phi even where the block dominates it.
+                let handler = cfg.blocks[block].handler;
+                for &frontier in frontiers[block].iter().chain(handler.iter()) {
+                    if has_phi.contains(&frontier) || !live_in[frontier].contains(&register) {
+                        continue;
+                    }
+                    has_phi.insert(frontier);
+
+                    let value = self.new_value(register, Definition::Phi(frontier));
+                    self.phis[frontier].push(Phi {
+                        value,
+                        arguments: Vec::new(),
+                    });
+                    worklist.push(frontier);
+                }
+            }
+        }
+    }
+
+    fn live_in(function: &Function, cfg: &ControlFlowGraph) -> Vec<HashSet<u32>> {
+        let mut upward_exposed = vec![HashSet::new(); cfg.blocks.len()];
+        let mut killed = vec![HashSet::new(); cfg.blocks.len()];
+
+        for (block, basic_block) in cfg.blocks.iter().enumerate() {
+            for index in basic_block.instructions.clone() {
+                let statement = &function.body[index].statement;
+                for register in statement.uses() {
+                    if !killed[block].contains(&register.index()) {
+                        upward_exposed[block].insert(register.index());
+                    }
+                }
+                for register in statement.defs() {
+                    killed[block].insert(register.index());
+                }
+            }
+        }
+
+        let mut live_in = upward_exposed.clone();
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for block in (0..cfg.blocks.len()).rev() {
+                let mut live = upward_exposed[block].clone();
+                for &successor in &cfg.blocks[block].successors {
+                    for register in &live_in[successor] {
+                        if !killed[block].contains(register) {
+                            live.insert(*register);
+                        }
+                    }
+                }
+
+                if live.len() != live_in[block].len() {
+                    live_in[block] = live;
+                    changed = true;
+                }
+            }
+        }
+
+        live_in
+    }
+
+    fn rename(&mut self, function: &Function, cfg: &ControlFlowGraph, dominators: &Dominators) {
+        let mut stacks: HashMap<u32, Vec<ValueId>> = HashMap::new();
+
+        // The dominator tree is walked iteratively, `false` marks leaving a block.
+        let mut walk = vec![(ControlFlowGraph::ENTRY, true)];
+
+        while let Some((block, entering)) = walk.pop() {
+            if !entering {
+                for register in self.block_defs(function, cfg, block) {
+                    if let Some(stack) = stacks.get_mut(&register) {
+                        stack.pop();
+                    }
+                }
+                continue;
+            }
+
+            for phi in &self.phis[block] {
+                let register = self.values[phi.value].register;
+                stacks.entry(register).or_default().push(phi.value);
+            }
+
+            // Any instruction in the block can throw, so the handler's phis
+            // take every value the register holds in it, from the one on
+            // entry to the last one written.
+            let handler = cfg.blocks[block].handler;
+            let handler_registers: Vec<u32> = handler
+                .map(|handler| {
+                    self.phis[handler]
+                        .iter()
+                        .map(|phi| self.values[phi.value].register)
+                        .collect()
+                })
+                .unwrap_or_default();
+            let mut thrown: Vec<Vec<ValueId>> = handler_registers
+                .iter()
+                .map(|register| vec![self.current(&stacks, *register)])
+                .collect();
+
+            for index in cfg.blocks[block].instructions.clone() {
+                let statement = &function.body[index].statement;
+
+                for register in statement.uses() {
+                    let value = self.current(&stacks, register.index());
+                    self.uses.insert((index, register.index()), value);
+                }
+                for register in statement.defs() {
+                    let value = self.new_value(register.index(), Definition::Instruction(index));
+                    stacks.entry(register.index()).or_default().push(value);
+                    self.defs.insert((index, register.index()), value);
+                    for (position, other) in handler_registers.iter().enumerate() {
+                        if *other == register.index() && !thrown[position].contains(&value) {
+                            thrown[position].push(value);
+                        }
+                    }
+                }
+            }
+
+            for &successor in &cfg.blocks[block].successors {
+                if Some(successor) == handler {
+                    for (position, values) in thrown.iter().enumerate() {
+                        let arguments = &mut self.phis[successor][position].arguments;
+                        arguments.extend(values.iter().map(|value| (block, *value)));
+                    }
+                    continue;
+                }
+                for position in 0..self.phis[successor].len() {
+                    let register = self.values[self.phis[successor][position].value].register;
+                    let value = self.current(&stacks, register);
+                    self.phis[successor][position]
+                        .arguments
+                        .push((block, value));
+                }
+            }
+
+            walk.push((block, false));
+            for &child in dominators.children(block).iter().rev() {
+                walk.push((child, true));
+            }
+        }
+    }
+
+    /// Every register pushed while visiting `block`, phis included.
+    fn block_defs(&self, function: &Function, cfg: &ControlFlowGraph, block: BlockId) -> Vec<u32> {
+        let phi_registers = self.phis[block]
+            .iter()
+            .map(|phi| self.values[phi.value].register);
+
+        let instruction_registers = cfg.blocks[block]
+            .instructions
+            .clone()
+            .flat_map(|index| function.body[index].statement.defs())
+            .map(|register| register.index());
+
+        phi_registers.chain(instruction_registers).collect()
+    }
+
+    fn current(&mut self, stacks: &HashMap<u32, Vec<ValueId>>, register: u32) -> ValueId {
+        match stacks.get(&register).and_then(|stack| stack.last()) {
+            Some(value) => *value,
+            None => self.entry_value(register),
+        }
+    }
+
+    fn entry_value(&mut self, register: u32) -> ValueId {
+        if let Some(value) = self.entry_values.get(&register) {
+            return *value;
+        }
+        let value = self.new_value(register, Definition::Entry);
+        self.entry_values.insert(register, value);
+        value
+    }
+
+    fn new_value(&mut self, register: u32, definition: Definition) -> ValueId {
+        self.values.push(Value {
+            register,
+            definition,
+        });
+        self.values.len() - 1
+    }
+
+    /// The value `register` holds when read by the instruction at `index`.
+    pub fn use_of(&self, index: usize, register: Register) -> Option<ValueId> {
+        self.uses.get(&(index, register.index())).copied()
+    }
+
+    /// The value the instruction at `index` writes to `register`.
+    pub fn def_of(&self, index: usize, register: Register) -> Option<ValueId> {
+        self.defs.get(&(index, register.index())).copied()
+    }
+
+    /// Leaves SSA form, see `Variables::new`.
+    pub fn variables(&self) -> Variables {
+        Variables::new(
+            self.values.iter().map(|value| value.register).collect(),
+            self.phis.iter().flatten(),
+        )
+    }
+}
+
+pub type VariableId = usize;
+
+/// The result of leaving SSA form: every value belongs to one variable.
+#[derive(Debug)]
+pub struct Variables {
+    variable_of: Vec<VariableId>,
+    registers: Vec<u32>,
+}
+
+impl Variables {
+    /// Coalesces every phi with its arguments. `registers` holds the register of
+    /// each value.
+    ///
+    /// This is only sound in conventional SSA form, where values joined by a
+    /// phi never have overlapping lifetimes and can share one variable without
+    /// copies. The form is built that way from register code, and the passes
+    /// that forward values restore it with `insert_phi_copies`. Values of the
+    /// same register that no phi joins end up as separate variables.
+    pub fn new<'a, I>(value_registers: Vec<u32>, phis: I) -> Self
+    where
+        I: Iterator<Item = &'a Phi>,
+    {
+        let mut parents: Vec<ValueId> = (0..value_registers.len()).collect();
+
+        fn find(parents: &mut [ValueId], mut value: ValueId) -> ValueId {
+            while parents[value] != value {
+                parents[value] = parents[parents[value]];
+                value = parents[value];
+            }
+            value
+        }
+
+        for phi in phis {
+            for (_, argument) in &phi.arguments {
+                let left = find(&mut parents, phi.value);
+                let right = find(&mut parents, *argument);
+                if left != right {
+                    parents[right] = left;
+                }
+            }
+        }
+
+        let mut variable_of = vec![0; value_registers.len()];
+        let mut registers = Vec::new();
+        let mut numbering = HashMap::new();
+
+        for (value, variable) in variable_of.iter_mut().enumerate() {
+            let root = find(&mut parents, value);
+            *variable = *numbering.entry(root).or_insert_with(|| {
+                registers.push(value_registers[root]);
+                registers.len() - 1
+            });
+        }
+
+        Variables {
+            variable_of,
+            registers,
+        }
+    }
+
+    pub fn of(&self, value: ValueId) -> VariableId {
+        self.variable_of[value]
+    }
+
+    /// The register a variable was carved out of.
+    pub fn register(&self, variable: VariableId) -> u32 {
+        self.registers[variable]
+    }
+
+    pub fn len(&self) -> usize {
+        self.registers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.registers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parsers::program_parser, testing};
+
+    #[test]
+    fn handler_takes_every_value_of_the_protected_block() {
+        let bytes = testing::build(&[testing::function(
+            r#"
+            LoadConstUInt8 r0, 1
+            start:
+            GetGlobalObject r1
+            GetById r2, r1, 1, "f"
+            Call1 r3, r2, r1
+            LoadConstUInt8 r0, 2
+            Call1 r3, r2, r1
+            end:
+            Ret r0
+            handler:
+            Catch r4
+            Ret r0
+            "#,
+        )
+        .handler("start", "end", "handler")]);
+        let program = program_parser(&bytes).unwrap();
+        let function = &program.functions[0];
+        let cfg = ControlFlowGraph::new(function);
+        let dominators = Dominators::new(&cfg);
+        let ssa = SsaForm::new(function, &cfg, &dominators);
+
+        let handler = cfg.block_of(function.body.len() - 2);
+        let protected = cfg.block_of(1);
+        assert!(dominators.dominates(protected, handler));
+
+        let phi = match ssa.phis[handler].as_slice() {
+            [phi] => phi,
+            phis => panic!("expected one phi, found {:?}", phis),
+        };
+        assert_eq!(ssa.values[phi.value].register, 0);
+        let definitions: Vec<Definition> = phi
+            .arguments
+            .iter()
+            .map(|(block, value)| {
+                assert_eq!(*block, protected);
+                ssa.values[*value].definition
+            })
+            .collect();
+        assert_eq!(
+            definitions,
+            [Definition::Instruction(0), Definition::Instruction(4)]
+        );
+    }
+
+    #[test]
+    fn loop_phi_takes_one_value_per_predecessor() {
+        let bytes = testing::build(&[testing::function(
+            r#"
+            LoadConstZero r0
+            LoadConstUInt8 r1, 10
+            loop:
+            LoadConstUInt8 r2, 1
+            Add r0, r0, r2
+            JLess @loop, r0, r1
+            Ret r0
+            "#,
+        )]);
+        let program = program_parser(&bytes).unwrap();
+        let function = &program.functions[0];
+        let cfg = ControlFlowGraph::new(function);
+        let dominators = Dominators::new(&cfg);
+        let ssa = SsaForm::new(function, &cfg, &dominators);
+
+        let header = cfg.block_of(2);
+        let phis: Vec<&Phi> = ssa.phis[header].iter().collect();
+        assert_eq!(phis.len(), 1, "only r0 changes in the loop: {:?}", phis);
+        let definitions: Vec<Definition> = phis[0]
+            .arguments
+            .iter()
+            .map(|(_, value)| ssa.values[*value].definition)
+            .collect();
+        assert_eq!(
+            definitions,
+            [Definition::Instruction(0), Definition::Instruction(3)]
+        );
+        assert_eq!(ssa.use_of(3, Register::Byte(0)), Some(phis[0].value));
+    }
+
+    #[test]
+    fn phis_coalesce_with_their_arguments() {
+        let arguments = |values: &[ValueId]| values.iter().map(|value| (0, *value)).collect();
+        let phis = [
+            Phi {
+                value: 2,
+                arguments: arguments(&[0, 1]),
+            },
+            Phi {
+                value: 5,
+                arguments: arguments(&[2, 4]),
+            },
+        ];
+        let variables = Variables::new(vec![0, 0, 0, 0, 0, 0, 1], phis.iter());
+
+        let first = variables.of(0);
+        for value in [1, 2, 4, 5] {
+            assert_eq!(variables.of(value), first);
+        }
+        // Same register, but nothing joins it to the others.
+        assert_ne!(variables.of(3), first);
+        assert_ne!(variables.of(6), first);
+        assert_eq!(variables.len(), 3);
+        assert_eq!(variables.register(variables.of(6)), 1);
+    }
+}
@@ -0,0 +1,491 @@
+use std::cmp::Ordering;
+
+use crate::{
+    bytecode_file_format::BytecodeFile,
+    ir::{BinaryOperator, Boolean, Conversion, Literal, Number, StringIndex, UnaryOperator},
+};
+
+/// A primitive JavaScript value. Strings are kept in UTF-16 code units, the
+/// way the engine compares and concatenates them.
+#[derive(Debug, Clone)]
+pub enum Constant {
+    Undefined,
+    Null,
+    Boolean(bool),
+    Number(f64),
+    String(Vec<u16>),
+}
+
+impl Constant {
+    /// `None` for a regular expression, which is an object.
+    pub fn from_literal(literal: &Literal, bytecode: &BytecodeFile) -> Option<Self> {
+        Some(match literal {
+            Literal::Undefined => Self::Undefined,
+            Literal::Null => Self::Null,
+            Literal::Boolean(boolean) => Self::Boolean(matches!(boolean, Boolean::True)),
+            Literal::Number(Number::UInt(number)) => Self::Number(f64::from(*number)),
+            Literal::Number(Number::Int(number)) => Self::Number(f64::from(*number)),
+            Literal::Number(Number::Double(number)) => Self::Number(*number),
+            Literal::String(string) => Self::String(bytecode.string_units(string.index())?),
+            Literal::RegExp(_) => return None,
+        })
+    }
+
+    /// The literal spelling the value. Strings can only be spelled with an
+    /// entry of the string table, which `string` looks up.
+    pub fn to_literal(&self, string: impl FnOnce(&[u16]) -> Option<u32>) -> Option<Literal> {
+        Some(match self {
+            Self::Undefined => Literal::Undefined,
+            Self::Null => Literal::Null,
+            Self::Boolean(true) => Literal::Boolean(Boolean::True),
+            Self::Boolean(false) => Literal::Boolean(Boolean::False),
+            Self::Number(number) => Literal::Number(match is_int32(*number) {
+                true if (0.0..=255.0).contains(number) => Number::UInt(*number as u8),
+                true => Number::Int(*number as i32),
+                false => Number::Double(*number),
+            }),
+            Self::String(units) => Literal::String(match string(units)? {
+                index if index <= u32::from(u16::MAX) => StringIndex::Word(index as u16),
+                index => StringIndex::Dword(index),
+            }),
+        })
+    }
+
+    /// SameValue: NaN is itself, and 0 is not -0.
+    pub fn is_same(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Number(first), Self::Number(second)) => {
+                first.to_bits() == second.to_bits() || (first.is_nan() && second.is_nan())
+            }
+            _ => self.strict_equals(other),
+        }
+    }
+
+    pub fn to_boolean(&self) -> bool {
+        match self {
+            Self::Undefined | Self::Null => false,
+            Self::Boolean(boolean) => *boolean,
+            Self::Number(number) => *number != 0.0 && !number.is_nan(),
+            Self::String(units) => !units.is_empty(),
+        }
+    }
+
+    pub fn to_number(&self) -> f64 {
+        match self {
+            Self::Undefined => f64::NAN,
+            Self::Null | Self::Boolean(false) => 0.0,
+            Self::Boolean(true) => 1.0,
+            Self::Number(number) => *number,
+            Self::String(units) => string_to_number(&String::from_utf16_lossy(units)),
+        }
+    }
+
+    pub fn to_int32(&self) -> i32 {
+        self.to_uint32() as i32
+    }
+
+    pub fn to_uint32(&self) -> u32 {
+        let number = self.to_number();
+        if !number.is_finite() {
+            return 0;
+        }
+        number.trunc().rem_euclid(4294967296.0) as u32
+    }
+
+    pub fn to_js_string(&self) -> Vec<u16> {
+        match self {
+            Self::String(units) => units.clone(),
+            Self::Number(number) => number_to_string(*number).encode_utf16().collect(),
+            Self::Undefined => "undefined".encode_utf16().collect(),
+            Self::Null => "null".encode_utf16().collect(),
+            Self::Boolean(true) => "true".encode_utf16().collect(),
+            Self::Boolean(false) => "false".encode_utf16().collect(),
+        }
+    }
+
+    pub fn type_of(&self) -> &'static str {
+        match self {
+            Self::Undefined => "undefined",
+            Self::Null => "object",
+            Self::Boolean(_) => "boolean",
+            Self::Number(_) => "number",
+            Self::String(_) => "string",
+        }
+    }
+
+    pub fn strict_equals(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Undefined, Self::Undefined) | (Self::Null, Self::Null) => true,
+            (Self::Boolean(first), Self::Boolean(second)) => first == second,
+            (Self::Number(first), Self::Number(second)) => first == second,
+            (Self::String(first), Self::String(second)) => first == second,
+            _ => false,
+        }
+    }
+
+    pub fn loose_equals(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Undefined | Self::Null, Self::Undefined | Self::Null) => true,
+            (Self::Undefined | Self::Null, _) | (_, Self::Undefined | Self::Null) => false,
+            (Self::Boolean(_), _) => Self::Number(self.to_number()).loose_equals(other),
+            (_, Self::Boolean(_)) => self.loose_equals(&Self::Number(other.to_number())),
+            (Self::Number(_), Self::String(_)) | (Self::String(_), Self::Number(_)) => {
+                self.to_number() == other.to_number()
+            }
+            _ => self.strict_equals(other),
+        }
+    }
+
+    /// `self < other`, `None` when either side is NaN.
+    fn less_than(&self, other: &Self) -> Option<bool> {
+        match (self, other) {
+            (Self::String(first), Self::String(second)) => Some(first < second),
+            _ => {
+                let (first, second) = (self.to_number(), other.to_number());
+                first
+                    .partial_cmp(&second)
+                    .map(|ordering| ordering == Ordering::Less)
+            }
+        }
+    }
+
+    /// `left operator right`, `None` for `in` and `instanceof`, which throw
+    /// on primitives.
+    pub fn binary(operator: BinaryOperator, left: &Self, right: &Self) -> Option<Self> {
+        use BinaryOperator::*;
+
+        let shift = right.to_uint32() & 31;
+        Some(match operator {
+            Equality => Self::Boolean(left.loose_equals(right)),
+            InEquality => Self::Boolean(!left.loose_equals(right)),
+            Identity => Self::Boolean(left.strict_equals(right)),
+            NonIdentity => Self::Boolean(!left.strict_equals(right)),
+            LessThan => Self::Boolean(left.less_than(right) == Some(true)),
+            GreaterThan => Self::Boolean(right.less_than(left) == Some(true)),
+            LessThanEqual => Self::Boolean(right.less_than(left) == Some(false)),
+            GreaterThanEqual => Self::Boolean(left.less_than(right) == Some(false)),
+            Addition => match (left, right) {
+                (Self::String(_), _) | (_, Self::String(_)) => {
+                    let mut units = left.to_js_string();
+                    units.extend(right.to_js_string());
+                    Self::String(units)
+                }
+                _ => Self::Number(left.to_number() + right.to_number()),
+            },
+            Subtraction => Self::Number(left.to_number() - right.to_number()),
+            Multiplication => Self::Number(left.to_number() * right.to_number()),
+            Division => Self::Number(left.to_number() / right.to_number()),
+            Remainder => Self::Number(left.to_number() % right.to_number()),
+            LeftShift => Self::Number(f64::from(left.to_int32().wrapping_shl(shift))),
+            RightShift => Self::Number(f64::from(left.to_int32() >> shift)),
+            UnsignedRightShift => Self::Number(f64::from(left.to_uint32() >> shift)),
+            BitwiseAnd => Self::Number(f64::from(left.to_int32() & right.to_int32())),
+            BitwiseOr => Self::Number(f64::from(left.to_int32() | right.to_int32())),
+            BitwiseXor => Self::Number(f64::from(left.to_int32() ^ right.to_int32())),
+            InstanceOf | In => return None,
+        })
+    }
+
+    pub fn unary(operator: UnaryOperator, argument: &Self) -> Self {
+        match operator {
+            UnaryOperator::Negation => Self::Number(-argument.to_number()),
+            UnaryOperator::LogicalNot => Self::Boolean(!argument.to_boolean()),
+            UnaryOperator::BitwiseNot => Self::Number(f64::from(!argument.to_int32())),
+            UnaryOperator::TypeOf => Self::String(argument.type_of().encode_utf16().collect()),
+        }
+    }
+
+    pub fn convert(conversion: Conversion, argument: &Self) -> Self {
+        match conversion {
+            Conversion::ToNumber => Self::Number(argument.to_number()),
+            Conversion::ToInt32 => Self::Number(f64::from(argument.to_int32())),
+            Conversion::ToString => Self::String(argument.to_js_string()),
+        }
+    }
+}
+
+fn is_int32(number: f64) -> bool {
+    number.fract() == 0.0
+        && number >= f64::from(i32::MIN)
+        && number <= f64::from(i32::MAX)
+        && !(number == 0.0 && number.is_sign_negative())
+}
+
+/// Number::toString: the shortest digits that read back as `number`, in
+/// plain notation from 1e-6 up to 1e21 and in exponent notation outside.
+pub fn number_to_string(number: f64) -> String {
+    if number.is_nan() {
+        return String::from("NaN");
+    }
+    if number == 0.0 {
+        return String::from("0");
+    }
+    if number.is_infinite() {
+        return String::from(if number < 0.0 {
+            "-Infinity"
+        } else {
+            "Infinity"
+        });
+    }
+    if number < 0.0 {
+        return format!("-{}", number_to_string(-number));
+    }
+
+    // `{:e}` prints the shortest round-tripping digits, like `1.25e-7`.
+    let scientific = format!("{:e}", number);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().unwrap_or_default() + 1;
+
+    if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
+    } else {
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        match k {
+            1 => format!("{}e{}{}", digits, sign, (n - 1).abs()),
+            _ => format!(
+                "{}.{}e{}{}",
+                &digits[..1],
+                &digits[1..],
+                sign,
+                (n - 1).abs()
+            ),
+        }
+    }
+}
+
+/// StringToNumber: a decimal, `0x`, `0o` or `0b` literal or `Infinity`,
+/// surrounded by any whitespace. Empty text is 0, anything else NaN.
+pub fn string_to_number(text: &str) -> f64 {
+    let text = text.trim_matches(is_whitespace);
+    if text.is_empty() {
+        return 0.0;
+    }
+
+    for (prefix, radix) in [("0x", 16), ("0o", 8), ("0b", 2)] {
+        let digits = match text.get(..2) {
+            Some(start) if start.eq_ignore_ascii_case(prefix) => &text[2..],
+            _ => continue,
+        };
+        if digits.is_empty() || !digits.chars().all(|c| c.is_digit(radix)) {
+            return f64::NAN;
+        }
+        return match u128::from_str_radix(digits, radix) {
+            Ok(value) => value as f64,
+            Err(_) => digits.chars().fold(0.0, |value, digit| {
+                value * f64::from(radix) + f64::from(digit.to_digit(radix).unwrap_or_default())
+            }),
+        };
+    }
+
+    let (negative, unsigned) = match text.as_bytes()[0] {
+        b'-' => (true, &text[1..]),
+        b'+' => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let magnitude = match unsigned {
+        "Infinity" => f64::INFINITY,
+        unsigned if is_decimal(unsigned) => unsigned.parse().unwrap_or(f64::NAN),
+        _ => f64::NAN,
+    };
+    match negative {
+        true => -magnitude,
+        false => magnitude,
+    }
+}
+
+/// `1`, `1.`, `.5` or `1.5`, optionally followed by an exponent.
+fn is_decimal(text: &str) -> bool {
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(position) => (&text[..position], Some(&text[position + 1..])),
+        None => (text, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((integer, fraction)) => (integer, fraction),
+        None => (mantissa, ""),
+    };
+    let all_digits = |text: &str| text.bytes().all(|byte| byte.is_ascii_digit());
+    let mantissa_valid = (!integer.is_empty() || !fraction.is_empty())
+        && all_digits(integer)
+        && all_digits(fraction);
+    let exponent_valid = match exponent {
+        Some(exponent) => {
+            let digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+            !digits.is_empty() && all_digits(digits)
+        }
+        None => true,
+    };
+    mantissa_valid && exponent_valid
+}
+
+/// WhiteSpace and LineTerminator, which differ from Unicode's White_Space.
+fn is_whitespace(c: char) -> bool {
+    matches!(
+        c,
+        '\t' | '\n' | '\u{b}' | '\u{c}' | '\r' | ' ' | '\u{a0}' | '\u{1680}' | '\u{2000}'
+            ..='\u{200a}'
+                | '\u{2028}'
+                | '\u{2029}'
+                | '\u{202f}'
+                | '\u{205f}'
+                | '\u{3000}'
+                | '\u{feff}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(text: &str) -> Constant {
+        Constant::String(text.encode_utf16().collect())
+    }
+
+    fn number(constant: Option<Constant>) -> f64 {
+        match constant {
+            Some(Constant::Number(number)) => number,
+            constant => panic!("not a number: {:?}", constant),
+        }
+    }
+
+    #[test]
+    fn number_to_string_matches_javascript() {
+        let cases = [
+            (0.0, "0"),
+            (-0.0, "0"),
+            (f64::NAN, "NaN"),
+            (f64::INFINITY, "Infinity"),
+            (f64::NEG_INFINITY, "-Infinity"),
+            (1.0, "1"),
+            (-42.0, "-42"),
+            (123.456, "123.456"),
+            (0.1 + 0.2, "0.30000000000000004"),
+            (1e20, "100000000000000000000"),
+            (1e21, "1e+21"),
+            (1.5e21, "1.5e+21"),
+            (0.000001, "0.000001"),
+            (1e-7, "1e-7"),
+            (1.25e-7, "1.25e-7"),
+            (9007199254740992.0, "9007199254740992"),
+            (5e-324, "5e-324"),
+            (f64::MAX, "1.7976931348623157e+308"),
+        ];
+        for (number, expected) in cases {
+            assert_eq!(number_to_string(number), expected, "{:e}", number);
+        }
+    }
+
+    #[test]
+    fn string_to_number_matches_javascript() {
+        let cases = [
+            ("", 0.0),
+            ("  \n\t ", 0.0),
+            ("42", 42.0),
+            ("  12  ", 12.0),
+            ("\u{a0}7\u{feff}", 7.0),
+            ("-3.5", -3.5),
+            ("+.5", 0.5),
+            ("5.", 5.0),
+            ("1e3", 1000.0),
+            ("1E-2", 0.01),
+            ("0x1F", 31.0),
+            ("0B101", 5.0),
+            ("0o17", 15.0),
+            ("Infinity", f64::INFINITY),
+            ("-Infinity", f64::NEG_INFINITY),
+            ("1e1000", f64::INFINITY),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(string_to_number(text), expected, "{:?}", text);
+        }
+        assert!(string_to_number("-0").is_sign_negative());
+
+        for text in [
+            ".", "e5", "1e", "1_000", "0x", "-0x10", "0x1G", "infinity", "12px", "1 2",
+        ] {
+            assert!(string_to_number(text).is_nan(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn loose_equality_matches_javascript() {
+        let equal = [
+            (Constant::Null, Constant::Undefined),
+            (Constant::Undefined, Constant::Undefined),
+            (string(""), Constant::Number(0.0)),
+            (string(" \n"), Constant::Number(0.0)),
+            (string("1"), Constant::Boolean(true)),
+            (string("0"), Constant::Boolean(false)),
+            (Constant::Number(1.0), Constant::Boolean(true)),
+            (string("0x10"), Constant::Number(16.0)),
+            (Constant::Number(0.0), Constant::Number(-0.0)),
+        ];
+        for (left, right) in &equal {
+            assert!(left.loose_equals(right), "{:?} == {:?}", left, right);
+            assert!(right.loose_equals(left), "{:?} == {:?}", right, left);
+        }
+
+        let unequal = [
+            (Constant::Null, Constant::Number(0.0)),
+            (Constant::Undefined, Constant::Boolean(false)),
+            (Constant::Null, string("")),
+            (Constant::Number(f64::NAN), Constant::Number(f64::NAN)),
+            (string("abc"), Constant::Number(f64::NAN)),
+            (string("1"), string("01")),
+            (string("true"), Constant::Boolean(true)),
+        ];
+        for (left, right) in &unequal {
+            assert!(!left.loose_equals(right), "{:?} != {:?}", left, right);
+            assert!(!right.loose_equals(left), "{:?} != {:?}", right, left);
+        }
+    }
+
+    #[test]
+    fn to_int32_wraps_like_javascript() {
+        let cases = [
+            (0.0, 0),
+            (-1.9, -1),
+            (1.9, 1),
+            (2147483648.0, -2147483648),
+            (4294967301.0, 5),
+            (3e9, -1294967296),
+            (-4294967296.0, 0),
+            (f64::NAN, 0),
+            (f64::INFINITY, 0),
+            (f64::NEG_INFINITY, 0),
+            (1e300, 0),
+        ];
+        for (number, expected) in cases {
+            assert_eq!(Constant::Number(number).to_int32(), expected, "{}", number);
+        }
+        assert_eq!(Constant::Number(-1.0).to_uint32(), 4294967295);
+        assert_eq!(string(" 0x7fffffff ").to_int32(), 2147483647);
+    }
+
+    #[test]
+    fn shifts_mask_their_count() {
+        use BinaryOperator::*;
+
+        let shift = |operator, left: f64, right: f64| {
+            number(Constant::binary(
+                operator,
+                &Constant::Number(left),
+                &Constant::Number(right),
+            ))
+        };
+        assert_eq!(shift(LeftShift, 1.0, 31.0), -2147483648.0);
+        assert_eq!(shift(LeftShift, 1.0, 32.0), 1.0);
+        assert_eq!(shift(LeftShift, 1.0, -1.0), -2147483648.0);
+        assert_eq!(shift(LeftShift, 3.0, 30.0), -1073741824.0);
+        assert_eq!(shift(RightShift, -16.0, 2.0), -4.0);
+        assert_eq!(shift(RightShift, -1.0, 33.0), -1.0);
+        assert_eq!(shift(UnsignedRightShift, -1.0, 0.0), 4294967295.0);
+        assert_eq!(shift(UnsignedRightShift, -16.0, 28.0), 15.0);
+        assert_eq!(shift(UnsignedRightShift, 4294967296.0, 0.0), 0.0);
+    }
+}
@@ -0,0 +1,740 @@
+use super::CapturedVariable;
+
+use crate::{
+    analysis::ValueId,
+    builtins::Builtins,
+    ir::{
+        AssignmentOperator, BinaryOperator, ClosureKind, Conversion, Literal, Number, StringIndex,
+        UnaryOperator, UpdateOperator,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemberProperty {
+    /// `object.name`
+    Named(StringIndex),
+    /// `object[0]`
+    Index(u32),
+    /// `object[expression]`
+    Computed(Box<Expression>),
+}
+
+impl MemberProperty {
+    /// The member a property key evaluating to `key` names.
+    pub fn from_key(key: &Expression) -> Self {
+        match key {
+            Expression::Literal(Literal::String(name)) => MemberProperty::Named(*name),
+            Expression::Literal(Literal::Number(Number::UInt(index))) => {
+                MemberProperty::Index(u32::from(*index))
+            }
+            Expression::Literal(Literal::Number(Number::Int(index))) if *index >= 0 => {
+                MemberProperty::Index(*index as u32)
+            }
+            key => MemberProperty::Computed(Box::new(key.clone())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectProperty {
+    /// `key: value`
+    Value {
+        key: MemberProperty,
+        value: Expression,
+    },
+    /// `get key() {}`
+    Getter {
+        key: MemberProperty,
+        function: Expression,
+    },
+    /// `set key(value) {}`
+    Setter {
+        key: MemberProperty,
+        function: Expression,
+    },
+    /// `...source`
+    Spread(Expression),
+    /// `__proto__: parent`
+    Proto(Expression),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LogicalOperator {
+    And,
+    Or,
+    NullishCoalescing,
+}
+
+impl From<LogicalOperator> for &'static str {
+    fn from(operator: LogicalOperator) -> Self {
+        use LogicalOperator::*;
+
+        match operator {
+            And => "&&",
+            Or => "||",
+            NullishCoalescing => "??",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClassMemberKind {
+    Method,
+    Getter,
+    Setter,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassMember {
+    pub kind: ClassMemberKind,
+    pub is_static: bool,
+    pub key: MemberProperty,
+    /// The closure of the method or accessor.
+    pub function: Expression,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expression {
+    /// Read of an SSA value.
+    Value(ValueId),
+    Literal(Literal),
+    /// 0 is `this`.
+    Parameter(u32),
+    This,
+    CoerceThis(Box<Expression>),
+    NewTarget,
+    /// The global object.
+    Global,
+    /// The environment `level` scopes up, 0 being the current one.
+    Environment {
+        level: u8,
+    },
+    /// A fresh environment from CreateEnvironment.
+    NewEnvironment,
+    /// The environment created by the given function, once resolved.
+    Scope(u32),
+    EnvironmentSlot {
+        environment: Box<Expression>,
+        slot: u32,
+    },
+    /// An environment slot once it is known which scope it belongs to.
+    Captured(CapturedVariable),
+    Unary {
+        operator: UnaryOperator,
+        argument: Box<Expression>,
+    },
+    Binary {
+        operator: BinaryOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    Conversion {
+        conversion: Conversion,
+        argument: Box<Expression>,
+    },
+    Logical {
+        operator: LogicalOperator,
+        left: Box<Expression>,
+        right: Box<Expression>,
+    },
+    /// `test ? consequent : alternate`
+    Conditional {
+        test: Box<Expression>,
+        consequent: Box<Expression>,
+        alternate: Box<Expression>,
+    },
+    /// The object of `object?.property` or the callee of `callee?.()`. The
+    /// rest of the chain is skipped when it is null or undefined.
+    Optional(Box<Expression>),
+    Member {
+        object: Box<Expression>,
+        property: MemberProperty,
+    },
+    Delete {
+        object: Box<Expression>,
+        property: MemberProperty,
+    },
+    /// `target` is a `Member`, an `EnvironmentSlot`, a `Captured` or a `Value`.
+    Assignment {
+        operator: AssignmentOperator,
+        target: Box<Expression>,
+        value: Box<Expression>,
+    },
+    /// `++argument`, or `argument++` unless `prefix`. `argument` is a target
+    /// like an `Assignment`'s.
+    Update {
+        operator: UpdateOperator,
+        prefix: bool,
+        argument: Box<Expression>,
+    },
+    DefineAccessor {
+        object: Box<Expression>,
+        property: Box<Expression>,
+        getter: Box<Expression>,
+        setter: Box<Expression>,
+        enumerable: bool,
+    },
+    /// A non-enumerable own property, as PutNewOwnNEById defines methods.
+    DefineProperty {
+        object: Box<Expression>,
+        property: MemberProperty,
+        value: Box<Expression>,
+    },
+    NewObject {
+        parent: Option<Box<Expression>>,
+        /// (no_of_static_elements, key_index, value_index)
+        buffer: Option<(u16, u32, u32)>,
+    },
+    NewArray {
+        size: u16,
+        /// (no_of_static_elements, array_index)
+        buffer: Option<(u16, u32)>,
+    },
+    Call {
+        callee: Box<Expression>,
+        this: Box<Expression>,
+        arguments: Vec<Expression>,
+    },
+    /// `this` is the object CreateThis made for the constructor, until
+    /// `restore_constructions` drops it.
+    Construct {
+        callee: Box<Expression>,
+        this: Box<Expression>,
+        arguments: Vec<Expression>,
+    },
+    Builtin(Builtins),
+    /// A function table entry, as used by CallDirect.
+    Function(u32),
+    Closure {
+        kind: ClosureKind,
+        function: u32,
+        environment: Box<Expression>,
+    },
+    /// `class`, standing in for the closure of its constructor `function`.
+    Class {
+        function: u32,
+        environment: Box<Expression>,
+        superclass: Option<Box<Expression>>,
+        members: Vec<ClassMember>,
+    },
+    /// `` `text${expression}text` ``, rebuilt from string concatenation.
+    /// `quasis` holds the cooked text around the substitutions, `None` when
+    /// empty, and has one entry more than `expressions`.
+    Template {
+        quasis: Vec<Option<StringIndex>>,
+        expressions: Vec<Expression>,
+    },
+    /// `` tag`text${expression}text` ``, with the raw text the tag receives.
+    TaggedTemplate {
+        tag: Box<Expression>,
+        this: Box<Expression>,
+        quasis: Vec<StringIndex>,
+        expressions: Vec<Expression>,
+    },
+    /// `[a, , ...b]`, `None` being a hole.
+    ArrayLiteral(Vec<Option<Expression>>),
+    ObjectLiteral(Vec<ObjectProperty>),
+    /// `...argument`, in call arguments and array literals.
+    Spread(Box<Expression>),
+    /// The arguments from parameter `index` on, 0 being `this`, where they
+    /// are not collected into a trailing rest parameter.
+    RestParameter(u32),
+    ArgumentsElement(Box<Expression>),
+    ArgumentsLength,
+    CreateThis {
+        prototype: Box<Expression>,
+        constructor: Box<Expression>,
+    },
+    SelectObject {
+        this: Box<Expression>,
+        result: Box<Expression>,
+    },
+    Eval(Box<Expression>),
+    /// `require(...)` of the module with the given id, once resolved. What
+    /// the module is called depends on the bundle format.
+    Require(u32),
+    /// The live binding a module exports under `name`, once imported.
+    Imported {
+        module: u32,
+        name: StringIndex,
+    },
+    Yield {
+        argument: Box<Expression>,
+        delegate: bool,
+    },
+    Await(Box<Expression>),
+}
+
+impl Expression {
+    /// Direct sub-expressions, in the order they are evaluated.
+    pub fn children(&self) -> Vec<&Expression> {
+        use Expression::*;
+
+        match self {
+            Value(_)
+            | Literal(_)
+            | Parameter(_)
+            | This
+            | NewTarget
+            | Global
+            | Environment { .. }
+            | NewEnvironment
+            | Scope(_)
+            | Captured(_)
+            | Builtin(_)
+            | Function(_)
+            | RestParameter(_)
+            | Require(_)
+            | Imported { .. }
+            | ArgumentsLength => vec![],
+
+            CoerceThis(argument)
+            | Unary { argument, .. }
+            | Conversion { argument, .. }
+            | ArgumentsElement(argument)
+            | Eval(argument)
+            | Spread(argument)
+            | Optional(argument)
+            | Yield { argument, .. }
+            | Await(argument) => vec![argument],
+
+            EnvironmentSlot { environment, .. } => vec![environment],
+            Closure { environment, .. } => vec![environment],
+
+            Binary { left, right, .. } | Logical { left, right, .. } => vec![left, right],
+            Conditional {
+                test,
+                consequent,
+                alternate,
+            } => vec![test, consequent, alternate],
+
+            Member { object, property } | Delete { object, property } => {
+                let mut children = vec![object.as_ref()];
+                if let MemberProperty::Computed(property) = property {
+                    children.push(property);
+                }
+                children
+            }
+
+            // A plain assignment only writes its target, so only the target's
+            // parts count; compound assignments and updates read it as well.
+            Assignment {
+                operator,
+                target,
+                value,
+            } => {
+                let mut children = match target.as_ref() {
+                    Member { .. } | EnvironmentSlot { .. } => target.children(),
+                    _ if *operator == AssignmentOperator::Assignment => vec![],
+                    _ => vec![target.as_ref()],
+                };
+                children.push(value);
+                children
+            }
+            Update { argument, .. } => match argument.as_ref() {
+                Member { .. } | EnvironmentSlot { .. } => argument.children(),
+                _ => vec![argument.as_ref()],
+            },
+
+            DefineAccessor {
+                object,
+                property,
+                getter,
+                setter,
+                ..
+            } => vec![object, property, getter, setter],
+
+            DefineProperty {
+                object,
+                property,
+                value,
+            } => {
+                let mut children = vec![object.as_ref()];
+                if let MemberProperty::Computed(property) = property {
+                    children.push(property);
+                }
+                children.push(value);
+                children
+            }
+
+            Class {
+                environment,
+                superclass,
+                members,
+                ..
+            } => {
+                let mut children: Vec<&Expression> =
+                    superclass.iter().map(|s| s.as_ref()).collect();
+                children.push(environment);
+                for member in members {
+                    if let MemberProperty::Computed(key) = &member.key {
+                        children.push(key);
+                    }
+                    children.push(&member.function);
+                }
+                children
+            }
+
+            NewObject { parent, .. } => parent.iter().map(|parent| parent.as_ref()).collect(),
+
+            Template { expressions, .. } => expressions.iter().collect(),
+
+            ArrayLiteral(elements) => elements.iter().flatten().collect(),
+            ObjectLiteral(properties) => {
+                let mut children = Vec::new();
+                for property in properties {
+                    match property {
+                        ObjectProperty::Value { key, value }
+                        | ObjectProperty::Getter {
+                            key,
+                            function: value,
+                        }
+                        | ObjectProperty::Setter {
+                            key,
+                            function: value,
+                        } => {
+                            if let MemberProperty::Computed(key) = key {
+                                children.push(key.as_ref());
+                            }
+                            children.push(value);
+                        }
+                        ObjectProperty::Spread(source) | ObjectProperty::Proto(source) => {
+                            children.push(source)
+                        }
+                    }
+                }
+                children
+            }
+            TaggedTemplate {
+                tag,
+                this,
+                expressions,
+                ..
+            } => {
+                let mut children = vec![tag.as_ref(), this.as_ref()];
+                children.extend(expressions.iter());
+                children
+            }
+            NewArray { .. } => vec![],
+
+            Call {
+                callee,
+                this,
+                arguments,
+            }
+            | Construct {
+                callee,
+                this,
+                arguments,
+            } => {
+                let mut children = vec![callee.as_ref(), this.as_ref()];
+                children.extend(arguments.iter());
+                children
+            }
+
+            CreateThis {
+                prototype,
+                constructor,
+            } => vec![prototype, constructor],
+            SelectObject { this, result } => vec![this, result],
+        }
+    }
+
+    /// Mutable version of `children`, in the same order.
+    pub fn children_mut(&mut self) -> Vec<&mut Expression> {
+        use Expression::*;
+
+        match self {
+            Value(_)
+            | Literal(_)
+            | Parameter(_)
+            | This
+            | NewTarget
+            | Global
+            | Environment { .. }
+            | NewEnvironment
+            | Scope(_)
+            | Captured(_)
+            | Builtin(_)
+            | Function(_)
+            | RestParameter(_)
+            | Require(_)
+            | Imported { .. }
+            | ArgumentsLength => vec![],
+
+            CoerceThis(argument)
+            | Unary { argument, .. }
+            | Conversion { argument, .. }
+            | ArgumentsElement(argument)
+            | Eval(argument)
+            | Spread(argument)
+            | Optional(argument)
+            | Yield { argument, .. }
+            | Await(argument) => vec![argument],
+
+            EnvironmentSlot { environment, .. } => vec![environment],
+            Closure { environment, .. } => vec![environment],
+
+            Binary { left, right, .. } | Logical { left, right, .. } => vec![left, right],
+            Conditional {
+                test,
+                consequent,
+                alternate,
+            } => vec![test, consequent, alternate],
+
+            Member { object, property } | Delete { object, property } => {
+                let mut children = vec![object.as_mut()];
+                if let MemberProperty::Computed(property) = property {
+                    children.push(property);
+                }
+                children
+            }
+
+            Assignment {
+                operator,
+                target,
+                value,
+            } => {
+                let mut children = match target.as_ref() {
+                    Member { .. } | EnvironmentSlot { .. } => target.children_mut(),
+                    _ if *operator == AssignmentOperator::Assignment => vec![],
+                    _ => vec![target.as_mut()],
+                };
+                children.push(value);
+                children
+            }
+            Update { argument, .. } => match argument.as_mut() {
+                Member { .. } | EnvironmentSlot { .. } => argument.children_mut(),
+                _ => vec![argument.as_mut()],
+            },
+
+            DefineAccessor {
+                object,
+                property,
+                getter,
+                setter,
+                ..
+            } => vec![object, property, getter, setter],
+
+            DefineProperty {
+                object,
+                property,
+                value,
+            } => {
+                let mut children = vec![object.as_mut()];
+                if let MemberProperty::Computed(property) = property {
+                    children.push(property);
+                }
+                children.push(value);
+                children
+            }
+
+            Class {
+                environment,
+                superclass,
+                members,
+                ..
+            } => {
+                let mut children: Vec<&mut Expression> =
+                    superclass.iter_mut().map(|s| s.as_mut()).collect();
+                children.push(environment);
+                for member in members {
+                    if let MemberProperty::Computed(key) = &mut member.key {
+                        children.push(key);
+                    }
+                    children.push(&mut member.function);
+                }
+                children
+            }
+
+            NewObject { parent, .. } => parent.iter_mut().map(|parent| parent.as_mut()).collect(),
+
+            Template { expressions, .. } => expressions.iter_mut().collect(),
+
+            ArrayLiteral(elements) => elements.iter_mut().flatten().collect(),
+            ObjectLiteral(properties) => {
+                let mut children = Vec::new();
+                for property in properties {
+                    match property {
+                        ObjectProperty::Value { key, value }
+                        | ObjectProperty::Getter {
+                            key,
+                            function: value,
+                        }
+                        | ObjectProperty::Setter {
+                            key,
+                            function: value,
+                        } => {
+                            if let MemberProperty::Computed(key) = key {
+                                children.push(key.as_mut());
+                            }
+                            children.push(value);
+                        }
+                        ObjectProperty::Spread(source) | ObjectProperty::Proto(source) => {
+                            children.push(source)
+                        }
+                    }
+                }
+                children
+            }
+            TaggedTemplate {
+                tag,
+                this,
+                expressions,
+                ..
+            } => {
+                let mut children = vec![tag.as_mut(), this.as_mut()];
+                children.extend(expressions.iter_mut());
+                children
+            }
+            NewArray { .. } => vec![],
+
+            Call {
+                callee,
+                this,
+                arguments,
+            }
+            | Construct {
+                callee,
+                this,
+                arguments,
+            } => {
+                let mut children = vec![callee.as_mut(), this.as_mut()];
+                children.extend(arguments.iter_mut());
+                children
+            }
+
+            CreateThis {
+                prototype,
+                constructor,
+            } => vec![prototype, constructor],
+            SelectObject { this, result } => vec![this, result],
+        }
+    }
+
+    /// Every value read, in evaluation order.
+    pub fn values(&self) -> Vec<ValueId> {
+        let mut values = Vec::new();
+        self.collect_values(&mut values);
+        values
+    }
+
+    fn collect_values(&self, values: &mut Vec<ValueId>) {
+        match self {
+            Expression::Value(value) => values.push(*value),
+            _ => {
+                for child in self.children() {
+                    child.collect_values(values);
+                }
+            }
+        }
+    }
+
+    /// Replaces the read of `value`, taking `replacement` the first time.
+    pub fn replace_value(&mut self, value: ValueId, replacement: &mut Option<Expression>) -> bool {
+        if let Expression::Value(current) = self {
+            if *current == value {
+                if let Some(expression) = replacement.take() {
+                    *self = expression;
+                    return true;
+                }
+            }
+            return false;
+        }
+
+        self.children_mut()
+            .into_iter()
+            .any(|child| child.replace_value(value, replacement))
+    }
+
+    /// Replaces every read of `value` with a copy of `replacement`.
+    pub fn substitute(&mut self, value: ValueId, replacement: &Expression) {
+        match self {
+            Expression::Value(current) if *current == value => *self = replacement.clone(),
+            _ => {
+                for child in self.children_mut() {
+                    child.substitute(value, replacement);
+                }
+            }
+        }
+    }
+
+    /// Rewrites every read of `value` to a read of `other`.
+    pub fn rename_value(&mut self, value: ValueId, other: ValueId) {
+        match self {
+            Expression::Value(current) if *current == value => *current = other,
+            _ => {
+                for child in self.children_mut() {
+                    child.rename_value(value, other);
+                }
+            }
+        }
+    }
+
+    /// Whether evaluating this node, children excluded, changes program state.
+    /// Spreading runs iterators and copying properties runs getters.
+    pub fn has_own_side_effects(&self) -> bool {
+        if let Expression::ObjectLiteral(properties) = self {
+            return properties
+                .iter()
+                .any(|property| matches!(property, ObjectProperty::Spread(_)));
+        }
+        matches!(
+            self,
+            Expression::Delete { .. }
+                | Expression::Assignment { .. }
+                | Expression::Update { .. }
+                | Expression::DefineAccessor { .. }
+                | Expression::DefineProperty { .. }
+                | Expression::Call { .. }
+                | Expression::Construct { .. }
+                | Expression::TaggedTemplate { .. }
+                | Expression::Spread(_)
+                | Expression::Eval(_)
+                | Expression::Require(_)
+                | Expression::Yield { .. }
+                | Expression::Await(_)
+        )
+    }
+
+    /// Whether evaluating this node, children excluded, depends on program state.
+    pub fn reads_own_memory(&self) -> bool {
+        self.has_own_side_effects()
+            || matches!(
+                self,
+                Expression::Member { .. }
+                    | Expression::EnvironmentSlot { .. }
+                    | Expression::Captured(_)
+                    | Expression::Imported { .. }
+                    | Expression::ArgumentsElement(_)
+                    | Expression::ArgumentsLength
+            )
+    }
+
+    /// Whether the children after the first are only evaluated on some paths.
+    pub fn short_circuits(&self) -> bool {
+        match self {
+            Expression::Logical { .. } | Expression::Conditional { .. } => true,
+            Expression::Member { object, .. } => object.is_optional_chain(),
+            Expression::Call { callee, .. } => callee.is_optional_chain(),
+            _ => false,
+        }
+    }
+
+    fn is_optional_chain(&self) -> bool {
+        match self {
+            Expression::Optional(_) => true,
+            Expression::Member { object, .. } => object.is_optional_chain(),
+            Expression::Call { callee, .. } => callee.is_optional_chain(),
+            _ => false,
+        }
+    }
+
+    pub fn has_side_effects(&self) -> bool {
+        self.has_own_side_effects() || self.children().iter().any(|c| c.has_side_effects())
+    }
+
+    pub fn reads_memory(&self) -> bool {
+        self.reads_own_memory() || self.children().iter().any(|c| c.reads_memory())
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{
+    analysis::{BlockId, Phi, ValueId, Variables},
+    bytecode_file_format::{BytecodeFile, LargeFunctionHeader},
+};
+
+use super::{Expression, Statement, Terminator, TryStatement, Type};
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub phis: Vec<Phi>,
+    pub statements: Vec<Statement>,
+    pub terminator: Terminator,
+    pub predecessors: Vec<BlockId>,
+    /// The innermost exception handler protecting the block.
+    pub handler: Option<BlockId>,
+}
+
+impl Block {
+    /// The terminator's successors plus the resume point of a yield and the
+    /// exception handler.
+    pub fn successors(&self) -> Vec<BlockId> {
+        let mut successors = self.terminator.successors();
+        for statement in &self.statements {
+            if let Statement::SaveGenerator { resume } = statement {
+                successors.push(*resume);
+            }
+        }
+        successors.extend(self.handler);
+
+        let mut unique = Vec::with_capacity(successors.len());
+        for successor in successors {
+            if !unique.contains(&successor) {
+                unique.push(successor);
+            }
+        }
+        unique
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FunctionKind {
+    Normal,
+    Generator,
+    Async,
+}
+
+/// A function lifted out of register code into SSA values and expressions.
+#[derive(Debug)]
+pub struct LiftedFunction {
+    pub index: u32,
+    pub kind: FunctionKind,
+    /// For a `function*`, the function CreateGenerator builds. Its body is
+    /// the one the source had, this function only wraps it.
+    pub generator: Option<u32>,
+    pub header: LargeFunctionHeader,
+    /// Whether a trailing `...rest` parameter follows the header's
+    /// parameters, read as `Parameter(param_count)`. Set by
+    /// `restore_spread`.
+    pub rest: bool,
+    /// The register each value was carved out of.
+    pub registers: Vec<u32>,
+    /// What each value is known to hold. Lifting records the operands of
+    /// AddN and the other numeric opcodes, `infer_types` fills in the rest.
+    pub types: Vec<Type>,
+    pub blocks: Vec<Block>,
+    /// Filled in by `restore_try_statements`.
+    pub try_statements: Vec<TryStatement>,
+}
+
+impl LiftedFunction {
+    pub fn new_value(&mut self, register: u32) -> ValueId {
+        self.registers.push(register);
+        self.types.push(Type::Unknown);
+        self.registers.len() - 1
+    }
+
+    /// Rebuilds predecessor lists from the blocks' successors, dropping phi
+    /// arguments for edges that no longer exist.
+    pub fn recompute_predecessors(&mut self) {
+        let mut predecessors = vec![Vec::new(); self.blocks.len()];
+        for (block, basic_block) in self.blocks.iter().enumerate() {
+            for successor in basic_block.successors() {
+                predecessors[successor].push(block);
+            }
+        }
+
+        for (block, predecessors) in self.blocks.iter_mut().zip(predecessors) {
+            for phi in &mut block.phis {
+                phi.arguments
+                    .retain(|(predecessor, _)| predecessors.contains(predecessor));
+            }
+            block.predecessors = predecessors;
+        }
+    }
+
+    /// Replaces every read of `value` outside phis with `replacement`.
+    pub fn replace_uses(&mut self, value: ValueId, replacement: &Expression) {
+        for block in &mut self.blocks {
+            let expressions = block
+                .statements
+                .iter_mut()
+                .flat_map(|statement| statement.expressions_mut())
+                .chain(block.terminator.expressions_mut());
+            for expression in expressions {
+                expression.substitute(value, replacement);
+            }
+        }
+    }
+
+    /// Drops phis that merge a single value, apart from themselves, reading
+    /// that value instead.
+    pub fn remove_trivial_phis(&mut self) {
+        loop {
+            let trivial = self
+                .blocks
+                .iter()
+                .flat_map(|block| block.phis.iter())
+                .find_map(|phi| {
+                    let mut others = phi
+                        .arguments
+                        .iter()
+                        .map(|(_, argument)| *argument)
+                        .filter(|argument| *argument != phi.value);
+                    let first = others.next()?;
+                    others
+                        .all(|argument| argument == first)
+                        .then_some((phi.value, first))
+                });
+            let (phi, replacement) = match trivial {
+                Some(trivial) => trivial,
+                None => break,
+            };
+
+            for block in &mut self.blocks {
+                block.phis.retain(|other| other.value != phi);
+                for (_, argument) in block
+                    .phis
+                    .iter_mut()
+                    .flat_map(|other| other.arguments.iter_mut())
+                {
+                    if *argument == phi {
+                        *argument = replacement;
+                    }
+                }
+            }
+            self.replace_uses(phi, &Expression::Value(replacement));
+        }
+    }
+
+    /// Leaves SSA form, see `Variables::new`.
+    pub fn variables(&self) -> Variables {
+        Variables::new(
+            self.registers.clone(),
+            self.blocks.iter().flat_map(|block| block.phis.iter()),
+        )
+    }
+
+    /// How many times each value is read, phi arguments included.
+    pub fn use_counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.registers.len()];
+
+        for block in &self.blocks {
+            for phi in &block.phis {
+                for (_, argument) in &phi.arguments {
+                    counts[*argument] += 1;
+                }
+            }
+
+            let expressions = block
+                .statements
+                .iter()
+                .flat_map(|statement| statement.expressions())
+                .chain(block.terminator.expressions());
+            for expression in expressions {
+                for value in expression.values() {
+                    counts[value] += 1;
+                }
+            }
+        }
+
+        counts
+    }
+
+    /// Whether each value is an argument of some phi.
+    pub fn phi_arguments(&self) -> Vec<bool> {
+        let mut arguments = vec![false; self.registers.len()];
+        for phi in self.blocks.iter().flat_map(|block| block.phis.iter()) {
+            for (_, argument) in &phi.arguments {
+                arguments[*argument] = true;
+            }
+        }
+        arguments
+    }
+
+    /// The expression assigned to each value, if it comes from an `Assign`.
+    pub fn definitions(&self) -> Vec<Option<&Expression>> {
+        let mut definitions = vec![None; self.registers.len()];
+        for statement in self.blocks.iter().flat_map(|block| block.statements.iter()) {
+            if let Statement::Assign { value, expression } = statement {
+                definitions[*value] = Some(expression);
+            }
+        }
+        definitions
+    }
+
+    /// The name the function was declared with, `None` when anonymous.
+    pub fn name(&self, bytecode: &BytecodeFile) -> Option<String> {
+        bytecode
+            .string(self.header.function_name)
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Blocks reachable from the entry, exception handlers included, in
+    /// reverse postorder.
+    pub fn reverse_postorder(&self) -> Vec<BlockId> {
+        if self.blocks.is_empty() {
+            return vec![];
+        }
+
+        let successors: Vec<Vec<BlockId>> =
+            self.blocks.iter().map(|block| block.successors()).collect();
+        let mut visited = vec![false; self.blocks.len()];
+        let mut postorder = Vec::with_capacity(self.blocks.len());
+        let mut stack = vec![(0, 0)];
+        visited[0] = true;
+
+        while let Some((block, next)) = stack.pop() {
+            match successors[block].get(next) {
+                Some(&successor) => {
+                    stack.push((block, next + 1));
+                    if !visited[successor] {
+                        visited[successor] = true;
+                        stack.push((successor, 0));
+                    }
+                }
+                None => postorder.push(block),
+            }
+        }
+
+        postorder.reverse();
+        postorder
+    }
+
+    pub fn statement_count(&self) -> usize {
+        self.blocks.iter().map(|block| block.statements.len()).sum()
+    }
+}
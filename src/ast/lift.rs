@@ -0,0 +1,539 @@
+use std::collections::HashMap;
+
+use crate::{
+    analysis::{ControlFlowGraph, SsaForm, ValueId},
+    ir::{
+        self, ArgumentsExpression, AssignmentOperator, BinaryOperator, FunctionIndex, FunctionType,
+        GeneratorStatement, IteratorStatement, JumpCondition, Literal, Object, ObjectExpKind,
+        Property, Register, UnaryOperator,
+    },
+};
+
+use super::{
+    Block, Expression, FunctionKind, LiftedFunction, MemberProperty, Statement, Terminator, Type,
+};
+
+/// Maps register reads and writes of one instruction to values. Blocks the
+/// SSA construction never reached get fresh values instead.
+struct Lifter<'a> {
+    ssa: &'a SsaForm,
+    registers: Vec<u32>,
+    local: HashMap<u32, ValueId>,
+    /// Operands of the numeric opcodes.
+    numbers: Vec<ValueId>,
+}
+
+impl Lifter<'_> {
+    fn fresh(&mut self, register: Register) -> ValueId {
+        self.registers.push(register.index());
+        let value = self.registers.len() - 1;
+        self.local.insert(register.index(), value);
+        value
+    }
+
+    fn read(&mut self, index: usize, register: Register) -> ValueId {
+        if let Some(value) = self.ssa.use_of(index, register) {
+            return value;
+        }
+        match self.local.get(&register.index()) {
+            Some(value) => *value,
+            None => self.fresh(register),
+        }
+    }
+
+    fn value(&mut self, index: usize, register: Register) -> Expression {
+        Expression::Value(self.read(index, register))
+    }
+
+    fn boxed(&mut self, index: usize, register: Register) -> Box<Expression> {
+        Box::new(self.value(index, register))
+    }
+
+    fn def(&mut self, index: usize, register: Register) -> ValueId {
+        match self.ssa.def_of(index, register) {
+            Some(value) => value,
+            None => self.fresh(register),
+        }
+    }
+
+    fn property(&mut self, index: usize, property: Property) -> MemberProperty {
+        match property {
+            Property::String(string) => MemberProperty::Named(string),
+            Property::Index(array_index) => MemberProperty::Index(array_index.value()),
+            Property::Register(register) => MemberProperty::Computed(self.boxed(index, register)),
+        }
+    }
+
+    fn callee(&mut self, index: usize, function: FunctionIndex) -> Box<Expression> {
+        match function {
+            FunctionIndex::Register(register) => self.boxed(index, register),
+            _ => Box::new(Expression::Function(function.index().unwrap_or_default())),
+        }
+    }
+
+    /// Splits call operands into `this` and the arguments proper.
+    fn arguments(
+        &mut self,
+        index: usize,
+        registers: &[Register],
+    ) -> (Box<Expression>, Vec<Expression>) {
+        let mut arguments: Vec<Expression> = registers
+            .iter()
+            .map(|register| self.value(index, *register))
+            .collect();
+        let this = match arguments.is_empty() {
+            true => Expression::Literal(Literal::Undefined),
+            false => arguments.remove(0),
+        };
+        (Box::new(this), arguments)
+    }
+
+    fn expression(&mut self, index: usize, expression: &ir::Expression) -> Expression {
+        use ir::Expression as Ir;
+
+        match expression {
+            Ir::Literal(literal) => Expression::Literal(*literal),
+            Ir::Register(register) => self.value(index, *register),
+            Ir::NewObject(new_object) => Expression::NewObject {
+                parent: new_object.parent.map(|parent| self.boxed(index, parent)),
+                buffer: match new_object.no_of_static_elements {
+                    0 => None,
+                    count => Some((
+                        count,
+                        new_object.key_index.offset(),
+                        new_object.value_index.offset(),
+                    )),
+                },
+            },
+            Ir::NewArray(new_array) => Expression::NewArray {
+                size: new_array.array_size,
+                buffer: new_array
+                    .array_index
+                    .map(|buffer| (new_array.no_of_static_elements, buffer.offset())),
+            },
+            Ir::LoadFromEnv(env_expression) => Expression::EnvironmentSlot {
+                environment: self.boxed(index, env_expression.environment),
+                slot: env_expression.index.slot(),
+            },
+            Ir::Object(object) => match object {
+                Object::This => Expression::This,
+                Object::Global => Expression::Global,
+                Object::Environment { id } => Expression::Environment { level: *id },
+                Object::NewEnvironment => Expression::NewEnvironment,
+                Object::Normal | Object::Array => Expression::Literal(Literal::Undefined),
+            },
+            Ir::ObjExp(object_expression) => match object_expression.kind {
+                ObjectExpKind::Get { object } => Expression::Member {
+                    object: self.boxed(index, object),
+                    property: self.property(index, object_expression.property),
+                },
+                ObjectExpKind::Delete { object } => Expression::Delete {
+                    object: self.boxed(index, object),
+                    property: self.property(index, object_expression.property),
+                },
+                // Puts are lifted by `statement`, they have no destination.
+                ObjectExpKind::Set { .. } | ObjectExpKind::Define { .. } => {
+                    Expression::Literal(Literal::Undefined)
+                }
+            },
+            Ir::Unary(unary) => Expression::Unary {
+                operator: unary.operator,
+                argument: self.boxed(index, unary.argument),
+            },
+            Ir::Binary(binary) => {
+                let left = self.read(index, binary.operands.0);
+                let right = self.read(index, binary.operands.1);
+                if binary.numeric {
+                    self.numbers.extend([left, right]);
+                }
+                Expression::Binary {
+                    operator: binary.operator,
+                    left: Box::new(Expression::Value(left)),
+                    right: Box::new(Expression::Value(right)),
+                }
+            }
+            Ir::Conversion(conversion) => Expression::Conversion {
+                conversion: conversion.conversion,
+                argument: self.boxed(index, conversion.argument),
+            },
+            Ir::FrameCall(frame_call) => {
+                let (callee, is_constructor) = match frame_call.function_type {
+                    FunctionType::Normal(function) => (self.callee(index, function), false),
+                    FunctionType::Constructor(function) => (self.callee(index, function), true),
+                    FunctionType::BuiltIn(builtin) => {
+                        (Box::new(Expression::Builtin(builtin)), false)
+                    }
+                };
+                let (this, arguments) = self.arguments(index, &frame_call.arguments);
+                match is_constructor {
+                    true => Expression::Construct {
+                        callee,
+                        this,
+                        arguments,
+                    },
+                    false => Expression::Call {
+                        callee,
+                        this,
+                        arguments,
+                    },
+                }
+            }
+            Ir::CallExp(call) => {
+                let callee = self.callee(index, call.function);
+                let (this, arguments) = self.arguments(index, &call.arguments);
+                Expression::Call {
+                    callee,
+                    this,
+                    arguments,
+                }
+            }
+            Ir::NewTarget => Expression::NewTarget,
+            Ir::Closure(closure) => Expression::Closure {
+                kind: closure.kind,
+                function: closure.function.index().unwrap_or_default(),
+                environment: self.boxed(index, closure.environment),
+            },
+            Ir::Arguments(arguments) => match arguments {
+                ArgumentsExpression::Get { index: element, .. } => {
+                    Expression::ArgumentsElement(self.boxed(index, *element))
+                }
+                ArgumentsExpression::Length { .. } => Expression::ArgumentsLength,
+            },
+            Ir::Parameter(parameter) => Expression::Parameter(*parameter),
+            Ir::CoerceThis(this) => Expression::CoerceThis(self.boxed(index, *this)),
+            Ir::CreateThis {
+                prototype,
+                constructor,
+            } => Expression::CreateThis {
+                prototype: self.boxed(index, *prototype),
+                constructor: self.boxed(index, *constructor),
+            },
+            Ir::SelectObject { this, result } => Expression::SelectObject {
+                this: self.boxed(index, *this),
+                result: self.boxed(index, *result),
+            },
+            Ir::Eval(argument) => Expression::Eval(self.boxed(index, *argument)),
+        }
+    }
+
+    fn statement(
+        &mut self,
+        function: &ir::Function,
+        cfg: &ControlFlowGraph,
+        index: usize,
+    ) -> Option<Statement> {
+        use ir::Statement as Ir;
+
+        let statement = match &function.body[index].statement {
+            Ir::Expression {
+                register,
+                expression: ir::Expression::ObjExp(object_expression),
+            } if matches!(
+                object_expression.kind,
+                ObjectExpKind::Set { .. } | ObjectExpKind::Define { .. }
+            ) =>
+            {
+                let object = self.boxed(index, *register);
+                let expression = match object_expression.kind {
+                    ObjectExpKind::Set {
+                        value,
+                        enumerable: false,
+                    } => Expression::DefineProperty {
+                        object,
+                        property: self.property(index, object_expression.property),
+                        value: self.boxed(index, value),
+                    },
+                    ObjectExpKind::Set { value, .. } => Expression::Assignment {
+                        operator: AssignmentOperator::Assignment,
+                        target: Box::new(Expression::Member {
+                            object,
+                            property: self.property(index, object_expression.property),
+                        }),
+                        value: self.boxed(index, value),
+                    },
+                    ObjectExpKind::Define {
+                        getter,
+                        setter,
+                        enumerable,
+                    } => Expression::DefineAccessor {
+                        object,
+                        property: Box::new(match object_expression.property {
+                            Property::Register(property) => self.value(index, property),
+                            Property::String(string) => {
+                                Expression::Literal(Literal::String(string))
+                            }
+                            Property::Index(array_index) => Expression::Literal(Literal::Number(
+                                ir::Number::Int(array_index.value() as i32),
+                            )),
+                        }),
+                        getter: self.boxed(index, getter),
+                        setter: self.boxed(index, setter),
+                        enumerable,
+                    },
+                    _ => unreachable!(),
+                };
+                Statement::Expression(expression)
+            }
+            Ir::Expression {
+                register,
+                expression,
+            } => {
+                let expression = self.expression(index, expression);
+                Statement::Assign {
+                    value: self.def(index, *register),
+                    expression,
+                }
+            }
+            Ir::StoreToEnv(env_expression) => {
+                let environment = self.boxed(index, env_expression.environment);
+                let value = match env_expression.value {
+                    Some(value) => self.value(index, value),
+                    None => Expression::Literal(Literal::Undefined),
+                };
+                Statement::Expression(Expression::Assignment {
+                    operator: AssignmentOperator::Assignment,
+                    target: Box::new(Expression::EnvironmentSlot {
+                        environment,
+                        slot: env_expression.index.slot(),
+                    }),
+                    value: Box::new(value),
+                })
+            }
+            Ir::Catch(register) => Statement::Catch(self.def(index, *register)),
+            Ir::DeclareGlobalVar(name) => Statement::DeclareGlobalVar(*name),
+            Ir::ThrowIfUndefined(register) => {
+                Statement::ThrowIfUndefined(self.value(index, *register))
+            }
+            Ir::Generator(generator) => match generator {
+                GeneratorStatement::Start => Statement::StartGenerator,
+                GeneratorStatement::Save { offset } => Statement::SaveGenerator {
+                    resume: cfg.block_of(function.jump_target(index, *offset)?),
+                },
+                GeneratorStatement::Resume { result, is_return } => Statement::ResumeGenerator {
+                    result: self.def(index, *result),
+                    is_return: self.def(index, *is_return),
+                },
+                GeneratorStatement::Complete => Statement::CompleteGenerator,
+            },
+            Ir::Iterator(iterator) => match *iterator {
+                IteratorStatement::PropertyNames {
+                    names,
+                    object,
+                    index: position,
+                    size,
+                } => {
+                    let object = self.value(index, object);
+                    Statement::PropertyNames {
+                        names: self.def(index, names),
+                        index: self.def(index, position),
+                        size: self.def(index, size),
+                        object,
+                    }
+                }
+                IteratorStatement::NextPropertyName {
+                    property,
+                    names,
+                    object,
+                    index: position,
+                    size,
+                } => {
+                    let names = self.value(index, names);
+                    let object = self.value(index, object);
+                    let size = self.value(index, size);
+                    // The index is read and written in place.
+                    self.read(index, position);
+                    Statement::NextPropertyName {
+                        property: self.def(index, property),
+                        index: self.def(index, position),
+                        names,
+                        object,
+                        size,
+                    }
+                }
+                IteratorStatement::Begin { iterator, source } => {
+                    let source_value = self.value(index, source);
+                    Statement::IteratorBegin {
+                        iterator: self.def(index, iterator),
+                        next: self.def(index, source),
+                        source: source_value,
+                    }
+                }
+                IteratorStatement::Next {
+                    result,
+                    iterator,
+                    source,
+                } => {
+                    let previous = self.value(index, iterator);
+                    let next = self.value(index, source);
+                    Statement::IteratorNext {
+                        result: self.def(index, result),
+                        iterator: self.def(index, iterator),
+                        previous,
+                        next,
+                    }
+                }
+                IteratorStatement::Close {
+                    iterator,
+                    ignore_inner_exception,
+                } => Statement::IteratorClose {
+                    iterator: self.value(index, iterator),
+                    ignore_inner_exception,
+                },
+            },
+            Ir::Debugger => Statement::Debugger,
+            Ir::Nop
+            | Ir::Return(_)
+            | Ir::Throw(_)
+            | Ir::Jump(_)
+            | Ir::Switch(_)
+            | Ir::Unreachable => return None,
+        };
+        Some(statement)
+    }
+
+    fn terminator(
+        &mut self,
+        function: &ir::Function,
+        cfg: &ControlFlowGraph,
+        index: usize,
+    ) -> Terminator {
+        let block_at = |relative: i32| {
+            function
+                .jump_target(index, relative)
+                .map(|target| cfg.block_of(target))
+        };
+        let next = if index + 1 < function.body.len() {
+            Some(cfg.block_of(index + 1))
+        } else {
+            None
+        };
+
+        match &function.body[index].statement {
+            ir::Statement::Return(register) => Terminator::Return(self.value(index, *register)),
+            ir::Statement::Throw(register) => Terminator::Throw(self.value(index, *register)),
+            ir::Statement::Jump(jump) => {
+                let target = match block_at(jump.offset) {
+                    Some(target) => target,
+                    None => return Terminator::Unreachable,
+                };
+
+                let condition = match &jump.condition {
+                    JumpCondition::Always => return Terminator::Goto(target),
+                    JumpCondition::True(register) => self.value(index, *register),
+                    JumpCondition::False(register) => Expression::Unary {
+                        operator: UnaryOperator::LogicalNot,
+                        argument: self.boxed(index, *register),
+                    },
+                    JumpCondition::Undefined(register) => Expression::Binary {
+                        operator: BinaryOperator::Identity,
+                        left: self.boxed(index, *register),
+                        right: Box::new(Expression::Literal(Literal::Undefined)),
+                    },
+                    JumpCondition::Compare {
+                        operator,
+                        operands,
+                        negated,
+                        numeric,
+                    } => {
+                        let left = self.read(index, operands.0);
+                        let right = self.read(index, operands.1);
+                        if *numeric {
+                            self.numbers.extend([left, right]);
+                        }
+                        let comparison = Expression::Binary {
+                            operator: *operator,
+                            left: Box::new(Expression::Value(left)),
+                            right: Box::new(Expression::Value(right)),
+                        };
+                        match negated {
+                            true => Expression::Unary {
+                                operator: UnaryOperator::LogicalNot,
+                                argument: Box::new(comparison),
+                            },
+                            false => comparison,
+                        }
+                    }
+                };
+
+                match next {
+                    Some(fallthrough) => Terminator::Branch {
+                        condition,
+                        target,
+                        fallthrough,
+                    },
+                    None => Terminator::Unreachable,
+                }
+            }
+            ir::Statement::Switch(switch) => {
+                let value = self.value(index, switch.value);
+                match block_at(switch.default) {
+                    Some(default) => Terminator::Switch {
+                        value,
+                        minimum: switch.minimum,
+                        targets: switch
+                            .offsets
+                            .iter()
+                            .map(|offset| block_at(*offset).unwrap_or(default))
+                            .collect(),
+                        default,
+                    },
+                    None => Terminator::Unreachable,
+                }
+            }
+            ir::Statement::Unreachable => Terminator::Unreachable,
+            _ => match next {
+                Some(next) => Terminator::Goto(next),
+                None => Terminator::Unreachable,
+            },
+        }
+    }
+}
+
+impl LiftedFunction {
+    /// Turns every instruction into statements over SSA values, with the last
+    /// instruction of each block becoming its terminator.
+    pub fn new(function: &ir::Function, cfg: &ControlFlowGraph, ssa: &SsaForm) -> Self {
+        let mut lifter = Lifter {
+            ssa,
+            registers: ssa.values.iter().map(|value| value.register).collect(),
+            local: HashMap::new(),
+            numbers: Vec::new(),
+        };
+
+        let mut blocks = Vec::with_capacity(cfg.blocks.len());
+        for (block, basic_block) in cfg.blocks.iter().enumerate() {
+            lifter.local.clear();
+
+            let mut statements = Vec::new();
+            for index in basic_block.instructions.clone() {
+                statements.extend(lifter.statement(function, cfg, index));
+            }
+            let terminator = lifter.terminator(function, cfg, basic_block.instructions.end - 1);
+
+            blocks.push(Block {
+                phis: ssa.phis[block].clone(),
+                statements,
+                terminator,
+                predecessors: basic_block.predecessors.clone(),
+                handler: basic_block.handler,
+            });
+        }
+
+        let mut types = vec![Type::Unknown; lifter.registers.len()];
+        for value in lifter.numbers {
+            types[value] = Type::Number;
+        }
+
+        Self {
+            index: function.index,
+            kind: FunctionKind::Normal,
+            generator: None,
+            header: function.header,
+            rest: false,
+            registers: lifter.registers,
+            types,
+            blocks,
+            try_statements: Vec::new(),
+        }
+    }
+}
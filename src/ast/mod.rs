@@ -0,0 +1,20 @@
+mod constant;
+mod expression;
+mod function;
+mod lift;
+mod modules;
+mod names;
+mod nesting;
+mod scopes;
+mod statement;
+mod types;
+
+pub use constant::*;
+pub use expression::*;
+pub use function::*;
+pub use modules::*;
+pub use names::*;
+pub use nesting::*;
+pub use scopes::*;
+pub use statement::*;
+pub use types::*;
@@ -0,0 +1,339 @@
+use std::convert::TryFrom;
+
+use crate::{
+    bytecode_file_format::BytecodeFile,
+    ir::{Literal, Number},
+};
+
+use super::{Expression, LiftedFunction, MemberProperty, Nesting, Statement};
+
+/// The parameters Metro passes a module factory.
+pub const METRO_PARAMETERS: &[&str] = &[
+    "global",
+    "require",
+    "importDefault",
+    "importAll",
+    "module",
+    "exports",
+    "dependencyMap",
+];
+
+/// A module of a Metro bundle, registered by
+/// `__d(factory, id, dependencies, verboseName)`.
+#[derive(Debug, Clone)]
+pub struct MetroModule {
+    pub id: u32,
+    /// The function called with the `METRO_PARAMETERS` to run the module.
+    pub factory: u32,
+    /// The ids `dependencyMap[n]` maps to.
+    pub dependencies: Vec<u32>,
+    /// The source path development builds pass along.
+    pub verbose_name: Option<String>,
+}
+
+/// The modules a Metro bundle registers in its global code, and the ones it
+/// runs with `__r(id)` once they are all defined.
+#[derive(Debug, Clone, Default)]
+pub struct MetroBundle {
+    pub modules: Vec<MetroModule>,
+    pub entry_points: Vec<u32>,
+}
+
+impl MetroBundle {
+    /// Every `__d` and `__r` call in `global`, in the order they are made.
+    /// A program without any has no modules.
+    pub fn find(global: &LiftedFunction, bytecode: &BytecodeFile) -> Self {
+        let definitions = global.definitions();
+
+        let mut bundle = Self::default();
+        for block in &global.blocks {
+            for statement in &block.statements {
+                let call = match statement {
+                    Statement::Expression(call)
+                    | Statement::Assign {
+                        expression: call, ..
+                    } => call,
+                    _ => continue,
+                };
+                let (callee, arguments) = match call {
+                    Expression::Call {
+                        callee, arguments, ..
+                    } => (callee, arguments),
+                    _ => continue,
+                };
+
+                match global_function(callee, &definitions, bytecode).as_deref() {
+                    Some("__d") => {
+                        let factory = match arguments
+                            .first()
+                            .and_then(|factory| resolve(factory, &definitions))
+                        {
+                            Some(Expression::Closure { function, .. }) => *function,
+                            _ => continue,
+                        };
+                        let id = match arguments.get(1).and_then(module_id) {
+                            Some(id) => id,
+                            None => continue,
+                        };
+                        let dependencies = match arguments.get(2) {
+                            Some(Expression::ArrayLiteral(elements)) => elements
+                                .iter()
+                                .filter_map(|element| element.as_ref().and_then(module_id))
+                                .collect(),
+                            _ => Vec::new(),
+                        };
+                        let verbose_name = match arguments.get(3) {
+                            Some(Expression::Literal(Literal::String(name))) => {
+                                bytecode.string(name.index())
+                            }
+                            _ => None,
+                        };
+                        bundle.modules.push(MetroModule {
+                            id,
+                            factory,
+                            dependencies,
+                            verbose_name,
+                        });
+                    }
+                    Some("__r") => bundle
+                        .entry_points
+                        .extend(arguments.first().and_then(module_id)),
+                    _ => {}
+                }
+            }
+        }
+        bundle
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    pub fn module(&self, id: u32) -> Option<&MetroModule> {
+        self.modules.iter().find(|module| module.id == id)
+    }
+}
+
+/// A module some `Require` can refer to.
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub id: u32,
+    /// The function that runs the module.
+    pub factory: u32,
+    /// The path it was compiled from, when the file records it.
+    pub path: Option<String>,
+}
+
+/// A `Require` of `imported` made while running `importer`, `None` when
+/// the global code makes it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ModuleImport {
+    pub importer: Option<u32>,
+    pub imported: u32,
+    /// The function the `Require` is in, the importer's factory or a closure
+    /// nested in it.
+    pub function: u32,
+}
+
+/// The modules of a program and which of them require which, by module id.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleGraph {
+    modules: Vec<Module>,
+    imports: Vec<ModuleImport>,
+}
+
+impl ModuleGraph {
+    /// The modules in the CommonJS table. Once the compiler resolved them,
+    /// entries pair a module id with its function and RequireFast takes the
+    /// id; otherwise they pair a filename with the function and ids count up
+    /// from the segment's `cjs_module_offset`.
+    pub fn from_cjs_table(bytecode: &BytecodeFile) -> Self {
+        let resolved = bytecode
+            .header
+            .bytecode_options
+            .cjs_modules_statically_resolved();
+        let modules = bytecode
+            .cjs_module_table
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| match resolved {
+                true => Module {
+                    id: entry.0,
+                    factory: entry.1,
+                    path: None,
+                },
+                false => Module {
+                    id: bytecode.header.cjs_module_offset + index as u32,
+                    factory: entry.1,
+                    path: bytecode.string(entry.0).filter(|path| !path.is_empty()),
+                },
+            })
+            .collect();
+        Self {
+            modules,
+            imports: Vec::new(),
+        }
+    }
+
+    pub fn from_metro_bundle(bundle: &MetroBundle) -> Self {
+        let modules = bundle
+            .modules
+            .iter()
+            .map(|module| Module {
+                id: module.id,
+                factory: module.factory,
+                path: module.verbose_name.clone(),
+            })
+            .collect();
+        Self {
+            modules,
+            imports: Vec::new(),
+        }
+    }
+
+    /// Records every `Require`, import and `export *` in `functions`,
+    /// attributed to the module whose factory the function is nested in.
+    pub fn add_imports(&mut self, functions: &[LiftedFunction], nesting: &Nesting) {
+        for function in functions {
+            let mut importer = None;
+            let mut current = Some(function.index);
+            while let (None, Some(index)) = (importer, current) {
+                importer = self
+                    .modules
+                    .iter()
+                    .find(|module| module.factory == index)
+                    .map(|module| module.id);
+                current = nesting.parent(index);
+            }
+
+            let mut required = Vec::new();
+            for block in &function.blocks {
+                for statement in &block.statements {
+                    if let Statement::Import { module, .. } | Statement::ExportAll(module) =
+                        statement
+                    {
+                        required.push(*module);
+                    }
+                }
+                let expressions = block
+                    .statements
+                    .iter()
+                    .flat_map(|statement| statement.expressions())
+                    .chain(block.terminator.expressions());
+                for expression in expressions {
+                    collect_requires(expression, &mut required);
+                }
+            }
+            for imported in required {
+                let import = ModuleImport {
+                    importer,
+                    imported,
+                    function: function.index,
+                };
+                if !self.imports.contains(&import) {
+                    self.imports.push(import);
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+
+    pub fn module(&self, id: u32) -> Option<&Module> {
+        self.modules.iter().find(|module| module.id == id)
+    }
+
+    pub fn imports(&self) -> &[ModuleImport] {
+        &self.imports
+    }
+
+    /// The modules `id` requires, each once, in the order first required.
+    pub fn dependencies(&self, id: u32) -> Vec<u32> {
+        let mut dependencies = Vec::new();
+        for import in &self.imports {
+            if import.importer == Some(id) && !dependencies.contains(&import.imported) {
+                dependencies.push(import.imported);
+            }
+        }
+        dependencies
+    }
+
+    /// The modules requiring `id`, each once.
+    pub fn dependents(&self, id: u32) -> Vec<u32> {
+        let mut dependents = Vec::new();
+        for import in &self.imports {
+            if let Some(importer) = import.importer {
+                if import.imported == id && !dependents.contains(&importer) {
+                    dependents.push(importer);
+                }
+            }
+        }
+        dependents
+    }
+
+    /// What `require` is passed for module `id`: its path, or
+    /// `./module_<id>` after the file unbundling writes it to.
+    pub fn path(&self, id: u32) -> String {
+        self.module(id)
+            .and_then(|module| module.path.clone())
+            .unwrap_or_else(|| format!("./module_{}", id))
+    }
+}
+
+fn collect_requires(expression: &Expression, required: &mut Vec<u32>) {
+    if let Expression::Require(module) = expression {
+        required.push(*module);
+    }
+    for child in expression.children() {
+        collect_requires(child, required);
+    }
+}
+
+/// A module id, which Metro writes as a number literal.
+fn module_id(expression: &Expression) -> Option<u32> {
+    match expression {
+        Expression::Literal(Literal::Number(number)) => match number {
+            Number::UInt(id) => Some(u32::from(*id)),
+            Number::Int(id) => u32::try_from(*id).ok(),
+            Number::Double(id) if id.fract() == 0.0 && *id >= 0.0 && *id <= f64::from(u32::MAX) => {
+                Some(*id as u32)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// What a value holds, for an expression that may only read it.
+fn resolve<'a>(
+    expression: &'a Expression,
+    definitions: &[Option<&'a Expression>],
+) -> Option<&'a Expression> {
+    match expression {
+        Expression::Value(value) => definitions[*value],
+        expression => Some(expression),
+    }
+}
+
+/// The name of the global a call is made to, like `__d` for `__d(...)`.
+fn global_function(
+    callee: &Expression,
+    definitions: &[Option<&Expression>],
+    bytecode: &BytecodeFile,
+) -> Option<String> {
+    match resolve(callee, definitions)? {
+        Expression::Member {
+            object,
+            property: MemberProperty::Named(name),
+        } if matches!(resolve(object, definitions), Some(Expression::Global)) => {
+            bytecode.string(name.index())
+        }
+        _ => None,
+    }
+}
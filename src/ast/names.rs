@@ -0,0 +1,916 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    analysis::{ValueId, VariableId},
+    builtins::Builtins,
+    bytecode_file_format::BytecodeFile,
+    ir::{AssignmentOperator, BinaryOperator, Conversion, Literal, Number, Program},
+};
+
+use super::{
+    CapturedVariable, Expression, LiftedFunction, MemberProperty, ModuleGraph, Nesting,
+    ObjectProperty, Scopes, Statement, Terminator, Type, METRO_PARAMETERS,
+};
+
+/// Words that cannot name a variable, in strict code or as a module, plus the
+/// globals the output would otherwise shadow.
+const RESERVED: &[&str] = &[
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "eval",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "Infinity",
+    "instanceof",
+    "interface",
+    "let",
+    "NaN",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+/// Calls whose function arguments are named after the call, like
+/// `thenCallback` for the closure in `promise.then(function () {})`.
+const CALLBACKS: &[&str] = &[
+    "addEventListener",
+    "catch",
+    "every",
+    "filter",
+    "finally",
+    "find",
+    "findIndex",
+    "forEach",
+    "map",
+    "on",
+    "once",
+    "reduce",
+    "requestAnimationFrame",
+    "setImmediate",
+    "setInterval",
+    "setTimeout",
+    "some",
+    "sort",
+    "then",
+];
+
+/// Names tried in turn for loop counters.
+const COUNTERS: &[&str] = &["i", "j", "k", "l", "m"];
+
+pub fn is_reserved(name: &str) -> bool {
+    RESERVED.contains(&name)
+}
+
+/// What a function is called in listings: the name compiled into its header,
+/// or one inferred from where its closure is stored. Inferred names display
+/// in brackets, since the source never spelled them out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionName {
+    pub name: String,
+    pub inferred: bool,
+}
+
+impl fmt::Display for FunctionName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.inferred {
+            true => write!(f, "[{}]", self.name),
+            false => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// Identifiers for everything the output declares. Parameters are `a0`,
+/// `a1`, ... in LoadParam order; locals and captured variables are named
+/// after how they are defined or used, and captured variables take their
+/// source name when the file has debug info. A parameter a closure captures
+/// shares its name with the slot holding it, both being one source
+/// variable. Apart from that no two names in a function are the same, none
+/// shadows a captured variable of an enclosing function or a global the
+/// program reads, and none is a reserved word.
+#[derive(Debug)]
+pub struct Names {
+    /// By function index, then parameter index, 0 being `this`.
+    parameters: Vec<Vec<String>>,
+    /// By function index, then the `VariableId` from `variables()`. `None`
+    /// for variables with nothing left defining them.
+    locals: Vec<Vec<Option<String>>>,
+    captured: HashMap<CapturedVariable, String>,
+    /// By function index.
+    functions: Vec<Option<FunctionName>>,
+}
+
+impl Names {
+    /// `functions` is indexed by function index and should be fully
+    /// restored, since locals are numbered by the variables they end with.
+    pub fn new(
+        functions: &[LiftedFunction],
+        program: &Program,
+        nesting: &Nesting,
+        scopes: &Scopes,
+        modules: &ModuleGraph,
+    ) -> Self {
+        let mut names = Self {
+            parameters: vec![Vec::new(); functions.len()],
+            locals: vec![Vec::new(); functions.len()],
+            captured: HashMap::new(),
+            functions: functions
+                .iter()
+                .map(|function| {
+                    Some(FunctionName {
+                        name: function.name(&program.bytecode)?,
+                        inferred: false,
+                    })
+                })
+                .collect(),
+        };
+
+        let mut globals = HashSet::new();
+        for function in functions {
+            for statement in function.blocks.iter().flat_map(|block| &block.statements) {
+                match statement {
+                    Statement::DeclareGlobalVar(name) => {
+                        globals.extend(program.bytecode.string(name.index()))
+                    }
+                    // Named imports are bound under the names they export.
+                    Statement::Import { names, .. } => globals.extend(
+                        names
+                            .iter()
+                            .filter_map(|name| program.bytecode.string(name.index())),
+                    ),
+                    _ => {}
+                }
+            }
+            // Globals the program writes count too, since assigning one is
+            // spelled with its bare name as well.
+            for_each_expression(function, &mut |expression| {
+                let member = match expression {
+                    Expression::Assignment { target, .. } => target.as_ref(),
+                    expression => expression,
+                };
+                if let Expression::Member {
+                    object,
+                    property: MemberProperty::Named(name),
+                } = member
+                {
+                    if matches!(object.as_ref(), Expression::Global) {
+                        globals.extend(program.bytecode.string(name.index()));
+                    }
+                }
+            });
+        }
+
+        // Parents come before their closures, which inherit the names of the
+        // captured variables they could refer to.
+        let root = program.bytecode.header.global_code_index;
+        let mut order = vec![root];
+        let mut position = 0;
+        while position < order.len() {
+            order.extend_from_slice(nesting.children(order[position]));
+            position += 1;
+        }
+        let mut seen = vec![false; functions.len()];
+        order.retain(|function| !std::mem::replace(&mut seen[*function as usize], true));
+        order.extend((0..functions.len() as u32).filter(|function| !seen[*function as usize]));
+
+        let mut inherited: Vec<HashSet<String>> = vec![HashSet::new(); functions.len()];
+        for index in order {
+            let function = &functions[index as usize];
+            let mut taken = match nesting.parent(index) {
+                Some(parent) => inherited[parent as usize].clone(),
+                None => HashSet::new(),
+            };
+            taken.extend(globals.iter().cloned());
+            // A module factory's parameters are printed under their Metro
+            // names, which its locals and closures must not shadow.
+            let is_factory = modules
+                .modules()
+                .iter()
+                .any(|module| module.factory == index);
+            if is_factory {
+                taken.extend(METRO_PARAMETERS.iter().map(|name| String::from(*name)));
+            }
+
+            let slot_names = program
+                .functions
+                .get(index as usize)
+                .map(|function| function.slot_names.as_slice())
+                .unwrap_or_default();
+            let namer = Namer {
+                function,
+                functions,
+                bytecode: &program.bytecode,
+                modules,
+                definitions: function.definitions(),
+            };
+            names.name_function(&namer, scopes, slot_names, &mut taken);
+
+            inherited[index as usize] = match nesting.parent(index) {
+                Some(parent) => inherited[parent as usize].clone(),
+                None => HashSet::new(),
+            };
+            if is_factory {
+                inherited[index as usize]
+                    .extend(METRO_PARAMETERS.iter().map(|name| String::from(*name)));
+            }
+            for variable in scopes.declared_in(index) {
+                if let Some(name) = names.captured.get(&variable) {
+                    inherited[index as usize].insert(name.clone());
+                }
+            }
+        }
+
+        for function in functions {
+            names.infer_function_names(function, &program.bytecode);
+        }
+        names
+    }
+
+    /// Names the anonymous closures `function` creates after the property or
+    /// captured variable they are stored in, or after the call they are a
+    /// known callback of. The first place a closure is stored wins, and no
+    /// two closures of one function get the same name.
+    fn infer_function_names(&mut self, function: &LiftedFunction, bytecode: &BytecodeFile) {
+        let definitions = function.definitions();
+        let closure = |expression: &Expression| match expression {
+            Expression::Closure { function, .. } | Expression::Class { function, .. } => {
+                Some(*function)
+            }
+            Expression::Value(value) => match definitions[*value]? {
+                Expression::Closure { function, .. } | Expression::Class { function, .. } => {
+                    Some(*function)
+                }
+                _ => None,
+            },
+            _ => None,
+        };
+        let property = |property: &MemberProperty| match property {
+            MemberProperty::Named(name) => bytecode
+                .string(name.index())
+                .filter(|name| !name.is_empty()),
+            _ => None,
+        };
+
+        let mut inferred = Vec::new();
+        for_each_expression(function, &mut |expression| match expression {
+            Expression::Assignment {
+                operator: AssignmentOperator::Assignment,
+                target,
+                value,
+            } => {
+                let name = match target.as_ref() {
+                    Expression::Member { property: key, .. } => property(key),
+                    target => captured_variable(target)
+                        .and_then(|variable| self.captured(variable))
+                        .map(String::from),
+                };
+                inferred.extend(closure(value).zip(name));
+            }
+            Expression::DefineProperty {
+                property: key,
+                value,
+                ..
+            } => inferred.extend(closure(value).zip(property(key))),
+            Expression::ObjectLiteral(properties) => {
+                for object_property in properties {
+                    if let ObjectProperty::Value { key, value }
+                    | ObjectProperty::Getter {
+                        key,
+                        function: value,
+                    }
+                    | ObjectProperty::Setter {
+                        key,
+                        function: value,
+                    } = object_property
+                    {
+                        inferred.extend(closure(value).zip(property(key)));
+                    }
+                }
+            }
+            Expression::Class { members, .. } => {
+                for member in members {
+                    inferred.extend(closure(&member.function).zip(property(&member.key)));
+                }
+            }
+            Expression::Call {
+                callee, arguments, ..
+            } => {
+                if let Expression::Member { property: key, .. } = callee.as_ref() {
+                    let callback = property(key)
+                        .filter(|name| CALLBACKS.contains(&name.as_str()))
+                        .map(|name| format!("{}Callback", name));
+                    if let Some(callback) = callback {
+                        for argument in arguments {
+                            inferred.extend(closure(argument).zip(Some(callback.clone())));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        });
+
+        // Inferred names are only shown in comments, where reserved words
+        // are fine.
+        let mut counts: HashMap<String, u32> = HashMap::new();
+        for (closure, name) in inferred {
+            if let Some(slot @ None) = self.functions.get_mut(closure as usize) {
+                let count = counts.entry(name.clone()).or_insert(0);
+                *count += 1;
+                *slot = Some(FunctionName {
+                    name: match *count {
+                        1 => name,
+                        count => format!("{}{}", name, count),
+                    },
+                    inferred: true,
+                });
+            }
+        }
+    }
+
+    fn name_function(
+        &mut self,
+        namer: &Namer,
+        scopes: &Scopes,
+        slot_names: &[String],
+        taken: &mut HashSet<String>,
+    ) {
+        let function = namer.function;
+        let index = function.index as usize;
+
+        // What each slot is first assigned, to name it and to tell which
+        // parameters it holds.
+        let mut stored: HashMap<CapturedVariable, &Expression> = HashMap::new();
+        for_each_expression(function, &mut |expression| {
+            if let Expression::Assignment {
+                operator: AssignmentOperator::Assignment,
+                target,
+                value,
+            } = expression
+            {
+                if let Some(variable) = captured_variable(target) {
+                    if variable.scope == function.index {
+                        stored.entry(variable).or_insert(value);
+                    }
+                }
+            }
+        });
+        let debug_name = |variable: CapturedVariable| {
+            slot_names
+                .get(variable.slot as usize)
+                .and_then(|name| identifier(name))
+        };
+
+        let mut parameters = vec![String::from("this")];
+        for parameter in 1..function.header.param_count + function.rest as u32 {
+            let slot = scopes.declared_in(function.index).find(|variable| {
+                matches!(stored.get(variable), Some(Expression::Parameter(stored)) if *stored == parameter)
+            });
+            let base = slot.and_then(debug_name).unwrap_or_else(|| {
+                match parameter == function.header.param_count {
+                    true => String::from("rest"),
+                    false => format!("a{}", parameter - 1),
+                }
+            });
+            let name = unique(&base, taken);
+            if let Some(slot) = slot {
+                self.captured.insert(slot, name.clone());
+            }
+            parameters.push(name);
+        }
+        self.parameters[index] = parameters;
+
+        for variable in scopes.declared_in(function.index) {
+            if self.captured.contains_key(&variable) {
+                continue;
+            }
+            let base = debug_name(variable)
+                .or_else(|| stored.get(&variable).and_then(|value| namer.hint(value)))
+                .unwrap_or_else(|| String::from("v"));
+            self.captured.insert(variable, unique(&base, taken));
+        }
+
+        let variables = function.variables();
+        let mut hints: Vec<Option<Hint>> = vec![None; variables.len()];
+        for (value, hint) in namer.hints() {
+            let hint_slot = &mut hints[variables.of(value)];
+            if hint_slot.as_ref().is_none_or(|current| hint < *current) {
+                *hint_slot = Some(hint);
+            }
+        }
+        // Values propagated into their uses are no longer declared anywhere.
+        let mut types: Vec<Option<Type>> = vec![None; variables.len()];
+        for block in &function.blocks {
+            let defined = block
+                .phis
+                .iter()
+                .map(|phi| phi.value)
+                .chain(block.statements.iter().flat_map(Statement::defs))
+                .chain(block.terminator.defs());
+            for value in defined {
+                let variable_type = &mut types[variables.of(value)];
+                *variable_type = Some(
+                    variable_type
+                        .unwrap_or(Type::Never)
+                        .join(function.types[value]),
+                );
+            }
+        }
+
+        self.locals[index] = hints
+            .into_iter()
+            .zip(types)
+            .map(|(hint, value_type)| {
+                Some(match (hint, value_type?) {
+                    (Some(Hint::Counter), _) => COUNTERS
+                        .iter()
+                        .find(|name| !taken.contains(**name))
+                        .map(|name| unique(name, taken))
+                        .unwrap_or_else(|| unique("i", taken)),
+                    (Some(Hint::Named(_, name)), _) => unique(&name, taken),
+                    (None, value_type) => unique(fallback(value_type), taken),
+                })
+            })
+            .collect();
+    }
+
+    pub fn parameter(&self, function: u32, parameter: u32) -> Option<&str> {
+        self.parameters
+            .get(function as usize)?
+            .get(parameter as usize)
+            .map(String::as_str)
+    }
+
+    pub fn local(&self, function: u32, variable: VariableId) -> Option<&str> {
+        self.locals
+            .get(function as usize)?
+            .get(variable)?
+            .as_deref()
+    }
+
+    pub fn captured(&self, variable: CapturedVariable) -> Option<&str> {
+        self.captured.get(&variable).map(String::as_str)
+    }
+
+    /// `None` for a function nothing gives a name.
+    pub fn function(&self, function: u32) -> Option<&FunctionName> {
+        self.functions.get(function as usize)?.as_ref()
+    }
+}
+
+/// How a local got its name, better ones first.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Hint {
+    /// A loop counter.
+    Counter,
+    /// Named after its definition at rank 0, after a use at rank 1.
+    Named(u8, String),
+}
+
+struct Namer<'a> {
+    function: &'a LiftedFunction,
+    functions: &'a [LiftedFunction],
+    bytecode: &'a BytecodeFile<'a>,
+    modules: &'a ModuleGraph,
+    definitions: Vec<Option<&'a Expression>>,
+}
+
+impl Namer<'_> {
+    /// Every hint the function gives about one of its values.
+    fn hints(&self) -> Vec<(ValueId, Hint)> {
+        let function = self.function;
+        let mut hints = Vec::new();
+
+        for block in &function.blocks {
+            for phi in &block.phis {
+                let counts = phi.arguments.iter().any(|(_, argument)| {
+                    self.definitions[*argument]
+                        .is_some_and(|definition| counts_up(definition, phi.value))
+                });
+                if counts {
+                    hints.push((phi.value, Hint::Counter));
+                }
+            }
+
+            for statement in &block.statements {
+                let named =
+                    |value: ValueId, name: &str| (value, Hint::Named(0, String::from(name)));
+                match statement {
+                    Statement::Assign { value, expression } => {
+                        if let Some(name) = self.hint(expression) {
+                            hints.push((*value, Hint::Named(0, name)));
+                        }
+                    }
+                    Statement::Catch(value) => hints.push(named(*value, "e")),
+                    Statement::Import {
+                        module,
+                        default,
+                        namespace,
+                        ..
+                    } => {
+                        let name = module_name(&self.modules.path(*module));
+                        for value in default.iter().chain(namespace) {
+                            if let Some(name) = &name {
+                                hints.push((*value, Hint::Named(0, name.clone())));
+                            }
+                        }
+                    }
+                    Statement::PropertyNames { names, .. } => hints.push(named(*names, "keys")),
+                    Statement::NextPropertyName { property, .. } => {
+                        hints.push(named(*property, "key"))
+                    }
+                    Statement::IteratorBegin { iterator, .. } => {
+                        hints.push(named(*iterator, "iterator"))
+                    }
+                    Statement::DestructureObject {
+                        properties, rest, ..
+                    } => {
+                        for (key, value) in properties {
+                            if let MemberProperty::Named(name) = key {
+                                let name =
+                                    self.string(name.index()).and_then(|name| identifier(&name));
+                                if let Some(name) = name {
+                                    hints.push(named(*value, &name));
+                                }
+                            }
+                        }
+                        hints.push(named(*rest, "rest"));
+                    }
+                    _ => {}
+                }
+            }
+            match &block.terminator {
+                Terminator::ForIn { property, .. } => {
+                    hints.push((*property, Hint::Named(0, String::from("key"))))
+                }
+                Terminator::ForOf { value, .. } => {
+                    hints.push((*value, Hint::Named(0, String::from("item"))))
+                }
+                _ => {}
+            }
+        }
+
+        // `object.name = value` names the value after the property.
+        for_each_expression(function, &mut |expression| {
+            if let Expression::Assignment {
+                operator: AssignmentOperator::Assignment,
+                target,
+                value,
+            } = expression
+            {
+                if let (
+                    Expression::Member {
+                        property: MemberProperty::Named(name),
+                        ..
+                    },
+                    Expression::Value(value),
+                ) = (target.as_ref(), value.as_ref())
+                {
+                    if let Some(name) = self.string(name.index()).and_then(|name| identifier(&name))
+                    {
+                        hints.push((*value, Hint::Named(1, name)));
+                    }
+                }
+            }
+        });
+
+        hints
+    }
+
+    /// A name for what `expression` evaluates to.
+    fn hint(&self, expression: &Expression) -> Option<String> {
+        match expression {
+            Expression::Member {
+                property: MemberProperty::Named(name),
+                ..
+            } => identifier(&self.string(name.index())?),
+            Expression::Call {
+                callee, arguments, ..
+            } => match callee.as_ref() {
+                Expression::Builtin(Builtins::HermesBuiltinRequireFast) => {
+                    Some(String::from("module"))
+                }
+                callee if self.callee_name(callee).as_deref() == Some("require") => {
+                    match arguments.first() {
+                        Some(Expression::Literal(Literal::String(path))) => {
+                            module_name(&self.string(path.index())?)
+                        }
+                        _ => Some(String::from("module")),
+                    }
+                }
+                callee => {
+                    let name = self.callee_name(callee)?;
+                    let rest = name.strip_prefix("get")?;
+                    match rest.chars().next()?.is_ascii_uppercase() {
+                        true => identifier(&lower_first(rest)),
+                        false => None,
+                    }
+                }
+            },
+            Expression::Construct { callee, .. } => {
+                identifier(&lower_first(&self.callee_name(callee)?))
+            }
+            Expression::Closure { function, .. } | Expression::Class { function, .. } => {
+                identifier(
+                    &self
+                        .functions
+                        .get(*function as usize)?
+                        .name(self.bytecode)?,
+                )
+            }
+            Expression::ArrayLiteral(_) | Expression::NewArray { .. } => {
+                Some(String::from("array"))
+            }
+            Expression::ObjectLiteral(_) | Expression::NewObject { .. } => {
+                Some(String::from("object"))
+            }
+            Expression::Template { .. } => Some(String::from("text")),
+            Expression::Global => Some(String::from("globalObject")),
+            Expression::Require(module) => module_name(&self.modules.path(*module)),
+            _ => None,
+        }
+    }
+
+    /// The name a callee is referred to by, like `b` for `a.b`.
+    fn callee_name(&self, callee: &Expression) -> Option<String> {
+        match callee {
+            Expression::Member {
+                property: MemberProperty::Named(name),
+                ..
+            } => self.string(name.index()),
+            _ => None,
+        }
+    }
+
+    fn string(&self, index: u32) -> Option<String> {
+        self.bytecode.string(index).filter(|name| !name.is_empty())
+    }
+}
+
+/// Whether `definition` adds or subtracts one from `phi`, the way a loop
+/// counter steps.
+fn counts_up(definition: &Expression, phi: ValueId) -> bool {
+    let is_phi = |expression: &Expression| match expression {
+        Expression::Value(value) => *value == phi,
+        Expression::Conversion {
+            conversion: Conversion::ToNumber,
+            argument,
+        } => matches!(argument.as_ref(), Expression::Value(value) if *value == phi),
+        _ => false,
+    };
+    let is_one = |expression: &Expression| {
+        matches!(
+            expression,
+            Expression::Literal(Literal::Number(Number::UInt(1)))
+                | Expression::Literal(Literal::Number(Number::Int(1)))
+        )
+    };
+    match definition {
+        Expression::Update { argument, .. } => is_phi(argument),
+        Expression::Assignment {
+            operator: AssignmentOperator::Addition | AssignmentOperator::Subtraction,
+            target,
+            value,
+        } => is_phi(target) && is_one(value),
+        Expression::Binary {
+            operator: BinaryOperator::Addition | BinaryOperator::Subtraction,
+            left,
+            right,
+        } => is_phi(left) && is_one(right),
+        _ => false,
+    }
+}
+
+fn captured_variable(expression: &Expression) -> Option<CapturedVariable> {
+    match expression {
+        Expression::Captured(variable) => Some(*variable),
+        expression => Scopes::slot_of(expression),
+    }
+}
+
+fn for_each_expression<'a>(function: &'a LiftedFunction, visit: &mut impl FnMut(&'a Expression)) {
+    fn walk<'a>(expression: &'a Expression, visit: &mut impl FnMut(&'a Expression)) {
+        visit(expression);
+        if let Expression::Assignment { target, .. } = expression {
+            if !matches!(target.as_ref(), Expression::Member { .. }) {
+                visit(target);
+            }
+        }
+        for child in expression.children() {
+            walk(child, visit);
+        }
+    }
+
+    for block in &function.blocks {
+        let expressions = block
+            .statements
+            .iter()
+            .flat_map(|statement| statement.expressions())
+            .chain(block.terminator.expressions());
+        for expression in expressions {
+            walk(expression, visit);
+        }
+    }
+}
+
+fn fallback(value_type: Type) -> &'static str {
+    match value_type {
+        Type::Int32 | Type::Number => "n",
+        Type::String => "str",
+        Type::Boolean => "flag",
+        Type::Object => "object",
+        _ => "v",
+    }
+}
+
+/// `base` when it is free, otherwise the first free `base2`, `base3`, ...
+/// The name returned is marked as taken.
+fn unique(base: &str, taken: &mut HashSet<String>) -> String {
+    let mut name = String::from(base);
+    let mut suffix = 2;
+    while taken.contains(&name) || is_reserved(&name) {
+        name = format!("{}{}", base, suffix);
+        suffix += 1;
+    }
+    taken.insert(name.clone());
+    name
+}
+
+/// `text` as a camelCase identifier, `None` when nothing of it is usable.
+fn identifier(text: &str) -> Option<String> {
+    let mut name = String::new();
+    let mut upper = false;
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '$' {
+            match upper && !name.is_empty() {
+                true => name.push(c.to_ascii_uppercase()),
+                false => name.push(c),
+            }
+            upper = false;
+        } else {
+            upper = true;
+        }
+    }
+    if name.is_empty() || name.len() > 32 {
+        return None;
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    Some(name)
+}
+
+fn lower_first(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// `fooBar` for `./lib/foo-bar.js`, `pkg` for `@scope/pkg`.
+fn module_name(path: &str) -> Option<String> {
+    let last = path.trim_end_matches('/').rsplit('/').next()?;
+    let stem = match last.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => last,
+    };
+    identifier(stem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// `g(o.property)` with a call between the read and `g`, so the read
+    /// needs a local.
+    fn read_before_a_call(property: &str) -> String {
+        let source = format!(
+            r#"
+                GetGlobalObject r0
+                GetById r1, r0, 1, "o"
+                GetById r2, r1, 2, "{}"
+                GetGlobalObject r0
+                GetById r3, r0, 3, "f"
+                LoadConstUndefined r7
+                Call1 r4, r3, r7
+                GetGlobalObject r0
+                GetById r5, r0, 4, "g"
+                LoadConstUndefined r7
+                Call2 r6, r5, r7, r2
+                LoadConstUndefined r8
+                Ret r8
+                "#,
+            property
+        );
+        testing::decompile(&[testing::function(source)])
+    }
+
+    #[test]
+    fn local_is_named_after_its_property() {
+        let text = read_before_a_call("size");
+        assert_eq!(text, "let size;\nsize = o.size;\nf();\ng(size);\n");
+    }
+
+    #[test]
+    fn reserved_word_gets_a_suffix() {
+        let text = read_before_a_call("class");
+        assert_eq!(text, "let class2;\nclass2 = o.class;\nf();\ng(class2);\n");
+    }
+
+    #[test]
+    fn global_read_is_not_shadowed() {
+        let text = read_before_a_call("g");
+        assert_eq!(text, "let g2;\ng2 = o.g;\nf();\ng(g2);\n");
+    }
+
+    #[test]
+    fn sibling_callbacks_get_distinct_names() {
+        let text = testing::decompile(&[
+            testing::function(
+                r#"
+                CreateEnvironment r0
+                GetGlobalObject r1
+                GetById r2, r1, 1, "p"
+                GetById r3, r2, 2, "then"
+                CreateClosure r4, r0, 1
+                Call2 r5, r3, r2, r4
+                GetById r3, r2, 2, "then"
+                CreateClosure r4, r0, 2
+                Call2 r5, r3, r2, r4
+                LoadConstUndefined r6
+                Ret r6
+                "#,
+            ),
+            testing::function("LoadConstUndefined r0\nRet r0"),
+            testing::function("LoadConstUndefined r0\nRet r0"),
+        ]);
+        assert_eq!(
+            text,
+            concat!(
+                "let p2;\n",
+                "p2 = p;\n",
+                "p2.then(function /* thenCallback */ () {});\n",
+                "p2.then(function /* thenCallback2 */ () {});\n"
+            )
+        );
+    }
+
+    #[test]
+    fn unique_skips_taken_and_reserved_names() {
+        let mut taken = HashSet::new();
+        assert_eq!(unique("default", &mut taken), "default2");
+        assert_eq!(unique("default", &mut taken), "default3");
+        assert_eq!(unique("n", &mut taken), "n");
+        assert_eq!(unique("n", &mut taken), "n2");
+    }
+
+    #[test]
+    fn identifiers_from_text() {
+        assert_eq!(identifier("foo-bar baz").as_deref(), Some("fooBarBaz"));
+        assert_eq!(identifier("2d").as_deref(), Some("_2d"));
+        assert_eq!(identifier("--"), None);
+        assert_eq!(module_name("./lib/foo-bar.js").as_deref(), Some("fooBar"));
+        assert_eq!(module_name("@scope/pkg").as_deref(), Some("pkg"));
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::VecDeque;
+
+use super::{Expression, LiftedFunction};
+
+/// Where every function is created. A closure is printed at the point its
+/// parent creates it, so together these form the shape of the source file.
+#[derive(Debug)]
+pub struct Nesting {
+    parents: Vec<Option<u32>>,
+    children: Vec<Vec<u32>>,
+    /// The function whose environment each closure captured.
+    captured: Vec<Option<u32>>,
+}
+
+impl Nesting {
+    /// `functions` is indexed by function index, `root` is the global code.
+    pub fn new(functions: &[LiftedFunction], root: u32) -> Self {
+        let mut nesting = Self {
+            parents: vec![None; functions.len()],
+            children: vec![Vec::new(); functions.len()],
+            captured: vec![None; functions.len()],
+        };
+
+        let mut visited = vec![false; functions.len()];
+        let mut queue = VecDeque::new();
+        if let Some(seen) = visited.get_mut(root as usize) {
+            *seen = true;
+            queue.push_back(root);
+        }
+
+        // Breadth first, so a parent's own scope is known before its closures'.
+        while let Some(parent) = queue.pop_front() {
+            let function = &functions[parent as usize];
+            let definitions = function.definitions();
+
+            let mut closures = Vec::new();
+            for block in &function.blocks {
+                let expressions = block
+                    .statements
+                    .iter()
+                    .flat_map(|statement| statement.expressions())
+                    .chain(block.terminator.expressions());
+                for expression in expressions {
+                    collect_closures(expression, &mut closures);
+                }
+            }
+
+            for (child, environment) in closures {
+                let is_new = visited.get(child as usize) == Some(&false);
+                if !is_new {
+                    continue;
+                }
+                visited[child as usize] = true;
+
+                nesting.parents[child as usize] = Some(parent);
+                nesting.children[parent as usize].push(child);
+                nesting.captured[child as usize] =
+                    nesting.resolve(function, &definitions, environment);
+                queue.push_back(child);
+            }
+        }
+
+        nesting
+    }
+
+    pub fn parent(&self, function: u32) -> Option<u32> {
+        self.parents[function as usize]
+    }
+
+    /// Closures created by `function`, in the order they first appear.
+    pub fn children(&self, function: u32) -> &[u32] {
+        &self.children[function as usize]
+    }
+
+    /// The function whose environment the closure of `function` captured.
+    pub fn captured_scope(&self, function: u32) -> Option<u32> {
+        self.captured[function as usize]
+    }
+
+    /// The function owning the environment GetEnvironment `level` yields
+    /// inside `function`. Level 0 is the captured environment, and each level
+    /// above it is the environment that one captured in turn.
+    pub fn environment_owner(&self, function: u32, level: u8) -> Option<u32> {
+        let mut owner = self.captured_scope(function)?;
+        for _ in 0..level {
+            owner = self.captured_scope(owner)?;
+        }
+        Some(owner)
+    }
+
+    /// The function owning the environment `expression` evaluates to.
+    pub fn resolve(
+        &self,
+        function: &LiftedFunction,
+        definitions: &[Option<&Expression>],
+        expression: &Expression,
+    ) -> Option<u32> {
+        match expression {
+            Expression::Scope(owner) => Some(*owner),
+            Expression::NewEnvironment => Some(function.index),
+            Expression::Environment { level } => self.environment_owner(function.index, *level),
+            Expression::Value(value) => {
+                let definition = (*definitions.get(*value)?)?;
+                self.resolve(function, definitions, definition)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn collect_closures<'a>(expression: &'a Expression, closures: &mut Vec<(u32, &'a Expression)>) {
+    if let Expression::Closure {
+        function,
+        environment,
+        ..
+    } = expression
+    {
+        closures.push((*function, environment));
+    }
+    for child in expression.children() {
+        collect_closures(child, closures);
+    }
+}
@@ -0,0 +1,127 @@
+use super::{Expression, LiftedFunction};
+
+/// A slot of the environment some function creates. It reads like a local of
+/// that function which its closures capture.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CapturedVariable {
+    /// The function whose CreateEnvironment holds the slot.
+    pub scope: u32,
+    pub slot: u32,
+}
+
+/// Every captured variable in the program, numbered so that each
+/// (scope, slot) pair is one stable id.
+#[derive(Debug)]
+pub struct Scopes {
+    first: Vec<usize>,
+    sizes: Vec<u32>,
+    accessed_by: Vec<Vec<u32>>,
+}
+
+impl Scopes {
+    /// Slots come from each function's `environment_size`. `functions` is
+    /// indexed by function index and should have its scopes resolved.
+    pub fn new(functions: &[LiftedFunction]) -> Self {
+        let sizes: Vec<u32> = functions
+            .iter()
+            .map(|function| u32::from(function.header.environment_size))
+            .collect();
+
+        let mut first = Vec::with_capacity(sizes.len());
+        let mut total = 0;
+        for size in &sizes {
+            first.push(total);
+            total += *size as usize;
+        }
+
+        let mut scopes = Self {
+            first,
+            sizes,
+            accessed_by: vec![Vec::new(); total],
+        };
+
+        for function in functions {
+            let expressions = function.blocks.iter().flat_map(|block| {
+                block
+                    .statements
+                    .iter()
+                    .flat_map(|statement| statement.expressions())
+                    .chain(block.terminator.expressions())
+            });
+            for expression in expressions {
+                scopes.record_accesses(function.index, expression);
+            }
+        }
+
+        scopes
+    }
+
+    fn record_accesses(&mut self, function: u32, expression: &Expression) {
+        let target = match expression {
+            Expression::Assignment { target, .. } => Self::slot_of(target),
+            _ => None,
+        };
+        if let Some(variable) = Self::slot_of(expression).or(target) {
+            if let Some(id) = self.id(variable) {
+                if !self.accessed_by[id].contains(&function) {
+                    self.accessed_by[id].push(function);
+                }
+            }
+        }
+        for child in expression.children() {
+            self.record_accesses(function, child);
+        }
+    }
+
+    /// The variable an `EnvironmentSlot` of a resolved scope refers to.
+    pub fn slot_of(expression: &Expression) -> Option<CapturedVariable> {
+        match expression {
+            Expression::EnvironmentSlot { environment, slot } => match environment.as_ref() {
+                Expression::Scope(scope) => Some(CapturedVariable {
+                    scope: *scope,
+                    slot: *slot,
+                }),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// `None` when the slot lies outside the scope's environment.
+    pub fn id(&self, variable: CapturedVariable) -> Option<usize> {
+        let size = *self.sizes.get(variable.scope as usize)?;
+        match variable.slot < size {
+            true => Some(self.first[variable.scope as usize] + variable.slot as usize),
+            false => None,
+        }
+    }
+
+    /// The variables `function` declares, in slot order.
+    pub fn declared_in(&self, function: u32) -> impl Iterator<Item = CapturedVariable> {
+        let size = self
+            .sizes
+            .get(function as usize)
+            .copied()
+            .unwrap_or_default();
+        (0..size).map(move |slot| CapturedVariable {
+            scope: function,
+            slot,
+        })
+    }
+
+    /// Functions reading or writing the variable, its own scope included.
+    pub fn accessed_by(&self, variable: CapturedVariable) -> &[u32] {
+        match self.id(variable) {
+            Some(id) => &self.accessed_by[id],
+            None => &[],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.accessed_by.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accessed_by.is_empty()
+    }
+}
@@ -0,0 +1,369 @@
+use crate::{
+    analysis::{BlockId, ValueId},
+    ir::StringIndex,
+};
+
+use super::{Expression, MemberProperty};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    /// `value = expression`, the only way a value is defined outside a phi.
+    Assign {
+        value: ValueId,
+        expression: Expression,
+    },
+    /// Evaluated for its side effects alone.
+    Expression(Expression),
+    Catch(ValueId),
+    DeclareGlobalVar(StringIndex),
+    ThrowIfUndefined(Expression),
+    PropertyNames {
+        names: ValueId,
+        index: ValueId,
+        size: ValueId,
+        object: Expression,
+    },
+    NextPropertyName {
+        property: ValueId,
+        index: ValueId,
+        names: Expression,
+        object: Expression,
+        size: Expression,
+    },
+    IteratorBegin {
+        iterator: ValueId,
+        next: ValueId,
+        source: Expression,
+    },
+    IteratorNext {
+        result: ValueId,
+        iterator: ValueId,
+        previous: Expression,
+        next: Expression,
+    },
+    IteratorClose {
+        iterator: Expression,
+        ignore_inner_exception: bool,
+    },
+    /// `[a, b, ...rest] = source`, one value per element in order.
+    Destructure {
+        elements: Vec<ValueId>,
+        rest: Option<ValueId>,
+        source: Expression,
+    },
+    /// `({ a: first, b: second, ...rest } = source)`, one value per key in
+    /// order.
+    DestructureObject {
+        properties: Vec<(MemberProperty, ValueId)>,
+        rest: ValueId,
+        source: Expression,
+    },
+    /// `import default, * as namespace, { names } from "module"`, each part
+    /// optional. The named bindings are read through `Expression::Imported`.
+    Import {
+        module: u32,
+        default: Option<ValueId>,
+        namespace: Option<ValueId>,
+        names: Vec<StringIndex>,
+    },
+    ExportDefault(Expression),
+    /// `export * from "module"`
+    ExportAll(u32),
+    StartGenerator,
+    SaveGenerator {
+        resume: BlockId,
+    },
+    ResumeGenerator {
+        result: ValueId,
+        is_return: ValueId,
+    },
+    CompleteGenerator,
+    Debugger,
+}
+
+impl Statement {
+    /// Values written by the statement.
+    pub fn defs(&self) -> Vec<ValueId> {
+        match self {
+            Statement::Assign { value, .. } | Statement::Catch(value) => vec![*value],
+            Statement::PropertyNames {
+                names, index, size, ..
+            } => vec![*names, *index, *size],
+            Statement::NextPropertyName {
+                property, index, ..
+            } => vec![*property, *index],
+            Statement::IteratorBegin { iterator, next, .. } => vec![*iterator, *next],
+            Statement::IteratorNext {
+                result, iterator, ..
+            } => vec![*result, *iterator],
+            Statement::ResumeGenerator { result, is_return } => vec![*result, *is_return],
+            Statement::Destructure { elements, rest, .. } => {
+                elements.iter().chain(rest.iter()).copied().collect()
+            }
+            Statement::DestructureObject {
+                properties, rest, ..
+            } => properties
+                .iter()
+                .map(|(_, value)| *value)
+                .chain([*rest])
+                .collect(),
+            Statement::Import {
+                default, namespace, ..
+            } => default.iter().chain(namespace.iter()).copied().collect(),
+            _ => vec![],
+        }
+    }
+
+    pub fn defs_mut(&mut self) -> Vec<&mut ValueId> {
+        match self {
+            Statement::Assign { value, .. } | Statement::Catch(value) => vec![value],
+            Statement::PropertyNames {
+                names, index, size, ..
+            } => vec![names, index, size],
+            Statement::NextPropertyName {
+                property, index, ..
+            } => vec![property, index],
+            Statement::IteratorBegin { iterator, next, .. } => vec![iterator, next],
+            Statement::IteratorNext {
+                result, iterator, ..
+            } => vec![result, iterator],
+            Statement::ResumeGenerator { result, is_return } => vec![result, is_return],
+            Statement::Destructure { elements, rest, .. } => {
+                elements.iter_mut().chain(rest.iter_mut()).collect()
+            }
+            Statement::DestructureObject {
+                properties, rest, ..
+            } => properties
+                .iter_mut()
+                .map(|(_, value)| value)
+                .chain([rest])
+                .collect(),
+            Statement::Import {
+                default, namespace, ..
+            } => default.iter_mut().chain(namespace.iter_mut()).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Expressions read by the statement, in evaluation order.
+    pub fn expressions(&self) -> Vec<&Expression> {
+        match self {
+            Statement::Assign { expression, .. }
+            | Statement::Expression(expression)
+            | Statement::ThrowIfUndefined(expression)
+            | Statement::ExportDefault(expression) => vec![expression],
+            Statement::PropertyNames { object, .. } => vec![object],
+            Statement::NextPropertyName {
+                names,
+                object,
+                size,
+                ..
+            } => vec![names, object, size],
+            Statement::IteratorBegin { source, .. } => vec![source],
+            Statement::IteratorNext { previous, next, .. } => vec![previous, next],
+            Statement::IteratorClose { iterator, .. } => vec![iterator],
+            Statement::Destructure { source, .. } => vec![source],
+            Statement::DestructureObject {
+                properties, source, ..
+            } => {
+                let keys = properties.iter().filter_map(|(key, _)| match key {
+                    MemberProperty::Computed(key) => Some(key.as_ref()),
+                    _ => None,
+                });
+                std::iter::once(source).chain(keys).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    pub fn expressions_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Statement::Assign { expression, .. }
+            | Statement::Expression(expression)
+            | Statement::ThrowIfUndefined(expression)
+            | Statement::ExportDefault(expression) => vec![expression],
+            Statement::PropertyNames { object, .. } => vec![object],
+            Statement::NextPropertyName {
+                names,
+                object,
+                size,
+                ..
+            } => vec![names, object, size],
+            Statement::IteratorBegin { source, .. } => vec![source],
+            Statement::IteratorNext { previous, next, .. } => vec![previous, next],
+            Statement::IteratorClose { iterator, .. } => vec![iterator],
+            Statement::Destructure { source, .. } => vec![source],
+            Statement::DestructureObject {
+                properties, source, ..
+            } => {
+                let keys = properties.iter_mut().filter_map(|(key, _)| match key {
+                    MemberProperty::Computed(key) => Some(key.as_mut()),
+                    _ => None,
+                });
+                std::iter::once(source).chain(keys).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Whether the statement does anything besides defining values.
+    pub fn has_side_effects(&self) -> bool {
+        match self {
+            Statement::Assign { expression, .. } => expression.has_side_effects(),
+            Statement::Expression(expression) => expression.has_side_effects(),
+            _ => true,
+        }
+    }
+
+    /// Whether the statement reads or writes memory that an expression moved
+    /// across it could observe.
+    pub fn touches_memory(&self) -> (bool, bool) {
+        match self {
+            Statement::Assign { expression, .. } | Statement::Expression(expression) => {
+                (expression.reads_memory(), expression.has_side_effects())
+            }
+            Statement::DeclareGlobalVar(_) | Statement::Debugger => (false, false),
+            _ => (true, true),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Terminator {
+    Goto(BlockId),
+    /// Goes to `target` when `condition` is truthy.
+    Branch {
+        condition: Expression,
+        target: BlockId,
+        fallthrough: BlockId,
+    },
+    Switch {
+        value: Expression,
+        minimum: u32,
+        targets: Vec<BlockId>,
+        default: BlockId,
+    },
+    Return(Expression),
+    Throw(Expression),
+    /// `for (property in object)`: takes the next key into `property` and
+    /// enters `body`, or leaves for `exit` once there are none left.
+    ForIn {
+        property: ValueId,
+        object: Expression,
+        body: BlockId,
+        exit: BlockId,
+    },
+    /// `for (value of iterable)`, leaving for `exit` once the iterator is
+    /// done. The iterator is closed on any other way out.
+    ForOf {
+        value: ValueId,
+        iterable: Expression,
+        body: BlockId,
+        exit: BlockId,
+    },
+    Unreachable,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Goto(target) => vec![*target],
+            Terminator::Branch {
+                target,
+                fallthrough,
+                ..
+            } => vec![*target, *fallthrough],
+            Terminator::Switch {
+                targets, default, ..
+            } => {
+                let mut successors = targets.clone();
+                successors.push(*default);
+                successors
+            }
+            Terminator::ForIn { body, exit, .. } | Terminator::ForOf { body, exit, .. } => {
+                vec![*body, *exit]
+            }
+            Terminator::Return(_) | Terminator::Throw(_) | Terminator::Unreachable => vec![],
+        }
+    }
+
+    pub fn successors_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Terminator::Goto(target) => vec![target],
+            Terminator::Branch {
+                target,
+                fallthrough,
+                ..
+            } => vec![target, fallthrough],
+            Terminator::Switch {
+                targets, default, ..
+            } => {
+                let mut successors: Vec<&mut BlockId> = targets.iter_mut().collect();
+                successors.push(default);
+                successors
+            }
+            Terminator::ForIn { body, exit, .. } | Terminator::ForOf { body, exit, .. } => {
+                vec![body, exit]
+            }
+            Terminator::Return(_) | Terminator::Throw(_) | Terminator::Unreachable => vec![],
+        }
+    }
+
+    /// Values the terminator defines on its way out.
+    pub fn defs(&self) -> Vec<ValueId> {
+        match self {
+            Terminator::ForIn { property, .. } => vec![*property],
+            Terminator::ForOf { value, .. } => vec![*value],
+            _ => vec![],
+        }
+    }
+
+    pub fn expressions(&self) -> Vec<&Expression> {
+        match self {
+            Terminator::Branch { condition, .. } => vec![condition],
+            Terminator::Switch { value, .. } => vec![value],
+            Terminator::Return(value) | Terminator::Throw(value) => vec![value],
+            Terminator::ForIn { object, .. } => vec![object],
+            Terminator::ForOf { iterable, .. } => vec![iterable],
+            _ => vec![],
+        }
+    }
+
+    pub fn expressions_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Terminator::Branch { condition, .. } => vec![condition],
+            Terminator::Switch { value, .. } => vec![value],
+            Terminator::Return(value) | Terminator::Throw(value) => vec![value],
+            Terminator::ForIn { object, .. } => vec![object],
+            Terminator::ForOf { iterable, .. } => vec![iterable],
+            _ => vec![],
+        }
+    }
+}
+
+/// A `try` rebuilt from the exception table. Its blocks stay in the function,
+/// this only records which clause each of them belongs to.
+#[derive(Debug, Clone)]
+pub struct TryStatement {
+    /// The blocks of `try { }`, its entry first.
+    pub body: Vec<BlockId>,
+    pub catch: Option<CatchClause>,
+    pub finally: Option<FinallyClause>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CatchClause {
+    /// The value the handler's `Catch` binds, `None` for `catch { }`.
+    pub parameter: Option<ValueId>,
+    /// The handler's blocks, the one starting with `Catch` first.
+    pub blocks: Vec<BlockId>,
+}
+
+/// The handler the compiler runs `finally` from when an exception escapes.
+/// Its blocks end by rethrowing `exception`, which the source leaves implicit.
+/// Copies of the body on the other exits have been removed.
+#[derive(Debug, Clone)]
+pub struct FinallyClause {
+    pub exception: ValueId,
+    pub blocks: Vec<BlockId>,
+}
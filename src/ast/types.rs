@@ -0,0 +1,245 @@
+use crate::{
+    analysis::ValueId,
+    builtins::Builtins,
+    ir::{AssignmentOperator, BinaryOperator, Conversion, Literal, Number, UnaryOperator},
+};
+
+use super::{Expression, LiftedFunction, LogicalOperator};
+
+/// What a value is known to hold. `Never` is below everything and `Unknown`
+/// above, `Int32` is a `Number`; the other types are unrelated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Type {
+    /// Nothing reaches the value, or nothing has yet.
+    Never,
+    Int32,
+    Number,
+    String,
+    Boolean,
+    Object,
+    /// `undefined` or `null`.
+    Nullish,
+    Unknown,
+}
+
+impl Type {
+    /// The least type holding both.
+    pub fn join(self, other: Self) -> Self {
+        use Type::*;
+
+        match (self, other) {
+            (Never, other) | (other, Never) => other,
+            (Int32, Number) | (Number, Int32) => Number,
+            (first, second) if first == second => first,
+            _ => Unknown,
+        }
+    }
+
+    /// The greatest type both hold.
+    pub fn meet(self, other: Self) -> Self {
+        use Type::*;
+
+        match (self, other) {
+            (Unknown, other) | (other, Unknown) => other,
+            (Int32, Number) | (Number, Int32) => Int32,
+            (first, second) if first == second => first,
+            _ => Never,
+        }
+    }
+
+    pub fn is_number(self) -> bool {
+        matches!(self, Type::Int32 | Type::Number)
+    }
+
+    /// The type `expression` evaluates to, given the type of every value.
+    pub fn of(expression: &Expression, value: &dyn Fn(ValueId) -> Type) -> Self {
+        use Expression as E;
+
+        let of = |expression: &Expression| Self::of(expression, value);
+        match expression {
+            E::Value(current) => value(*current),
+            E::Literal(literal) => Self::of_literal(literal),
+            E::Unary { operator, .. } => match operator {
+                UnaryOperator::Negation => Type::Number,
+                UnaryOperator::LogicalNot => Type::Boolean,
+                UnaryOperator::BitwiseNot => Type::Int32,
+                UnaryOperator::TypeOf => Type::String,
+            },
+            E::Binary {
+                operator,
+                left,
+                right,
+            } => Self::of_binary(*operator, of(left), of(right)),
+            E::Conversion {
+                conversion,
+                argument,
+            } => match conversion {
+                Conversion::ToNumber => match of(argument) {
+                    Type::Int32 => Type::Int32,
+                    Type::Never => Type::Never,
+                    _ => Type::Number,
+                },
+                Conversion::ToInt32 => Type::Int32,
+                Conversion::ToString => Type::String,
+            },
+            E::Logical {
+                operator,
+                left,
+                right,
+            } => match (operator, of(left)) {
+                (LogicalOperator::NullishCoalescing, Type::Nullish) => of(right),
+                (_, left) => left.join(of(right)),
+            },
+            E::Conditional {
+                consequent,
+                alternate,
+                ..
+            } => of(consequent).join(of(alternate)),
+            E::Assignment {
+                operator,
+                target,
+                value,
+            } => match binary_operator(*operator) {
+                Some(operator) => Self::of_binary(operator, of(target), of(value)),
+                None => of(value),
+            },
+            E::Update { .. } => Type::Number,
+            E::Delete { .. } => Type::Boolean,
+            E::Call { callee, .. } => match callee.as_ref() {
+                E::Builtin(builtin) => Self::returned_by(*builtin),
+                _ => Type::Unknown,
+            },
+            E::Template { .. } => Type::String,
+            E::ArgumentsLength => Type::Int32,
+            E::Global
+            | E::CoerceThis(_)
+            | E::NewObject { .. }
+            | E::NewArray { .. }
+            | E::Construct { .. }
+            | E::Builtin(_)
+            | E::Closure { .. }
+            | E::Class { .. }
+            | E::ArrayLiteral(_)
+            | E::ObjectLiteral(_)
+            | E::RestParameter(_)
+            | E::CreateThis { .. }
+            | E::SelectObject { .. } => Type::Object,
+            _ => Type::Unknown,
+        }
+    }
+
+    fn of_literal(literal: &Literal) -> Self {
+        match literal {
+            Literal::Number(Number::UInt(_)) | Literal::Number(Number::Int(_)) => Type::Int32,
+            Literal::Number(Number::Double(number)) => {
+                let is_int32 = number.fract() == 0.0
+                    && *number >= f64::from(i32::MIN)
+                    && *number <= f64::from(i32::MAX)
+                    && !(*number == 0.0 && number.is_sign_negative());
+                match is_int32 {
+                    true => Type::Int32,
+                    false => Type::Number,
+                }
+            }
+            Literal::String(_) => Type::String,
+            Literal::Boolean(_) => Type::Boolean,
+            Literal::RegExp(_) => Type::Object,
+            Literal::Null | Literal::Undefined => Type::Nullish,
+        }
+    }
+
+    fn of_binary(operator: BinaryOperator, left: Self, right: Self) -> Self {
+        use BinaryOperator::*;
+
+        match operator {
+            Equality | InEquality | Identity | NonIdentity | LessThan | LessThanEqual
+            | GreaterThan | GreaterThanEqual | InstanceOf | In => Type::Boolean,
+            LeftShift | RightShift | BitwiseAnd | BitwiseOr | BitwiseXor => Type::Int32,
+            UnsignedRightShift | Subtraction | Multiplication | Division | Remainder => {
+                Type::Number
+            }
+            // Objects convert to whatever their valueOf or toString returns.
+            Addition => match (left, right) {
+                (Type::String, _) | (_, Type::String) => Type::String,
+                (Type::Never, _) | (_, Type::Never) => Type::Never,
+                (Type::Object, _) | (_, Type::Object) | (Type::Unknown, _) | (_, Type::Unknown) => {
+                    Type::Unknown
+                }
+                _ => Type::Number,
+            },
+        }
+    }
+
+    fn returned_by(builtin: Builtins) -> Self {
+        use Builtins::*;
+
+        match builtin {
+            ArrayIsArray | ArrayBufferIsView | ObjectIsExtensible | ObjectIsFrozen => Type::Boolean,
+            MathImul => Type::Int32,
+            DateUTC
+            | DateNow
+            | DateParse
+            | MathAbs
+            | MathAcos
+            | MathAsin
+            | MathAtan
+            | MathAtan2
+            | MathCeil
+            | MathCos
+            | MathExp
+            | MathFloor
+            | MathHypot
+            | MathLog
+            | MathMax
+            | MathMin
+            | MathPow
+            | MathRandom
+            | MathRound
+            | MathSin
+            | MathSqrt
+            | MathTan
+            | MathTrunc
+            | HermesBuiltinArraySpread
+            | HermesBuiltinExponentiationOperator => Type::Number,
+            StringFromCharCode => Type::String,
+            ObjectCreate
+            | ObjectDefineProperties
+            | ObjectDefineProperty
+            | ObjectFreeze
+            | ObjectGetOwnPropertyNames
+            | ObjectKeys
+            | ObjectSeal
+            | HermesBuiltinGetTemplateObject
+            | HermesBuiltinCopyDataProperties
+            | HermesBuiltinCopyRestArgs => Type::Object,
+            _ => Type::Unknown,
+        }
+    }
+}
+
+/// The operator a compound assignment applies.
+fn binary_operator(operator: AssignmentOperator) -> Option<BinaryOperator> {
+    use AssignmentOperator::*;
+
+    match operator {
+        Assignment => None,
+        Addition => Some(BinaryOperator::Addition),
+        Subtraction => Some(BinaryOperator::Subtraction),
+        Multiplication => Some(BinaryOperator::Multiplication),
+        Division => Some(BinaryOperator::Division),
+        Remainder => Some(BinaryOperator::Remainder),
+        LeftShift => Some(BinaryOperator::LeftShift),
+        RightShift => Some(BinaryOperator::RightShift),
+        UnsignedShift => Some(BinaryOperator::UnsignedRightShift),
+        BitwiseOr => Some(BinaryOperator::BitwiseOr),
+        BitwiseXor => Some(BinaryOperator::BitwiseXor),
+        BitwiseAnd => Some(BinaryOperator::BitwiseAnd),
+    }
+}
+
+impl LiftedFunction {
+    /// The type `expression` evaluates to, as far as `types` knows.
+    pub fn type_of(&self, expression: &Expression) -> Type {
+        Type::of(expression, &|value| self.types[value])
+    }
+}
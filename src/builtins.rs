@@ -2,7 +2,7 @@ use std::convert::TryFrom;
 
 use crate::parsers::ParserError;
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Builtins {
     // Array
@@ -79,7 +79,7 @@ impl TryFrom<u8> for Builtins {
 
     fn try_from(byte: u8) -> Result<Self, Self::Error> {
         match byte {
-            0..=51 => Ok(unsafe { std::mem::transmute(byte) }),
+            0..=51 => Ok(unsafe { std::mem::transmute::<u8, Builtins>(byte) }),
             _ => Err(ParserError::new(
                 "Opcode",
                 format!("Invalid builtin method: {}", byte),
@@ -1,5 +1,7 @@
 use bitfield::bitfield;
 
+use crate::{ir::Literal, parsers::serialized_literals_parser};
+
 pub const MAGIC: u64 = 0x1F1903C103BC1FC6;
 pub const SHA1_NUM_BYTES: usize = 20;
 
@@ -55,6 +57,7 @@ impl From<u8> for Prohibit {
 }
 
 bitfield! {
+    #[derive(Copy, Clone)]
     pub struct FunctionHeaderFlag(u8);
     impl Debug;
     pub into Prohibit, prohibit_invoke, _: 1, 0;
@@ -89,6 +92,39 @@ bitfield! {
     pub u8, into FunctionHeaderFlag, flags, _: 127, 120; // 8 bits
 }
 
+/// The full size header used when a function does not fit in `FunctionHeader`,
+/// and the shape every function is normalised to once decoded.
+#[derive(Debug, Copy, Clone)]
+pub struct LargeFunctionHeader {
+    pub offset: u32,
+    pub param_count: u32,
+    pub bytecode_size_in_bytes: u32,
+    pub function_name: u32,
+    pub info_offset: u32,
+    pub frame_size: u32,
+    pub environment_size: u8,
+    pub highest_read_cache_index: u8,
+    pub highest_write_cache_index: u8,
+    pub flags: FunctionHeaderFlag,
+}
+
+impl From<&FunctionHeader> for LargeFunctionHeader {
+    fn from(header: &FunctionHeader) -> Self {
+        Self {
+            offset: header.offset(),
+            param_count: header.param_count(),
+            bytecode_size_in_bytes: header.bytecode_size_in_bytes(),
+            function_name: header.function_name(),
+            info_offset: header.info_offset(),
+            frame_size: header.frame_size(),
+            environment_size: header.environment_size(),
+            highest_read_cache_index: header.highest_read_cache_index(),
+            highest_write_cache_index: header.highest_write_cache_index(),
+            flags: header.flags(),
+        }
+    }
+}
+
 const COUNT_BITS: u32 = 31;
 const MAX_COUNT: u32 = (1 << COUNT_BITS) - 1;
 
@@ -166,6 +202,28 @@ impl RegExpTableEntry {
 #[derive(Debug)]
 pub struct CjsModuleTableEntry(pub u32, pub u32);
 
+/// Where a function's entries in the debug data start, `NO_DEBUG_OFFSET`
+/// when it has none.
+#[derive(Debug, Copy, Clone)]
+pub struct DebugOffsets {
+    pub source_locations: u32,
+    pub lexical_data: u32,
+}
+
+pub const NO_DEBUG_OFFSET: u32 = u32::MAX;
+
+/// Heads the debug info at `debug_info_offset`. The filename table, the
+/// filename storage and the file regions follow, then the debug data, where
+/// the lexical data starts `lexical_data_offset` bytes in.
+#[derive(Debug, Copy, Clone)]
+pub struct DebugInfoHeader {
+    pub filename_count: u32,
+    pub filename_storage_size: u32,
+    pub file_region_count: u32,
+    pub lexical_data_offset: u32,
+    pub debug_data_size: u32,
+}
+
 impl CjsModuleTableEntry {
     pub fn new((first, second): (u32, u32)) -> Self {
         Self(first, second)
@@ -188,3 +246,62 @@ pub struct BytecodeFile<'a> {
     pub regexp_storage: &'a [u8],
     pub cjs_module_table: Vec<CjsModuleTableEntry>,
 }
+
+/// Small string table entries with this length point into the overflow table.
+const OVERFLOW_STRING_LENGTH: u32 = 0xFF;
+
+impl BytecodeFile<'_> {
+    /// The UTF-16 code units of a string table entry. Strings are stored
+    /// either as Latin-1 bytes or as UTF-16, and may hold lone surrogates.
+    pub fn string_units(&self, index: u32) -> Option<Vec<u16>> {
+        let entry = self.small_string_table.get(index as usize)?;
+
+        let (offset, length) = if entry.length() == OVERFLOW_STRING_LENGTH {
+            let overflow = self.overflow_string_table.get(entry.offset() as usize)?;
+            (overflow.offset as usize, overflow.length as usize)
+        } else {
+            (entry.offset() as usize, entry.length() as usize)
+        };
+
+        if entry.is_utf_16() {
+            let bytes = self.string_storage.get(offset..offset + length * 2)?;
+            Some(
+                bytes
+                    .chunks_exact(2)
+                    .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                    .collect(),
+            )
+        } else {
+            let bytes = self.string_storage.get(offset..offset + length)?;
+            Some(bytes.iter().map(|byte| u16::from(*byte)).collect())
+        }
+    }
+
+    /// The `length` literals of an array buffer entry.
+    pub fn array_literals(&self, offset: u32, length: u16) -> Option<Vec<Literal>> {
+        let input = self.array_buffer.get(offset as usize..)?;
+        serialized_literals_parser(input, usize::from(length))
+            .ok()
+            .map(|(_, literals)| literals)
+    }
+
+    /// The keys and values of an object buffer entry, paired up.
+    pub fn object_literals(
+        &self,
+        key_offset: u32,
+        value_offset: u32,
+        length: u16,
+    ) -> Option<Vec<(Literal, Literal)>> {
+        let keys = self.obj_key_buffer.get(key_offset as usize..)?;
+        let values = self.obj_value_buffer.get(value_offset as usize..)?;
+        let (_, keys) = serialized_literals_parser(keys, usize::from(length)).ok()?;
+        let (_, values) = serialized_literals_parser(values, usize::from(length)).ok()?;
+        Some(keys.into_iter().zip(values).collect())
+    }
+
+    /// A string table entry, with lone surrogates replaced.
+    pub fn string(&self, index: u32) -> Option<String> {
+        self.string_units(index)
+            .map(|units| String::from_utf16_lossy(&units))
+    }
+}
@@ -0,0 +1,398 @@
+use crate::{
+    analysis::{ValueId, Variables},
+    ast::{
+        CapturedVariable, Expression, FunctionKind, LiftedFunction, MemberProperty, ModuleGraph,
+        Names, Nesting, Scopes, Statement,
+    },
+    bytecode_file_format::BytecodeFile,
+    ir::{Literal, StringIndex},
+};
+
+use super::{children_mut, is_identifier, structure, Structured};
+
+/// What a module's default export is assigned to when `export default`
+/// cannot stand where it is evaluated, and exported from afterwards.
+pub(super) const DEFAULT_EXPORT: &str = "$default";
+
+/// A restored program and what is known about it, which the code
+/// generators read the names and shapes of its functions from.
+pub struct Decompiled<'a> {
+    pub functions: &'a [LiftedFunction],
+    pub bytecode: &'a BytecodeFile<'a>,
+    pub names: &'a Names,
+    pub scopes: &'a Scopes,
+    pub nesting: &'a Nesting,
+    pub modules: &'a ModuleGraph,
+}
+
+/// What the statements of one function body are generated against.
+pub(super) struct Context<'a> {
+    pub(super) index: u32,
+    pub(super) function: &'a LiftedFunction,
+    pub(super) variables: Variables,
+    pub(super) use_counts: Vec<usize>,
+    pub(super) definitions: Vec<Option<&'a Expression>>,
+    /// By parameter index, 0 being `this`.
+    pub(super) parameters: Vec<String>,
+    /// Whether the body is generated as a script, where `return` is
+    /// spelled `break program`.
+    pub(super) top_level: bool,
+    /// Whether the body is generated as an ES module, where imports and
+    /// exports can stand as they are.
+    pub(super) module: bool,
+    /// Whether the module's body sits in the `program` block, which
+    /// `export default` cannot, so it assigns `DEFAULT_EXPORT` instead.
+    pub(super) deferred_default: bool,
+}
+
+/// What generating an `Assign` amounts to.
+pub(super) enum Store {
+    Nothing,
+    /// Evaluating the expression for its side effects.
+    Effect,
+    /// Evaluating an assignment to the variable itself.
+    InPlace,
+    Assignment,
+}
+
+impl<'a> Decompiled<'a> {
+    pub(super) fn context(&self, function: &'a LiftedFunction, top_level: bool) -> Context<'a> {
+        let index = function.index;
+        let parameters: Vec<String> = (0..function.header.param_count.max(1)
+            + function.rest as u32)
+            .map(|parameter| match parameter {
+                0 => String::from("this"),
+                parameter => self
+                    .names
+                    .parameter(index, parameter)
+                    .map_or_else(|| format!("a{}", parameter), String::from),
+            })
+            .collect();
+
+        Context {
+            index,
+            function,
+            variables: function.variables(),
+            use_counts: function.use_counts(),
+            definitions: function.definitions(),
+            parameters,
+            top_level,
+            module: false,
+            deferred_default: false,
+        }
+    }
+
+    /// The context of a script body, the factory's parameters under
+    /// `parameters` when there are any, the imports and exports that stand
+    /// before the `program` block, and its structured statements.
+    pub(super) fn script(
+        &self,
+        function: &'a LiftedFunction,
+        parameters: Option<&[&str]>,
+    ) -> (Context<'a>, Vec<Structured>, Vec<Structured>) {
+        let mut cx = self.context(function, true);
+        cx.module = parameters.is_some();
+        if let Some(parameters) = parameters {
+            for (index, name) in parameters.iter().enumerate() {
+                if let Some(parameter) = cx.parameters.get_mut(index + 1) {
+                    *parameter = String::from(*name);
+                }
+            }
+        }
+
+        let mut nodes = structure(function);
+        if let Some(Structured::Return(value)) = nodes.last() {
+            let value = value.clone();
+            nodes.pop();
+            if value.has_side_effects() {
+                nodes.push(Structured::Statement(Statement::Expression(value)));
+            }
+        }
+        if !contains_return(&nodes) {
+            cx.top_level = false;
+        }
+        // Imports and `export * from` are hoisted, so they can be moved out
+        // of the block; `export default` is evaluated where it stands.
+        let mut hoisted = Vec::new();
+        if cx.top_level && cx.module {
+            hoist_module_declarations(&mut nodes, &mut hoisted);
+            cx.deferred_default = contains_default_export(&nodes);
+        }
+        (cx, hoisted, nodes)
+    }
+
+    /// The functions a module's source covers: its factory and everything
+    /// nested in it.
+    pub(super) fn descendants(&self, factory: u32) -> Vec<u32> {
+        let mut descendants = Vec::new();
+        let mut pending = vec![factory];
+        while let Some(index) = pending.pop() {
+            descendants.push(index);
+            pending.extend(self.nesting.children(index).iter().rev());
+        }
+        descendants
+    }
+
+    /// The function `index` and the one holding its body, which differ
+    /// for generators and async functions.
+    pub(super) fn function(&self, index: u32) -> Option<(&'a LiftedFunction, &'a LiftedFunction)> {
+        let wrapper = self.functions.get(index as usize)?;
+        let body = match wrapper.kind {
+            FunctionKind::Normal => wrapper,
+            _ => wrapper
+                .generator
+                .and_then(|body| self.functions.get(body as usize))
+                .unwrap_or(wrapper),
+        };
+        Some((wrapper, body))
+    }
+
+    /// The name a function expression is given, if the source had one.
+    pub(super) fn function_name(&self, index: u32) -> Option<&'a str> {
+        match self.names.function(index) {
+            Some(name) if !name.inferred && is_identifier(&name.name) => Some(&name.name),
+            _ => None,
+        }
+    }
+
+    /// Every variable the body assigns, and the captured variables the
+    /// `scopes` create.
+    pub(super) fn declarations(&self, cx: &Context, scopes: &[u32]) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        let mut declare = |name: String| {
+            if !names.contains(&name) && !cx.parameters.contains(&name) {
+                names.push(name);
+            }
+        };
+
+        if cx.deferred_default {
+            declare(String::from(DEFAULT_EXPORT));
+        }
+        for scope in scopes {
+            for variable in self.scopes.declared_in(*scope) {
+                declare(self.captured(variable.scope, variable.slot));
+            }
+        }
+        for block in &cx.function.blocks {
+            for phi in &block.phis {
+                declare(self.value(cx, phi.value));
+            }
+            for statement in &block.statements {
+                match statement {
+                    Statement::Catch(_) | Statement::Import { .. } => {}
+                    Statement::Assign { value, expression } => {
+                        if let Store::InPlace | Store::Assignment =
+                            self.store(cx, *value, expression)
+                        {
+                            declare(self.value(cx, *value));
+                        }
+                    }
+                    statement => {
+                        for value in statement.defs() {
+                            declare(self.value(cx, value));
+                        }
+                    }
+                }
+            }
+            for value in block.terminator.defs() {
+                declare(self.value(cx, value));
+            }
+        }
+        names
+    }
+
+    pub(super) fn value(&self, cx: &Context, value: ValueId) -> String {
+        self.names
+            .local(cx.index, cx.variables.of(value))
+            .map_or_else(|| format!("$v{}", value), String::from)
+    }
+
+    pub(super) fn captured(&self, scope: u32, slot: u32) -> String {
+        let variable = CapturedVariable { scope, slot };
+        self.names
+            .captured(variable)
+            .map_or_else(|| format!("$captured{}_{}", scope, slot), String::from)
+    }
+
+    pub(super) fn text(&self, index: StringIndex) -> String {
+        self.bytecode.string(index.index()).unwrap_or_default()
+    }
+
+    pub(super) fn units(&self, index: StringIndex) -> Vec<u16> {
+        self.bytecode
+            .string_units(index.index())
+            .unwrap_or_default()
+    }
+
+    /// The variable `expression` reads, if it is one.
+    fn variable(&self, cx: &Context, expression: &Expression) -> Option<String> {
+        match expression {
+            Expression::Value(value) => Some(self.value(cx, *value)),
+            Expression::Parameter(parameter) => cx.parameters.get(*parameter as usize).cloned(),
+            Expression::Captured(variable) => Some(self.captured(variable.scope, variable.slot)),
+            _ => None,
+        }
+    }
+
+    pub(super) fn store(&self, cx: &Context, value: ValueId, expression: &Expression) -> Store {
+        let name = self.value(cx, value);
+        let is_variable =
+            |expression: &Expression| self.variable(cx, expression) == Some(name.clone());
+
+        if is_variable(expression) {
+            return Store::Nothing;
+        }
+        // `x += 1` and `x++` already store into the variable they define.
+        let in_place = match expression {
+            Expression::Assignment { target, .. } => is_variable(target),
+            Expression::Update { argument, .. } => is_variable(argument),
+            _ => false,
+        };
+        match (
+            in_place,
+            cx.use_counts[value],
+            expression.has_side_effects(),
+        ) {
+            (true, _, _) => Store::InPlace,
+            (false, 0, true) => Store::Effect,
+            (false, 0, false) => Store::Nothing,
+            (false, _, _) => Store::Assignment,
+        }
+    }
+
+    /// Whether `target = value` stores a variable into itself, as storing
+    /// a local into the captured variable of the same name does, the two
+    /// being one in the source.
+    pub(super) fn is_self_store(
+        &self,
+        cx: &Context,
+        target: &Expression,
+        value: &Expression,
+    ) -> bool {
+        let target = self.variable(cx, target);
+        target.is_some() && target == self.variable(cx, value)
+    }
+
+    /// Whether a call passes no `this`.
+    pub(super) fn is_undefined_this(&self, cx: &Context, this: &Expression) -> bool {
+        match this {
+            Expression::Value(value) => cx.definitions[*value].is_some_and(is_undefined),
+            this => is_undefined(this),
+        }
+    }
+}
+
+/// The local an imported name is bound to, the name itself when it can
+/// be one.
+pub(super) fn import_binding(name: &str) -> String {
+    match is_identifier(name) {
+        true => String::from(name),
+        false => {
+            let sanitized: String = name
+                .chars()
+                .map(|c| match c.is_ascii_alphanumeric() || c == '$' {
+                    true => c,
+                    false => '_',
+                })
+                .collect();
+            format!("_{}", sanitized)
+        }
+    }
+}
+
+/// What the passes left of `expression` unresolved, IR with no spelling in
+/// source, which is generated as `undefined` with this in a comment.
+pub(super) fn unresolved(expression: &Expression) -> Option<String> {
+    match expression {
+        Expression::Environment { level: 0 } => Some(String::from("the captured environment")),
+        Expression::Environment { level } => {
+            Some(format!("the environment {} above the captured one", level))
+        }
+        Expression::NewEnvironment => Some(String::from("a new environment")),
+        Expression::Scope(function) => Some(format!("the environment of function {}", function)),
+        Expression::EnvironmentSlot { environment, slot } => Some(format!(
+            "slot {} of {}",
+            slot,
+            unresolved(environment).unwrap_or_else(|| String::from("an environment"))
+        )),
+        _ => None,
+    }
+}
+
+pub(super) fn is_undefined(expression: &Expression) -> bool {
+    matches!(expression, Expression::Literal(Literal::Undefined))
+}
+
+pub(super) fn unoptional(expression: &Expression) -> &Expression {
+    match expression {
+        Expression::Optional(expression) => expression,
+        expression => expression,
+    }
+}
+
+/// Whether `this` is the object a method was read from, so a plain method
+/// call passes it.
+pub(super) fn is_same(object: &Expression, this: &Expression) -> bool {
+    match (object, this) {
+        (Expression::Value(first), Expression::Value(second)) => first == second,
+        (Expression::Parameter(first), Expression::Parameter(second)) => first == second,
+        (Expression::Captured(first), Expression::Captured(second)) => first == second,
+        (Expression::This, Expression::This) | (Expression::Global, Expression::Global) => true,
+        _ => false,
+    }
+}
+
+/// Whether a call's callee reads a property of the global object, which a
+/// bare name does without passing it as `this`.
+pub(super) fn is_global_member(callee: &Expression) -> bool {
+    matches!(
+        callee,
+        Expression::Member {
+            object,
+            property: MemberProperty::Named(_),
+        } if matches!(object.as_ref(), Expression::Global)
+    )
+}
+
+pub(super) fn is_optional_chain(expression: &Expression) -> bool {
+    match expression {
+        Expression::Optional(_) => true,
+        Expression::Member { object, .. } => is_optional_chain(object),
+        Expression::Call { callee, .. } => is_optional_chain(callee),
+        _ => false,
+    }
+}
+
+fn contains_return(nodes: &[Structured]) -> bool {
+    nodes.iter().any(|node| match node {
+        Structured::Return(_) => true,
+        node => super::children(node)
+            .into_iter()
+            .any(|body| contains_return(body)),
+    })
+}
+
+fn hoist_module_declarations(nodes: &mut Vec<Structured>, hoisted: &mut Vec<Structured>) {
+    let (declarations, rest) = std::mem::take(nodes).into_iter().partition(|node| {
+        matches!(
+            node,
+            Structured::Statement(Statement::Import { .. } | Statement::ExportAll(_))
+        )
+    });
+    *nodes = rest;
+    hoisted.extend::<Vec<Structured>>(declarations);
+    for node in nodes {
+        for body in children_mut(node) {
+            hoist_module_declarations(body, hoisted);
+        }
+    }
+}
+
+fn contains_default_export(nodes: &[Structured]) -> bool {
+    nodes.iter().any(|node| match node {
+        Structured::Statement(Statement::ExportDefault(_)) => true,
+        node => super::children(node)
+            .into_iter()
+            .any(|body| contains_default_export(body)),
+    })
+}
@@ -0,0 +1,1701 @@
+use crate::{
+    analysis::ValueId,
+    ast::{
+        ClassMember, ClassMemberKind, Expression, FunctionKind, MemberProperty, ObjectProperty,
+        Statement, METRO_PARAMETERS,
+    },
+    ir::{AssignmentOperator, Conversion, Literal, Number, StringIndex},
+};
+
+use super::{
+    decompiled::{
+        import_binding, is_global_member, is_optional_chain, is_same, is_undefined, unoptional,
+        unresolved, Context, Store, DEFAULT_EXPORT,
+    },
+    exponentiation, is_identifier, is_identifier_name, number_literal, string_literal, structure,
+    template_text, Decompiled, Json, Structured,
+};
+
+/// Builds restored functions as an ESTree AST, which the `Printer` spells
+/// out as source and tools like Babel, ESLint and prettier can take over.
+pub struct Estree<'a> {
+    decompiled: &'a Decompiled<'a>,
+    built: Vec<bool>,
+}
+
+/// How a function is introduced.
+enum Form {
+    Expression,
+    /// The value of a method, getter or setter, named by its key.
+    Method,
+}
+
+impl<'a> Estree<'a> {
+    pub fn new(decompiled: &'a Decompiled<'a>) -> Self {
+        Self {
+            decompiled,
+            built: vec![false; decompiled.functions.len()],
+        }
+    }
+
+    /// The whole program as a `Program` node, the global function's body
+    /// at the top level.
+    pub fn program(&mut self, root: u32) -> Json {
+        let body = self.script(root, None);
+        let remaining: Vec<u32> = (0..self.decompiled.functions.len() as u32).collect();
+        self.finish(body, remaining, "script")
+    }
+
+    /// A Metro module as a `Program` node, its factory's body at the top
+    /// level with the factory's parameters under the names Metro gives them.
+    pub fn module(&mut self, factory: u32) -> Json {
+        let body = self.script(factory, Some(METRO_PARAMETERS));
+        let descendants = self.decompiled.descendants(factory);
+        self.finish(body, descendants, "module")
+    }
+
+    fn script(&mut self, index: u32, parameters: Option<&[&str]>) -> Vec<Json> {
+        let function = match self.decompiled.functions.get(index as usize) {
+            Some(function) => function,
+            None => return Vec::new(),
+        };
+        self.built[index as usize] = true;
+        let (cx, hoisted, nodes) = self.decompiled.script(function, parameters);
+
+        let mut body = self.statements(&cx, &hoisted);
+        body.extend(self.declarations(&cx, &[index]));
+        let statements = self.statements(&cx, &nodes);
+        match cx.top_level {
+            true => body.push(labeled("program", block_statement(statements))),
+            false => body.extend(statements),
+        }
+        if cx.deferred_default {
+            let specifier = node(
+                "ExportSpecifier",
+                vec![
+                    ("local", identifier(DEFAULT_EXPORT)),
+                    ("exported", identifier("default")),
+                ],
+            );
+            body.push(node(
+                "ExportNamedDeclaration",
+                vec![
+                    ("declaration", Json::Null),
+                    ("specifiers", vec![specifier].into()),
+                    ("source", Json::Null),
+                ],
+            ));
+        }
+        body
+    }
+
+    /// The `Program` of `body` and the functions among `remaining` nothing
+    /// built, as expressions of their own.
+    fn finish(&mut self, mut body: Vec<Json>, remaining: Vec<u32>, source_type: &str) -> Json {
+        for index in remaining {
+            if !self.built.get(index as usize).copied().unwrap_or(true) {
+                let function = self.function(index, Form::Expression);
+                let statement = expression_statement(function);
+                body.push(comment(
+                    statement,
+                    "leadingComments",
+                    "Line",
+                    "Never created.",
+                ));
+            }
+        }
+        node(
+            "Program",
+            vec![("sourceType", source_type.into()), ("body", body.into())],
+        )
+    }
+
+    fn function(&mut self, index: u32, form: Form) -> Json {
+        let (kind, params, body) = match self.function_parts(index) {
+            Some(parts) => parts,
+            None => return unresolved_value(&format!("function {}", index)),
+        };
+        let (name, inferred) = match form {
+            Form::Expression => (
+                self.decompiled.function_name(index),
+                self.decompiled.names.function(index),
+            ),
+            Form::Method => (None, None),
+        };
+        let function = function_expression(name.map(identifier), kind, params, body);
+        match (name, inferred) {
+            // A name inferred from where the function was stored, which the
+            // function itself does not bind.
+            (None, Some(inferred)) => {
+                let text = inferred.name.replace("*/", "* /");
+                comment(function, "innerComments", "Block", &text)
+            }
+            _ => function,
+        }
+    }
+
+    /// The kind, parameters and body statements of the function `index`.
+    fn function_parts(&mut self, index: u32) -> Option<(FunctionKind, Vec<Json>, Vec<Json>)> {
+        let (wrapper, body) = self.decompiled.function(index)?;
+        self.built[index as usize] = true;
+        self.built[body.index as usize] = true;
+
+        let cx = self.decompiled.context(body, false);
+        let mut params: Vec<Json> = cx.parameters[1..]
+            .iter()
+            .map(|parameter| identifier(parameter))
+            .collect();
+        if body.rest {
+            if let Some(rest) = params.pop() {
+                params.push(node("RestElement", vec![("argument", rest)]));
+            }
+        }
+
+        let nodes = structure(body);
+        let mut statements = self.declarations(&cx, &[index, body.index]);
+        statements.extend(self.statements(&cx, &nodes));
+        Some((wrapper.kind, params, statements))
+    }
+
+    /// `let` for every variable the body assigns, and the captured
+    /// variables the `scopes` create.
+    fn declarations(&self, cx: &Context, scopes: &[u32]) -> Vec<Json> {
+        let names = self.decompiled.declarations(cx, scopes);
+        match names.is_empty() {
+            true => Vec::new(),
+            false => vec![variable_declaration(
+                "let",
+                names.iter().map(|name| (identifier(name), None)).collect(),
+            )],
+        }
+    }
+
+    fn statements(&mut self, cx: &Context, nodes: &[Structured]) -> Vec<Json> {
+        let mut statements = Vec::new();
+        for node in nodes {
+            self.node(cx, node, &mut statements);
+        }
+        statements
+    }
+
+    fn block(&mut self, cx: &Context, nodes: &[Structured]) -> Json {
+        block_statement(self.statements(cx, nodes))
+    }
+
+    fn node(&mut self, cx: &Context, structured: &Structured, out: &mut Vec<Json>) {
+        match structured {
+            Structured::Statement(statement) => self.statement(cx, statement, out),
+            Structured::Block { label, body } => {
+                let body = self.block(cx, body);
+                out.push(labeled(&format!("block{}", label), body));
+            }
+            Structured::Loop { label, body } => {
+                let body = self.block(cx, body);
+                let statement = node(
+                    "ForStatement",
+                    vec![
+                        ("init", Json::Null),
+                        ("test", Json::Null),
+                        ("update", Json::Null),
+                        ("body", body),
+                    ],
+                );
+                out.push(loop_labeled(*label, statement));
+            }
+            Structured::While {
+                label,
+                condition,
+                body,
+            } => {
+                let test = self.expression(cx, condition);
+                let body = self.block(cx, body);
+                let statement = node("WhileStatement", vec![("test", test), ("body", body)]);
+                out.push(loop_labeled(*label, statement));
+            }
+            Structured::ForIn {
+                label,
+                property,
+                object,
+                body,
+            } => {
+                let left = identifier(&self.decompiled.value(cx, *property));
+                let right = self.expression(cx, object);
+                let body = self.block(cx, body);
+                let statement = node(
+                    "ForInStatement",
+                    vec![("left", left), ("right", right), ("body", body)],
+                );
+                out.push(loop_labeled(*label, statement));
+            }
+            Structured::ForOf {
+                label,
+                value,
+                iterable,
+                body,
+            } => {
+                let left = identifier(&self.decompiled.value(cx, *value));
+                let right = self.expression(cx, iterable);
+                let body = self.block(cx, body);
+                let statement = node(
+                    "ForOfStatement",
+                    vec![
+                        ("await", false.into()),
+                        ("left", left),
+                        ("right", right),
+                        ("body", body),
+                    ],
+                );
+                out.push(loop_labeled(*label, statement));
+            }
+            Structured::If {
+                condition,
+                consequent,
+                alternate,
+            } => out.push(self.conditional(cx, condition, consequent, alternate)),
+            Structured::Switch {
+                discriminant,
+                cases,
+            } => {
+                let discriminant = self.expression(cx, discriminant);
+                let cases: Vec<Json> = cases
+                    .iter()
+                    .map(|case| {
+                        let test = case.value.map(|value| number(f64::from(value)));
+                        let consequent = self.statements(cx, &case.body);
+                        switch_case(test, consequent)
+                    })
+                    .collect();
+                out.push(node(
+                    "SwitchStatement",
+                    vec![("discriminant", discriminant), ("cases", cases.into())],
+                ));
+            }
+            Structured::Try {
+                body,
+                parameter,
+                catch,
+                finally,
+            } => {
+                let block = self.block(cx, body);
+                let handler = match catch {
+                    Some(catch) => {
+                        let param = parameter
+                            .map(|parameter| identifier(&self.decompiled.value(cx, parameter)));
+                        let body = self.block(cx, catch);
+                        Some(node(
+                            "CatchClause",
+                            vec![("param", param.into()), ("body", body)],
+                        ))
+                    }
+                    None if finally.is_none() => Some(node(
+                        "CatchClause",
+                        vec![("param", Json::Null), ("body", block_statement(Vec::new()))],
+                    )),
+                    None => None,
+                };
+                let finalizer = finally.as_ref().map(|finally| self.block(cx, finally));
+                out.push(node(
+                    "TryStatement",
+                    vec![
+                        ("block", block),
+                        ("handler", handler.into()),
+                        ("finalizer", finalizer.into()),
+                    ],
+                ));
+            }
+            Structured::Break(label) => {
+                let label = label.map(|label| format!("block{}", label));
+                out.push(jump("BreakStatement", label.as_deref()));
+            }
+            Structured::Continue(label) => {
+                let label = label.map(|label| format!("loop{}", label));
+                out.push(jump("ContinueStatement", label.as_deref()));
+            }
+            Structured::Return(value) if cx.top_level => {
+                if value.has_side_effects() {
+                    let value = self.expression(cx, value);
+                    out.push(expression_statement(value));
+                }
+                out.push(jump("BreakStatement", Some("program")));
+            }
+            Structured::Return(value) => {
+                let argument = match value {
+                    Expression::Literal(Literal::Undefined) => Json::Null,
+                    value => self.expression(cx, value),
+                };
+                out.push(node("ReturnStatement", vec![("argument", argument)]));
+            }
+            Structured::Throw(value) => {
+                let argument = self.expression(cx, value);
+                out.push(node("ThrowStatement", vec![("argument", argument)]));
+            }
+            Structured::Dispatch(blocks) => {
+                let first = blocks.first().map_or(0, |(block, _)| *block);
+                out.push(variable_declaration(
+                    "let",
+                    vec![(identifier("$next"), Some(number(first as f64)))],
+                ));
+                let cases: Vec<Json> = blocks
+                    .iter()
+                    .map(|(block, body)| {
+                        let mut consequent = self.statements(cx, body);
+                        if !matches!(
+                            body.last(),
+                            Some(
+                                Structured::Jump(_) | Structured::Return(_) | Structured::Throw(_)
+                            )
+                        ) {
+                            consequent.push(jump("BreakStatement", Some("dispatch")));
+                        }
+                        switch_case(Some(number(*block as f64)), consequent)
+                    })
+                    .collect();
+                let switch = node(
+                    "SwitchStatement",
+                    vec![
+                        ("discriminant", identifier("$next")),
+                        ("cases", cases.into()),
+                    ],
+                );
+                let statement = node(
+                    "ForStatement",
+                    vec![
+                        ("init", Json::Null),
+                        ("test", Json::Null),
+                        ("update", Json::Null),
+                        ("body", block_statement(vec![switch])),
+                    ],
+                );
+                out.push(labeled("dispatch", statement));
+            }
+            Structured::Jump(block) => {
+                out.push(assignment_statement(
+                    identifier("$next"),
+                    number(*block as f64),
+                ));
+                out.push(jump("ContinueStatement", Some("dispatch")));
+            }
+        }
+    }
+
+    fn conditional(
+        &mut self,
+        cx: &Context,
+        condition: &Expression,
+        consequent: &[Structured],
+        alternate: &[Structured],
+    ) -> Json {
+        let test = self.expression(cx, condition);
+        let consequent = self.block(cx, consequent);
+        let alternate = match alternate {
+            [] => Json::Null,
+            [Structured::If {
+                condition,
+                consequent,
+                alternate,
+            }] => self.conditional(cx, condition, consequent, alternate),
+            alternate => self.block(cx, alternate),
+        };
+        node(
+            "IfStatement",
+            vec![
+                ("test", test),
+                ("consequent", consequent),
+                ("alternate", alternate),
+            ],
+        )
+    }
+
+    fn statement(&mut self, cx: &Context, statement: &Statement, out: &mut Vec<Json>) {
+        let decompiled = self.decompiled;
+        let value = |value: ValueId| identifier(&decompiled.value(cx, value));
+        match statement {
+            Statement::Assign { value, expression } => self.assign(cx, *value, expression, out),
+            Statement::Expression(expression) => {
+                if let Expression::Assignment {
+                    operator: AssignmentOperator::Assignment,
+                    target,
+                    value,
+                } = expression
+                {
+                    if self.decompiled.is_self_store(cx, target, value) {
+                        return;
+                    }
+                }
+                let expression = self.effect(cx, expression);
+                out.push(expression_statement(expression));
+            }
+            Statement::Catch(_)
+            | Statement::ThrowIfUndefined(_)
+            | Statement::StartGenerator
+            | Statement::SaveGenerator { .. }
+            | Statement::CompleteGenerator => {}
+            Statement::DeclareGlobalVar(name) => {
+                let name = self.decompiled.text(*name);
+                if is_identifier(&name) {
+                    out.push(variable_declaration("var", vec![(identifier(&name), None)]));
+                }
+            }
+            Statement::PropertyNames {
+                names,
+                index,
+                size,
+                object,
+            } => {
+                let (names, index, size) = (value(*names), value(*index), value(*size));
+                let object = self.expression(cx, object);
+                let keys = call(path("Object.keys"), vec![object]);
+                out.push(assignment_statement(names.clone(), keys));
+                out.push(assignment_statement(index, number(0.0)));
+                out.push(assignment_statement(size, named_member(names, "length")));
+            }
+            Statement::NextPropertyName {
+                property,
+                index,
+                names,
+                size,
+                ..
+            } => {
+                let (property, index) = (value(*property), value(*index));
+                let names = self.expression(cx, names);
+                let size = self.expression(cx, size);
+                let next = node(
+                    "ConditionalExpression",
+                    vec![
+                        ("test", binary("<", index.clone(), size)),
+                        ("consequent", computed_member(names, index.clone())),
+                        ("alternate", identifier("undefined")),
+                    ],
+                );
+                out.push(assignment_statement(property, next));
+                out.push(expression_statement(update("++", false, index)));
+            }
+            Statement::IteratorBegin {
+                iterator,
+                next,
+                source,
+            } => {
+                let (iterator, next) = (value(*iterator), value(*next));
+                let source = self.expression(cx, source);
+                let method = computed_member(source, path("Symbol.iterator"));
+                out.push(assignment_statement(
+                    iterator.clone(),
+                    call(method, Vec::new()),
+                ));
+                out.push(assignment_statement(next, named_member(iterator, "next")));
+            }
+            Statement::IteratorNext {
+                result,
+                iterator,
+                previous,
+                next,
+            } => {
+                let (result, iterator) = (value(*result), value(*iterator));
+                let previous = self.expression(cx, previous);
+                let next = self.expression(cx, next);
+                out.push(assignment_statement(iterator.clone(), previous));
+                let stepped = call(named_member(next, "call"), vec![iterator]);
+                out.push(assignment_statement(result, named_member(stepped, "value")));
+            }
+            Statement::IteratorClose { iterator, .. } => {
+                let iterator = self.expression(cx, iterator);
+                let close = node(
+                    "CallExpression",
+                    vec![
+                        ("callee", named_member(iterator, "return")),
+                        ("arguments", Json::Array(Vec::new())),
+                        ("optional", true.into()),
+                    ],
+                );
+                out.push(expression_statement(chain(close)));
+            }
+            Statement::Destructure {
+                elements,
+                rest,
+                source,
+            } => {
+                let mut targets: Vec<Json> =
+                    elements.iter().map(|element| value(*element)).collect();
+                if let Some(rest) = rest {
+                    targets.push(node("RestElement", vec![("argument", value(*rest))]));
+                }
+                let pattern = node("ArrayPattern", vec![("elements", targets.into())]);
+                let source = self.expression(cx, source);
+                out.push(assignment_statement(pattern, source));
+            }
+            Statement::DestructureObject {
+                properties,
+                rest,
+                source,
+            } => {
+                let mut targets: Vec<Json> = properties
+                    .iter()
+                    .map(|(key, binding)| {
+                        let (key, computed) = self.property_key(cx, key);
+                        pattern_property(key, value(*binding), computed)
+                    })
+                    .collect();
+                targets.push(node("RestElement", vec![("argument", value(*rest))]));
+                let pattern = node("ObjectPattern", vec![("properties", targets.into())]);
+                let source = self.expression(cx, source);
+                out.push(assignment_statement(pattern, source));
+            }
+            Statement::Import {
+                module,
+                default,
+                namespace,
+                names,
+            } => self.import(cx, *module, *default, *namespace, names, out),
+            Statement::ExportDefault(expression) => {
+                let expression = self.expression(cx, expression);
+                match (cx.module, cx.deferred_default) {
+                    (true, true) => {
+                        out.push(assignment_statement(identifier(DEFAULT_EXPORT), expression))
+                    }
+                    (true, false) => out.push(node(
+                        "ExportDefaultDeclaration",
+                        vec![("declaration", expression)],
+                    )),
+                    (false, _) => out.push(comment(
+                        expression_statement(expression),
+                        "trailingComments",
+                        "Line",
+                        "unresolved: export default",
+                    )),
+                }
+            }
+            Statement::ExportAll(module) => {
+                let source = self.path(*module);
+                match cx.module {
+                    true => out.push(node(
+                        "ExportAllDeclaration",
+                        vec![("exported", Json::Null), ("source", source)],
+                    )),
+                    false => out.push(comment(
+                        expression_statement(call(identifier("require"), vec![source])),
+                        "trailingComments",
+                        "Line",
+                        "unresolved: export * from",
+                    )),
+                }
+            }
+            Statement::ResumeGenerator { result, is_return } => {
+                let pattern = node(
+                    "ArrayPattern",
+                    vec![("elements", vec![value(*result), value(*is_return)].into())],
+                );
+                let resumed = node(
+                    "ArrayExpression",
+                    vec![(
+                        "elements",
+                        vec![identifier("undefined"), boolean(false)].into(),
+                    )],
+                );
+                out.push(comment(
+                    assignment_statement(pattern, resumed),
+                    "trailingComments",
+                    "Line",
+                    "unresolved: resuming the generator",
+                ));
+            }
+            Statement::Debugger => out.push(node("DebuggerStatement", Vec::new())),
+        }
+    }
+
+    fn assign(
+        &mut self,
+        cx: &Context,
+        value: ValueId,
+        expression: &Expression,
+        out: &mut Vec<Json>,
+    ) {
+        match self.decompiled.store(cx, value, expression) {
+            Store::Nothing => {}
+            Store::Effect | Store::InPlace => {
+                let expression = self.effect(cx, expression);
+                out.push(expression_statement(expression));
+            }
+            Store::Assignment => {
+                let name = identifier(&self.decompiled.value(cx, value));
+                let expression = self.expression(cx, expression);
+                out.push(assignment_statement(name, expression));
+            }
+        }
+    }
+
+    /// An expression evaluated for its side effects alone, where `x++`
+    /// reads better than `++x`.
+    fn effect(&mut self, cx: &Context, expression: &Expression) -> Json {
+        match expression {
+            Expression::Update {
+                operator, argument, ..
+            } => {
+                let argument = self.expression(cx, argument);
+                update((*operator).into(), false, argument)
+            }
+            expression => self.expression(cx, expression),
+        }
+    }
+
+    fn path(&self, module: u32) -> Json {
+        string(
+            &self
+                .decompiled
+                .modules
+                .path(module)
+                .encode_utf16()
+                .collect::<Vec<u16>>(),
+        )
+    }
+
+    fn import(
+        &mut self,
+        cx: &Context,
+        module: u32,
+        default: Option<ValueId>,
+        namespace: Option<ValueId>,
+        names: &[StringIndex],
+        out: &mut Vec<Json>,
+    ) {
+        let source = self.path(module);
+        if !cx.module {
+            return self.require(cx, source, default, namespace, names, out);
+        }
+        let default = default.map(|value| {
+            let local = identifier(&self.decompiled.value(cx, value));
+            node("ImportDefaultSpecifier", vec![("local", local)])
+        });
+        let namespace = namespace.map(|value| {
+            let local = identifier(&self.decompiled.value(cx, value));
+            vec![node("ImportNamespaceSpecifier", vec![("local", local)])]
+        });
+        let names: Vec<Json> = names
+            .iter()
+            .map(|name| {
+                let text = self.decompiled.text(*name);
+                let imported = match is_identifier_name(&text) {
+                    true => identifier(&text),
+                    false => string(&self.decompiled.units(*name)),
+                };
+                let local = identifier(&import_binding(&text));
+                node(
+                    "ImportSpecifier",
+                    vec![("imported", imported), ("local", local)],
+                )
+            })
+            .collect();
+        let names = match names.is_empty() {
+            true => None,
+            false => Some(names),
+        };
+
+        // A namespace import cannot be combined with named ones.
+        let (first, second) = match (namespace, names) {
+            (Some(namespace), Some(names)) => (Some(namespace), Some(names)),
+            (namespace, names) => (namespace.or(names), None),
+        };
+        let specifiers: Vec<Json> = default
+            .into_iter()
+            .chain(first.into_iter().flatten())
+            .collect();
+        out.push(import_declaration(specifiers, source.clone()));
+        if let Some(second) = second {
+            out.push(import_declaration(second, source));
+        }
+    }
+
+    /// An import inside a function, spelled with `require`.
+    fn require(
+        &mut self,
+        cx: &Context,
+        source: Json,
+        default: Option<ValueId>,
+        namespace: Option<ValueId>,
+        names: &[StringIndex],
+        out: &mut Vec<Json>,
+    ) {
+        let required = call(identifier("require"), vec![source]);
+        if let Some(default) = default {
+            let default = identifier(&self.decompiled.value(cx, default));
+            let init = named_member(required.clone(), "default");
+            out.push(variable_declaration("let", vec![(default, Some(init))]));
+        }
+        if let Some(namespace) = namespace {
+            let namespace = identifier(&self.decompiled.value(cx, namespace));
+            out.push(variable_declaration(
+                "let",
+                vec![(namespace, Some(required.clone()))],
+            ));
+        }
+        if !names.is_empty() {
+            let properties: Vec<Json> = names
+                .iter()
+                .map(|name| {
+                    let binding = identifier(&import_binding(&self.decompiled.text(*name)));
+                    pattern_property(self.key(*name), binding, false)
+                })
+                .collect();
+            let pattern = node("ObjectPattern", vec![("properties", properties.into())]);
+            out.push(variable_declaration(
+                "let",
+                vec![(pattern, Some(required.clone()))],
+            ));
+        }
+        if default.is_none() && namespace.is_none() && names.is_empty() {
+            out.push(expression_statement(required));
+        }
+    }
+
+    /// `expression`, wrapped in a `ChainExpression` when it ends an
+    /// optional chain.
+    fn expression(&mut self, cx: &Context, expression: &Expression) -> Json {
+        let element = self.element(cx, expression);
+        match is_optional_chain(expression) && !matches!(expression, Expression::Optional(_)) {
+            true => chain(element),
+            false => element,
+        }
+    }
+
+    /// `expression` without the `ChainExpression` an optional chain ends
+    /// in, for the links inside one.
+    fn element(&mut self, cx: &Context, expression: &Expression) -> Json {
+        match expression {
+            Expression::Value(value) => identifier(&self.decompiled.value(cx, *value)),
+            Expression::Literal(literal) => self.literal(literal),
+            Expression::Parameter(parameter) => match cx.parameters.get(*parameter as usize) {
+                Some(_) if *parameter == 0 => node("ThisExpression", Vec::new()),
+                Some(name) => identifier(name),
+                None => computed_member(identifier("arguments"), number(f64::from(parameter - 1))),
+            },
+            Expression::This => node("ThisExpression", Vec::new()),
+            Expression::CoerceThis(argument) => self.element(cx, argument),
+            Expression::NewTarget => node(
+                "MetaProperty",
+                vec![
+                    ("meta", identifier("new")),
+                    ("property", identifier("target")),
+                ],
+            ),
+            Expression::Global => identifier("globalThis"),
+            Expression::Environment { .. }
+            | Expression::NewEnvironment
+            | Expression::Scope(_)
+            | Expression::EnvironmentSlot { .. } => {
+                unresolved_value(&unresolved(expression).unwrap_or_default())
+            }
+            Expression::Captured(variable) => {
+                identifier(&self.decompiled.captured(variable.scope, variable.slot))
+            }
+            Expression::Unary { operator, argument } => {
+                let argument = self.expression(cx, argument);
+                unary((*operator).into(), argument)
+            }
+            Expression::Delete { object, property } => {
+                let mut member = self.qualified_member(cx, object, property);
+                if is_optional_chain(object) {
+                    member = chain(member);
+                }
+                unary("delete", member)
+            }
+            Expression::Await(argument) => {
+                let argument = self.expression(cx, argument);
+                node("AwaitExpression", vec![("argument", argument)])
+            }
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let left = self.expression(cx, left);
+                let right = self.expression(cx, right);
+                binary((*operator).into(), left, right)
+            }
+            Expression::Conversion {
+                conversion,
+                argument,
+            } => {
+                let argument = self.expression(cx, argument);
+                match conversion {
+                    Conversion::ToNumber => unary("+", argument),
+                    Conversion::ToInt32 => binary("|", argument, number(0.0)),
+                    Conversion::ToString => binary("+", string(&[]), argument),
+                }
+            }
+            Expression::Logical {
+                operator,
+                left,
+                right,
+            } => {
+                let left = self.expression(cx, left);
+                let right = self.expression(cx, right);
+                let operator: &str = (*operator).into();
+                node(
+                    "LogicalExpression",
+                    vec![
+                        ("operator", operator.into()),
+                        ("left", left),
+                        ("right", right),
+                    ],
+                )
+            }
+            Expression::Conditional {
+                test,
+                consequent,
+                alternate,
+            } => {
+                let test = self.expression(cx, test);
+                let consequent = self.expression(cx, consequent);
+                let alternate = self.expression(cx, alternate);
+                node(
+                    "ConditionalExpression",
+                    vec![
+                        ("test", test),
+                        ("consequent", consequent),
+                        ("alternate", alternate),
+                    ],
+                )
+            }
+            Expression::Optional(argument) => self.element(cx, argument),
+            Expression::Member { object, property } => self.member(cx, object, property),
+            Expression::Assignment { target, value, .. } if unresolved(target).is_some() => {
+                let value = self.expression(cx, value);
+                let target = unresolved(target).unwrap_or_default();
+                let text = format!("unresolved: stored to {}", target);
+                comment(value, "trailingComments", "Block", &text)
+            }
+            Expression::Assignment {
+                operator,
+                target,
+                value,
+            } => {
+                let target = self.expression(cx, target);
+                let value = self.expression(cx, value);
+                let operator: &str = (*operator).into();
+                node(
+                    "AssignmentExpression",
+                    vec![
+                        ("operator", operator.into()),
+                        ("left", target),
+                        ("right", value),
+                    ],
+                )
+            }
+            Expression::Update {
+                operator,
+                prefix,
+                argument,
+            } => {
+                let argument = self.expression(cx, argument);
+                update((*operator).into(), *prefix, argument)
+            }
+            Expression::DefineAccessor {
+                object,
+                property: key,
+                getter,
+                setter,
+                enumerable,
+            } => {
+                let object = self.expression(cx, object);
+                let key = self.expression(cx, key);
+                let mut descriptor = Vec::new();
+                for (name, function) in [("get", getter), ("set", setter)] {
+                    if !is_undefined(function) {
+                        let function = self.expression(cx, function);
+                        descriptor.push(property("init", identifier(name), function, false));
+                    }
+                }
+                descriptor.push(property(
+                    "init",
+                    identifier("enumerable"),
+                    boolean(*enumerable),
+                    false,
+                ));
+                descriptor.push(property(
+                    "init",
+                    identifier("configurable"),
+                    boolean(true),
+                    false,
+                ));
+                call(
+                    path("Object.defineProperty"),
+                    vec![object, key, object_expression(descriptor)],
+                )
+            }
+            Expression::DefineProperty {
+                object,
+                property: key,
+                value,
+            } => {
+                let object = self.expression(cx, object);
+                let key = match key {
+                    MemberProperty::Named(name) => string(&self.decompiled.units(*name)),
+                    MemberProperty::Index(index) => number(f64::from(*index)),
+                    MemberProperty::Computed(key) => self.expression(cx, key),
+                };
+                let value = self.expression(cx, value);
+                let descriptor = vec![
+                    property("init", identifier("value"), value, false),
+                    property("init", identifier("writable"), boolean(true), false),
+                    property("init", identifier("configurable"), boolean(true), false),
+                ];
+                call(
+                    path("Object.defineProperty"),
+                    vec![object, key, object_expression(descriptor)],
+                )
+            }
+            Expression::NewObject { parent, buffer } => {
+                let mut properties = Vec::new();
+                if let Some(parent) = parent {
+                    let parent = self.expression(cx, parent);
+                    properties.push(property("init", identifier("__proto__"), parent, false));
+                }
+                if let Some((length, keys, values)) = buffer {
+                    let literals = self
+                        .decompiled
+                        .bytecode
+                        .object_literals(*keys, *values, *length)
+                        .unwrap_or_default();
+                    for (key, value) in literals {
+                        let key = match key {
+                            Literal::String(name) => self.key(name),
+                            key => self.literal(&key),
+                        };
+                        properties.push(property("init", key, self.literal(&value), false));
+                    }
+                }
+                object_expression(properties)
+            }
+            Expression::NewArray { size, buffer } => match buffer {
+                Some((length, offset)) => {
+                    let elements: Vec<Json> = self
+                        .decompiled
+                        .bytecode
+                        .array_literals(*offset, *length)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|literal| self.literal(literal))
+                        .collect();
+                    node("ArrayExpression", vec![("elements", elements.into())])
+                }
+                None if *size == 0 => node(
+                    "ArrayExpression",
+                    vec![("elements", Json::Array(Vec::new()))],
+                ),
+                None => node(
+                    "NewExpression",
+                    vec![
+                        ("callee", identifier("Array")),
+                        ("arguments", vec![number(f64::from(*size))].into()),
+                    ],
+                ),
+            },
+            Expression::Call {
+                callee,
+                this,
+                arguments,
+            } => match exponentiation(expression) {
+                Some((base, exponent)) => {
+                    let base = self.expression(cx, base);
+                    let exponent = self.expression(cx, exponent);
+                    binary("**", base, exponent)
+                }
+                None => self.call(cx, callee, this, arguments),
+            },
+            Expression::Construct {
+                callee, arguments, ..
+            } => {
+                let callee = self.expression(cx, callee);
+                let arguments = self.arguments(cx, arguments);
+                node(
+                    "NewExpression",
+                    vec![("callee", callee), ("arguments", arguments.into())],
+                )
+            }
+            Expression::Builtin(builtin) => path((*builtin).into()),
+            Expression::Function(function) => self.function(*function, Form::Expression),
+            Expression::Closure { function, .. } => self.function(*function, Form::Expression),
+            Expression::Class {
+                function,
+                superclass,
+                members,
+                ..
+            } => self.class(cx, *function, superclass.as_deref(), members),
+            Expression::Template {
+                quasis,
+                expressions,
+            } => {
+                let elements: Vec<Json> = (0..=expressions.len())
+                    .map(|index| {
+                        let units = quasis
+                            .get(index)
+                            .copied()
+                            .flatten()
+                            .map(|quasi| self.decompiled.units(quasi))
+                            .unwrap_or_default();
+                        let raw = template_text(&units);
+                        template_element(raw, Json::String(units), index == expressions.len())
+                    })
+                    .collect();
+                let expressions: Vec<Json> = expressions
+                    .iter()
+                    .map(|expression| self.expression(cx, expression))
+                    .collect();
+                template_literal(elements, expressions)
+            }
+            Expression::TaggedTemplate {
+                tag,
+                quasis,
+                expressions,
+                ..
+            } => {
+                let tag = self.expression(cx, tag);
+                // Only the raw strings are kept, which is what a tag with
+                // no escapes in it reads anyway.
+                let elements: Vec<Json> = (0..=expressions.len())
+                    .map(|index| {
+                        let raw = quasis
+                            .get(index)
+                            .map(|quasi| self.decompiled.text(*quasi))
+                            .unwrap_or_default();
+                        template_element(raw, Json::Null, index == expressions.len())
+                    })
+                    .collect();
+                let expressions: Vec<Json> = expressions
+                    .iter()
+                    .map(|expression| self.expression(cx, expression))
+                    .collect();
+                node(
+                    "TaggedTemplateExpression",
+                    vec![
+                        ("tag", tag),
+                        ("quasi", template_literal(elements, expressions)),
+                    ],
+                )
+            }
+            Expression::ArrayLiteral(elements) => {
+                let elements: Vec<Json> = elements
+                    .iter()
+                    .map(|element| match element {
+                        Some(element) => self.expression(cx, element),
+                        None => Json::Null,
+                    })
+                    .collect();
+                node("ArrayExpression", vec![("elements", elements.into())])
+            }
+            Expression::ObjectLiteral(properties) => {
+                let properties: Vec<Json> = properties
+                    .iter()
+                    .map(|property| self.object_property(cx, property))
+                    .collect();
+                object_expression(properties)
+            }
+            Expression::Spread(argument) => {
+                let argument = self.expression(cx, argument);
+                node("SpreadElement", vec![("argument", argument)])
+            }
+            Expression::RestParameter(parameter) => call(
+                path("Array.prototype.slice.call"),
+                vec![
+                    identifier("arguments"),
+                    number(f64::from(parameter.saturating_sub(1))),
+                ],
+            ),
+            Expression::ArgumentsElement(index) => {
+                let index = self.expression(cx, index);
+                computed_member(identifier("arguments"), index)
+            }
+            Expression::ArgumentsLength => named_member(identifier("arguments"), "length"),
+            Expression::CreateThis { prototype, .. } => {
+                let prototype = self.expression(cx, prototype);
+                call(path("Object.create"), vec![prototype])
+            }
+            Expression::SelectObject { this, result } if !result.has_side_effects() => {
+                let this = self.expression(cx, this);
+                let result = self.expression(cx, result);
+                select_object(result.clone(), result, this)
+            }
+            Expression::SelectObject { result, .. } => {
+                let result = self.expression(cx, result);
+                let text = "unresolved: `this` when not an object";
+                comment(result, "trailingComments", "Block", text)
+            }
+            Expression::Eval(argument) => {
+                let argument = self.expression(cx, argument);
+                call(identifier("eval"), vec![argument])
+            }
+            Expression::Require(module) => call(identifier("require"), vec![self.path(*module)]),
+            Expression::Imported { name, .. } => {
+                identifier(&import_binding(&self.decompiled.text(*name)))
+            }
+            Expression::Yield { argument, delegate } => {
+                let argument = match (delegate, argument.as_ref()) {
+                    (false, Expression::Literal(Literal::Undefined)) => Json::Null,
+                    (_, argument) => self.expression(cx, argument),
+                };
+                node(
+                    "YieldExpression",
+                    vec![("delegate", (*delegate).into()), ("argument", argument)],
+                )
+            }
+        }
+    }
+
+    fn literal(&self, literal: &Literal) -> Json {
+        match literal {
+            Literal::String(index) => string(&self.decompiled.units(*index)),
+            Literal::Number(Number::UInt(number)) => self::number(f64::from(*number)),
+            Literal::Number(Number::Int(number)) => self::number(f64::from(*number)),
+            Literal::Number(Number::Double(number)) => self::number(*number),
+            Literal::Boolean(value) => {
+                let value: &str = (*value).into();
+                boolean(value == "true")
+            }
+            Literal::RegExp(regexp) => {
+                let bytecode = self.decompiled.bytecode;
+                let mut pattern = bytecode
+                    .string(regexp.pattern_index)
+                    .unwrap_or_default()
+                    .replace('\n', "\\n")
+                    .replace('\r', "\\r");
+                if pattern.is_empty() {
+                    pattern = String::from("(?:)");
+                }
+                let flags = bytecode.string(regexp.flag_index).unwrap_or_default();
+                let raw = format!("/{}/{}", pattern, flags);
+                let regex =
+                    Json::Object(vec![("pattern", pattern.into()), ("flags", flags.into())]);
+                node(
+                    "Literal",
+                    vec![("value", Json::Null), ("raw", raw.into()), ("regex", regex)],
+                )
+            }
+            Literal::Null => node(
+                "Literal",
+                vec![("value", Json::Null), ("raw", "null".into())],
+            ),
+            Literal::Undefined => identifier("undefined"),
+        }
+    }
+
+    /// A property name as an object key.
+    fn key(&self, name: StringIndex) -> Json {
+        let text = self.decompiled.text(name);
+        match is_identifier_name(&text) {
+            true => identifier(&text),
+            false => string(&self.decompiled.units(name)),
+        }
+    }
+
+    /// A property key, and whether it is computed.
+    fn property_key(&mut self, cx: &Context, key: &MemberProperty) -> (Json, bool) {
+        match key {
+            MemberProperty::Named(name) => (self.key(*name), false),
+            MemberProperty::Index(index) => (number(f64::from(*index)), false),
+            MemberProperty::Computed(key) => (self.expression(cx, key), true),
+        }
+    }
+
+    fn member(&mut self, cx: &Context, object: &Expression, property: &MemberProperty) -> Json {
+        if let (Expression::Global, MemberProperty::Named(name)) = (object, property) {
+            let name = self.decompiled.text(*name);
+            if is_identifier(&name) {
+                return identifier(&name);
+            }
+        }
+        self.qualified_member(cx, object, property)
+    }
+
+    /// `object.property`, even on the global object, since `delete` of a
+    /// bare name is an error in strict code.
+    fn qualified_member(
+        &mut self,
+        cx: &Context,
+        object: &Expression,
+        property: &MemberProperty,
+    ) -> Json {
+        let (object, optional) = match object {
+            Expression::Optional(object) => (object.as_ref(), true),
+            object => (object, false),
+        };
+        let object = self.element(cx, object);
+        let (property, computed) = match property {
+            MemberProperty::Named(name) => {
+                let text = self.decompiled.text(*name);
+                match is_identifier_name(&text) {
+                    true => (identifier(&text), false),
+                    false => (string(&self.decompiled.units(*name)), true),
+                }
+            }
+            MemberProperty::Index(index) => (number(f64::from(*index)), true),
+            MemberProperty::Computed(key) => (self.expression(cx, key), true),
+        };
+        member(object, property, computed, optional)
+    }
+
+    fn call(
+        &mut self,
+        cx: &Context,
+        callee: &Expression,
+        this: &Expression,
+        arguments: &[Expression],
+    ) -> Json {
+        let mut arguments = self.arguments(cx, arguments);
+        let this_undefined = self.decompiled.is_undefined_this(cx, this);
+        let (callee, optional) = match callee {
+            Expression::Optional(callee) => (callee.as_ref(), true),
+            callee => (callee, false),
+        };
+
+        let callee = match callee {
+            Expression::Member { object, .. } if is_same(unoptional(object), this) => {
+                self.element(cx, callee)
+            }
+            Expression::Member { .. } if this_undefined && !is_global_member(callee) => {
+                let callee = self.expression(cx, callee);
+                node(
+                    "SequenceExpression",
+                    vec![("expressions", vec![number(0.0), callee].into())],
+                )
+            }
+            _ if this_undefined => self.element(cx, callee),
+            _ => {
+                let callee = self.element(cx, callee);
+                arguments.insert(0, self.expression(cx, this));
+                return node(
+                    "CallExpression",
+                    vec![
+                        (
+                            "callee",
+                            member(callee, identifier("call"), false, optional),
+                        ),
+                        ("arguments", arguments.into()),
+                        ("optional", false.into()),
+                    ],
+                );
+            }
+        };
+        node(
+            "CallExpression",
+            vec![
+                ("callee", callee),
+                ("arguments", arguments.into()),
+                ("optional", optional.into()),
+            ],
+        )
+    }
+
+    fn arguments(&mut self, cx: &Context, arguments: &[Expression]) -> Vec<Json> {
+        arguments
+            .iter()
+            .map(|argument| self.expression(cx, argument))
+            .collect()
+    }
+
+    fn object_property(&mut self, cx: &Context, object_property: &ObjectProperty) -> Json {
+        match object_property {
+            ObjectProperty::Value { key, value } => {
+                let (key, computed) = self.property_key(cx, key);
+                let value = self.expression(cx, value);
+                property("init", key, value, computed)
+            }
+            ObjectProperty::Getter { key, function } | ObjectProperty::Setter { key, function } => {
+                let (key, computed) = self.property_key(cx, key);
+                let kind = match object_property {
+                    ObjectProperty::Getter { .. } => "get",
+                    _ => "set",
+                };
+                match function {
+                    Expression::Closure { function, .. } => {
+                        let function = self.function(*function, Form::Method);
+                        property(kind, key, function, computed)
+                    }
+                    function => {
+                        let function = self.expression(cx, function);
+                        property("init", key, function, computed)
+                    }
+                }
+            }
+            ObjectProperty::Spread(argument) => {
+                let argument = self.expression(cx, argument);
+                node("SpreadElement", vec![("argument", argument)])
+            }
+            ObjectProperty::Proto(parent) => {
+                let parent = self.expression(cx, parent);
+                property("init", identifier("__proto__"), parent, false)
+            }
+        }
+    }
+
+    fn class(
+        &mut self,
+        cx: &Context,
+        constructor: u32,
+        superclass: Option<&Expression>,
+        members: &[ClassMember],
+    ) -> Json {
+        let id = self.decompiled.function_name(constructor).map(identifier);
+        let superclass = superclass.map(|superclass| self.expression(cx, superclass));
+
+        let mut body = Vec::new();
+        match self.function_parts(constructor) {
+            Some((_, params, statements)) if params.is_empty() && statements.is_empty() => {}
+            Some((kind, params, statements)) => {
+                let function = function_expression(None, kind, params, statements);
+                body.push(method(
+                    "constructor",
+                    identifier("constructor"),
+                    function,
+                    false,
+                    false,
+                ));
+            }
+            None => {}
+        }
+        for member in members {
+            let (key, computed) = self.property_key(cx, &member.key);
+            let kind = match member.kind {
+                ClassMemberKind::Method => "method",
+                ClassMemberKind::Getter => "get",
+                ClassMemberKind::Setter => "set",
+            };
+            let definition = match &member.function {
+                Expression::Closure { function, .. } => {
+                    let function = self.function(*function, Form::Method);
+                    method(kind, key, function, computed, member.is_static)
+                }
+                function => {
+                    let value = self.expression(cx, function);
+                    node(
+                        "PropertyDefinition",
+                        vec![
+                            ("key", key),
+                            ("value", value),
+                            ("computed", computed.into()),
+                            ("static", member.is_static.into()),
+                        ],
+                    )
+                }
+            };
+            body.push(definition);
+        }
+
+        node(
+            "ClassExpression",
+            vec![
+                ("id", id.into()),
+                ("superClass", superclass.into()),
+                ("body", node("ClassBody", vec![("body", body.into())])),
+            ],
+        )
+    }
+}
+
+/// A node of `kind` with `fields` after its `type`.
+fn node(kind: &'static str, fields: Vec<(&'static str, Json)>) -> Json {
+    let mut members = vec![("type", Json::from(kind))];
+    members.extend(fields);
+    Json::Object(members)
+}
+
+/// `node` with a `Block` or `Line` comment of `text` under `key`, the
+/// `leadingComments`, `innerComments` or `trailingComments` Babel and
+/// recast read.
+fn comment(node: Json, key: &'static str, kind: &str, text: &str) -> Json {
+    let value = match kind {
+        "Line" => format!(" {}", text),
+        _ => format!(" {} ", text),
+    };
+    let comment = Json::Object(vec![("type", kind.into()), ("value", value.into())]);
+    match node {
+        Json::Object(mut members) => {
+            members.push((key, vec![comment].into()));
+            Json::Object(members)
+        }
+        node => node,
+    }
+}
+
+/// `undefined`, standing in for what the passes left unresolved.
+fn unresolved_value(description: &str) -> Json {
+    let text = format!("unresolved: {}", description);
+    comment(identifier("undefined"), "trailingComments", "Block", &text)
+}
+
+fn identifier(name: &str) -> Json {
+    node("Identifier", vec![("name", name.into())])
+}
+
+/// A dotted name like `Object.keys` as the member expressions it reads.
+fn path(name: &str) -> Json {
+    let mut parts = name.split('.');
+    let first = identifier(parts.next().unwrap_or_default());
+    parts.fold(first, named_member)
+}
+
+fn string(units: &[u16]) -> Json {
+    node(
+        "Literal",
+        vec![
+            ("value", Json::String(units.to_vec())),
+            ("raw", string_literal(units).into()),
+        ],
+    )
+}
+
+/// A number, negative ones and those with no literal of their own spelled
+/// the way source spells them.
+fn number(value: f64) -> Json {
+    if value.is_nan() {
+        identifier("NaN")
+    } else if value.is_sign_negative() {
+        unary("-", number(-value))
+    } else if value.is_infinite() {
+        identifier("Infinity")
+    } else {
+        node(
+            "Literal",
+            vec![
+                ("value", value.into()),
+                ("raw", number_literal(value).into()),
+            ],
+        )
+    }
+}
+
+fn boolean(value: bool) -> Json {
+    let raw = match value {
+        true => "true",
+        false => "false",
+    };
+    node(
+        "Literal",
+        vec![("value", value.into()), ("raw", raw.into())],
+    )
+}
+
+fn unary(operator: &str, argument: Json) -> Json {
+    node(
+        "UnaryExpression",
+        vec![
+            ("operator", operator.into()),
+            ("prefix", true.into()),
+            ("argument", argument),
+        ],
+    )
+}
+
+fn update(operator: &str, prefix: bool, argument: Json) -> Json {
+    node(
+        "UpdateExpression",
+        vec![
+            ("operator", operator.into()),
+            ("prefix", prefix.into()),
+            ("argument", argument),
+        ],
+    )
+}
+
+fn binary(operator: &str, left: Json, right: Json) -> Json {
+    node(
+        "BinaryExpression",
+        vec![
+            ("operator", operator.into()),
+            ("left", left),
+            ("right", right),
+        ],
+    )
+}
+
+fn member(object: Json, property: Json, computed: bool, optional: bool) -> Json {
+    node(
+        "MemberExpression",
+        vec![
+            ("object", object),
+            ("property", property),
+            ("computed", computed.into()),
+            ("optional", optional.into()),
+        ],
+    )
+}
+
+fn named_member(object: Json, name: &str) -> Json {
+    member(object, identifier(name), false, false)
+}
+
+fn computed_member(object: Json, property: Json) -> Json {
+    member(object, property, true, false)
+}
+
+fn call(callee: Json, arguments: Vec<Json>) -> Json {
+    node(
+        "CallExpression",
+        vec![
+            ("callee", callee),
+            ("arguments", arguments.into()),
+            ("optional", false.into()),
+        ],
+    )
+}
+
+fn chain(expression: Json) -> Json {
+    node("ChainExpression", vec![("expression", expression)])
+}
+
+/// `result instanceof Object ? object : this`, what `new` evaluates to.
+fn select_object(result: Json, object: Json, this: Json) -> Json {
+    node(
+        "ConditionalExpression",
+        vec![
+            ("test", binary("instanceof", result, identifier("Object"))),
+            ("consequent", object),
+            ("alternate", this),
+        ],
+    )
+}
+
+fn function_expression(
+    id: Option<Json>,
+    kind: FunctionKind,
+    params: Vec<Json>,
+    body: Vec<Json>,
+) -> Json {
+    node(
+        "FunctionExpression",
+        vec![
+            ("id", id.into()),
+            ("params", params.into()),
+            ("body", block_statement(body)),
+            ("generator", (kind == FunctionKind::Generator).into()),
+            ("async", (kind == FunctionKind::Async).into()),
+        ],
+    )
+}
+
+fn property(kind: &str, key: Json, value: Json, computed: bool) -> Json {
+    node(
+        "Property",
+        vec![
+            ("key", key),
+            ("value", value),
+            ("kind", kind.into()),
+            ("method", false.into()),
+            ("shorthand", false.into()),
+            ("computed", computed.into()),
+        ],
+    )
+}
+
+/// A property of an object pattern, shorthand when it binds its own key.
+fn pattern_property(key: Json, value: Json, computed: bool) -> Json {
+    let shorthand = !computed && key == value;
+    node(
+        "Property",
+        vec![
+            ("key", key),
+            ("value", value),
+            ("kind", "init".into()),
+            ("method", false.into()),
+            ("shorthand", shorthand.into()),
+            ("computed", computed.into()),
+        ],
+    )
+}
+
+fn method(kind: &str, key: Json, value: Json, computed: bool, is_static: bool) -> Json {
+    node(
+        "MethodDefinition",
+        vec![
+            ("key", key),
+            ("value", value),
+            ("kind", kind.into()),
+            ("computed", computed.into()),
+            ("static", is_static.into()),
+        ],
+    )
+}
+
+fn object_expression(properties: Vec<Json>) -> Json {
+    node("ObjectExpression", vec![("properties", properties.into())])
+}
+
+fn template_element(raw: String, cooked: Json, tail: bool) -> Json {
+    let value = Json::Object(vec![("raw", raw.into()), ("cooked", cooked)]);
+    node(
+        "TemplateElement",
+        vec![("value", value), ("tail", tail.into())],
+    )
+}
+
+fn template_literal(quasis: Vec<Json>, expressions: Vec<Json>) -> Json {
+    node(
+        "TemplateLiteral",
+        vec![
+            ("quasis", quasis.into()),
+            ("expressions", expressions.into()),
+        ],
+    )
+}
+
+fn expression_statement(expression: Json) -> Json {
+    node("ExpressionStatement", vec![("expression", expression)])
+}
+
+fn assignment_statement(left: Json, right: Json) -> Json {
+    expression_statement(node(
+        "AssignmentExpression",
+        vec![("operator", "=".into()), ("left", left), ("right", right)],
+    ))
+}
+
+fn variable_declaration(kind: &str, declarators: Vec<(Json, Option<Json>)>) -> Json {
+    let declarations: Vec<Json> = declarators
+        .into_iter()
+        .map(|(id, init)| {
+            node(
+                "VariableDeclarator",
+                vec![("id", id), ("init", init.into())],
+            )
+        })
+        .collect();
+    node(
+        "VariableDeclaration",
+        vec![("kind", kind.into()), ("declarations", declarations.into())],
+    )
+}
+
+fn import_declaration(specifiers: Vec<Json>, source: Json) -> Json {
+    node(
+        "ImportDeclaration",
+        vec![("specifiers", specifiers.into()), ("source", source)],
+    )
+}
+
+fn block_statement(body: Vec<Json>) -> Json {
+    node("BlockStatement", vec![("body", body.into())])
+}
+
+fn labeled(label: &str, body: Json) -> Json {
+    node(
+        "LabeledStatement",
+        vec![("label", identifier(label)), ("body", body)],
+    )
+}
+
+fn loop_labeled(label: Option<usize>, statement: Json) -> Json {
+    match label {
+        Some(label) => labeled(&format!("loop{}", label), statement),
+        None => statement,
+    }
+}
+
+fn switch_case(test: Option<Json>, consequent: Vec<Json>) -> Json {
+    node(
+        "SwitchCase",
+        vec![("test", test.into()), ("consequent", consequent.into())],
+    )
+}
+
+/// A `break` or `continue`, to `label` if there is one.
+fn jump(kind: &'static str, label: Option<&str>) -> Json {
+    node(kind, vec![("label", label.map(identifier).into())])
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+
+/// A JSON value. Strings are kept as UTF-16 so lone surrogates in the
+/// program's strings survive as `\uXXXX` escapes, and object members stay
+/// in the order they were added.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Boolean(bool),
+    /// Written as `null` when not finite, as JSON has no spelling for it.
+    Number(f64),
+    String(Vec<u16>),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+static NULL: Json = Json::Null;
+
+impl Json {
+    /// The member `key` of an object, `null` when there is none.
+    pub fn get(&self, key: &str) -> &Json {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(name, _)| *name == key)
+                .map_or(&NULL, |(_, value)| value),
+            _ => &NULL,
+        }
+    }
+
+    /// The text of a string, empty for anything else.
+    pub fn text(&self) -> String {
+        match self {
+            Json::String(units) => String::from_utf16_lossy(units),
+            _ => String::new(),
+        }
+    }
+
+    /// Whether this is a string spelling `text`.
+    pub fn is(&self, text: &str) -> bool {
+        match self {
+            Json::String(units) => units.iter().copied().eq(text.encode_utf16()),
+            _ => false,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Json::Null)
+    }
+
+    pub fn is_true(&self) -> bool {
+        matches!(self, Json::Boolean(true))
+    }
+
+    /// The elements of an array, none for anything else.
+    pub fn elements(&self) -> &[Json] {
+        match self {
+            Json::Array(elements) => elements,
+            _ => &[],
+        }
+    }
+
+    /// The `type` of an ESTree node.
+    pub fn kind(&self) -> String {
+        self.get("type").text()
+    }
+}
+
+impl From<bool> for Json {
+    fn from(boolean: bool) -> Self {
+        Json::Boolean(boolean)
+    }
+}
+
+impl From<f64> for Json {
+    fn from(number: f64) -> Self {
+        Json::Number(number)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(text: &str) -> Self {
+        Json::String(text.encode_utf16().collect())
+    }
+}
+
+impl From<String> for Json {
+    fn from(text: String) -> Self {
+        Json::from(text.as_str())
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(elements: Vec<Json>) -> Self {
+        Json::Array(elements)
+    }
+}
+
+impl From<Option<Json>> for Json {
+    fn from(value: Option<Json>) -> Self {
+        value.unwrap_or(Json::Null)
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Boolean(boolean) => write!(f, "{}", boolean),
+            Json::Number(number) if !number.is_finite() => f.write_str("null"),
+            Json::Number(number) => f.write_str(&super::number_literal(*number)),
+            Json::String(units) => f.write_str(&json_units(units)),
+            Json::Array(elements) => {
+                f.write_str("[")?;
+                for (index, element) in elements.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(members) => {
+                f.write_str("{")?;
+                for (index, (key, value)) in members.iter().enumerate() {
+                    if index > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}:{}", json_string(key), value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// `text` as a JSON string.
+pub fn json_string(text: &str) -> String {
+    json_units(&text.encode_utf16().collect::<Vec<u16>>())
+}
+
+fn json_units(units: &[u16]) -> String {
+    let mut quoted = String::from("\"");
+    for decoded in char::decode_utf16(units.iter().copied()) {
+        match decoded {
+            Ok('"') => quoted.push_str("\\\""),
+            Ok('\\') => quoted.push_str("\\\\"),
+            Ok('\n') => quoted.push_str("\\n"),
+            Ok('\r') => quoted.push_str("\\r"),
+            Ok('\t') => quoted.push_str("\\t"),
+            Ok(c) if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            Ok(c) => quoted.push(c),
+            Err(error) => quoted.push_str(&format!("\\u{:04x}", error.unpaired_surrogate())),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
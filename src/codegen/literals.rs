@@ -0,0 +1,164 @@
+use crate::ast::{is_reserved, number_to_string};
+
+/// A double-quoted string literal spelling `units`. Surrogates that do not
+/// pair up are kept as `\u` escapes, so the literal is the same string.
+pub fn string_literal(units: &[u16]) -> String {
+    let mut literal = String::from("\"");
+    escape(units, '"', &mut literal);
+    literal.push('"');
+    literal
+}
+
+/// The cooked text of a template literal, between its backticks.
+pub fn template_text(units: &[u16]) -> String {
+    let mut text = String::new();
+    escape(units, '`', &mut text);
+    text
+}
+
+fn escape(units: &[u16], quote: char, out: &mut String) {
+    let decoded: Vec<Result<char, u16>> = char::decode_utf16(units.iter().copied())
+        .map(|decoded| decoded.map_err(|error| error.unpaired_surrogate()))
+        .collect();
+
+    for (index, unit) in decoded.iter().enumerate() {
+        let c = match unit {
+            Ok(c) => *c,
+            Err(surrogate) => {
+                out.push_str(&format!("\\u{:04X}", surrogate));
+                continue;
+            }
+        };
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{8}' => out.push_str("\\b"),
+            '\u{b}' => out.push_str("\\v"),
+            '\u{c}' => out.push_str("\\f"),
+            c if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            // `${` would start a substitution in a template.
+            '$' if quote == '`' && decoded.get(index + 1) == Some(&Ok('{')) => out.push_str("\\$"),
+            c if c < ' ' || c == '\u{7f}' => out.push_str(&format!("\\x{:02X}", c as u32)),
+            '\u{2028}' | '\u{2029}' => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+}
+
+/// A numeric literal that reads back as `number`, sign included. `NaN`,
+/// `Infinity` and `-0` have no literal and are spelled as expressions.
+pub fn number_literal(number: f64) -> String {
+    match number == 0.0 && number.is_sign_negative() {
+        true => String::from("-0"),
+        false => number_to_string(number),
+    }
+}
+
+/// Whether `text` can follow a `.` or stand as a property key unquoted.
+pub fn is_identifier_name(text: &str) -> bool {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' || first == '$' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+        }
+        _ => false,
+    }
+}
+
+/// Whether `text` can name a variable.
+pub fn is_identifier(text: &str) -> bool {
+    is_identifier_name(text) && !is_reserved(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn units(text: &str) -> Vec<u16> {
+        text.encode_utf16().collect()
+    }
+
+    #[test]
+    fn string_literal_escapes() {
+        let cases = [
+            ("", r#""""#),
+            ("plain", r#""plain""#),
+            ("say \"hi\"", r#""say \"hi\"""#),
+            ("it's", r#""it's""#),
+            ("a\\b", r#""a\\b""#),
+            ("\n\r\t\u{8}\u{b}\u{c}", r#""\n\r\t\b\v\f""#),
+            ("\0\u{1}\u{1f}\u{7f}", r#""\x00\x01\x1F\x7F""#),
+            ("\u{2028}\u{2029}", r#""\u2028\u2029""#),
+            ("`${x}`", r#""`${x}`""#),
+            ("é😀", "\"é😀\""),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(string_literal(&units(text)), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn lone_surrogates_stay_escaped() {
+        assert_eq!(string_literal(&[0xd800]), r#""\uD800""#);
+        assert_eq!(string_literal(&[0x61, 0xdc00, 0x62]), r#""a\uDC00b""#);
+        assert_eq!(string_literal(&[0xdc00, 0xd800]), r#""\uDC00\uD800""#);
+        assert_eq!(string_literal(&[0xd83d, 0xde00]), "\"😀\"");
+    }
+
+    #[test]
+    fn template_text_escapes() {
+        let cases = [
+            ("a`b", r"a\`b"),
+            ("${x}", r"\${x}"),
+            ("$x", "$x"),
+            ("{$}", "{$}"),
+            ("\"quoted\"", "\"quoted\""),
+            ("line\nbreak", r"line\nbreak"),
+            ("back\\slash", r"back\\slash"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(template_text(&units(text)), expected, "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn number_literal_keeps_the_sign() {
+        assert_eq!(number_literal(-0.0), "-0");
+        assert_eq!(number_literal(0.0), "0");
+        assert_eq!(number_literal(-1.5), "-1.5");
+        assert_eq!(number_literal(1e21), "1e+21");
+        assert_eq!(number_literal(f64::NAN), "NaN");
+        assert_eq!(number_literal(f64::NEG_INFINITY), "-Infinity");
+    }
+
+    #[test]
+    fn identifiers() {
+        for name in ["a", "_", "$", "$v1", "camelCase", "a_b$2"] {
+            assert!(is_identifier(name), "{:?}", name);
+        }
+        for name in [
+            "",
+            "1a",
+            "a-b",
+            "a b",
+            "é",
+            "class",
+            "let",
+            "await",
+            "arguments",
+        ] {
+            assert!(!is_identifier(name), "{:?}", name);
+        }
+        for name in ["class", "default", "let"] {
+            assert!(is_identifier_name(name), "{:?}", name);
+        }
+        for name in ["", "0", "a.b", "\"a\""] {
+            assert!(!is_identifier_name(name), "{:?}", name);
+        }
+    }
+}
@@ -0,0 +1,15 @@
+mod decompiled;
+mod estree;
+mod json;
+mod literals;
+mod precedence;
+mod printer;
+mod structure;
+
+pub use decompiled::*;
+pub use estree::*;
+pub use json::*;
+pub use literals::*;
+pub use precedence::*;
+pub use printer::*;
+pub use structure::*;
@@ -0,0 +1,131 @@
+use crate::{ast::Expression, builtins::Builtins};
+
+use super::Json;
+
+/// How tightly an expression binds, loosest first. An operand binding less
+/// tightly than its position asks for is parenthesized.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Precedence {
+    Sequence,
+    /// Assignments, `yield` and spread.
+    Assignment,
+    Conditional,
+    /// `||` and `??`, which do not mix with `&&` without parentheses.
+    LogicalOr,
+    LogicalAnd,
+    BitwiseOr,
+    BitwiseXor,
+    BitwiseAnd,
+    Equality,
+    Relational,
+    Shift,
+    Additive,
+    Multiplicative,
+    /// `**`, which is right-associative and takes no unary left operand.
+    Exponentiation,
+    Unary,
+    Update,
+    /// Calls, member accesses and `new` with arguments.
+    Call,
+    Primary,
+}
+
+impl Precedence {
+    /// The next tighter level, which the right operand of a left-associative
+    /// operator needs.
+    pub fn tighter(self) -> Self {
+        use Precedence::*;
+
+        match self {
+            Sequence => Assignment,
+            Assignment => Conditional,
+            Conditional => LogicalOr,
+            LogicalOr => LogicalAnd,
+            LogicalAnd => BitwiseOr,
+            BitwiseOr => BitwiseXor,
+            BitwiseXor => BitwiseAnd,
+            BitwiseAnd => Equality,
+            Equality => Relational,
+            Relational => Shift,
+            Shift => Additive,
+            Additive => Multiplicative,
+            Multiplicative => Exponentiation,
+            Exponentiation => Unary,
+            Unary => Update,
+            Update => Call,
+            Call | Primary => Primary,
+        }
+    }
+
+    /// The level of a binary or logical `operator`.
+    pub fn of_operator(operator: &str) -> Self {
+        match operator {
+            "||" | "??" => Self::LogicalOr,
+            "&&" => Self::LogicalAnd,
+            "|" => Self::BitwiseOr,
+            "^" => Self::BitwiseXor,
+            "&" => Self::BitwiseAnd,
+            "==" | "!=" | "===" | "!==" => Self::Equality,
+            "<" | "<=" | ">" | ">=" | "instanceof" | "in" => Self::Relational,
+            "<<" | ">>" | ">>>" => Self::Shift,
+            "+" | "-" => Self::Additive,
+            "*" | "/" | "%" => Self::Multiplicative,
+            "**" => Self::Exponentiation,
+            _ => Self::Primary,
+        }
+    }
+}
+
+/// How tightly `node`, an ESTree expression, binds as the printer spells it.
+pub fn precedence(node: &Json) -> Precedence {
+    match node.kind().as_str() {
+        "SequenceExpression" => Precedence::Sequence,
+        "AssignmentExpression" | "YieldExpression" | "SpreadElement" | "RestElement" => {
+            Precedence::Assignment
+        }
+        "ConditionalExpression" => Precedence::Conditional,
+        "LogicalExpression" | "BinaryExpression" => {
+            Precedence::of_operator(&node.get("operator").text())
+        }
+        "UnaryExpression" | "AwaitExpression" => Precedence::Unary,
+        "UpdateExpression" => Precedence::Update,
+        "CallExpression"
+        | "NewExpression"
+        | "MemberExpression"
+        | "ChainExpression"
+        | "TaggedTemplateExpression"
+        | "MetaProperty" => Precedence::Call,
+        _ => Precedence::Primary,
+    }
+}
+
+/// The base and exponent of a call to the builtin Hermes compiles `**` to.
+pub fn exponentiation(expression: &Expression) -> Option<(&Expression, &Expression)> {
+    match expression {
+        Expression::Call {
+            callee, arguments, ..
+        } if matches!(
+            callee.as_ref(),
+            Expression::Builtin(Builtins::HermesBuiltinExponentiationOperator)
+        ) =>
+        {
+            match arguments.as_slice() {
+                [base, exponent]
+                    if !matches!(base, Expression::Spread(_))
+                        && !matches!(exponent, Expression::Spread(_)) =>
+                {
+                    Some((base, exponent))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Whether an operand of the logical `outer` that is `inner` needs
+/// parentheses even where precedence alone would allow it: `??` cannot be
+/// mixed with `||` or `&&` unparenthesized.
+pub fn mixes_coalescing(outer: &str, inner: &Json) -> bool {
+    inner.kind() == "LogicalExpression" && (outer == "??") != inner.get("operator").is("??")
+}
@@ -0,0 +1,710 @@
+use super::{mixes_coalescing, precedence, Decompiled, Estree, Json, Precedence};
+
+/// Prints restored functions as JavaScript source, spelling out the ESTree
+/// the `Estree` builds for them. Closures are printed where they are
+/// created, as function expressions; functions nothing creates are
+/// appended as expressions of their own.
+pub struct Printer<'a> {
+    decompiled: &'a Decompiled<'a>,
+    indent: usize,
+    lines: Vec<String>,
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(decompiled: &'a Decompiled<'a>) -> Self {
+        Self {
+            decompiled,
+            indent: 0,
+            lines: Vec::new(),
+        }
+    }
+
+    /// The whole program, the global function's body at the top level.
+    pub fn program(&mut self, root: u32) -> String {
+        let program = Estree::new(self.decompiled).program(root);
+        self.print(&program)
+    }
+
+    /// A Metro module, its factory's body at the top level with the
+    /// factory's parameters under the names Metro gives them.
+    pub fn module(&mut self, factory: u32) -> String {
+        let program = Estree::new(self.decompiled).module(factory);
+        self.print(&program)
+    }
+
+    /// The source of `program`, a `Program` node.
+    fn print(&mut self, program: &Json) -> String {
+        self.statements(program.get("body").elements());
+        let mut text = std::mem::take(&mut self.lines).join("\n");
+        text.push('\n');
+        text
+    }
+
+    fn line(&mut self, text: String) {
+        let indent = "  ".repeat(self.indent);
+        self.lines.push(format!("{}{}", indent, text));
+    }
+
+    /// Prints the lines `write` adds one level deeper, and takes them out.
+    fn nested(&mut self, write: impl FnOnce(&mut Self)) -> Vec<String> {
+        let saved = std::mem::take(&mut self.lines);
+        self.indent += 1;
+        write(self);
+        self.indent -= 1;
+        std::mem::replace(&mut self.lines, saved)
+    }
+
+    /// `{ ... }` around `lines`, closed at the current indentation.
+    fn braces(&self, lines: Vec<String>) -> String {
+        match lines.is_empty() {
+            true => String::from("{}"),
+            false => format!("{{\n{}\n{}}}", lines.join("\n"), "  ".repeat(self.indent)),
+        }
+    }
+
+    fn statements(&mut self, statements: &[Json]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn body(&mut self, statements: &[Json]) {
+        self.indent += 1;
+        self.statements(statements);
+        self.indent -= 1;
+    }
+
+    /// `head { body }` for a `BlockStatement`, on one line when it is empty.
+    fn block(&mut self, head: String, block: &Json) {
+        let body = block.get("body").elements();
+        let open = match head.is_empty() {
+            true => String::from("{"),
+            false => format!("{} {{", head),
+        };
+        match body.is_empty() {
+            true => self.line(format!("{}}}", open)),
+            false => {
+                self.line(open);
+                self.body(body);
+                self.line(String::from("}"));
+            }
+        }
+    }
+
+    /// `statement` with its comments, a statement commented ahead set
+    /// apart from the ones before it.
+    fn statement(&mut self, statement: &Json) {
+        let leading = statement.get("leadingComments").elements();
+        if !leading.is_empty() && !self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+        for comment in leading {
+            self.line(comment_text(comment));
+        }
+        self.uncommented(statement);
+        for comment in statement.get("trailingComments").elements() {
+            if let Some(line) = self.lines.last_mut() {
+                line.push(' ');
+                line.push_str(&comment_text(comment));
+            }
+        }
+    }
+
+    fn uncommented(&mut self, statement: &Json) {
+        match statement.kind().as_str() {
+            "ExpressionStatement" => {
+                let text = self.expression(statement.get("expression"), Precedence::Sequence);
+                self.expression_statement(text);
+            }
+            "VariableDeclaration" => {
+                let mut declarators = Vec::new();
+                for declarator in statement.get("declarations").elements() {
+                    let id = self.expression(declarator.get("id"), Precedence::Assignment);
+                    let init = declarator.get("init");
+                    declarators.push(match init.is_null() {
+                        true => id,
+                        false => {
+                            let init = self.expression(init, Precedence::Assignment);
+                            format!("{} = {}", id, init)
+                        }
+                    });
+                }
+                let kind = statement.get("kind").text();
+                self.line(format!("{} {};", kind, declarators.join(", ")));
+            }
+            "BlockStatement" => self.block(String::new(), statement),
+            "LabeledStatement" => {
+                let label = statement.get("label").get("name").text();
+                let body = statement.get("body");
+                match body.kind().as_str() {
+                    "BlockStatement" => self.block(format!("{}:", label), body),
+                    // The label starts the first line of what it labels.
+                    _ => {
+                        let start = self.lines.len();
+                        self.statement(body);
+                        let at = self.indent * 2;
+                        if let Some(line) = self.lines.get_mut(start) {
+                            line.insert_str(at, &format!("{}: ", label));
+                        }
+                    }
+                }
+            }
+            "ForStatement" => {
+                let part = |printer: &mut Self, key: &str, space: &str| {
+                    let part = statement.get(key);
+                    match part.is_null() {
+                        true => String::new(),
+                        false => format!(
+                            "{}{}",
+                            space,
+                            printer.expression(part, Precedence::Sequence)
+                        ),
+                    }
+                };
+                let init = part(self, "init", "");
+                let test = part(self, "test", " ");
+                let update = part(self, "update", " ");
+                let head = format!("for ({};{};{})", init, test, update);
+                self.block(head, statement.get("body"));
+            }
+            "WhileStatement" => {
+                let test = self.expression(statement.get("test"), Precedence::Sequence);
+                self.block(format!("while ({})", test), statement.get("body"));
+            }
+            "ForInStatement" | "ForOfStatement" => {
+                let left = self.expression(statement.get("left"), Precedence::Call);
+                let head = match statement.kind().as_str() {
+                    "ForInStatement" => {
+                        let right = self.expression(statement.get("right"), Precedence::Sequence);
+                        format!("for ({} in {})", left, right)
+                    }
+                    _ => {
+                        let right = self.expression(statement.get("right"), Precedence::Assignment);
+                        let keyword = match statement.get("await").is_true() {
+                            true => "for await",
+                            false => "for",
+                        };
+                        format!("{} ({} of {})", keyword, left, right)
+                    }
+                };
+                self.block(head, statement.get("body"));
+            }
+            "IfStatement" => self.conditional("", statement),
+            "SwitchStatement" => {
+                let discriminant =
+                    self.expression(statement.get("discriminant"), Precedence::Sequence);
+                self.line(format!("switch ({}) {{", discriminant));
+                self.indent += 1;
+                for case in statement.get("cases").elements() {
+                    let test = case.get("test");
+                    match test.is_null() {
+                        true => self.line(String::from("default:")),
+                        false => {
+                            let test = self.expression(test, Precedence::Sequence);
+                            self.line(format!("case {}:", test));
+                        }
+                    }
+                    self.body(case.get("consequent").elements());
+                }
+                self.indent -= 1;
+                self.line(String::from("}"));
+            }
+            "TryStatement" => {
+                self.line(String::from("try {"));
+                self.body(statement.get("block").get("body").elements());
+                let handler = statement.get("handler");
+                if !handler.is_null() {
+                    let param = handler.get("param");
+                    match param.is_null() {
+                        true => self.line(String::from("} catch {")),
+                        false => {
+                            let param = self.expression(param, Precedence::Sequence);
+                            self.line(format!("}} catch ({}) {{", param));
+                        }
+                    }
+                    self.body(handler.get("body").get("body").elements());
+                }
+                let finalizer = statement.get("finalizer");
+                if !finalizer.is_null() {
+                    self.line(String::from("} finally {"));
+                    self.body(finalizer.get("body").elements());
+                }
+                self.line(String::from("}"));
+            }
+            "BreakStatement" | "ContinueStatement" => {
+                let keyword = match statement.kind().as_str() {
+                    "BreakStatement" => "break",
+                    _ => "continue",
+                };
+                let label = statement.get("label");
+                match label.is_null() {
+                    true => self.line(format!("{};", keyword)),
+                    false => self.line(format!("{} {};", keyword, label.get("name").text())),
+                }
+            }
+            "ReturnStatement" => {
+                let argument = statement.get("argument");
+                match argument.is_null() {
+                    true => self.line(String::from("return;")),
+                    false => {
+                        let argument = self.expression(argument, Precedence::Sequence);
+                        self.line(format!("return {};", argument));
+                    }
+                }
+            }
+            "ThrowStatement" => {
+                let argument = self.expression(statement.get("argument"), Precedence::Sequence);
+                self.line(format!("throw {};", argument));
+            }
+            "ImportDeclaration" => self.import(statement),
+            "ExportNamedDeclaration" => {
+                let specifiers: Vec<String> = statement
+                    .get("specifiers")
+                    .elements()
+                    .iter()
+                    .map(|specifier| {
+                        let local = specifier.get("local").get("name").text();
+                        let exported = specifier.get("exported").get("name").text();
+                        match local == exported {
+                            true => local,
+                            false => format!("{} as {}", local, exported),
+                        }
+                    })
+                    .collect();
+                let source = statement.get("source");
+                let from = match source.is_null() {
+                    true => String::new(),
+                    false => format!(" from {}", source.get("raw").text()),
+                };
+                self.line(format!("export {{ {} }}{};", specifiers.join(", "), from));
+            }
+            "ExportDefaultDeclaration" => {
+                let declaration =
+                    self.expression(statement.get("declaration"), Precedence::Assignment);
+                self.line(format!("export default {};", declaration));
+            }
+            "ExportAllDeclaration" => {
+                let source = statement.get("source").get("raw").text();
+                self.line(format!("export * from {};", source));
+            }
+            "DebuggerStatement" => self.line(String::from("debugger;")),
+            _ => {}
+        }
+    }
+
+    fn conditional(&mut self, prefix: &str, statement: &Json) {
+        let test = self.expression(statement.get("test"), Precedence::Sequence);
+        self.line(format!("{}if ({}) {{", prefix, test));
+        self.body(statement.get("consequent").get("body").elements());
+        let alternate = statement.get("alternate");
+        match alternate.kind().as_str() {
+            "" => self.line(String::from("}")),
+            "IfStatement" => self.conditional("} else ", alternate),
+            _ => {
+                self.line(String::from("} else {"));
+                self.body(alternate.get("body").elements());
+                self.line(String::from("}"));
+            }
+        }
+    }
+
+    /// Terminates `text` as a statement, parenthesized where it would
+    /// otherwise start a block or a declaration.
+    fn expression_statement(&mut self, text: String) {
+        let ambiguous = text.starts_with('{')
+            || starts_with_word(&text, "function")
+            || starts_with_word(&text, "class")
+            || text.starts_with("async function")
+            || text.starts_with("let [");
+        match ambiguous {
+            true => self.line(format!("({});", text)),
+            false => self.line(format!("{};", text)),
+        }
+    }
+
+    fn import(&mut self, declaration: &Json) {
+        let source = declaration.get("source").get("raw").text();
+        let mut clause = Vec::new();
+        let mut names = Vec::new();
+        for specifier in declaration.get("specifiers").elements() {
+            let local = specifier.get("local").get("name").text();
+            match specifier.kind().as_str() {
+                "ImportDefaultSpecifier" => clause.push(local),
+                "ImportNamespaceSpecifier" => clause.push(format!("* as {}", local)),
+                _ => {
+                    let imported = specifier.get("imported");
+                    names.push(match imported.get("name").is(&local) {
+                        true => local,
+                        false => {
+                            let imported = self.expression(imported, Precedence::Primary);
+                            format!("{} as {}", imported, local)
+                        }
+                    });
+                }
+            }
+        }
+        if !names.is_empty() {
+            clause.push(format!("{{ {} }}", names.join(", ")));
+        }
+        match clause.is_empty() {
+            true => self.line(format!("import {};", source)),
+            false => self.line(format!("import {} from {};", clause.join(", "), source)),
+        }
+    }
+
+    /// `node`, parenthesized unless it binds at least as tightly as
+    /// `minimum`.
+    fn expression(&mut self, node: &Json, minimum: Precedence) -> String {
+        let text = self.unparenthesized(node);
+        match precedence(node) < minimum {
+            true => format!("({})", text),
+            false => text,
+        }
+    }
+
+    /// `node` followed by the comments after it.
+    fn unparenthesized(&mut self, node: &Json) -> String {
+        let mut text = self.uncommented_expression(node);
+        for comment in node.get("trailingComments").elements() {
+            text.push(' ');
+            text.push_str(&comment_text(comment));
+        }
+        text
+    }
+
+    fn uncommented_expression(&mut self, node: &Json) -> String {
+        match node.kind().as_str() {
+            "Identifier" => node.get("name").text(),
+            "Literal" => node.get("raw").text(),
+            "ThisExpression" => String::from("this"),
+            "MetaProperty" => format!(
+                "{}.{}",
+                node.get("meta").get("name").text(),
+                node.get("property").get("name").text()
+            ),
+            "UnaryExpression" => {
+                let argument = self.expression(node.get("argument"), Precedence::Unary);
+                prefix(&node.get("operator").text(), argument)
+            }
+            "UpdateExpression" => {
+                let operator = node.get("operator").text();
+                let argument = self.expression(node.get("argument"), Precedence::Call);
+                match node.get("prefix").is_true() {
+                    true => format!("{}{}", operator, argument),
+                    false => format!("{}{}", argument, operator),
+                }
+            }
+            "BinaryExpression" => {
+                let operator = node.get("operator").text();
+                let (left, right) = match operator.as_str() {
+                    "**" => (Precedence::Update, Precedence::Exponentiation),
+                    operator => {
+                        let level = Precedence::of_operator(operator);
+                        (level, level.tighter())
+                    }
+                };
+                let left = self.expression(node.get("left"), left);
+                let right = self.expression(node.get("right"), right);
+                format!("{} {} {}", left, operator, right)
+            }
+            "LogicalExpression" => {
+                let operator = node.get("operator").text();
+                let level = Precedence::of_operator(&operator);
+                let (left, right) = (node.get("left"), node.get("right"));
+                let mut left_text = self.expression(left, level);
+                if mixes_coalescing(&operator, left) && precedence(left) >= level {
+                    left_text = format!("({})", left_text);
+                }
+                let mut right_text = self.expression(right, level.tighter());
+                if mixes_coalescing(&operator, right) && precedence(right) > level {
+                    right_text = format!("({})", right_text);
+                }
+                format!("{} {} {}", left_text, operator, right_text)
+            }
+            "ConditionalExpression" => {
+                let test = self.expression(node.get("test"), Precedence::LogicalOr);
+                let consequent = self.expression(node.get("consequent"), Precedence::Assignment);
+                let alternate = self.expression(node.get("alternate"), Precedence::Assignment);
+                format!("{} ? {} : {}", test, consequent, alternate)
+            }
+            "AssignmentExpression" => {
+                let left = self.expression(node.get("left"), Precedence::Call);
+                let right = self.expression(node.get("right"), Precedence::Assignment);
+                format!("{} {} {}", left, node.get("operator").text(), right)
+            }
+            "SequenceExpression" => self.list(node.get("expressions").elements()),
+            "MemberExpression" => {
+                let object = self.object(node.get("object"));
+                let property = node.get("property");
+                let optional = node.get("optional").is_true();
+                match (node.get("computed").is_true(), optional) {
+                    (true, _) => {
+                        let property = self.expression(property, Precedence::Sequence);
+                        let open = if optional { "?.[" } else { "[" };
+                        format!("{}{}{}]", object, open, property)
+                    }
+                    (false, true) => format!("{}?.{}", object, property.get("name").text()),
+                    (false, false) => format!("{}.{}", object, property.get("name").text()),
+                }
+            }
+            "CallExpression" => {
+                let callee = self.object(node.get("callee"));
+                let optional = if node.get("optional").is_true() {
+                    "?."
+                } else {
+                    ""
+                };
+                let arguments = self.list(node.get("arguments").elements());
+                format!("{}{}({})", callee, optional, arguments)
+            }
+            "NewExpression" => {
+                let callee = node.get("callee");
+                let mut callee_text = self.expression(callee, Precedence::Call);
+                if !is_constructor_callee(callee) && precedence(callee) >= Precedence::Call {
+                    callee_text = format!("({})", callee_text);
+                }
+                let arguments = self.list(node.get("arguments").elements());
+                format!("new {}({})", callee_text, arguments)
+            }
+            "ChainExpression" => self.unparenthesized(node.get("expression")),
+            "TaggedTemplateExpression" => {
+                let tag = self.object(node.get("tag"));
+                format!("{}{}", tag, self.template(node.get("quasi")))
+            }
+            "TemplateLiteral" => self.template(node),
+            "ArrayExpression" | "ArrayPattern" => {
+                let elements = node.get("elements").elements();
+                let mut texts: Vec<String> = elements
+                    .iter()
+                    .map(|element| match element.is_null() {
+                        true => String::new(),
+                        false => self.expression(element, Precedence::Assignment),
+                    })
+                    .collect();
+                // A trailing hole needs a comma of its own.
+                if elements.last().is_some_and(Json::is_null) {
+                    texts.push(String::new());
+                }
+                format!("[{}]", texts.join(", "))
+            }
+            "ObjectExpression" | "ObjectPattern" => {
+                let properties: Vec<String> = node
+                    .get("properties")
+                    .elements()
+                    .iter()
+                    .map(|property| self.property(property))
+                    .collect();
+                match properties.is_empty() {
+                    true => String::from("{}"),
+                    false => format!("{{ {} }}", properties.join(", ")),
+                }
+            }
+            "SpreadElement" | "RestElement" => {
+                let argument = self.expression(node.get("argument"), Precedence::Assignment);
+                format!("...{}", argument)
+            }
+            "FunctionExpression" => self.function(node, None),
+            "ClassExpression" => self.class(node),
+            "AwaitExpression" => {
+                let argument = self.expression(node.get("argument"), Precedence::Unary);
+                format!("await {}", argument)
+            }
+            "YieldExpression" => {
+                let keyword = match node.get("delegate").is_true() {
+                    true => "yield*",
+                    false => "yield",
+                };
+                let argument = node.get("argument");
+                match argument.is_null() {
+                    true => String::from(keyword),
+                    false => {
+                        let argument = self.expression(argument, Precedence::Assignment);
+                        format!("{} {}", keyword, argument)
+                    }
+                }
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// `nodes` separated by commas, as arguments or a sequence.
+    fn list(&mut self, nodes: &[Json]) -> String {
+        let texts: Vec<String> = nodes
+            .iter()
+            .map(|node| self.expression(node, Precedence::Assignment))
+            .collect();
+        texts.join(", ")
+    }
+
+    /// What a member access, call or tagged template goes on, parenthesized
+    /// where it ends an optional chain the access would otherwise join.
+    fn object(&mut self, node: &Json) -> String {
+        let text = self.expression(node, Precedence::Call);
+        // `1.toString` would read the dot as a decimal point.
+        let number = node.kind() == "Literal" && matches!(node.get("value"), Json::Number(_));
+        match (node.kind() == "ChainExpression" || number) && !text.starts_with('(') {
+            true => format!("({})", text),
+            false => text,
+        }
+    }
+
+    fn template(&mut self, node: &Json) -> String {
+        let expressions = node.get("expressions").elements();
+        let mut text = String::from("`");
+        for (index, quasi) in node.get("quasis").elements().iter().enumerate() {
+            text.push_str(&quasi.get("value").get("raw").text());
+            if let Some(expression) = expressions.get(index) {
+                let expression = self.expression(expression, Precedence::Sequence);
+                text.push_str(&format!("${{{}}}", expression));
+            }
+        }
+        text.push('`');
+        text
+    }
+
+    /// The key of a property or class member.
+    fn key(&mut self, node: &Json) -> String {
+        let key = node.get("key");
+        match node.get("computed").is_true() {
+            true => format!("[{}]", self.expression(key, Precedence::Assignment)),
+            false => self.expression(key, Precedence::Primary),
+        }
+    }
+
+    fn property(&mut self, node: &Json) -> String {
+        if node.kind() != "Property" {
+            return self.expression(node, Precedence::Assignment);
+        }
+        let key = self.key(node);
+        let value = node.get("value");
+        if node.get("shorthand").is_true() {
+            return key;
+        }
+        match node.get("kind").text().as_str() {
+            kind @ ("get" | "set") if value.kind() == "FunctionExpression" => {
+                self.function(value, Some((&format!("{} ", kind), key)))
+            }
+            _ => format!(
+                "{}: {}",
+                key,
+                self.expression(value, Precedence::Assignment)
+            ),
+        }
+    }
+
+    /// A function expression, or the method `key` with `prefix` before it.
+    fn function(&mut self, node: &Json, method: Option<(&str, String)>) -> String {
+        let (generator, is_async) = (node.get("generator").is_true(), node.get("async").is_true());
+        let head = match method {
+            None => {
+                let keyword = match (is_async, generator) {
+                    (true, _) => "async function",
+                    (false, true) => "function*",
+                    (false, false) => "function",
+                };
+                let id = node.get("id");
+                let name = match node.get("innerComments").elements().first() {
+                    _ if !id.is_null() => format!(" {}", id.get("name").text()),
+                    Some(comment) => format!(" {} ", comment_text(comment)),
+                    None => String::new(),
+                };
+                format!("{}{}", keyword, name)
+            }
+            Some((prefix, key)) => {
+                let star = match (is_async, generator) {
+                    (true, _) => "async ",
+                    (false, true) => "*",
+                    (false, false) => "",
+                };
+                format!("{}{}{}", prefix, star, key)
+            }
+        };
+        let params = self.list(node.get("params").elements());
+        let body = node.get("body").get("body").elements();
+        let lines = self.nested(|printer| printer.statements(body));
+        format!("{}({}) {}", head, params, self.braces(lines))
+    }
+
+    fn class(&mut self, node: &Json) -> String {
+        let mut head = String::from("class");
+        let id = node.get("id");
+        if !id.is_null() {
+            head.push(' ');
+            head.push_str(&id.get("name").text());
+        }
+        let superclass = node.get("superClass");
+        if !superclass.is_null() {
+            let superclass = self.expression(superclass, Precedence::Call);
+            head.push_str(&format!(" extends {}", superclass));
+        }
+
+        let mut body = Vec::new();
+        let indent = "  ".repeat(self.indent + 1);
+        self.indent += 1;
+        for member in node.get("body").get("body").elements() {
+            let key = self.key(member);
+            let value = member.get("value");
+            let is_static = match member.get("static").is_true() {
+                true => "static ",
+                false => "",
+            };
+            let text = match member.kind().as_str() {
+                "MethodDefinition" if value.kind() == "FunctionExpression" => {
+                    let prefix = match member.get("kind").text().as_str() {
+                        "get" => format!("{}get ", is_static),
+                        "set" => format!("{}set ", is_static),
+                        _ => String::from(is_static),
+                    };
+                    self.function(value, Some((&prefix, key)))
+                }
+                _ => {
+                    let value = self.expression(value, Precedence::Assignment);
+                    format!("{}{} = {};", is_static, key, value)
+                }
+            };
+            body.push(format!("{}{}", indent, text));
+        }
+        self.indent -= 1;
+        format!("{} {}", head, self.braces(body))
+    }
+}
+
+/// A comment node as source.
+fn comment_text(comment: &Json) -> String {
+    let value = comment.get("value").text();
+    match comment.get("type").is("Line") {
+        true => format!("//{}", value),
+        false => format!("/*{}*/", value),
+    }
+}
+
+/// `operator argument`, spaced where the two would run together into
+/// another token like `--` or `+ +`.
+fn prefix(operator: &str, argument: String) -> String {
+    let alphabetic = operator.chars().all(char::is_alphabetic);
+    let joins = operator
+        .chars()
+        .last()
+        .is_some_and(|last| argument.starts_with(last));
+    match alphabetic || joins {
+        true => format!("{} {}", operator, argument),
+        false => format!("{}{}", operator, argument),
+    }
+}
+
+fn starts_with_word(text: &str, word: &str) -> bool {
+    text.starts_with(word)
+        && !text[word.len()..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Whether `new callee()` reads `callee` whole, which a call or optional
+/// chain inside it would cut short.
+fn is_constructor_callee(callee: &Json) -> bool {
+    match callee.kind().as_str() {
+        "MemberExpression" => is_constructor_callee(callee.get("object")),
+        _ => precedence(callee) == Precedence::Primary,
+    }
+}
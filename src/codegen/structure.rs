@@ -0,0 +1,971 @@
+use std::collections::HashSet;
+
+use crate::{
+    analysis::{BlockId, Dominators, ValueId},
+    ast::{Expression, LiftedFunction, Statement, Terminator},
+    ir::{BinaryOperator, Literal, UnaryOperator},
+};
+
+/// A function's statements with the jumps between its blocks rebuilt as
+/// nested blocks, loops and conditionals. Labels are block ids: a `break`
+/// names the block it leads to, a `continue` the block its loop starts at.
+#[derive(Debug, Clone)]
+pub enum Structured {
+    Statement(Statement),
+    /// `label: { body }`, which a `break` leaves for the code after it.
+    Block {
+        label: BlockId,
+        body: Vec<Structured>,
+    },
+    /// `for (;;) { body }`
+    Loop {
+        label: Option<BlockId>,
+        body: Vec<Structured>,
+    },
+    While {
+        label: Option<BlockId>,
+        condition: Expression,
+        body: Vec<Structured>,
+    },
+    ForIn {
+        label: Option<BlockId>,
+        property: ValueId,
+        object: Expression,
+        body: Vec<Structured>,
+    },
+    ForOf {
+        label: Option<BlockId>,
+        value: ValueId,
+        iterable: Expression,
+        body: Vec<Structured>,
+    },
+    If {
+        condition: Expression,
+        consequent: Vec<Structured>,
+        alternate: Vec<Structured>,
+    },
+    Switch {
+        discriminant: Expression,
+        cases: Vec<SwitchCase>,
+    },
+    Try {
+        body: Vec<Structured>,
+        /// The value `catch` binds, `None` for `catch { }`.
+        parameter: Option<ValueId>,
+        catch: Option<Vec<Structured>>,
+        finally: Option<Vec<Structured>>,
+    },
+    Break(Option<BlockId>),
+    Continue(Option<BlockId>),
+    Return(Expression),
+    Throw(Expression),
+    /// Control flow that could not be structured: `body` runs each block
+    /// as a case of a `switch` over the block to run next, in a loop.
+    Dispatch(Vec<(BlockId, Vec<Structured>)>),
+    /// Sets the block a `Dispatch` runs next and goes on with it.
+    Jump(BlockId),
+}
+
+/// `case value:`, or `default:` without a value. An empty body falls
+/// through to the next case.
+#[derive(Debug, Clone)]
+pub struct SwitchCase {
+    pub value: Option<u32>,
+    pub body: Vec<Structured>,
+}
+
+/// The body of `function` as structured statements. Reducible control flow,
+/// which is all compiled JavaScript has once `try` and loops are known, is
+/// rebuilt following Ramsey's "Beyond Relooper"; anything else falls back
+/// to a `Dispatch`.
+pub fn structure(function: &LiftedFunction) -> Vec<Structured> {
+    let mut body = Structurer::new(function)
+        .and_then(Structurer::run)
+        .unwrap_or_else(|| dispatch(function));
+
+    strip_tail(&mut body, &|node| {
+        matches!(
+            node,
+            Structured::Return(Expression::Literal(Literal::Undefined))
+        )
+    });
+    simplify(&mut body);
+    body
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Wrapper {
+    Loop(BlockId),
+    /// An index into `try_statements`.
+    Try(usize),
+}
+
+struct Structurer<'a> {
+    function: &'a LiftedFunction,
+    /// Reverse postorder position of each block, `None` if unreachable.
+    order: Vec<Option<usize>>,
+    dominators: Dominators,
+    /// Blocks reached from more than one place, or by leaving a loop, which
+    /// the code jumping there leaves a labeled block for.
+    merges: Vec<bool>,
+    /// The blocks of the loop starting at each loop header.
+    loops: Vec<Option<HashSet<BlockId>>>,
+    emitted: Vec<usize>,
+}
+
+impl<'a> Structurer<'a> {
+    /// `None` when the control flow is irreducible.
+    fn new(function: &'a LiftedFunction) -> Option<Self> {
+        let count = function.blocks.len();
+        let reverse_postorder = function.reverse_postorder();
+        let mut order = vec![None; count];
+        for (position, block) in reverse_postorder.iter().enumerate() {
+            order[*block] = Some(position);
+        }
+
+        let mut predecessors = vec![Vec::new(); count];
+        for block in &reverse_postorder {
+            for successor in function.blocks[*block].successors() {
+                predecessors[successor].push(*block);
+            }
+        }
+        let slices: Vec<&[BlockId]> = predecessors.iter().map(Vec::as_slice).collect();
+        let dominators = Dominators::from_edges(&reverse_postorder, &slices);
+
+        // Exception edges neither merge nor loop, only jumps do.
+        let mut entries: Vec<HashSet<BlockId>> = vec![HashSet::new(); count];
+        let mut back_edges: Vec<Vec<BlockId>> = vec![Vec::new(); count];
+        for block in &reverse_postorder {
+            for successor in function.blocks[*block].terminator.successors() {
+                if order[successor] > order[*block] {
+                    entries[successor].insert(*block);
+                } else if dominators.dominates(successor, *block) {
+                    back_edges[successor].push(*block);
+                } else {
+                    return None;
+                }
+            }
+        }
+
+        let mut merges: Vec<bool> = entries.iter().map(|entries| entries.len() > 1).collect();
+        let mut loops = vec![None; count];
+        for (header, sources) in back_edges.iter().enumerate() {
+            if sources.is_empty() {
+                continue;
+            }
+            let mut blocks = HashSet::new();
+            blocks.insert(header);
+            let mut pending = sources.clone();
+            while let Some(block) = pending.pop() {
+                if blocks.insert(block) {
+                    pending.extend(predecessors[block].iter().copied());
+                }
+            }
+            for block in &blocks {
+                for successor in function.blocks[*block].terminator.successors() {
+                    if !blocks.contains(&successor) {
+                        merges[successor] = true;
+                    }
+                }
+            }
+            loops[header] = Some(blocks);
+        }
+
+        Some(Self {
+            function,
+            order,
+            dominators,
+            merges,
+            loops,
+            emitted: vec![0; count],
+        })
+    }
+
+    /// `None` when some block did not end up exactly once in the result, or
+    /// a jump would not find its label.
+    fn run(mut self) -> Option<Vec<Structured>> {
+        if self.function.blocks.is_empty() {
+            return Some(vec![]);
+        }
+        let body = self.tree(0);
+        let complete = self
+            .order
+            .iter()
+            .zip(&self.emitted)
+            .all(|(order, emitted)| order.is_none() || *emitted == 1);
+        match complete && labels_resolve(&body, &mut Vec::new()) {
+            true => Some(body),
+            false => None,
+        }
+    }
+
+    /// The code of `block` and every block it dominates.
+    fn tree(&mut self, block: BlockId) -> Vec<Structured> {
+        self.emitted[block] += 1;
+        if self.emitted[block] > 1 {
+            return vec![];
+        }
+
+        let mut wrappers = Vec::new();
+        if self.loops[block].is_some() && !self.is_loop_statement(block) {
+            wrappers.push(Wrapper::Loop(block));
+        }
+        for (index, statement) in self.function.try_statements.iter().enumerate() {
+            if statement.body.first() == Some(&block) {
+                wrappers.push(Wrapper::Try(index));
+            }
+        }
+        // Outermost first, a `try` around a loop it holds all of.
+        wrappers.sort_by_key(|wrapper| {
+            let (size, inner) = match wrapper {
+                Wrapper::Loop(header) => (self.loops[*header].as_ref().map_or(0, HashSet::len), 1),
+                Wrapper::Try(index) => (self.function.try_statements[*index].body.len(), 0),
+            };
+            (std::cmp::Reverse(size), inner)
+        });
+
+        let mut merges: Vec<BlockId> = self
+            .dominators
+            .children(block)
+            .iter()
+            .copied()
+            .filter(|child| self.merges[*child])
+            .collect();
+        merges.sort_by_key(|merge| std::cmp::Reverse(self.order[*merge]));
+
+        self.within(block, &wrappers, merges)
+    }
+
+    /// The code of `block` inside `wrappers`, with the labeled blocks for
+    /// the `merges` it dominates around it. The merges a wrapper holds go
+    /// inside it, the others around it.
+    fn within(
+        &mut self,
+        block: BlockId,
+        wrappers: &[Wrapper],
+        merges: Vec<BlockId>,
+    ) -> Vec<Structured> {
+        let (inside, here): (Vec<BlockId>, Vec<BlockId>) = match wrappers.first() {
+            Some(wrapper) => merges
+                .into_iter()
+                .partition(|merge| self.wraps(*wrapper, *merge)),
+            None => (Vec::new(), merges),
+        };
+
+        let mut nodes = match wrappers.split_first() {
+            Some((wrapper, rest)) => {
+                let body = self.within(block, rest, inside);
+                self.wrap(*wrapper, body)
+            }
+            None => self.leaf(block),
+        };
+        for merge in here.into_iter().rev() {
+            let mut wrapped = vec![Structured::Block {
+                label: merge,
+                body: nodes,
+            }];
+            wrapped.extend(self.tree(merge));
+            nodes = wrapped;
+        }
+        nodes
+    }
+
+    fn wraps(&self, wrapper: Wrapper, block: BlockId) -> bool {
+        match wrapper {
+            Wrapper::Loop(header) => self.loops[header]
+                .as_ref()
+                .is_some_and(|blocks| blocks.contains(&block)),
+            Wrapper::Try(index) => self.function.try_statements[index].body.contains(&block),
+        }
+    }
+
+    fn wrap(&mut self, wrapper: Wrapper, body: Vec<Structured>) -> Vec<Structured> {
+        match wrapper {
+            Wrapper::Loop(header) => vec![Structured::Loop {
+                label: Some(header),
+                body,
+            }],
+            Wrapper::Try(index) => {
+                let statement = &self.function.try_statements[index];
+                let (parameter, catch) = match &statement.catch {
+                    Some(clause) => {
+                        let parameter = clause.parameter;
+                        let entry = clause.blocks[0];
+                        (parameter, Some(self.tree(entry)))
+                    }
+                    None => (None, None),
+                };
+                let finally = match &statement.finally {
+                    Some(clause) => {
+                        let exception = clause.exception;
+                        let mut nodes = self.tree(clause.blocks[0]);
+                        remove_rethrows(&mut nodes, exception);
+                        Some(nodes)
+                    }
+                    None => None,
+                };
+                vec![Structured::Try {
+                    body,
+                    parameter,
+                    catch,
+                    finally,
+                }]
+            }
+        }
+    }
+
+    /// Whether `block` is a loop header that `for in` or `for of` stands for
+    /// on its own.
+    fn is_loop_statement(&self, block: BlockId) -> bool {
+        matches!(
+            self.function.blocks[block].terminator,
+            Terminator::ForIn { .. } | Terminator::ForOf { .. }
+        )
+    }
+
+    /// The statements of `block` and where it goes next.
+    fn leaf(&mut self, block: BlockId) -> Vec<Structured> {
+        let function = self.function;
+        let basic_block = &function.blocks[block];
+        let mut nodes: Vec<Structured> = basic_block
+            .statements
+            .iter()
+            .cloned()
+            .map(Structured::Statement)
+            .collect();
+
+        // A `for in` or `for of` header runs its statements once per
+        // iteration, which the statement has no place for.
+        if self.is_loop_statement(block)
+            && self.loops[block].is_some()
+            && (!nodes.is_empty()
+                || self
+                    .dominators
+                    .children(block)
+                    .iter()
+                    .any(|child| self.merges[*child] && self.wraps(Wrapper::Loop(block), *child)))
+        {
+            self.emitted[block] += 1;
+            return nodes;
+        }
+
+        match &basic_block.terminator {
+            Terminator::Goto(target) => nodes.extend(self.branch(block, *target)),
+            Terminator::Branch {
+                condition,
+                target,
+                fallthrough,
+            } if target == fallthrough => {
+                nodes.push(Structured::Statement(Statement::Expression(
+                    condition.clone(),
+                )));
+                nodes.extend(self.branch(block, *target));
+            }
+            Terminator::Branch {
+                condition,
+                target,
+                fallthrough,
+            } => nodes.push(Structured::If {
+                condition: condition.clone(),
+                consequent: self.branch(block, *target),
+                alternate: self.branch(block, *fallthrough),
+            }),
+            Terminator::Switch {
+                value,
+                minimum,
+                targets,
+                default,
+            } => {
+                let mut groups: Vec<(BlockId, Vec<Option<u32>>)> = Vec::new();
+                let cases = targets
+                    .iter()
+                    .enumerate()
+                    .map(|(index, target)| (*target, Some(minimum + index as u32)))
+                    .chain(std::iter::once((*default, None)));
+                for (target, value) in cases {
+                    match groups.iter_mut().find(|(known, _)| *known == target) {
+                        Some((_, values)) => values.push(value),
+                        None => groups.push((target, vec![value])),
+                    }
+                }
+
+                let mut cases = Vec::new();
+                for (target, values) in groups {
+                    let last = values.len() - 1;
+                    for (index, value) in values.into_iter().enumerate() {
+                        let body = match index == last {
+                            true => self.branch(block, target),
+                            false => Vec::new(),
+                        };
+                        cases.push(SwitchCase { value, body });
+                    }
+                }
+                nodes.push(Structured::Switch {
+                    discriminant: value.clone(),
+                    cases,
+                });
+            }
+            Terminator::Return(value) => nodes.push(Structured::Return(value.clone())),
+            Terminator::Throw(value) => nodes.push(Structured::Throw(value.clone())),
+            Terminator::ForIn {
+                property,
+                object,
+                body,
+                exit,
+            } => {
+                let body = self.branch(block, *body);
+                nodes.push(Structured::ForIn {
+                    label: Some(block),
+                    property: *property,
+                    object: object.clone(),
+                    body,
+                });
+                nodes.extend(self.branch(block, *exit));
+            }
+            Terminator::ForOf {
+                value,
+                iterable,
+                body,
+                exit,
+            } => {
+                let body = self.branch(block, *body);
+                nodes.push(Structured::ForOf {
+                    label: Some(block),
+                    value: *value,
+                    iterable: iterable.clone(),
+                    body,
+                });
+                nodes.extend(self.branch(block, *exit));
+            }
+            Terminator::Unreachable => {}
+        }
+        nodes
+    }
+
+    /// Going from the end of `from` to `to`.
+    fn branch(&mut self, from: BlockId, to: BlockId) -> Vec<Structured> {
+        if self.order[to] <= self.order[from] {
+            vec![Structured::Continue(Some(to))]
+        } else if self.merges[to] {
+            vec![Structured::Break(Some(to))]
+        } else {
+            self.tree(to)
+        }
+    }
+}
+
+/// Whether every `break` and `continue` is inside what it names.
+fn labels_resolve(nodes: &[Structured], enclosing: &mut Vec<(bool, BlockId)>) -> bool {
+    nodes.iter().all(|node| match node {
+        Structured::Break(Some(label)) => enclosing.contains(&(false, *label)),
+        Structured::Continue(Some(label)) => enclosing.contains(&(true, *label)),
+        Structured::Block { label, body } => {
+            enclosing.push((false, *label));
+            let resolved = labels_resolve(body, enclosing);
+            enclosing.pop();
+            resolved
+        }
+        Structured::Loop { label, body }
+        | Structured::While { label, body, .. }
+        | Structured::ForIn { label, body, .. }
+        | Structured::ForOf { label, body, .. } => {
+            enclosing.extend(label.map(|label| (true, label)));
+            let resolved = labels_resolve(body, enclosing);
+            if label.is_some() {
+                enclosing.pop();
+            }
+            resolved
+        }
+        node => children(node)
+            .into_iter()
+            .all(|body| labels_resolve(body, enclosing)),
+    })
+}
+
+/// The statement lists directly inside `node`.
+pub(super) fn children(node: &Structured) -> Vec<&Vec<Structured>> {
+    match node {
+        Structured::Block { body, .. }
+        | Structured::Loop { body, .. }
+        | Structured::While { body, .. }
+        | Structured::ForIn { body, .. }
+        | Structured::ForOf { body, .. } => vec![body],
+        Structured::If {
+            consequent,
+            alternate,
+            ..
+        } => vec![consequent, alternate],
+        Structured::Switch { cases, .. } => cases.iter().map(|case| &case.body).collect(),
+        Structured::Try {
+            body,
+            catch,
+            finally,
+            ..
+        } => {
+            let mut children = vec![body];
+            children.extend(catch);
+            children.extend(finally);
+            children
+        }
+        Structured::Dispatch(blocks) => blocks.iter().map(|(_, body)| body).collect(),
+        _ => vec![],
+    }
+}
+
+pub(super) fn children_mut(node: &mut Structured) -> Vec<&mut Vec<Structured>> {
+    match node {
+        Structured::Block { body, .. }
+        | Structured::Loop { body, .. }
+        | Structured::While { body, .. }
+        | Structured::ForIn { body, .. }
+        | Structured::ForOf { body, .. } => vec![body],
+        Structured::If {
+            consequent,
+            alternate,
+            ..
+        } => vec![consequent, alternate],
+        Structured::Switch { cases, .. } => cases.iter_mut().map(|case| &mut case.body).collect(),
+        Structured::Try {
+            body,
+            catch,
+            finally,
+            ..
+        } => {
+            let mut children = vec![body];
+            children.extend(catch);
+            children.extend(finally);
+            children
+        }
+        Structured::Dispatch(blocks) => blocks.iter_mut().map(|(_, body)| body).collect(),
+        _ => vec![],
+    }
+}
+
+/// The `finally` handler ends by rethrowing what it caught, which the
+/// source leaves implicit.
+fn remove_rethrows(nodes: &mut Vec<Structured>, exception: ValueId) {
+    nodes.retain(
+        |node| !matches!(node, Structured::Throw(Expression::Value(value)) if *value == exception),
+    );
+    for node in nodes {
+        for body in children_mut(node) {
+            remove_rethrows(body, exception);
+        }
+    }
+}
+
+/// Every block as a case of one `switch` in a loop, for control flow with
+/// no better shape. `try` is lost, and `for in` and `for of` only enter
+/// their body.
+fn dispatch(function: &LiftedFunction) -> Vec<Structured> {
+    let jump = |target: BlockId| vec![Structured::Jump(target)];
+    let blocks = function
+        .reverse_postorder()
+        .into_iter()
+        .map(|block| {
+            let basic_block = &function.blocks[block];
+            let mut nodes: Vec<Structured> = basic_block
+                .statements
+                .iter()
+                .cloned()
+                .map(Structured::Statement)
+                .collect();
+            match &basic_block.terminator {
+                Terminator::Goto(target)
+                | Terminator::ForIn { body: target, .. }
+                | Terminator::ForOf { body: target, .. } => nodes.extend(jump(*target)),
+                Terminator::Branch {
+                    condition,
+                    target,
+                    fallthrough,
+                } => nodes.push(Structured::If {
+                    condition: condition.clone(),
+                    consequent: jump(*target),
+                    alternate: jump(*fallthrough),
+                }),
+                Terminator::Switch {
+                    value,
+                    minimum,
+                    targets,
+                    default,
+                } => {
+                    let mut cases: Vec<SwitchCase> = targets
+                        .iter()
+                        .enumerate()
+                        .map(|(index, target)| SwitchCase {
+                            value: Some(minimum + index as u32),
+                            body: jump(*target),
+                        })
+                        .collect();
+                    cases.push(SwitchCase {
+                        value: None,
+                        body: jump(*default),
+                    });
+                    nodes.push(Structured::Switch {
+                        discriminant: value.clone(),
+                        cases,
+                    });
+                }
+                Terminator::Return(value) => nodes.push(Structured::Return(value.clone())),
+                Terminator::Throw(value) => nodes.push(Structured::Throw(value.clone())),
+                Terminator::Unreachable => {}
+            }
+            (block, nodes)
+        })
+        .collect();
+    vec![Structured::Dispatch(blocks)]
+}
+
+/// Removes what `matches` at the end of `nodes`, and at the end of every
+/// statement the end of `nodes` is also the end of.
+fn strip_tail(nodes: &mut Vec<Structured>, matches: &dyn Fn(&Structured) -> bool) {
+    match nodes.last_mut() {
+        Some(last) if matches(last) => {
+            nodes.pop();
+        }
+        Some(Structured::If {
+            consequent,
+            alternate,
+            ..
+        }) => {
+            strip_tail(consequent, matches);
+            strip_tail(alternate, matches);
+        }
+        Some(Structured::Block { body, .. }) => strip_tail(body, matches),
+        Some(Structured::Try { body, catch, .. }) => {
+            strip_tail(body, matches);
+            if let Some(catch) = catch {
+                strip_tail(catch, matches);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn simplify(nodes: &mut Vec<Structured>) {
+    remove_jumps_to_end(nodes);
+    unlabel(nodes, None, None);
+    remove_jumps_to_end(nodes);
+    restore_while_loops(nodes);
+
+    remove_unused_labels(nodes);
+    invert_continues(nodes);
+    remove_unused_labels(nodes);
+    simplify_conditionals(nodes);
+}
+
+fn remove_unused_labels(nodes: &mut Vec<Structured>) {
+    let mut breaks = HashSet::new();
+    let mut continues = HashSet::new();
+    collect_labels(nodes, &mut breaks, &mut continues);
+    remove_labels(nodes, &breaks, &continues);
+}
+
+/// Drops a `break` that leaves a block at its end, and a `continue` that
+/// starts the next iteration at the end of the loop body.
+fn remove_jumps_to_end(nodes: &mut [Structured]) {
+    for node in nodes {
+        for body in children_mut(node) {
+            remove_jumps_to_end(body);
+        }
+        match node {
+            Structured::Block { label, body } => {
+                let label = *label;
+                strip_tail(
+                    body,
+                    &|node| matches!(node, Structured::Break(Some(target)) if *target == label),
+                );
+            }
+            Structured::Loop { label, body }
+            | Structured::While { label, body, .. }
+            | Structured::ForIn { label, body, .. }
+            | Structured::ForOf { label, body, .. } => {
+                let label = *label;
+                strip_tail(body, &|node| match node {
+                    Structured::Continue(None) => true,
+                    Structured::Continue(target) => *target == label,
+                    _ => false,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// What an unlabeled `break` leaves: a loop, and the labeled block it ends
+/// when it is the last statement of one, or a `switch`.
+#[derive(Debug, Copy, Clone)]
+enum Breakable {
+    Loop(Option<BlockId>),
+    Switch,
+}
+
+/// Leaves out the labels of jumps that go where they would without one.
+fn unlabel(
+    nodes: &mut [Structured],
+    innermost_loop: Option<BlockId>,
+    breakable: Option<Breakable>,
+) {
+    for node in nodes {
+        match node {
+            Structured::Continue(label) if label.is_some() && *label == innermost_loop => {
+                *label = None;
+            }
+            Structured::Break(Some(label)) => {
+                if let Some(Breakable::Loop(Some(exit))) = breakable {
+                    if exit == *label {
+                        *node = Structured::Break(None);
+                    }
+                }
+            }
+            Structured::Block { label, body } => {
+                let label = *label;
+                let last = body.len().saturating_sub(1);
+                for (position, child) in body.iter_mut().enumerate() {
+                    let exit = Some(label).filter(|_| position == last);
+                    unlabel_one(child, innermost_loop, breakable, exit);
+                }
+            }
+            node => unlabel_one(node, innermost_loop, breakable, None),
+        }
+    }
+}
+
+/// `unlabel` for one statement. `exit` is the labeled block a loop ends.
+fn unlabel_one(
+    node: &mut Structured,
+    innermost_loop: Option<BlockId>,
+    breakable: Option<Breakable>,
+    exit: Option<BlockId>,
+) {
+    match node {
+        Structured::Loop { label, body }
+        | Structured::While { label, body, .. }
+        | Structured::ForIn { label, body, .. }
+        | Structured::ForOf { label, body, .. } => {
+            unlabel(body, *label, Some(Breakable::Loop(exit)))
+        }
+        Structured::Switch { cases, .. } => {
+            for case in cases {
+                unlabel(&mut case.body, innermost_loop, Some(Breakable::Switch));
+            }
+        }
+        Structured::Block { .. } | Structured::Break(_) | Structured::Continue(_) => {
+            unlabel(std::slice::from_mut(node), innermost_loop, breakable)
+        }
+        node => {
+            for body in children_mut(node) {
+                unlabel(body, innermost_loop, breakable);
+            }
+        }
+    }
+}
+
+/// `for (;;) { if (condition) { ... } else { break; } ... }` is a `while`.
+fn restore_while_loops(nodes: &mut [Structured]) {
+    for node in nodes {
+        for body in children_mut(node) {
+            restore_while_loops(body);
+        }
+
+        let (label, body) = match node {
+            Structured::Loop { label, body } => (*label, body),
+            _ => continue,
+        };
+        let (condition, rest) = match body.first_mut() {
+            Some(Structured::If {
+                condition,
+                consequent,
+                alternate,
+            }) => match (consequent.as_slice(), alternate.as_slice()) {
+                (_, [Structured::Break(None)]) => (condition.clone(), std::mem::take(consequent)),
+                ([Structured::Break(None)], _) => {
+                    (negate(condition.clone()), std::mem::take(alternate))
+                }
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let mut body = std::mem::take(body);
+        body.splice(0..1, rest);
+        *node = Structured::While {
+            label,
+            condition,
+            body,
+        };
+    }
+}
+
+fn collect_labels(
+    nodes: &[Structured],
+    breaks: &mut HashSet<BlockId>,
+    continues: &mut HashSet<BlockId>,
+) {
+    for node in nodes {
+        match node {
+            Structured::Break(Some(label)) => {
+                breaks.insert(*label);
+            }
+            Structured::Continue(Some(label)) => {
+                continues.insert(*label);
+            }
+            node => {
+                for body in children(node) {
+                    collect_labels(body, breaks, continues);
+                }
+            }
+        }
+    }
+}
+
+/// Unwraps blocks nothing breaks out of and drops loop labels nothing
+/// continues.
+fn remove_labels(
+    nodes: &mut Vec<Structured>,
+    breaks: &HashSet<BlockId>,
+    continues: &HashSet<BlockId>,
+) {
+    let mut index = 0;
+    while index < nodes.len() {
+        for body in children_mut(&mut nodes[index]) {
+            remove_labels(body, breaks, continues);
+        }
+        match &mut nodes[index] {
+            Structured::Block { label, body } if !breaks.contains(label) => {
+                let body = std::mem::take(body);
+                let length = body.len();
+                nodes.splice(index..=index, body);
+                index += length;
+                continue;
+            }
+            Structured::Loop { label, .. }
+            | Structured::While { label, .. }
+            | Structured::ForIn { label, .. }
+            | Structured::ForOf { label, .. }
+                if label.is_some_and(|label| !continues.contains(&label)) =>
+            {
+                *label = None;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+}
+
+/// `if (c) { continue; } rest` at the end of a loop body is
+/// `if (!c) { rest }`.
+fn invert_continues(nodes: &mut [Structured]) {
+    for node in nodes {
+        for body in children_mut(node) {
+            invert_continues(body);
+        }
+
+        let (label, body) = match node {
+            Structured::Loop { label, body }
+            | Structured::While { label, body, .. }
+            | Structured::ForIn { label, body, .. }
+            | Structured::ForOf { label, body, .. } => (*label, body),
+            _ => continue,
+        };
+        let position = body.iter().rposition(|node| match node {
+            Structured::If {
+                consequent,
+                alternate,
+                ..
+            } => {
+                alternate.is_empty()
+                    && matches!(consequent.as_slice(), [Structured::Continue(target)] if target.is_none() || *target == label)
+            }
+            _ => false,
+        });
+        let position = match position {
+            Some(position) if position + 1 < body.len() => position,
+            _ => continue,
+        };
+        let rest = body.split_off(position + 1);
+        if let Some(Structured::If {
+            condition,
+            consequent,
+            ..
+        }) = body.last_mut()
+        {
+            *condition = negate(condition.clone());
+            *consequent = rest;
+        }
+    }
+}
+
+/// Turns `if (c) {} else { a }` into `if (!c) { a }`, drops empty
+/// conditionals, and takes the `else` out of an `if` whose body always
+/// jumps away.
+fn simplify_conditionals(nodes: &mut Vec<Structured>) {
+    let mut index = 0;
+    while index < nodes.len() {
+        for body in children_mut(&mut nodes[index]) {
+            simplify_conditionals(body);
+        }
+
+        if let Structured::If {
+            condition,
+            consequent,
+            alternate,
+        } = &mut nodes[index]
+        {
+            if consequent.is_empty() && alternate.is_empty() {
+                let condition = condition.clone();
+                match condition.has_side_effects() {
+                    true => nodes[index] = Structured::Statement(Statement::Expression(condition)),
+                    false => {
+                        nodes.remove(index);
+                        continue;
+                    }
+                }
+            } else if consequent.is_empty() {
+                *condition = negate(condition.clone());
+                std::mem::swap(consequent, alternate);
+            } else if !alternate.is_empty() && consequent.last().is_some_and(jumps_away) {
+                let alternate = std::mem::take(alternate);
+                nodes.splice(index + 1..index + 1, alternate);
+            }
+        }
+        index += 1;
+    }
+}
+
+fn jumps_away(node: &Structured) -> bool {
+    matches!(
+        node,
+        Structured::Break(_)
+            | Structured::Continue(_)
+            | Structured::Return(_)
+            | Structured::Throw(_)
+            | Structured::Jump(_)
+    )
+}
+
+fn negate(mut condition: Expression) -> Expression {
+    if let Expression::Binary { operator, .. } = &mut condition {
+        let inverse = match operator {
+            BinaryOperator::Equality => Some(BinaryOperator::InEquality),
+            BinaryOperator::InEquality => Some(BinaryOperator::Equality),
+            BinaryOperator::Identity => Some(BinaryOperator::NonIdentity),
+            BinaryOperator::NonIdentity => Some(BinaryOperator::Identity),
+            _ => None,
+        };
+        if let Some(inverse) = inverse {
+            *operator = inverse;
+            return condition;
+        }
+    }
+
+    match condition {
+        Expression::Unary {
+            operator: UnaryOperator::LogicalNot,
+            argument,
+        } => *argument,
+        condition => Expression::Unary {
+            operator: UnaryOperator::LogicalNot,
+            argument: Box::new(condition),
+        },
+    }
+}
@@ -9,7 +9,7 @@ use crate::{
     parsers::{OpcodeStatement, ParserError, ParserResult},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum BinaryOperator {
     Equality,
     InEquality,
@@ -84,8 +84,10 @@ impl TryFrom<Opcode> for BinaryOperator {
             Opcode::Sub | Opcode::SubN => Ok(Self::Subtraction),
             Opcode::LShift => Ok(Self::LeftShift),
             Opcode::RShift => Ok(Self::RightShift),
+            Opcode::URShift => Ok(Self::UnsignedRightShift),
             Opcode::BitAnd => Ok(Self::BitwiseAnd),
             Opcode::BitXor => Ok(Self::BitwiseXor),
+            Opcode::BitOr => Ok(Self::BitwiseOr),
             Opcode::InstanceOf => Ok(Self::InstanceOf),
             Opcode::IsIn => Ok(Self::In),
             _ => Err(ParserError::new(
@@ -98,8 +100,10 @@ impl TryFrom<Opcode> for BinaryOperator {
 
 #[derive(Debug)]
 pub struct BinaryExpression {
-    operator: BinaryOperator,
-    operands: (Register, Register),
+    pub operator: BinaryOperator,
+    pub operands: (Register, Register),
+    /// AddN and friends, emitted once both operands are known to be numbers.
+    pub numeric: bool,
 }
 
 impl OpcodeStatement for BinaryExpression {
@@ -110,6 +114,10 @@ impl OpcodeStatement for BinaryExpression {
         let expression = Expression::Binary(Self {
             operator: BinaryOperator::try_from(opcode)?,
             operands: (Register::Byte(operand_1), Register::Byte(operand_2)),
+            numeric: matches!(
+                opcode,
+                Opcode::AddN | Opcode::SubN | Opcode::MulN | Opcode::DivN
+            ),
         });
 
         let statement = Statement::Expression {
@@ -121,7 +129,7 @@ impl OpcodeStatement for BinaryExpression {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum AssignmentOperator {
     Assignment,
     Addition,
@@ -137,6 +145,26 @@ pub enum AssignmentOperator {
     BitwiseAnd,
 }
 
+impl AssignmentOperator {
+    /// The compound assignment applying `operator`, like `+=` for `+`.
+    pub fn compound(operator: BinaryOperator) -> Option<Self> {
+        match operator {
+            BinaryOperator::Addition => Some(Self::Addition),
+            BinaryOperator::Subtraction => Some(Self::Subtraction),
+            BinaryOperator::Multiplication => Some(Self::Multiplication),
+            BinaryOperator::Division => Some(Self::Division),
+            BinaryOperator::Remainder => Some(Self::Remainder),
+            BinaryOperator::LeftShift => Some(Self::LeftShift),
+            BinaryOperator::RightShift => Some(Self::RightShift),
+            BinaryOperator::UnsignedRightShift => Some(Self::UnsignedShift),
+            BinaryOperator::BitwiseOr => Some(Self::BitwiseOr),
+            BinaryOperator::BitwiseXor => Some(Self::BitwiseXor),
+            BinaryOperator::BitwiseAnd => Some(Self::BitwiseAnd),
+            _ => None,
+        }
+    }
+}
+
 impl From<AssignmentOperator> for &'static str {
     fn from(operator: AssignmentOperator) -> Self {
         use AssignmentOperator::*;
@@ -148,8 +176,8 @@ impl From<AssignmentOperator> for &'static str {
             Multiplication => "*=",
             Division => "/=",
             Remainder => "%=",
-            LeftShift => ">>=",
-            RightShift => "<<=",
+            LeftShift => "<<=",
+            RightShift => ">>=",
             UnsignedShift => ">>>=",
             BitwiseOr => "|=",
             BitwiseXor => "^=",
@@ -160,6 +188,6 @@ impl From<AssignmentOperator> for &'static str {
 
 #[derive(Debug)]
 pub struct AssignmentExpression {
-    operator: AssignmentOperator,
-    right: Register,
+    pub operator: AssignmentOperator,
+    pub right: Register,
 }
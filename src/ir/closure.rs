@@ -0,0 +1,68 @@
+use nom::{
+    combinator::map,
+    number::complete::{le_u16, le_u32, le_u8},
+    sequence::tuple,
+};
+
+use super::{Expression, FunctionIndex, Register, Statement};
+
+use crate::{
+    opcodes::Opcode,
+    parsers::{OpcodeStatement, ParserError, ParserResult},
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClosureKind {
+    /// CreateClosure
+    Function,
+    /// CreateGeneratorClosure, the `function*` a caller sees.
+    GeneratorFunction,
+    /// CreateGenerator, the generator object built inside a GeneratorFunction.
+    Generator,
+}
+
+#[derive(Debug)]
+pub struct Closure {
+    pub kind: ClosureKind,
+    pub environment: Register,
+    pub function: FunctionIndex,
+}
+
+impl OpcodeStatement for Closure {
+    fn parse(opcode: Opcode, input: &[u8]) -> ParserResult<Statement> {
+        use Opcode::*;
+
+        let kind = match opcode {
+            CreateClosure | CreateClosureLongIndex => ClosureKind::Function,
+            CreateGeneratorClosure | CreateGeneratorClosureLongIndex => {
+                ClosureKind::GeneratorFunction
+            }
+            CreateGenerator | CreateGeneratorLongIndex => ClosureKind::Generator,
+            _ => Err(ParserError::new(
+                "Opcode",
+                format!("{:?} is not a Closure", opcode),
+            ))?,
+        };
+
+        let (input, (register, environment)) = map(tuple((le_u8, le_u8)), |(byte_1, byte_2)| {
+            (Register::Byte(byte_1), Register::Byte(byte_2))
+        })(input)?;
+
+        let (input, function) = match opcode {
+            CreateClosureLongIndex | CreateGeneratorClosureLongIndex | CreateGeneratorLongIndex => {
+                map(le_u32, FunctionIndex::Dword)(input)
+            }
+            _ => map(le_u16, FunctionIndex::Word)(input),
+        }?;
+
+        let statement = Statement::Expression {
+            register,
+            expression: Expression::Closure(Self {
+                kind,
+                environment,
+                function,
+            }),
+        };
+        Ok((input, statement))
+    }
+}
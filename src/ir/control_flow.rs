@@ -0,0 +1,182 @@
+use nom::{
+    combinator::map,
+    number::complete::{le_i32, le_i8, le_u32, le_u8},
+    sequence::tuple,
+};
+
+use super::{BinaryOperator, Register, Statement};
+
+use crate::{
+    opcodes::Opcode,
+    parsers::{OpcodeStatement, ParserError, ParserResult},
+};
+
+#[derive(Debug)]
+pub enum JumpCondition {
+    Always,
+    True(Register),
+    False(Register),
+    Undefined(Register),
+    Compare {
+        operator: BinaryOperator,
+        operands: (Register, Register),
+        negated: bool,
+        numeric: bool,
+    },
+}
+
+#[derive(Debug)]
+pub struct Jump {
+    /// Relative to the start of the jump instruction.
+    pub offset: i32,
+    pub condition: JumpCondition,
+}
+
+impl Jump {
+    fn parse_offset(input: &[u8], is_long: bool) -> ParserResult<i32> {
+        match is_long {
+            true => le_i32(input),
+            false => map(le_i8, i32::from)(input),
+        }
+    }
+
+    fn parse_compare(
+        input: &[u8],
+        is_long: bool,
+        operator: BinaryOperator,
+        negated: bool,
+        numeric: bool,
+    ) -> ParserResult<Self> {
+        let (input, offset) = Self::parse_offset(input, is_long)?;
+        let (input, operands) = tuple((Register::parse_byte, Register::parse_byte))(input)?;
+
+        let jump = Self {
+            offset,
+            condition: JumpCondition::Compare {
+                operator,
+                operands,
+                negated,
+                numeric,
+            },
+        };
+        Ok((input, jump))
+    }
+}
+
+impl OpcodeStatement for Jump {
+    fn parse(opcode: Opcode, input: &[u8]) -> ParserResult<Statement> {
+        use BinaryOperator::*;
+        use Opcode::*;
+
+        let (input, jump) = match opcode {
+            Jmp | JmpLong => {
+                let (input, offset) = Self::parse_offset(input, opcode == JmpLong)?;
+                let condition = JumpCondition::Always;
+                Ok((input, Self { offset, condition }))
+            }
+
+            JmpTrue | JmpTrueLong | JmpFalse | JmpFalseLong | JmpUndefined | JmpUndefinedLong => {
+                let is_long = matches!(opcode, JmpTrueLong | JmpFalseLong | JmpUndefinedLong);
+                let (input, offset) = Self::parse_offset(input, is_long)?;
+                let (input, register) = Register::parse_byte(input)?;
+
+                let condition = match opcode {
+                    JmpTrue | JmpTrueLong => JumpCondition::True(register),
+                    JmpFalse | JmpFalseLong => JumpCondition::False(register),
+                    _ => JumpCondition::Undefined(register),
+                };
+                Ok((input, Self { offset, condition }))
+            }
+
+            JLess => Self::parse_compare(input, false, LessThan, false, false),
+            JLessLong => Self::parse_compare(input, true, LessThan, false, false),
+            JNotLess => Self::parse_compare(input, false, LessThan, true, false),
+            JNotLessLong => Self::parse_compare(input, true, LessThan, true, false),
+            JLessN => Self::parse_compare(input, false, LessThan, false, true),
+            JLessNLong => Self::parse_compare(input, true, LessThan, false, true),
+            JNotLessN => Self::parse_compare(input, false, LessThan, true, true),
+            JNotLessNLong => Self::parse_compare(input, true, LessThan, true, true),
+
+            JLessEqual => Self::parse_compare(input, false, LessThanEqual, false, false),
+            JLessEqualLong => Self::parse_compare(input, true, LessThanEqual, false, false),
+            JNotLessEqual => Self::parse_compare(input, false, LessThanEqual, true, false),
+            JNotLessEqualLong => Self::parse_compare(input, true, LessThanEqual, true, false),
+            JLessEqualN => Self::parse_compare(input, false, LessThanEqual, false, true),
+            JLessEqualNLong => Self::parse_compare(input, true, LessThanEqual, false, true),
+            JNotLessEqualN => Self::parse_compare(input, false, LessThanEqual, true, true),
+            JNotLessEqualNLong => Self::parse_compare(input, true, LessThanEqual, true, true),
+
+            JGreater => Self::parse_compare(input, false, GreaterThan, false, false),
+            JGreaterLong => Self::parse_compare(input, true, GreaterThan, false, false),
+            JNotGreater => Self::parse_compare(input, false, GreaterThan, true, false),
+            JNotGreaterLong => Self::parse_compare(input, true, GreaterThan, true, false),
+            JGreaterN => Self::parse_compare(input, false, GreaterThan, false, true),
+            JGreaterNLong => Self::parse_compare(input, true, GreaterThan, false, true),
+            JNotGreaterN => Self::parse_compare(input, false, GreaterThan, true, true),
+            JNotGreaterNLong => Self::parse_compare(input, true, GreaterThan, true, true),
+
+            JGreaterEqual => Self::parse_compare(input, false, GreaterThanEqual, false, false),
+            JGreaterEqualLong => Self::parse_compare(input, true, GreaterThanEqual, false, false),
+            JNotGreaterEqual => Self::parse_compare(input, false, GreaterThanEqual, true, false),
+            JNotGreaterEqualLong => Self::parse_compare(input, true, GreaterThanEqual, true, false),
+            JGreaterEqualN => Self::parse_compare(input, false, GreaterThanEqual, false, true),
+            JGreaterEqualNLong => Self::parse_compare(input, true, GreaterThanEqual, false, true),
+            JNotGreaterEqualN => Self::parse_compare(input, false, GreaterThanEqual, true, true),
+            JNotGreaterEqualNLong => Self::parse_compare(input, true, GreaterThanEqual, true, true),
+
+            JEqual => Self::parse_compare(input, false, Equality, false, false),
+            JEqualLong => Self::parse_compare(input, true, Equality, false, false),
+            JNotEqual => Self::parse_compare(input, false, Equality, true, false),
+            JNotEqualLong => Self::parse_compare(input, true, Equality, true, false),
+
+            JStrictEqual => Self::parse_compare(input, false, Identity, false, false),
+            JStrictEqualLong => Self::parse_compare(input, true, Identity, false, false),
+            JStrictNotEqual => Self::parse_compare(input, false, Identity, true, false),
+            JStrictNotEqualLong => Self::parse_compare(input, true, Identity, true, false),
+
+            _ => Err(ParserError::new(
+                "Opcode",
+                format!("{:?} is not a Jump", opcode),
+            ))?,
+        }?;
+
+        Ok((input, Statement::Jump(jump)))
+    }
+}
+
+#[derive(Debug)]
+pub struct Switch {
+    pub value: Register,
+    /// Relative to the start of the switch instruction, before alignment.
+    pub table_offset: u32,
+    pub default: i32,
+    pub minimum: u32,
+    pub maximum: u32,
+    /// Jump table entries, filled in once the whole function has been read.
+    pub offsets: Vec<i32>,
+}
+
+impl OpcodeStatement for Switch {
+    fn parse(opcode: Opcode, input: &[u8]) -> ParserResult<Statement> {
+        if opcode != Opcode::SwitchImm {
+            Err(ParserError::new(
+                "Opcode",
+                format!("{:?} is not a Switch", opcode),
+            ))?
+        }
+
+        map(
+            tuple((le_u8, le_u32, le_i32, le_u32, le_u32)),
+            |(value_byte, table_offset, default, minimum, maximum)| {
+                Statement::Switch(Self {
+                    value: Register::Byte(value_byte),
+                    table_offset,
+                    default,
+                    minimum,
+                    maximum,
+                    offsets: Vec::new(),
+                })
+            },
+        )(input)
+    }
+}
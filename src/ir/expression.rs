@@ -1,10 +1,15 @@
-use nom::{combinator::map, number::complete::le_u8};
+use nom::{
+    combinator::map,
+    number::complete::{le_u32, le_u8},
+    sequence::tuple,
+};
 
 use crate::parsers::ParserResult;
 
 use super::{
-    BinaryExpression, CallExpression, EnvExpression, FrameCall, Literal, NewArrayExpression,
-    NewObjectExpression, Object, ObjectExpression, Register, Statement, UnaryExpression,
+    ArgumentsExpression, BinaryExpression, CallExpression, Closure, ConversionExpression,
+    EnvExpression, FrameCall, Literal, NewArrayExpression, NewObjectExpression, Object,
+    ObjectExpression, Register, Statement, UnaryExpression,
 };
 
 #[derive(Debug)]
@@ -21,6 +26,21 @@ pub enum Expression {
     FrameCall(FrameCall),
     CallExp(CallExpression),
     NewTarget,
+    Conversion(ConversionExpression),
+    Closure(Closure),
+    Arguments(ArgumentsExpression),
+    /// LoadParam, 0 is `this`.
+    Parameter(u32),
+    CoerceThis(Register),
+    CreateThis {
+        prototype: Register,
+        constructor: Register,
+    },
+    SelectObject {
+        this: Register,
+        result: Register,
+    },
+    Eval(Register),
 }
 
 impl Expression {
@@ -30,4 +50,77 @@ impl Expression {
             expression: Self::NewTarget,
         })(input)
     }
+
+    pub fn parse_parameter(input: &[u8], is_long: bool) -> ParserResult<Statement> {
+        let (input, register) = Register::parse_byte(input)?;
+
+        let (input, index) = match is_long {
+            true => le_u32(input),
+            false => map(le_u8, u32::from)(input),
+        }?;
+
+        let statement = Statement::Expression {
+            register,
+            expression: Self::Parameter(index),
+        };
+        Ok((input, statement))
+    }
+
+    pub fn parse_this(input: &[u8]) -> ParserResult<Statement> {
+        map(Register::parse_byte, |register| Statement::Expression {
+            register,
+            expression: Self::Object(Object::This),
+        })(input)
+    }
+
+    pub fn parse_coerce_this(input: &[u8]) -> ParserResult<Statement> {
+        map(
+            tuple((Register::parse_byte, Register::parse_byte)),
+            |(register, this)| Statement::Expression {
+                register,
+                expression: Self::CoerceThis(this),
+            },
+        )(input)
+    }
+
+    pub fn parse_create_this(input: &[u8]) -> ParserResult<Statement> {
+        map(
+            tuple((
+                Register::parse_byte,
+                Register::parse_byte,
+                Register::parse_byte,
+            )),
+            |(register, prototype, constructor)| Statement::Expression {
+                register,
+                expression: Self::CreateThis {
+                    prototype,
+                    constructor,
+                },
+            },
+        )(input)
+    }
+
+    pub fn parse_select_object(input: &[u8]) -> ParserResult<Statement> {
+        map(
+            tuple((
+                Register::parse_byte,
+                Register::parse_byte,
+                Register::parse_byte,
+            )),
+            |(register, this, result)| Statement::Expression {
+                register,
+                expression: Self::SelectObject { this, result },
+            },
+        )(input)
+    }
+
+    pub fn parse_eval(input: &[u8]) -> ParserResult<Statement> {
+        map(
+            tuple((Register::parse_byte, Register::parse_byte)),
+            |(register, argument)| Statement::Expression {
+                register,
+                expression: Self::Eval(argument),
+            },
+        )(input)
+    }
 }
@@ -14,19 +14,39 @@ use crate::{
     parsers::{OpcodeStatement, ParserError, ParserResult},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub enum FunctionIndex {
     Register(Register),
     Word(u16),
     Dword(u32),
 }
 
-#[derive(Debug)]
+impl FunctionIndex {
+    /// The function table index, when it is known statically.
+    pub fn index(&self) -> Option<u32> {
+        match self {
+            FunctionIndex::Register(_) => None,
+            FunctionIndex::Word(word) => Some(u32::from(*word)),
+            FunctionIndex::Dword(dword) => Some(*dword),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
 pub enum ArgsNo {
     Byte(u8),
     Dword(u32),
 }
 
+impl ArgsNo {
+    pub fn count(&self) -> u32 {
+        match self {
+            ArgsNo::Byte(byte) => u32::from(*byte),
+            ArgsNo::Dword(dword) => *dword,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum FunctionType {
     Normal(FunctionIndex),
@@ -36,11 +56,26 @@ pub enum FunctionType {
 
 #[derive(Debug)]
 pub struct FrameCall {
-    no_of_arguments: ArgsNo,
-    function_type: FunctionType,
+    pub no_of_arguments: ArgsNo,
+    pub function_type: FunctionType,
+    /// The outgoing registers, `this` first. Filled in by
+    /// `resolve_arguments` once the frame size is known.
+    pub arguments: Vec<Register>,
 }
 
 impl FrameCall {
+    /// Arguments are passed at the top of the caller's frame, in reverse
+    /// order, so argument `i` lives in register `frame_size - 1 - i`.
+    pub fn resolve_arguments(&mut self, frame_size: u32) {
+        self.arguments = (0..self.no_of_arguments.count())
+            .filter_map(|argument| frame_size.checked_sub(argument + 1))
+            .map(|register| match u8::try_from(register) {
+                Ok(byte) => Register::Byte(byte),
+                Err(_) => Register::Dword(register),
+            })
+            .collect();
+    }
+
     fn parse_call(is_constructor: bool, is_long: bool, input: &[u8]) -> ParserResult<Self> {
         let (input, function_register) = map(le_u8, Register::Byte)(input)?;
         let function = FunctionIndex::Register(function_register);
@@ -59,6 +94,7 @@ impl FrameCall {
         let frame_call = Self {
             no_of_arguments,
             function_type,
+            arguments: Vec::new(),
         };
         Ok((input, frame_call))
     }
@@ -74,6 +110,7 @@ impl FrameCall {
         let frame_call = Self {
             no_of_arguments,
             function_type: FunctionType::Normal(function),
+            arguments: Vec::new(),
         };
         Ok((input, frame_call))
     }
@@ -87,6 +124,7 @@ impl FrameCall {
         let frame_call = Self {
             no_of_arguments,
             function_type: FunctionType::BuiltIn(builtin),
+            arguments: Vec::new(),
         };
         Ok((input, frame_call))
     }
@@ -123,8 +161,8 @@ impl OpcodeStatement for FrameCall {
 
 #[derive(Debug)]
 pub struct CallExpression {
-    function: FunctionIndex,
-    arguments: Vec<Register>,
+    pub function: FunctionIndex,
+    pub arguments: Vec<Register>,
 }
 
 impl OpcodeStatement for CallExpression {
@@ -164,3 +202,37 @@ impl OpcodeStatement for CallExpression {
         Ok((input, statement))
     }
 }
+
+#[derive(Debug)]
+pub enum ArgumentsExpression {
+    /// arguments[index]
+    Get { index: Register, lazy: Register },
+    /// arguments.length
+    Length { lazy: Register },
+}
+
+impl OpcodeStatement for ArgumentsExpression {
+    fn parse(opcode: Opcode, input: &[u8]) -> ParserResult<Statement> {
+        let (input, register) = Register::parse_byte(input)?;
+
+        let (input, arguments) = match opcode {
+            Opcode::GetArgumentsPropByVal => map(
+                tuple((Register::parse_byte, Register::parse_byte)),
+                |(index, lazy)| Self::Get { index, lazy },
+            )(input),
+            Opcode::GetArgumentsLength => {
+                map(Register::parse_byte, |lazy| Self::Length { lazy })(input)
+            }
+            _ => Err(ParserError::new(
+                "Opcode",
+                format!("{:?} is not an Arguments Expression", opcode),
+            ))?,
+        }?;
+
+        let statement = Statement::Expression {
+            register,
+            expression: Expression::Arguments(arguments),
+        };
+        Ok((input, statement))
+    }
+}
@@ -0,0 +1,54 @@
+use nom::{
+    combinator::map,
+    number::complete::{le_i32, le_i8, le_u8},
+    sequence::tuple,
+};
+
+use super::{Register, Statement};
+
+use crate::{
+    opcodes::Opcode,
+    parsers::{OpcodeStatement, ParserError, ParserResult},
+};
+
+#[derive(Debug)]
+pub enum GeneratorStatement {
+    Start,
+    /// Yield, resuming at `offset` (relative to the instruction) when next run.
+    Save {
+        offset: i32,
+    },
+    Resume {
+        result: Register,
+        is_return: Register,
+    },
+    Complete,
+}
+
+impl OpcodeStatement for GeneratorStatement {
+    fn parse(opcode: Opcode, input: &[u8]) -> ParserResult<Statement> {
+        use Opcode::*;
+
+        let (input, generator) = match opcode {
+            StartGenerator => Ok((input, Self::Start)),
+            CompleteGenerator => Ok((input, Self::Complete)),
+
+            SaveGenerator => map(le_i8, |offset| Self::Save {
+                offset: i32::from(offset),
+            })(input),
+            SaveGeneratorLong => map(le_i32, |offset| Self::Save { offset })(input),
+
+            ResumeGenerator => map(tuple((le_u8, le_u8)), |(result, is_return)| Self::Resume {
+                result: Register::Byte(result),
+                is_return: Register::Byte(is_return),
+            })(input),
+
+            _ => Err(ParserError::new(
+                "Opcode",
+                format!("{:?} is not a Generator Statement", opcode),
+            ))?,
+        }?;
+
+        Ok((input, Statement::Generator(generator)))
+    }
+}
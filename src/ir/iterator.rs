@@ -0,0 +1,102 @@
+use nom::{combinator::map, number::complete::le_u8, sequence::tuple};
+
+use super::{Register, Statement};
+
+use crate::{
+    opcodes::Opcode,
+    parsers::{OpcodeStatement, ParserError, ParserResult},
+};
+
+/// The for..in and iteration protocol opcodes. Most of them write more than
+/// one register, so they are kept apart from `Statement::Expression`.
+#[derive(Debug)]
+pub enum IteratorStatement {
+    /// GetPNameList
+    PropertyNames {
+        names: Register,
+        object: Register,
+        index: Register,
+        size: Register,
+    },
+    /// GetNextPName
+    NextPropertyName {
+        property: Register,
+        names: Register,
+        object: Register,
+        index: Register,
+        size: Register,
+    },
+    /// IteratorBegin, `source` is replaced by the next method.
+    Begin {
+        iterator: Register,
+        source: Register,
+    },
+    /// IteratorNext, `iterator` is advanced in place.
+    Next {
+        result: Register,
+        iterator: Register,
+        source: Register,
+    },
+    /// IteratorClose
+    Close {
+        iterator: Register,
+        ignore_inner_exception: bool,
+    },
+}
+
+impl OpcodeStatement for IteratorStatement {
+    fn parse(opcode: Opcode, input: &[u8]) -> ParserResult<Statement> {
+        let register = Register::parse_byte;
+
+        use Opcode::*;
+        let (input, iterator_statement) = match opcode {
+            GetPNameList => map(
+                tuple((register, register, register, register)),
+                |(names, object, index, size)| Self::PropertyNames {
+                    names,
+                    object,
+                    index,
+                    size,
+                },
+            )(input),
+
+            GetNextPName => map(
+                tuple((register, register, register, register, register)),
+                |(property, names, object, index, size)| Self::NextPropertyName {
+                    property,
+                    names,
+                    object,
+                    index,
+                    size,
+                },
+            )(input),
+
+            IteratorBegin => map(tuple((register, register)), |(iterator, source)| {
+                Self::Begin { iterator, source }
+            })(input),
+
+            IteratorNext => map(
+                tuple((register, register, register)),
+                |(result, iterator, source)| Self::Next {
+                    result,
+                    iterator,
+                    source,
+                },
+            )(input),
+
+            IteratorClose => map(tuple((register, le_u8)), |(iterator, ignore_byte)| {
+                Self::Close {
+                    iterator,
+                    ignore_inner_exception: ignore_byte != 0,
+                }
+            })(input),
+
+            _ => Err(ParserError::new(
+                "Opcode",
+                format!("{:?} is not an Iterator Statement", opcode),
+            ))?,
+        }?;
+
+        Ok((input, Statement::Iterator(iterator_statement)))
+    }
+}
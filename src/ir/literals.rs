@@ -17,6 +17,19 @@ pub enum Number {
     Double(f64),
 }
 
+/// Same spelling: doubles compare by their bits, so `-0` and `0` differ
+/// and `NaN` equals itself.
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Number::UInt(first), Number::UInt(second)) => first == second,
+            (Number::Int(first), Number::Int(second)) => first == second,
+            (Number::Double(first), Number::Double(second)) => first.to_bits() == second.to_bits(),
+            _ => false,
+        }
+    }
+}
+
 impl Number {
     fn parse_number(opcode: Opcode, input: &[u8]) -> ParserResult<Literal> {
         let (input, number) = match opcode {
@@ -33,7 +46,7 @@ impl Number {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Boolean {
     True,
@@ -51,11 +64,11 @@ impl From<Boolean> for &'static str {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct RegExp {
-    pattern_index: u32,
-    flag_index: u32,
-    bytecode_index: u32,
+    pub pattern_index: u32,
+    pub flag_index: u32,
+    pub bytecode_index: u32,
 }
 
 impl RegExp {
@@ -80,7 +93,24 @@ pub enum StringIndex {
     Dword(u32),
 }
 
+/// The same string, however wide the operand it was loaded with.
+impl PartialEq for StringIndex {
+    fn eq(&self, other: &Self) -> bool {
+        self.index() == other.index()
+    }
+}
+
+impl Eq for StringIndex {}
+
 impl StringIndex {
+    pub fn index(&self) -> u32 {
+        match self {
+            StringIndex::Byte(byte) => u32::from(*byte),
+            StringIndex::Word(word) => u32::from(*word),
+            StringIndex::Dword(dword) => *dword,
+        }
+    }
+
     fn parse_index(input: &[u8], is_long: bool) -> ParserResult<Literal> {
         let (input, string_index) = match is_long {
             true => map(le_u32, StringIndex::Dword)(input),
@@ -90,7 +120,7 @@ impl StringIndex {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Literal {
     String(StringIndex),
     Number(Number),
@@ -1,20 +1,29 @@
 mod binary_operations;
+mod closure;
+mod control_flow;
 mod expression;
 mod function;
+mod generator;
+mod iterator;
 mod literals;
 mod object;
+mod operands;
 mod register;
 mod unary_operations;
 
 pub use binary_operations::*;
+pub use closure::*;
+pub use control_flow::*;
 pub use expression::*;
 pub use function::*;
+pub use generator::*;
+pub use iterator::*;
 pub use literals::*;
 pub use object::*;
 pub use register::*;
 pub use unary_operations::*;
 
-use super::bytecode_file_format::{BytecodeFile, FunctionHeader};
+use super::bytecode_file_format::{BytecodeFile, LargeFunctionHeader};
 
 #[derive(Debug)]
 pub enum Statement {
@@ -24,16 +33,83 @@ pub enum Statement {
         expression: Expression,
     },
     StoreToEnv(EnvExpression),
+    Jump(Jump),
+    Switch(Switch),
+    Throw(Register),
+    /// ThrowIfUndefinedInst, the temporal dead zone check.
+    ThrowIfUndefined(Register),
+    Catch(Register),
+    DeclareGlobalVar(StringIndex),
+    Generator(GeneratorStatement),
+    Iterator(IteratorStatement),
+    Debugger,
+    Unreachable,
     Nop,
 }
 
+impl Statement {
+    /// Whether control can fall through to the next instruction.
+    pub fn falls_through(&self) -> bool {
+        match self {
+            Statement::Jump(jump) => !matches!(jump.condition, JumpCondition::Always),
+            Statement::Return(_)
+            | Statement::Throw(_)
+            | Statement::Switch(_)
+            | Statement::Unreachable => false,
+            _ => true,
+        }
+    }
+}
+
 #[derive(Debug)]
-pub struct Function<'a> {
-    header: &'a FunctionHeader,
-    body: Vec<Statement>,
+pub struct Instruction {
+    /// Relative to the start of the function's bytecode.
+    pub offset: u32,
+    pub statement: Statement,
+}
+
+/// An entry of a function's exception table. Offsets are relative to the
+/// start of the function's bytecode, `end` is exclusive.
+#[derive(Debug, Copy, Clone)]
+pub struct ExceptionHandler {
+    pub start: u32,
+    pub end: u32,
+    pub target: u32,
 }
 
-struct Program<'a> {
-    bytecode: BytecodeFile<'a>,
-    functions: Vec<Function<'a>>,
+#[derive(Debug)]
+pub struct Function {
+    pub index: u32,
+    pub header: LargeFunctionHeader,
+    pub body: Vec<Instruction>,
+    /// Innermost handlers come first, so the first range that covers an
+    /// instruction is the one catching its exceptions.
+    pub exception_handlers: Vec<ExceptionHandler>,
+    /// The source names of the environment's slots, when the file carries
+    /// debug info.
+    pub slot_names: Vec<String>,
+}
+
+impl Function {
+    /// The index in `body` of the instruction starting at `offset`.
+    pub fn instruction_at(&self, offset: u32) -> Option<usize> {
+        self.body
+            .binary_search_by_key(&offset, |instruction| instruction.offset)
+            .ok()
+    }
+
+    /// The index in `body` a jump `relative` bytes away from `from` lands on.
+    pub fn jump_target(&self, from: usize, relative: i32) -> Option<usize> {
+        let offset = i64::from(self.body[from].offset) + i64::from(relative);
+        if offset < 0 {
+            return None;
+        }
+        self.instruction_at(offset as u32)
+    }
+}
+
+#[derive(Debug)]
+pub struct Program<'a> {
+    pub bytecode: BytecodeFile<'a>,
+    pub functions: Vec<Function>,
 }
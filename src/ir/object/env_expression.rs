@@ -10,17 +10,26 @@ use crate::{
     parsers::{OpcodeStatement, ParserError, ParserResult},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub enum EnvIndex {
     Byte(u8),
     Word(u16),
 }
 
+impl EnvIndex {
+    pub fn slot(&self) -> u32 {
+        match self {
+            EnvIndex::Byte(byte) => u32::from(*byte),
+            EnvIndex::Word(word) => u32::from(*word),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct EnvExpression {
-    environment: Register,
-    index: EnvIndex,
-    value: Option<Register>,
+    pub environment: Register,
+    pub index: EnvIndex,
+    pub value: Option<Register>,
 }
 
 impl EnvExpression {
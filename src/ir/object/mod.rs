@@ -8,8 +8,17 @@ pub use new_array::*;
 pub use new_object::*;
 pub use object_expression::*;
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub enum BufferIndex {
     Word(u16),
     Dword(u32),
 }
+
+impl BufferIndex {
+    pub fn offset(&self) -> u32 {
+        match self {
+            BufferIndex::Word(word) => u32::from(*word),
+            BufferIndex::Dword(dword) => *dword,
+        }
+    }
+}
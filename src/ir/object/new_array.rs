@@ -14,20 +14,24 @@ use crate::{
 
 #[derive(Debug)]
 pub struct NewArrayExpression {
-    array_size: u16,
-    array_index: Option<BufferIndex>,
+    pub array_size: u16,
+    pub no_of_static_elements: u16,
+    pub array_index: Option<BufferIndex>,
 }
 
 impl NewArrayExpression {
     fn parse_new(input: &[u8], array_size: u16) -> ParserResult<Self> {
         let new_array = Self {
             array_size,
+            no_of_static_elements: 0,
             array_index: None,
         };
         Ok((input, new_array))
     }
 
     fn parse_buffer(input: &[u8], array_size: u16, is_long: bool) -> ParserResult<Self> {
+        let (input, no_of_static_elements) = le_u16(input)?;
+
         let (input, array_index) = match is_long {
             true => map(le_u32, |x| Some(BufferIndex::Dword(x)))(input),
             false => map(le_u16, |x| Some(BufferIndex::Word(x)))(input),
@@ -35,6 +39,7 @@ impl NewArrayExpression {
 
         let new_array = Self {
             array_size,
+            no_of_static_elements,
             array_index,
         };
         Ok((input, new_array))
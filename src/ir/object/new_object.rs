@@ -14,10 +14,10 @@ use crate::{
 
 #[derive(Debug)]
 pub struct NewObjectExpression {
-    no_of_static_elements: u16,
-    key_index: BufferIndex,
-    value_index: BufferIndex,
-    parent: Option<Register>,
+    pub no_of_static_elements: u16,
+    pub key_index: BufferIndex,
+    pub value_index: BufferIndex,
+    pub parent: Option<Register>,
 }
 
 impl NewObjectExpression {
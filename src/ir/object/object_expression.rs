@@ -10,20 +10,29 @@ use crate::{
     parsers::{OpcodeStatement, ParserError, ParserResult},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub enum ArrayIndex {
     Byte(u8),
     Dword(u32),
 }
 
-#[derive(Debug)]
+impl ArrayIndex {
+    pub fn value(&self) -> u32 {
+        match self {
+            ArrayIndex::Byte(byte) => u32::from(*byte),
+            ArrayIndex::Dword(dword) => *dword,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
 pub enum Property {
     String(StringIndex),
     Index(ArrayIndex),
     Register(Register),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone)]
 pub enum ObjectExpKind {
     Delete {
         object: Register,
@@ -42,13 +51,18 @@ pub enum ObjectExpKind {
     },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Object {
     Normal,
     Array,
     Global,
     This,
-    Environment { id: u8 },
+    /// GetEnvironment, `id` levels up.
+    Environment {
+        id: u8,
+    },
+    /// CreateEnvironment
+    NewEnvironment,
 }
 
 impl OpcodeStatement for Object {
@@ -58,7 +72,7 @@ impl OpcodeStatement for Object {
         let (input, object) = match opcode {
             Opcode::GetEnvironment => map(le_u8, |id| Object::Environment { id })(input),
             Opcode::GetGlobalObject => Ok((input, Object::Global)),
-            Opcode::CreateEnvironment => Ok((input, Object::Environment { id: 0 })),
+            Opcode::CreateEnvironment => Ok((input, Object::NewEnvironment)),
             _ => Err(ParserError::new(
                 "Opcode",
                 format!("{:?} is not an Object", opcode),
@@ -75,9 +89,9 @@ impl OpcodeStatement for Object {
 
 #[derive(Debug)]
 pub struct ObjectExpression {
-    obj_type: Object,
-    property: Property,
-    kind: ObjectExpKind,
+    pub obj_type: Object,
+    pub property: Property,
+    pub kind: ObjectExpKind,
 }
 
 impl ObjectExpression {
@@ -115,12 +129,18 @@ impl ObjectExpression {
         enumerable: bool,
         input: &[u8],
     ) -> ParserResult<Self> {
-        let (input, kind) = map(terminated(le_u8, le_u8), |byte| ObjectExpKind::Set {
-            value: Register::Byte(byte),
+        use Opcode::*;
+
+        // Only the plain puts carry a property cache index.
+        let (input, value_byte) = match opcode {
+            PutById | PutByIdLong | TryPutById | TryPutByIdLong => terminated(le_u8, le_u8)(input),
+            _ => le_u8(input),
+        }?;
+        let kind = ObjectExpKind::Set {
+            value: Register::Byte(value_byte),
             enumerable,
-        })(input)?;
+        };
 
-        use Opcode::*;
         let (input, string_index) = match opcode {
             PutNewOwnByIdShort => map(le_u8, StringIndex::Byte)(input),
             PutById | TryPutById | PutNewOwnById | PutNewOwnNEById => {
@@ -0,0 +1,156 @@
+use super::{
+    ArgumentsExpression, Expression, FunctionIndex, FunctionType, GeneratorStatement,
+    IteratorStatement, JumpCondition, ObjectExpKind, Property, Register, Statement,
+};
+
+impl Statement {
+    /// Registers written by the statement.
+    pub fn defs(&self) -> Vec<Register> {
+        match self {
+            Statement::Expression {
+                register,
+                expression,
+            } => match expression {
+                Expression::ObjExp(object_expression) => match object_expression.kind {
+                    // Puts name the object being written to, not a destination.
+                    ObjectExpKind::Set { .. } | ObjectExpKind::Define { .. } => vec![],
+                    _ => vec![*register],
+                },
+                _ => vec![*register],
+            },
+            Statement::Catch(register) => vec![*register],
+            Statement::Generator(GeneratorStatement::Resume { result, is_return }) => {
+                vec![*result, *is_return]
+            }
+            Statement::Iterator(iterator_statement) => match iterator_statement {
+                IteratorStatement::PropertyNames {
+                    names, index, size, ..
+                } => vec![*names, *index, *size],
+                IteratorStatement::NextPropertyName {
+                    property, index, ..
+                } => vec![*property, *index],
+                IteratorStatement::Begin { iterator, source } => vec![*iterator, *source],
+                IteratorStatement::Next {
+                    result, iterator, ..
+                } => vec![*result, *iterator],
+                IteratorStatement::Close { .. } => vec![],
+            },
+            _ => vec![],
+        }
+    }
+
+    /// Registers read by the statement.
+    pub fn uses(&self) -> Vec<Register> {
+        match self {
+            Statement::Return(register)
+            | Statement::Throw(register)
+            | Statement::ThrowIfUndefined(register) => vec![*register],
+            Statement::Expression {
+                register,
+                expression,
+            } => {
+                let mut registers = expression.uses();
+                if let Expression::ObjExp(object_expression) = expression {
+                    if let ObjectExpKind::Set { .. } | ObjectExpKind::Define { .. } =
+                        object_expression.kind
+                    {
+                        registers.insert(0, *register);
+                    }
+                }
+                registers
+            }
+            Statement::StoreToEnv(env_expression) => {
+                let mut registers = vec![env_expression.environment];
+                registers.extend(env_expression.value);
+                registers
+            }
+            Statement::Jump(jump) => match &jump.condition {
+                JumpCondition::Always => vec![],
+                JumpCondition::True(register)
+                | JumpCondition::False(register)
+                | JumpCondition::Undefined(register) => vec![*register],
+                JumpCondition::Compare { operands, .. } => vec![operands.0, operands.1],
+            },
+            Statement::Switch(switch) => vec![switch.value],
+            Statement::Iterator(iterator_statement) => match iterator_statement {
+                IteratorStatement::PropertyNames { object, .. } => vec![*object],
+                IteratorStatement::NextPropertyName {
+                    names,
+                    object,
+                    index,
+                    size,
+                    ..
+                } => vec![*names, *object, *index, *size],
+                IteratorStatement::Begin { source, .. } => vec![*source],
+                IteratorStatement::Next {
+                    iterator, source, ..
+                } => vec![*iterator, *source],
+                IteratorStatement::Close { iterator, .. } => vec![*iterator],
+            },
+            _ => vec![],
+        }
+    }
+}
+
+impl Expression {
+    /// Registers read by the expression.
+    pub fn uses(&self) -> Vec<Register> {
+        match self {
+            Expression::Register(register)
+            | Expression::CoerceThis(register)
+            | Expression::Eval(register) => vec![*register],
+            Expression::NewObject(new_object) => new_object.parent.into_iter().collect(),
+            Expression::LoadFromEnv(env_expression) => vec![env_expression.environment],
+            Expression::ObjExp(object_expression) => {
+                let mut registers = match object_expression.kind {
+                    ObjectExpKind::Get { object } | ObjectExpKind::Delete { object } => {
+                        vec![object]
+                    }
+                    ObjectExpKind::Set { value, .. } => vec![value],
+                    ObjectExpKind::Define { getter, setter, .. } => vec![getter, setter],
+                };
+                if let Property::Register(property) = object_expression.property {
+                    registers.push(property);
+                }
+                registers
+            }
+            Expression::Unary(unary) => vec![unary.argument],
+            Expression::Binary(binary) => vec![binary.operands.0, binary.operands.1],
+            Expression::Conversion(conversion) => vec![conversion.argument],
+            Expression::FrameCall(frame_call) => {
+                let mut registers = match frame_call.function_type {
+                    FunctionType::Normal(FunctionIndex::Register(register))
+                    | FunctionType::Constructor(FunctionIndex::Register(register)) => {
+                        vec![register]
+                    }
+                    _ => vec![],
+                };
+                registers.extend(frame_call.arguments.iter().copied());
+                registers
+            }
+            Expression::CallExp(call) => {
+                let mut registers = match call.function {
+                    FunctionIndex::Register(register) => vec![register],
+                    _ => vec![],
+                };
+                registers.extend(call.arguments.iter().copied());
+                registers
+            }
+            Expression::Closure(closure) => vec![closure.environment],
+            Expression::Arguments(arguments) => match arguments {
+                ArgumentsExpression::Get { index, lazy } => vec![*index, *lazy],
+                ArgumentsExpression::Length { lazy } => vec![*lazy],
+            },
+            Expression::CreateThis {
+                prototype,
+                constructor,
+            } => vec![*prototype, *constructor],
+            Expression::SelectObject { this, result } => vec![*this, *result],
+            Expression::Literal(_)
+            | Expression::NewArray(_)
+            | Expression::Object(_)
+            | Expression::NewTarget
+            | Expression::Parameter(_) => vec![],
+        }
+    }
+}
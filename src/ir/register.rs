@@ -18,6 +18,18 @@ pub enum Register {
 }
 
 impl Register {
+    /// The register number, regardless of how wide its encoding was.
+    pub fn index(&self) -> u32 {
+        match self {
+            Register::Byte(byte) => u32::from(*byte),
+            Register::Dword(dword) => *dword,
+        }
+    }
+
+    pub fn parse_byte(input: &[u8]) -> ParserResult<Register> {
+        map(le_u8, Register::Byte)(input)
+    }
+
     fn parse_mov(input: &[u8], is_long: bool) -> ParserResult<Statement> {
         let (input, (left_register, right_register)) = if is_long {
             map(tuple((le_u32, le_u32)), |(left, right)| {
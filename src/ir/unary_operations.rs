@@ -9,7 +9,7 @@ use crate::{
     parsers::{OpcodeStatement, ParserError, ParserResult},
 };
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum UnaryOperator {
     Negation,
     LogicalNot,
@@ -49,9 +49,9 @@ impl TryFrom<Opcode> for UnaryOperator {
 
 #[derive(Debug)]
 pub struct UnaryExpression {
-    operator: UnaryOperator,
-    prefix: bool,
-    argument: Register,
+    pub operator: UnaryOperator,
+    pub prefix: bool,
+    pub argument: Register,
 }
 
 impl OpcodeStatement for UnaryExpression {
@@ -72,7 +72,53 @@ impl OpcodeStatement for UnaryExpression {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Conversion {
+    ToNumber,
+    ToInt32,
+    ToString,
+}
+
+impl TryFrom<Opcode> for Conversion {
+    type Error = ParserError;
+
+    fn try_from(opcode: Opcode) -> Result<Self, Self::Error> {
+        match opcode {
+            Opcode::ToNumber => Ok(Self::ToNumber),
+            Opcode::ToInt32 => Ok(Self::ToInt32),
+            Opcode::AddEmptyString => Ok(Self::ToString),
+            _ => Err(ParserError::new(
+                "Opcode",
+                format!("{:?} is not a valid Conversion", opcode),
+            )),
+        }
+    }
+}
+
 #[derive(Debug)]
+pub struct ConversionExpression {
+    pub conversion: Conversion,
+    pub argument: Register,
+}
+
+impl OpcodeStatement for ConversionExpression {
+    fn parse(opcode: Opcode, input: &[u8]) -> ParserResult<Statement> {
+        let (remaining, (register_byte, operand)) = tuple((le_u8, le_u8))(input)?;
+
+        let expression = Expression::Conversion(Self {
+            conversion: Conversion::try_from(opcode)?,
+            argument: Register::Byte(operand),
+        });
+
+        let statement = Statement::Expression {
+            register: Register::Byte(register_byte),
+            expression,
+        };
+        Ok((remaining, statement))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum UpdateOperator {
     Increment,
     Decrement,
@@ -91,7 +137,7 @@ impl From<UpdateOperator> for &'static str {
 
 #[derive(Debug)]
 pub struct UpdateExpression {
-    operator: UpdateOperator,
-    prefix: bool,
-    argument: Register,
+    pub operator: UpdateOperator,
+    pub prefix: bool,
+    pub argument: Register,
 }
@@ -0,0 +1,15 @@
+// `fn parse(input: &[u8]) -> ParserResult<T>` is the parsers' idiom.
+#![allow(mismatched_lifetime_syntaxes)]
+
+pub mod analysis;
+pub mod ast;
+pub mod builtins;
+pub mod bytecode_file_format;
+pub mod codegen;
+pub mod ir;
+pub mod opcodes;
+pub mod parsers;
+pub mod passes;
+
+#[cfg(test)]
+mod testing;
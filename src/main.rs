@@ -1,22 +1,176 @@
-use std::fs;
+use std::{env, fs, path::Path, process};
 
-use nom::Offset;
+use hbcdecomp::{
+    ast::{LiftedFunction, MetroBundle, MetroModule, Type},
+    codegen::{json_string, Decompiled, Estree, Printer},
+    parsers::program_parser,
+    passes::{restore_program, Restored},
+};
 
-mod bytecode_file_format;
-mod parsers;
+const USAGE: &str =
+    "usage: hbcdecomp [--esm] [--estree] [--summary | --unbundle <directory>] [file.hbc]";
 
-mod builtins;
+fn main() {
+    let mut path = String::from("target/test.hbc");
+    let mut unbundle = None;
+    let mut esm = false;
+    let mut estree = false;
+    let mut summarize = false;
+    let mut arguments = env::args().skip(1);
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--esm" => esm = true,
+            "--estree" => estree = true,
+            "--summary" => summarize = true,
+            "--unbundle" => unbundle = Some(arguments.next().unwrap_or_else(|| usage())),
+            "--help" | "-h" => usage(),
+            _ => path = argument,
+        }
+    }
 
-mod ir;
-mod opcodes;
+    let bytes_vec = fs::read(&path).expect("Unable to read file");
+    let bytes = bytes_vec.as_slice();
 
-use parsers::bytecode_file_parser;
+    let program = program_parser(bytes).unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        process::exit(1);
+    });
 
-fn main() {
-    let bytes_vec = fs::read("target/test.hbc").expect("Unable to read file");
-    let bytes = bytes_vec.as_slice();
+    let Restored {
+        functions,
+        nesting,
+        scopes,
+        bundle,
+        modules,
+        names,
+    } = restore_program(&program, esm);
+    let root = program.bytecode.header.global_code_index;
+    let summary = |function: &LiftedFunction| {
+        let parameters: Vec<&str> = (1..function.header.param_count + function.rest as u32)
+            .filter_map(|parameter| names.parameter(function.index, parameter))
+            .collect();
+        format!(
+            "Function {} {}({}): {} blocks, {} statements, {} variables, {} typed, {} captured, nested in {:?}",
+            function.index,
+            names
+                .function(function.index)
+                .map(ToString::to_string)
+                .unwrap_or_else(|| String::from("<anonymous>")),
+            parameters.join(", "),
+            function.blocks.len(),
+            function.statement_count(),
+            function.variables().len(),
+            function
+                .types
+                .iter()
+                .filter(|value| !matches!(value, Type::Never | Type::Unknown))
+                .count(),
+            scopes.declared_in(function.index).count(),
+            nesting.parent(function.index)
+        )
+    };
+
+    let decompiled = Decompiled {
+        functions: &functions,
+        bytecode: &program.bytecode,
+        names: &names,
+        scopes: &scopes,
+        nesting: &nesting,
+        modules: &modules,
+    };
+
+    if let Some(directory) = unbundle {
+        if bundle.is_empty() {
+            eprintln!("{} has no Metro module registrations", path);
+            process::exit(1);
+        }
+        let written = match estree {
+            true => {
+                let mut estree = Estree::new(&decompiled);
+                write_modules(Path::new(&directory), &bundle, "json", &mut |module| {
+                    format!("{}\n", estree.module(module.factory))
+                })
+            }
+            false => {
+                let mut printer = Printer::new(&decompiled);
+                write_modules(Path::new(&directory), &bundle, "js", &mut |module| {
+                    printer.module(module.factory)
+                })
+            }
+        };
+        written.unwrap_or_else(|error| {
+            eprintln!("Unable to write to {}: {}", directory, error);
+            process::exit(1);
+        });
+        return;
+    }
+
+    if !summarize {
+        match estree {
+            true => println!("{}", Estree::new(&decompiled).program(root)),
+            false => print!("{}", Printer::new(&decompiled).program(root)),
+        }
+        return;
+    }
+
+    for function in &functions {
+        println!("{}", summary(function));
+    }
+    for module in modules.modules() {
+        let dependencies: Vec<String> = modules
+            .dependencies(module.id)
+            .into_iter()
+            .map(|dependency| modules.path(dependency))
+            .collect();
+        println!(
+            "Module {} {}: function {}, requires [{}]",
+            module.id,
+            modules.path(module.id),
+            module.factory,
+            dependencies.join(", ")
+        );
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+/// Writes each module's source to `module_<id>.<extension>` and the
+/// bundle's module graph to `manifest.json`.
+fn write_modules(
+    directory: &Path,
+    bundle: &MetroBundle,
+    extension: &str,
+    source: &mut dyn FnMut(&MetroModule) -> String,
+) -> std::io::Result<()> {
+    fs::create_dir_all(directory)?;
+
+    let mut entries = Vec::new();
+    for module in &bundle.modules {
+        let file = format!("module_{}.{}", module.id, extension);
+        fs::write(directory.join(&file), source(module))?;
 
-    let (bytes_remaining, _bytecode_file) = bytecode_file_parser(bytes).unwrap();
+        let dependencies: Vec<String> = module.dependencies.iter().map(u32::to_string).collect();
+        entries.push(format!(
+            "    {{\"id\": {}, \"file\": {}, \"factory\": {}, \"name\": {}, \"dependencies\": [{}]}}",
+            module.id,
+            json_string(&file),
+            module.factory,
+            module
+                .verbose_name
+                .as_deref()
+                .map_or_else(|| String::from("null"), json_string),
+            dependencies.join(", ")
+        ));
+    }
 
-    println!("{:?}", bytes.offset(bytes_remaining));
+    let entry_points: Vec<String> = bundle.entry_points.iter().map(u32::to_string).collect();
+    let manifest = format!(
+        "{{\n  \"modules\": [\n{}\n  ],\n  \"entryPoints\": [{}]\n}}\n",
+        entries.join(",\n"),
+        entry_points.join(", ")
+    );
+    fs::write(directory.join("manifest.json"), manifest)
 }
@@ -2,7 +2,7 @@ use std::convert::TryFrom;
 
 use crate::parsers::ParserError;
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[repr(u8)]
 pub enum Opcode {
     /// Create an object from a static map of values, as for var={'a': 3}.
@@ -514,6 +514,7 @@ pub enum Opcode {
     /// - next(val): Set Arg1 to val, Arg2 to false, run next instruction
     /// - return(val): Set Arg1 to val, Arg2 to true, run next instruction
     /// - throw(val): Throw val as an error
+    ///
     /// Arg1 is the result provided by the user.
     /// Arg2 is a boolean which is true if the user requested a return().
     ResumeGenerator = 122,
@@ -640,7 +641,7 @@ impl TryFrom<u8> for Opcode {
 
     fn try_from(byte: u8) -> Result<Self, Self::Error> {
         match byte {
-            0..=178 => Ok(unsafe { std::mem::transmute(byte) }),
+            0..=178 => Ok(unsafe { std::mem::transmute::<u8, Opcode>(byte) }),
             _ => Err(ParserError::new(
                 "Opcode",
                 format!("Unknown Opcode: {}", byte),
@@ -3,15 +3,19 @@ use nom::{
     combinator::{map, verify},
     error::context,
     multi::count,
-    number::complete::{le_u128, le_u32, le_u64, le_u8},
+    number::complete::{le_f64, le_i32, le_u128, le_u16, le_u32, le_u64, le_u8},
     sequence::{terminated, tuple},
     Offset,
 };
 
-use crate::bytecode_file_format::{
-    ByteCodeOptions, BytecodeFile, CjsModuleTableEntry, FileHeader, FunctionHeader,
-    OverflowStringTableEntry, RegExpTableEntry, SmallStringTableEntry, StringKind,
-    BYTECODE_ALIGNMENT, MAGIC, SHA1_NUM_BYTES,
+use crate::{
+    bytecode_file_format::{
+        ByteCodeOptions, BytecodeFile, CjsModuleTableEntry, DebugInfoHeader, DebugOffsets,
+        FileHeader, FunctionHeader, FunctionHeaderFlag, LargeFunctionHeader,
+        OverflowStringTableEntry, RegExpTableEntry, SmallStringTableEntry, StringKind,
+        BYTECODE_ALIGNMENT, MAGIC, NO_DEBUG_OFFSET, SHA1_NUM_BYTES,
+    },
+    ir::{Boolean, ExceptionHandler, Literal, Number, StringIndex},
 };
 
 use super::{ParserError, ParserResult};
@@ -22,7 +26,7 @@ trait Align {
     fn align(self, alignment: usize, other: Self) -> Self;
 }
 
-impl<'a> Align for &'a [u8] {
+impl Align for &[u8] {
     fn align(self, alignment: usize, other: Self) -> Self {
         // Necessary??
         // assert!(alignment > 0 && alignment <= 8 && ((alignment & (alignment - 1)) == 0))
@@ -107,6 +111,272 @@ fn function_header(input: &[u8]) -> ParserResult<FunctionHeader> {
     context("Function Headers", map(le_u128, FunctionHeader))(input)
 }
 
+fn large_function_header(input: &[u8]) -> ParserResult<LargeFunctionHeader> {
+    context(
+        "Large Function Header",
+        map(
+            tuple((
+                le_u32, le_u32, le_u32, le_u32, le_u32, le_u32, le_u8, le_u8, le_u8, le_u8,
+            )),
+            |(
+                offset,
+                param_count,
+                bytecode_size_in_bytes,
+                function_name,
+                info_offset,
+                frame_size,
+                environment_size,
+                highest_read_cache_index,
+                highest_write_cache_index,
+                flags,
+            )| LargeFunctionHeader {
+                offset,
+                param_count,
+                bytecode_size_in_bytes,
+                function_name,
+                info_offset,
+                frame_size,
+                environment_size,
+                highest_read_cache_index,
+                highest_write_cache_index,
+                flags: FunctionHeaderFlag::from(flags),
+            },
+        ),
+    )(input)
+}
+
+/// Widens a small header, following it to the large header when it overflowed.
+/// `input` is the whole bytecode file.
+pub fn resolve_function_header<'a>(
+    input: &'a [u8],
+    header: &FunctionHeader,
+) -> ParserResult<'a, LargeFunctionHeader> {
+    if !header.flags().overflowed() {
+        return Ok((input, LargeFunctionHeader::from(header)));
+    }
+
+    let large_header_offset = ((header.info_offset() << 16) | header.offset()) as usize;
+    match input.get(large_header_offset..) {
+        Some(bytes) => large_function_header(bytes),
+        None => Err(ParserError::new(
+            "Large Function Header",
+            format!("Offset {:#X} is out of bounds", large_header_offset),
+        ))?,
+    }
+}
+
+/// Size of `LargeFunctionHeader` in the file, the function's info follows it.
+const LARGE_FUNCTION_HEADER_SIZE: usize = 28;
+
+/// Where the function's info starts: right after the large header of an
+/// overflowed function, at `info_offset` otherwise.
+fn function_info_offset(header: &FunctionHeader) -> usize {
+    let offset = match header.flags().overflowed() {
+        true => {
+            ((header.info_offset() << 16) | header.offset()) as usize + LARGE_FUNCTION_HEADER_SIZE
+        }
+        false => header.info_offset() as usize,
+    };
+    (offset + BYTECODE_ALIGNMENT - 1) & !(BYTECODE_ALIGNMENT - 1)
+}
+
+/// Reads the exception table from the function's info. `input` is the whole
+/// file.
+pub fn exception_handlers_parser<'a>(
+    input: &'a [u8],
+    header: &FunctionHeader,
+) -> ParserResult<'a, Vec<ExceptionHandler>> {
+    if !header.flags().has_exception_handler() {
+        return Ok((input, Vec::new()));
+    }
+
+    let table_offset = function_info_offset(header);
+    let table = input.get(table_offset..).ok_or_else(|| {
+        ParserError::new(
+            "Exception Table",
+            format!("Offset {:#X} is out of bounds", table_offset),
+        )
+    })?;
+
+    let (table, handler_count) = le_u32(table)?;
+    context(
+        "Exception Table",
+        count(
+            map(tuple((le_u32, le_u32, le_u32)), |(start, end, target)| {
+                ExceptionHandler { start, end, target }
+            }),
+            handler_count as usize,
+        ),
+    )(table)
+}
+
+/// Reads the debug offsets, which follow the exception table in the
+/// function's info. `input` is the whole file.
+pub fn debug_offsets_parser<'a>(
+    input: &'a [u8],
+    header: &FunctionHeader,
+) -> ParserResult<'a, Option<DebugOffsets>> {
+    if !header.flags().has_debug_info() {
+        return Ok((input, None));
+    }
+
+    let mut offset = function_info_offset(header);
+    if header.flags().has_exception_handler() {
+        let table = input.get(offset..).unwrap_or_default();
+        let (_, handler_count) = context("Exception Table", le_u32)(table)?;
+        offset += 4 + handler_count as usize * 12;
+    }
+    offset = (offset + BYTECODE_ALIGNMENT - 1) & !(BYTECODE_ALIGNMENT - 1);
+
+    let info = input.get(offset..).ok_or_else(|| {
+        ParserError::new(
+            "Debug Offsets",
+            format!("Offset {:#X} is out of bounds", offset),
+        )
+    })?;
+    context(
+        "Debug Offsets",
+        map(
+            tuple((le_u32, le_u32)),
+            |(source_locations, lexical_data)| {
+                Some(DebugOffsets {
+                    source_locations,
+                    lexical_data,
+                })
+            },
+        ),
+    )(info)
+}
+
+fn debug_info_header(input: &[u8]) -> ParserResult<DebugInfoHeader> {
+    context(
+        "Debug Info Header",
+        map(
+            tuple((le_u32, le_u32, le_u32, le_u32, le_u32)),
+            |(
+                filename_count,
+                filename_storage_size,
+                file_region_count,
+                lexical_data_offset,
+                debug_data_size,
+            )| DebugInfoHeader {
+                filename_count,
+                filename_storage_size,
+                file_region_count,
+                lexical_data_offset,
+                debug_data_size,
+            },
+        ),
+    )(input)
+}
+
+fn signed_leb128(input: &[u8]) -> ParserResult<i64> {
+    let mut result = 0i64;
+    let mut shift = 0;
+    let mut input = input;
+    loop {
+        let (rest, byte) = le_u8(input)?;
+        input = rest;
+        if shift < 64 {
+            result |= i64::from(byte & 0x7F) << shift;
+        }
+        shift += 7;
+        if byte & 0x80 == 0 {
+            if shift < 64 && byte & 0x40 != 0 {
+                result |= -1 << shift;
+            }
+            return Ok((input, result));
+        }
+    }
+}
+
+/// The names of a function's environment slots, from its entry in the
+/// lexical data: the parent function, the name count, then each name as a
+/// length and UTF-8 bytes. `input` is the whole file.
+pub fn slot_names_parser<'a>(
+    input: &'a [u8],
+    debug_info_offset: u32,
+    offsets: &DebugOffsets,
+) -> ParserResult<'a, Vec<String>> {
+    if offsets.lexical_data == NO_DEBUG_OFFSET {
+        return Ok((input, Vec::new()));
+    }
+
+    let out_of_bounds = |offset: usize| {
+        ParserError::new(
+            "Debug Info",
+            format!("Offset {:#X} is out of bounds", offset),
+        )
+    };
+    let debug_info = input
+        .get(debug_info_offset as usize..)
+        .ok_or_else(|| out_of_bounds(debug_info_offset as usize))?;
+    let (_, header) = debug_info_header(debug_info)?;
+
+    let debug_data = debug_info_offset as usize
+        + 20
+        + header.filename_count as usize * 4
+        + header.filename_storage_size as usize
+        + header.file_region_count as usize * 12;
+    let entry = debug_data + header.lexical_data_offset as usize + offsets.lexical_data as usize;
+    if entry >= debug_data + header.debug_data_size as usize {
+        Err(out_of_bounds(entry))?
+    }
+    let data = input.get(entry..).ok_or_else(|| out_of_bounds(entry))?;
+
+    let (data, _parent) = signed_leb128(data)?;
+    let (mut data, name_count) = signed_leb128(data)?;
+    let mut names = Vec::new();
+    for _ in 0..name_count.max(0) {
+        let (rest, length) = signed_leb128(data)?;
+        let (rest, bytes) = take(length.max(0) as usize)(rest)?;
+        let name = std::str::from_utf8(bytes).map_err(|_| {
+            nom::Err::Error(ParserError::new(
+                "Debug Info",
+                String::from("Variable name is not UTF-8"),
+            ))
+        })?;
+        names.push(String::from(name));
+        data = rest;
+    }
+    Ok((data, names))
+}
+
+/// Literals serialized into the array and object buffers. Each run starts
+/// with a tag byte naming the type in bits 4-6 and the run length in the low
+/// nibble, extended by a second byte when bit 7 is set.
+pub fn serialized_literals_parser(input: &[u8], length: usize) -> ParserResult<Vec<Literal>> {
+    let mut literals = Vec::with_capacity(length);
+    let mut input = input;
+
+    while literals.len() < length {
+        let (rest, tag) = le_u8(input)?;
+        let (rest, run) = match tag & 0x80 {
+            0 => (rest, usize::from(tag & 0x0F)),
+            _ => map(le_u8, |low| usize::from(tag & 0x0F) << 8 | usize::from(low))(rest)?,
+        };
+        input = rest;
+
+        for _ in 0..run {
+            let (rest, literal) = match tag & 0x70 {
+                0x00 => Ok((input, Literal::Null)),
+                0x10 => Ok((input, Literal::Boolean(Boolean::True))),
+                0x20 => Ok((input, Literal::Boolean(Boolean::False))),
+                0x30 => map(le_f64, |number| Literal::Number(Number::Double(number)))(input),
+                0x40 => map(le_u32, |index| Literal::String(StringIndex::Dword(index)))(input),
+                0x50 => map(le_u16, |index| Literal::String(StringIndex::Word(index)))(input),
+                0x60 => map(le_u8, |index| Literal::String(StringIndex::Byte(index)))(input),
+                _ => map(le_i32, |number| Literal::Number(Number::Int(number)))(input),
+            }?;
+            input = rest;
+            literals.push(literal);
+        }
+    }
+
+    literals.truncate(length);
+    Ok((input, literals))
+}
+
 fn string_kind(input: &[u8]) -> ParserResult<StringKind> {
     context("String Kinds", map(le_u32, StringKind::new))(input)
 }
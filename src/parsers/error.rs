@@ -1,3 +1,5 @@
+use std::fmt;
+
 use nom::error::{ContextError, ErrorKind, ParseError};
 
 #[derive(Debug)]
@@ -15,6 +17,15 @@ impl ParserError {
     }
 }
 
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{}: {}", self.context, message),
+            None => write!(f, "{}", self.context),
+        }
+    }
+}
+
 impl<I> ParseError<I> for ParserError {
     fn from_error_kind(_: I, kind: ErrorKind) -> Self {
         Self {
@@ -45,3 +56,12 @@ impl From<ParserError> for nom::Err<ParserError> {
         nom::Err::Failure(error)
     }
 }
+
+impl From<nom::Err<ParserError>> for ParserError {
+    fn from(error: nom::Err<ParserError>) -> Self {
+        match error {
+            nom::Err::Error(error) | nom::Err::Failure(error) => error,
+            nom::Err::Incomplete(_) => Self::new("Nom Error", "Incomplete input".to_string()),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use nom::{error::context, multi::count, number::complete::le_i32, Offset};
+
+use crate::{
+    bytecode_file_format::{FunctionHeader, BYTECODE_ALIGNMENT},
+    ir::{Expression, Function, Instruction, Program, Statement},
+};
+
+use super::{
+    bytecode_file_parser, debug_offsets_parser, exception_handlers_parser, instruction_parser,
+    resolve_function_header, slot_names_parser, ParserError,
+};
+
+fn align(offset: usize) -> usize {
+    (offset + BYTECODE_ALIGNMENT - 1) & !(BYTECODE_ALIGNMENT - 1)
+}
+
+/// Decodes the body of a function. `input` is the whole bytecode file, since
+/// both overflowed headers and switch jump tables are addressed from its start.
+/// Debug info is optional, so slot names it fails to provide are left out.
+pub fn function_parser(
+    input: &[u8],
+    index: u32,
+    header: &FunctionHeader,
+    debug_info_offset: u32,
+) -> Result<Function, ParserError> {
+    let (_, exception_handlers) = exception_handlers_parser(input, header)?;
+    let slot_names = match debug_offsets_parser(input, header) {
+        Ok((_, Some(offsets))) => slot_names_parser(input, debug_info_offset, &offsets)
+            .map(|(_, names)| names)
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let (_, header) = resolve_function_header(input, header)?;
+
+    let start = header.offset as usize;
+    let end = start + header.bytecode_size_in_bytes as usize;
+    let bytecode = input.get(start..end).ok_or_else(|| {
+        ParserError::new(
+            "Function",
+            format!("Function {} lies outside of the file", index),
+        )
+    })?;
+
+    let mut body = Vec::new();
+
+    // Jump tables are appended to the bytecode, so stop once the first is reached.
+    let mut limit = bytecode.len();
+    let mut position = 0;
+
+    while position < limit {
+        let (remaining, mut statement) =
+            context("Instruction", instruction_parser)(&bytecode[position..])?;
+
+        if let Statement::Switch(switch) = &mut statement {
+            let table = align(start + position + switch.table_offset as usize);
+            let entries = switch.maximum.saturating_sub(switch.minimum) as usize + 1;
+
+            let table_bytes = input.get(table..).unwrap_or_default();
+            let (_, offsets) = context("Jump Table", count(le_i32, entries))(table_bytes)?;
+
+            switch.offsets = offsets;
+            limit = limit.min(table.saturating_sub(start));
+        }
+
+        if let Statement::Expression {
+            expression: Expression::FrameCall(frame_call),
+            ..
+        } = &mut statement
+        {
+            frame_call.resolve_arguments(header.frame_size);
+        }
+
+        body.push(Instruction {
+            offset: position as u32,
+            statement,
+        });
+        position = bytecode.offset(remaining);
+    }
+
+    Ok(Function {
+        index,
+        header,
+        body,
+        exception_handlers,
+        slot_names,
+    })
+}
+
+pub fn program_parser(input: &[u8]) -> Result<Program, ParserError> {
+    let (_, bytecode) = bytecode_file_parser(input)?;
+
+    let functions = bytecode
+        .function_headers
+        .iter()
+        .enumerate()
+        .map(|(index, header)| {
+            function_parser(
+                input,
+                index as u32,
+                header,
+                bytecode.header.debug_info_offset,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Program {
+        bytecode,
+        functions,
+    })
+}
@@ -4,10 +4,16 @@ use crate::{ir::Statement, opcodes::Opcode};
 
 mod bytecode;
 mod error;
+mod function;
 mod opcodes;
 
-pub use bytecode::bytecode_file_parser;
+pub use bytecode::{
+    bytecode_file_parser, debug_offsets_parser, exception_handlers_parser, resolve_function_header,
+    serialized_literals_parser, slot_names_parser,
+};
 pub use error::ParserError;
+pub use function::{function_parser, program_parser};
+pub use opcodes::instruction_parser;
 
 pub type ParserResult<'a, O> = IResult<&'a [u8], O, ParserError>;
 
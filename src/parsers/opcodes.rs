@@ -0,0 +1,192 @@
+use std::convert::TryFrom;
+
+use nom::{
+    combinator::map,
+    number::complete::{le_u16, le_u32, le_u8},
+};
+
+use crate::{
+    ir::{
+        ArgumentsExpression, BinaryExpression, CallExpression, Closure, ConversionExpression,
+        EnvExpression, Expression, FrameCall, GeneratorStatement, IteratorStatement, Jump, Literal,
+        NewArrayExpression, NewObjectExpression, Object, ObjectExpression, Register, Statement,
+        StringIndex, Switch, UnaryExpression,
+    },
+    opcodes::Opcode,
+};
+
+use super::{OpcodeStatement, ParserResult};
+
+fn register_statement<F>(input: &[u8], statement: F) -> ParserResult<Statement>
+where
+    F: Fn(Register) -> Statement,
+{
+    map(Register::parse_byte, statement)(input)
+}
+
+/// Decodes a single instruction, opcode byte included.
+pub fn instruction_parser(input: &[u8]) -> ParserResult<Statement> {
+    let (input, opcode_byte) = le_u8(input)?;
+    let opcode = Opcode::try_from(opcode_byte)?;
+
+    use Opcode::*;
+    match opcode {
+        NewObjectWithBuffer | NewObjectWithBufferLong | NewObject | NewObjectWithParent => {
+            NewObjectExpression::parse(opcode, input)
+        }
+        NewArrayWithBuffer | NewArrayWithBufferLong | NewArray => {
+            NewArrayExpression::parse(opcode, input)
+        }
+
+        Mov | MovLong => Register::parse(opcode, input),
+
+        Negate | Not | BitNot | TypeOf => UnaryExpression::parse(opcode, input),
+
+        Eq | StrictEq | Neq | StrictNeq | Less | LessEq | Greater | GreaterEq | Add | AddN
+        | Mul | MulN | Div | DivN | Mod | Sub | SubN | LShift | RShift | URShift | BitAnd
+        | BitXor | BitOr | InstanceOf | IsIn => BinaryExpression::parse(opcode, input),
+
+        GetEnvironment | GetGlobalObject | CreateEnvironment => Object::parse(opcode, input),
+
+        StoreToEnvironment
+        | StoreToEnvironmentL
+        | StoreNPToEnvironment
+        | StoreNPToEnvironmentL
+        | LoadFromEnvironment
+        | LoadFromEnvironmentL => EnvExpression::parse(opcode, input),
+
+        GetNewTarget => Expression::parse_new_target(input),
+
+        DeclareGlobalVar => map(le_u32, |index| {
+            Statement::DeclareGlobalVar(StringIndex::Dword(index))
+        })(input),
+
+        GetByIdShort
+        | GetById
+        | GetByIdLong
+        | TryGetById
+        | TryGetByIdLong
+        | PutById
+        | PutByIdLong
+        | TryPutById
+        | TryPutByIdLong
+        | PutNewOwnByIdShort
+        | PutNewOwnById
+        | PutNewOwnByIdLong
+        | PutNewOwnNEById
+        | PutNewOwnNEByIdLong
+        | PutOwnByIndex
+        | PutOwnByIndexL
+        | PutOwnByVal
+        | DelById
+        | DelByIdLong
+        | GetByVal
+        | PutByVal
+        | DelByVal
+        | PutOwnGetterSetterByVal => ObjectExpression::parse(opcode, input),
+
+        GetPNameList | GetNextPName | IteratorBegin | IteratorNext | IteratorClose => {
+            IteratorStatement::parse(opcode, input)
+        }
+
+        Call | Construct | CallDirect | CallLong | ConstructLong | CallDirectLongIndex
+        | CallBuiltin => FrameCall::parse(opcode, input),
+        Call1 | Call2 | Call3 | Call4 => CallExpression::parse(opcode, input),
+
+        Ret => register_statement(input, Statement::Return),
+        Catch => register_statement(input, Statement::Catch),
+        Throw => register_statement(input, Statement::Throw),
+        ThrowIfUndefindedInst => register_statement(input, Statement::ThrowIfUndefined),
+        DirectEval => Expression::parse_eval(input),
+
+        Debugger => Ok((input, Statement::Debugger)),
+        AsyncBreakCheck => Ok((input, Statement::Nop)),
+        ProfilePoint => map(le_u16, |_| Statement::Nop)(input),
+        Unreachable => Ok((input, Statement::Unreachable)),
+
+        CreateClosure
+        | CreateClosureLongIndex
+        | CreateGeneratorClosure
+        | CreateGeneratorClosureLongIndex
+        | CreateGenerator
+        | CreateGeneratorLongIndex => Closure::parse(opcode, input),
+
+        CreateThis => Expression::parse_create_this(input),
+        SelectObject => Expression::parse_select_object(input),
+        CoerceThisNS => Expression::parse_coerce_this(input),
+        LoadThisNS => Expression::parse_this(input),
+
+        LoadParam => Expression::parse_parameter(input, false),
+        LoadParamLong => Expression::parse_parameter(input, true),
+
+        LoadConstUInt8
+        | LoadConstInt
+        | LoadConstDouble
+        | LoadConstString
+        | LoadConstStringLongIndex
+        | LoadConstUndefined
+        | LoadConstNull
+        | LoadConstTrue
+        | LoadConstFalse
+        | LoadConstZero
+        | CreateRegExp => Literal::parse(opcode, input),
+
+        ToNumber | ToInt32 | AddEmptyString => ConversionExpression::parse(opcode, input),
+
+        GetArgumentsPropByVal | GetArgumentsLength => ArgumentsExpression::parse(opcode, input),
+
+        SwitchImm => Switch::parse(opcode, input),
+
+        StartGenerator | ResumeGenerator | CompleteGenerator | SaveGenerator
+        | SaveGeneratorLong => GeneratorStatement::parse(opcode, input),
+
+        Jmp
+        | JmpLong
+        | JmpTrue
+        | JmpTrueLong
+        | JmpFalse
+        | JmpFalseLong
+        | JmpUndefined
+        | JmpUndefinedLong
+        | JLess
+        | JLessLong
+        | JNotLess
+        | JNotLessLong
+        | JLessN
+        | JLessNLong
+        | JNotLessN
+        | JNotLessNLong
+        | JLessEqual
+        | JLessEqualLong
+        | JNotLessEqual
+        | JNotLessEqualLong
+        | JLessEqualN
+        | JLessEqualNLong
+        | JNotLessEqualN
+        | JNotLessEqualNLong
+        | JGreater
+        | JGreaterLong
+        | JNotGreater
+        | JNotGreaterLong
+        | JGreaterN
+        | JGreaterNLong
+        | JNotGreaterN
+        | JNotGreaterNLong
+        | JGreaterEqual
+        | JGreaterEqualLong
+        | JNotGreaterEqual
+        | JNotGreaterEqualLong
+        | JGreaterEqualN
+        | JGreaterEqualNLong
+        | JNotGreaterEqualN
+        | JNotGreaterEqualNLong
+        | JEqual
+        | JEqualLong
+        | JNotEqual
+        | JNotEqualLong
+        | JStrictEqual
+        | JStrictEqualLong
+        | JStrictNotEqual
+        | JStrictNotEqualLong => Jump::parse(opcode, input),
+    }
+}
@@ -0,0 +1,698 @@
+use std::collections::HashSet;
+
+use crate::{
+    analysis::ValueId,
+    ast::{
+        CapturedVariable, ClassMember, ClassMemberKind, Expression, LiftedFunction, MemberProperty,
+        Statement,
+    },
+    builtins::Builtins,
+    bytecode_file_format::BytecodeFile,
+    ir::{AssignmentOperator, ClosureKind, Literal},
+};
+
+use super::{eliminate_dead_code, helpers::Helper};
+
+/// Turns constructor closures back into `class`. Hermes defines methods on
+/// the prototype with non-enumerable puts and accessors, and links `extends`
+/// with `HermesBuiltin.silentSetPrototypeOf`. Babel passes descriptors to
+/// `_createClass`, links with `_inherits` and makes the constructor call
+/// `_classCallCheck`, which goes away with the rest of the wiring.
+pub fn restore_classes(functions: &mut [LiftedFunction], bytecode: &BytecodeFile) {
+    let helpers = ClassHelpers {
+        call_check: Helper::new(functions, bytecode, "classCallCheck"),
+        create_class: Helper::new(functions, bytecode, "createClass"),
+        inherits: Helper::new(functions, bytecode, "inherits"),
+    };
+    let checked: Vec<bool> = functions
+        .iter()
+        .map(|function| !helpers.call_checks(function).is_empty())
+        .collect();
+
+    let mut constructors = Vec::new();
+    for function in functions.iter_mut() {
+        let count = constructors.len();
+        for block in 0..function.blocks.len() {
+            // Rebuilding a class removes statements after it, never before.
+            let mut position = 0;
+            while position < function.blocks[block].statements.len() {
+                constructors.extend(restore_class(
+                    function, block, position, &helpers, bytecode, &checked,
+                ));
+                position += 1;
+            }
+        }
+        if constructors.len() > count {
+            eliminate_dead_code(function);
+        }
+    }
+
+    for constructor in constructors {
+        let function = match functions.get_mut(constructor as usize) {
+            Some(function) => function,
+            None => continue,
+        };
+        for (block, position) in helpers.call_checks(function).into_iter().rev() {
+            function.blocks[block].statements.remove(position);
+        }
+        eliminate_dead_code(function);
+    }
+}
+
+struct ClassHelpers<'a> {
+    call_check: Helper<'a>,
+    create_class: Helper<'a>,
+    inherits: Helper<'a>,
+}
+
+impl ClassHelpers<'_> {
+    /// The `_classCallCheck(this, X)` statements of a constructor.
+    fn call_checks(&self, function: &LiftedFunction) -> Vec<(usize, usize)> {
+        let definitions = function.definitions();
+        let mut checks = Vec::new();
+        for (block, basic_block) in function.blocks.iter().enumerate() {
+            for (position, statement) in basic_block.statements.iter().enumerate() {
+                if let Statement::Expression(Expression::Call {
+                    callee, arguments, ..
+                }) = statement
+                {
+                    let checks_this = matches!(
+                        arguments.first(),
+                        Some(
+                            Expression::This | Expression::CoerceThis(_) | Expression::Parameter(0)
+                        )
+                    );
+                    if checks_this && self.call_check.resolves(&definitions, callee) {
+                        checks.push((block, position));
+                    }
+                }
+            }
+        }
+        checks
+    }
+}
+
+/// Rebuilds the class whose constructor closure is assigned at `position`,
+/// from the wiring that follows it in the same block. Returns the
+/// constructor when it turned out to be one.
+fn restore_class(
+    function: &mut LiftedFunction,
+    block: usize,
+    position: usize,
+    helpers: &ClassHelpers,
+    bytecode: &BytecodeFile,
+    checked: &[bool],
+) -> Option<u32> {
+    let (class, constructor, environment) = match &function.blocks[block].statements[position] {
+        Statement::Assign {
+            value,
+            expression:
+                Expression::Closure {
+                    kind: ClosureKind::Function,
+                    function,
+                    environment,
+                },
+        } => (*value, *function, environment.clone()),
+        _ => return None,
+    };
+
+    let definitions = function.definitions();
+    let use_counts = function.use_counts();
+    let statements = &function.blocks[block].statements;
+    let wiring = Wiring::new(function, class, bytecode);
+
+    let mut members: Vec<(usize, ClassMember)> = Vec::new();
+    let mut assigned: Vec<(usize, ClassMember)> = Vec::new();
+    let mut superclass = None;
+    let mut removed = Vec::new();
+    let mut is_class = checked.get(constructor as usize).copied().unwrap_or(false);
+
+    for (index, statement) in statements.iter().enumerate().skip(position + 1) {
+        let expression = match statement {
+            Statement::Expression(expression) => expression,
+            _ => continue,
+        };
+        match expression {
+            Expression::DefineProperty {
+                object,
+                property,
+                value,
+            } if is_function(&definitions, value) => {
+                let is_static = match wiring.owner(object) {
+                    Some(is_static) => is_static,
+                    None => continue,
+                };
+                members.push((
+                    index,
+                    ClassMember {
+                        kind: ClassMemberKind::Method,
+                        is_static,
+                        key: property.clone(),
+                        function: value.as_ref().clone(),
+                    },
+                ));
+                removed.push(index);
+                is_class = true;
+            }
+            Expression::DefineAccessor {
+                object,
+                property,
+                getter,
+                setter,
+                enumerable: false,
+            } => {
+                let is_static = match wiring.owner(object) {
+                    Some(is_static) => is_static,
+                    None => continue,
+                };
+                let accessors = [
+                    (ClassMemberKind::Getter, getter),
+                    (ClassMemberKind::Setter, setter),
+                ];
+                for (kind, accessor) in accessors {
+                    if is_function(&definitions, accessor) {
+                        members.push((
+                            index,
+                            ClassMember {
+                                kind,
+                                is_static,
+                                key: MemberProperty::from_key(property),
+                                function: accessor.as_ref().clone(),
+                            },
+                        ));
+                    }
+                }
+                removed.push(index);
+                is_class = true;
+            }
+            Expression::Assignment {
+                operator: AssignmentOperator::Assignment,
+                target,
+                value,
+            } if is_function(&definitions, value) => {
+                if let Expression::Member { object, property } = target.as_ref() {
+                    if let Some(is_static) = wiring.owner(object) {
+                        assigned.push((
+                            index,
+                            ClassMember {
+                                kind: ClassMemberKind::Method,
+                                is_static,
+                                key: property.clone(),
+                                function: value.as_ref().clone(),
+                            },
+                        ));
+                    }
+                }
+            }
+            Expression::Call {
+                callee, arguments, ..
+            } if arguments.len() == 2
+                && (matches!(
+                    callee.as_ref(),
+                    Expression::Builtin(Builtins::HermesBuiltinSilentSetPrototypeOf)
+                ) || helpers.inherits.resolves(&definitions, callee)) =>
+            {
+                if wiring.is_class(&arguments[0]) {
+                    superclass = Some(Box::new(arguments[1].clone()));
+                } else if !wiring.is_prototype(&arguments[0]) {
+                    continue;
+                }
+                removed.push(index);
+                is_class = true;
+            }
+            Expression::Call {
+                callee, arguments, ..
+            } if arguments
+                .first()
+                .is_some_and(|first| wiring.is_class(first))
+                && helpers.create_class.resolves(&definitions, callee) =>
+            {
+                let descriptors = Descriptors {
+                    statements,
+                    definitions: &definitions,
+                    use_counts: &use_counts,
+                    bytecode,
+                };
+                let mut created = Vec::new();
+                let mut puts = Vec::new();
+                for (argument, is_static) in arguments[1..].iter().zip([false, true]) {
+                    match descriptors.members(argument, is_static, &mut puts) {
+                        Some(found) => created.extend(found),
+                        None => return None,
+                    }
+                }
+                members.extend(created.into_iter().map(|member| (index, member)));
+                removed.push(index);
+                removed.extend(puts);
+                is_class = true;
+            }
+            _ => {}
+        }
+    }
+
+    if !is_class {
+        return None;
+    }
+
+    // Loose mode assigns methods, which only a class shown otherwise keeps.
+    removed.extend(assigned.iter().map(|(index, _)| *index));
+    members.extend(assigned);
+    members.sort_by_key(|(index, _)| *index);
+
+    // The class goes where the last value its parts read is defined, which
+    // the closure may only move past if nothing else looks at it meanwhile.
+    let mut moved_values = Vec::new();
+    if let Some(superclass) = &superclass {
+        moved_values.extend(superclass.values());
+    }
+    for (_, member) in &members {
+        if let MemberProperty::Computed(key) = &member.key {
+            moved_values.extend(key.values());
+        }
+        moved_values.extend(member.function.values());
+    }
+    let has_side_effects = superclass
+        .iter()
+        .any(|superclass| superclass.has_side_effects())
+        || members.iter().any(|(_, member)| {
+            member.function.has_side_effects()
+                || matches!(&member.key, MemberProperty::Computed(key) if key.has_side_effects())
+        });
+    if has_side_effects || moved_values.contains(&class) {
+        return None;
+    }
+
+    let target = statements
+        .iter()
+        .enumerate()
+        .skip(position)
+        .filter(|(_, statement)| {
+            statement
+                .defs()
+                .iter()
+                .any(|value| moved_values.contains(value))
+        })
+        .map(|(index, _)| index + 1)
+        .max()
+        .unwrap_or(position);
+
+    removed.sort_unstable();
+    removed.dedup();
+    for index in position + 1..target {
+        if removed.binary_search(&index).is_ok() {
+            continue;
+        }
+        let statement = &statements[index];
+        let prototype = match statement {
+            Statement::Assign { value, .. } if wiring.prototypes.contains(value) => Some(*value),
+            _ => None,
+        };
+        if let Some(prototype) = prototype {
+            let wiring_uses = removed
+                .iter()
+                .flat_map(|index| statements[*index].expressions())
+                .flat_map(|expression| expression.values())
+                .filter(|value| *value == prototype)
+                .count();
+            if use_counts[prototype] != wiring_uses {
+                return None;
+            }
+            removed.push(index);
+            continue;
+        }
+        let reads_class = statement.expressions().iter().any(|expression| {
+            expression.values().contains(&class) || reads_variable(expression, &wiring.variables)
+        });
+        if reads_class {
+            return None;
+        }
+    }
+    removed.push(position);
+    removed.sort_unstable();
+
+    let class = Statement::Assign {
+        value: class,
+        expression: Expression::Class {
+            function: constructor,
+            environment,
+            superclass,
+            members: members.into_iter().map(|(_, member)| member).collect(),
+        },
+    };
+    let statements = std::mem::take(&mut function.blocks[block].statements);
+    let insert_at = target.max(position + 1);
+    let mut class = Some(class);
+    for (index, statement) in statements.into_iter().enumerate() {
+        if index == insert_at {
+            function.blocks[block].statements.extend(class.take());
+        }
+        if removed.binary_search(&index).is_err() {
+            function.blocks[block].statements.push(statement);
+        }
+    }
+    function.blocks[block].statements.extend(class);
+
+    Some(constructor)
+}
+
+/// What refers to the class being rebuilt: its value, the captured variables
+/// it was stored in and the values read from its `prototype`.
+struct Wiring<'a> {
+    class: ValueId,
+    variables: HashSet<CapturedVariable>,
+    prototypes: HashSet<ValueId>,
+    bytecode: &'a BytecodeFile<'a>,
+}
+
+impl<'a> Wiring<'a> {
+    fn new(function: &LiftedFunction, class: ValueId, bytecode: &'a BytecodeFile<'a>) -> Self {
+        let mut wiring = Self {
+            class,
+            variables: HashSet::new(),
+            prototypes: HashSet::new(),
+            bytecode,
+        };
+
+        let statements = function
+            .blocks
+            .iter()
+            .flat_map(|block| block.statements.iter());
+        for statement in statements.clone() {
+            if let Statement::Expression(Expression::Assignment { target, value, .. }) = statement {
+                if let (Expression::Captured(variable), Expression::Value(value)) =
+                    (target.as_ref(), value.as_ref())
+                {
+                    if *value == class {
+                        wiring.variables.insert(*variable);
+                    }
+                }
+            }
+        }
+        for statement in statements {
+            if let Statement::Assign { value, expression } = statement {
+                if wiring.is_prototype(expression) {
+                    wiring.prototypes.insert(*value);
+                }
+            }
+        }
+
+        wiring
+    }
+
+    fn is_class(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::Value(value) => *value == self.class,
+            Expression::Captured(variable) => self.variables.contains(variable),
+            _ => false,
+        }
+    }
+
+    fn is_prototype(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::Value(value) => self.prototypes.contains(value),
+            Expression::Member {
+                object,
+                property: MemberProperty::Named(name),
+            } => {
+                self.is_class(object)
+                    && self.bytecode.string(name.index()).as_deref() == Some("prototype")
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether a member defined on `object` is static, `None` when `object`
+    /// is neither the class nor its prototype.
+    fn owner(&self, object: &Expression) -> Option<bool> {
+        match (self.is_prototype(object), self.is_class(object)) {
+            (true, _) => Some(false),
+            (_, true) => Some(true),
+            _ => None,
+        }
+    }
+}
+
+/// Reads the member arrays `_createClass` takes, arrays of descriptors like
+/// `{ key: "name", value: function () {} }` built by puts into fresh
+/// literals.
+struct Descriptors<'a> {
+    statements: &'a [Statement],
+    definitions: &'a [Option<&'a Expression>],
+    use_counts: &'a [usize],
+    bytecode: &'a BytecodeFile<'a>,
+}
+
+impl Descriptors<'_> {
+    /// The members of one array, adding the statements building it to
+    /// `puts`. `None` when it is not a literal array of descriptors.
+    fn members(
+        &self,
+        array: &Expression,
+        is_static: bool,
+        puts: &mut Vec<usize>,
+    ) -> Option<Vec<ClassMember>> {
+        let array = match array {
+            Expression::Literal(Literal::Undefined | Literal::Null) => return Some(Vec::new()),
+            Expression::Value(array) => *array,
+            _ => return None,
+        };
+        if !matches!(
+            self.definitions.get(array),
+            Some(Some(Expression::NewArray { buffer: None, .. }))
+        ) {
+            return None;
+        }
+
+        let mut elements = Vec::new();
+        for (index, property, value) in self.puts(array) {
+            match (property, value) {
+                (MemberProperty::Index(element), Expression::Value(descriptor)) => {
+                    elements.push((*element, *descriptor));
+                    puts.push(index);
+                }
+                _ => return None,
+            }
+        }
+        if self.use_counts[array] != elements.len() + 1 {
+            return None;
+        }
+        elements.sort_by_key(|(element, _)| *element);
+
+        let mut members = Vec::new();
+        for (_, descriptor) in elements {
+            members.extend(self.descriptor(descriptor, is_static, puts)?);
+        }
+        Some(members)
+    }
+
+    fn descriptor(
+        &self,
+        descriptor: ValueId,
+        is_static: bool,
+        puts: &mut Vec<usize>,
+    ) -> Option<Vec<ClassMember>> {
+        let mut fields: Vec<(String, Expression)> = Vec::new();
+        match self.definitions.get(descriptor) {
+            Some(Some(Expression::NewObject {
+                parent: None,
+                buffer,
+            })) => {
+                if let Some((length, keys, values)) = buffer {
+                    let literals = self.bytecode.object_literals(*keys, *values, *length)?;
+                    for (key, value) in literals {
+                        fields.push((
+                            self.name(&Expression::Literal(key))?,
+                            Expression::Literal(value),
+                        ));
+                    }
+                }
+            }
+            _ => return None,
+        }
+
+        let mut put_count = 0;
+        for (index, property, value) in self.puts(descriptor) {
+            let name = match property {
+                MemberProperty::Named(name) => self.bytecode.string(name.index())?,
+                _ => return None,
+            };
+            fields.push((name, value.clone()));
+            puts.push(index);
+            put_count += 1;
+        }
+        if self.use_counts[descriptor] != put_count + 1 {
+            return None;
+        }
+
+        let mut key = None;
+        let mut members: Vec<ClassMember> = Vec::new();
+        for (name, value) in fields {
+            let kind = match name.as_str() {
+                "key" => {
+                    key = Some(MemberProperty::from_key(&value));
+                    continue;
+                }
+                "value" => ClassMemberKind::Method,
+                "get" => ClassMemberKind::Getter,
+                "set" => ClassMemberKind::Setter,
+                _ => continue,
+            };
+            // Later puts fill in the placeholders of the literal.
+            members.retain(|member| member.kind != kind);
+            if is_function(self.definitions, &value) {
+                members.push(ClassMember {
+                    kind,
+                    is_static,
+                    key: MemberProperty::Index(0),
+                    function: value,
+                });
+            }
+        }
+
+        let key = key?;
+        for member in &mut members {
+            member.key = key.clone();
+        }
+        Some(members)
+    }
+
+    /// The `value[property] = ...` statements, with their positions.
+    fn puts(&self, object: ValueId) -> Vec<(usize, &MemberProperty, &Expression)> {
+        self.statements
+            .iter()
+            .enumerate()
+            .filter_map(|(index, statement)| match statement {
+                Statement::Expression(Expression::Assignment {
+                    operator: AssignmentOperator::Assignment,
+                    target,
+                    value,
+                }) => match target.as_ref() {
+                    Expression::Member {
+                        object: target,
+                        property,
+                    } if matches!(target.as_ref(), Expression::Value(value) if *value == object) => {
+                        Some((index, property, value.as_ref()))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn name(&self, expression: &Expression) -> Option<String> {
+        match expression {
+            Expression::Literal(Literal::String(string)) => self.bytecode.string(string.index()),
+            _ => None,
+        }
+    }
+}
+
+fn reads_variable(expression: &Expression, variables: &HashSet<CapturedVariable>) -> bool {
+    match expression {
+        Expression::Captured(variable) => variables.contains(variable),
+        expression => expression
+            .children()
+            .iter()
+            .any(|child| reads_variable(child, variables)),
+    }
+}
+
+fn is_function(definitions: &[Option<&Expression>], expression: &Expression) -> bool {
+    match expression {
+        Expression::Closure { kind, .. } => *kind != ClosureKind::Generator,
+        Expression::Value(value) => matches!(
+            definitions.get(*value),
+            Some(Some(definition)) if is_function(definitions, definition)
+        ),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    fn empty(name: &'static str) -> testing::Function {
+        testing::function("LoadConstUndefined r0\nRet r0").name(name)
+    }
+
+    #[test]
+    fn prototype_wiring_becomes_a_class() {
+        let text = testing::decompile(&[
+            testing::function(
+                r#"
+                CreateEnvironment r0
+                CreateClosure r1, r0, 1
+                GetById r2, r1, 0, "prototype"
+                CreateClosure r3, r0, 2
+                PutNewOwnNEById r2, r3, "m"
+                CreateClosure r4, r0, 3
+                LoadConstUndefined r5
+                LoadConstString r6, "p"
+                PutOwnGetterSetterByVal r2, r6, r4, r5, 0
+                CreateClosure r7, r0, 4
+                PutNewOwnNEById r1, r7, "s"
+                GetGlobalObject r8
+                GetById r9, r8, 0, "Base"
+                LoadConstUndefined r31
+                Mov r30, r1
+                Mov r29, r9
+                CallBuiltin r10, 40, 3
+                GetById r11, r9, 0, "prototype"
+                Mov r30, r2
+                Mov r29, r11
+                CallBuiltin r10, 40, 3
+                PutById r8, r1, 0, "Foo"
+                Ret r5
+                "#,
+            ),
+            empty("Foo"),
+            empty("m"),
+            empty("p"),
+            empty("s"),
+        ]);
+        assert_eq!(
+            text,
+            concat!(
+                "let globalObject;\n",
+                "globalObject = globalThis;\n",
+                "globalObject.Foo = class Foo extends globalObject.Base {\n",
+                "  m() {}\n",
+                "  get p() {}\n",
+                "  static s() {}\n",
+                "};\n"
+            )
+        );
+    }
+
+    #[test]
+    fn enumerable_prototype_methods_stay_a_function() {
+        let text = testing::decompile(&[
+            testing::function(
+                r#"
+                CreateEnvironment r0
+                CreateClosure r1, r0, 1
+                GetById r2, r1, 0, "prototype"
+                CreateClosure r3, r0, 2
+                PutById r2, r3, 0, "m"
+                GetGlobalObject r8
+                PutById r8, r1, 0, "Foo"
+                LoadConstUndefined r5
+                Ret r5
+                "#,
+            ),
+            empty("Foo"),
+            empty("m"),
+        ]);
+        assert_eq!(
+            text,
+            concat!(
+                "let Foo2;\n",
+                "Foo2 = function Foo() {};\n",
+                "Foo2.prototype.m = function m() {};\n",
+                "Foo = Foo2;\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,110 @@
+use crate::ast::{Expression, LiftedFunction, Nesting, Scopes};
+
+/// Replaces environment registers with the function whose scope they hold,
+/// so captured variables read the same in every function that touches them.
+pub fn resolve_scopes(function: &mut LiftedFunction, nesting: &Nesting) {
+    let scopes: Vec<Option<u32>> = {
+        let definitions = function.definitions();
+        definitions
+            .iter()
+            .map(|definition| {
+                definition
+                    .and_then(|expression| nesting.resolve(function, &definitions, expression))
+            })
+            .collect()
+    };
+
+    let index = function.index;
+    for block in &mut function.blocks {
+        let expressions = block
+            .statements
+            .iter_mut()
+            .flat_map(|statement| statement.expressions_mut())
+            .chain(block.terminator.expressions_mut());
+        for expression in expressions {
+            rewrite(expression, index, &scopes, nesting);
+        }
+    }
+}
+
+fn rewrite(expression: &mut Expression, function: u32, scopes: &[Option<u32>], nesting: &Nesting) {
+    let owner = match expression {
+        Expression::Value(value) => scopes[*value],
+        Expression::NewEnvironment => Some(function),
+        Expression::Environment { level } => nesting.environment_owner(function, *level),
+        _ => None,
+    };
+
+    match owner {
+        Some(owner) => *expression = Expression::Scope(owner),
+        None => {
+            for child in expression.children_mut() {
+                rewrite(child, function, scopes, nesting);
+            }
+        }
+    }
+}
+
+/// Turns environment slots of resolved scopes into captured variables.
+pub fn capture_variables(function: &mut LiftedFunction, scopes: &Scopes) {
+    for block in &mut function.blocks {
+        let expressions = block
+            .statements
+            .iter_mut()
+            .flat_map(|statement| statement.expressions_mut())
+            .chain(block.terminator.expressions_mut());
+        for expression in expressions {
+            capture(expression, scopes);
+        }
+    }
+}
+
+fn capture(expression: &mut Expression, scopes: &Scopes) {
+    // Targets are not children, they are written rather than read.
+    if let Expression::Assignment { target, .. } = expression {
+        if let Some(variable) = Scopes::slot_of(target) {
+            if scopes.id(variable).is_some() {
+                **target = Expression::Captured(variable);
+            }
+        }
+    }
+
+    match Scopes::slot_of(expression) {
+        Some(variable) if scopes.id(variable).is_some() => {
+            *expression = Expression::Captured(variable);
+        }
+        _ => {
+            for child in expression.children_mut() {
+                capture(child, scopes);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    #[test]
+    fn environment_of_a_function_nothing_creates_stays_unresolved() {
+        let text = testing::decompile(&[
+            testing::function(
+                r#"
+                LoadConstUndefined r0
+                Ret r0
+                "#,
+            ),
+            testing::function(
+                r#"
+                GetEnvironment r0, 0
+                LoadFromEnvironment r1, r0, 0
+                Ret r1
+                "#,
+            ),
+        ]);
+        assert_eq!(
+            text,
+            "// Never created.\n(function() {\n  return undefined /* unresolved: slot 0 of the captured environment */;\n});\n"
+        );
+    }
+}
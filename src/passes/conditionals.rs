@@ -0,0 +1,615 @@
+use crate::{
+    analysis::{BlockId, ValueId},
+    ast::{Expression, LiftedFunction, LogicalOperator, Statement, Terminator},
+    ir::{BinaryOperator, Literal, UnaryOperator},
+};
+
+use super::{eliminate_dead_code, merge_blocks, propagate, remove_unreachable_blocks};
+
+/// Collapses the small diamonds `&&`, `||`, `??`, `?:` and `?.` compile to,
+/// where each arm writes the register the join reads, back into expressions.
+/// Branches that only test a second condition on the way to the same place
+/// become one `&&` or `||` condition. Inner diamonds go first, so the arms
+/// of the outer ones are left with a single expression.
+pub fn restore_conditionals(function: &mut LiftedFunction) {
+    loop {
+        let literals: Vec<Option<Literal>> = function
+            .definitions()
+            .iter()
+            .map(|definition| match definition {
+                Some(Expression::Literal(literal)) => Some(*literal),
+                _ => None,
+            })
+            .collect();
+
+        let mut changed = false;
+        for head in 0..function.blocks.len() {
+            if collapse_diamond(function, head, &literals) || merge_conditions(function, head) {
+                function.recompute_predecessors();
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        function.remove_trivial_phis();
+        remove_unreachable_blocks(function);
+        merge_blocks(function);
+        eliminate_dead_code(function);
+        propagate(function);
+    }
+}
+
+/// Turns `head`, which branches either straight to a join or through an arm
+/// to it (`a && b`), or through one of two arms (`c ? x : y`), into an
+/// expression for the one phi of the join the arms disagree on.
+fn collapse_diamond(
+    function: &mut LiftedFunction,
+    head: BlockId,
+    literals: &[Option<Literal>],
+) -> bool {
+    let (condition, target, fallthrough) = match &function.blocks[head].terminator {
+        Terminator::Branch {
+            condition,
+            target,
+            fallthrough,
+        } if target != fallthrough => (condition, *target, *fallthrough),
+        _ => return false,
+    };
+
+    // The edges into the join the phi arguments come from, and the arms.
+    let (join, edges, arms) = match (
+        arm_join(function, head, target),
+        arm_join(function, head, fallthrough),
+    ) {
+        (_, Some(join)) if join == target => (join, [head, fallthrough], vec![fallthrough]),
+        (Some(join), _) if join == fallthrough => (join, [target, head], vec![target]),
+        (Some(join), Some(other)) if join == other => {
+            (join, [target, fallthrough], vec![target, fallthrough])
+        }
+        _ => return false,
+    };
+    if join == head {
+        return false;
+    }
+
+    let mut differing = None;
+    for phi in &function.blocks[join].phis {
+        let argument = |edge: BlockId| {
+            phi.arguments
+                .iter()
+                .find(|(predecessor, _)| *predecessor == edge)
+                .map(|(_, argument)| *argument)
+        };
+        match (argument(edges[0]), argument(edges[1])) {
+            (Some(first), Some(second)) if first == second => {}
+            (Some(first), Some(second)) if differing.is_none() => {
+                differing = Some((phi.value, first, second))
+            }
+            _ => return false,
+        }
+    }
+    let (phi, first, second) = match differing {
+        Some(differing) => differing,
+        None => return false,
+    };
+
+    let arm_expression = |arm: BlockId, argument: ValueId| match function.blocks[arm]
+        .statements
+        .as_slice()
+    {
+        [] => Some(Expression::Value(argument)),
+        [Statement::Assign { value, expression }] if *value == argument => Some(expression.clone()),
+        _ => None,
+    };
+    let expression = match arms.as_slice() {
+        [arm] if *arm == fallthrough => {
+            let rest = match arm_expression(*arm, second) {
+                Some(rest) => rest,
+                None => return false,
+            };
+            short_circuit(condition.clone(), first, rest, literals)
+        }
+        [arm] => {
+            let rest = match arm_expression(*arm, first) {
+                Some(rest) => rest,
+                None => return false,
+            };
+            short_circuit(negate(condition.clone()), second, rest, literals)
+        }
+        _ => match (
+            arm_expression(target, first),
+            arm_expression(fallthrough, second),
+        ) {
+            (Some(consequent), Some(alternate)) => {
+                conditional(condition.clone(), consequent, alternate, literals)
+            }
+            _ => return false,
+        },
+    };
+
+    let value = function.new_value(function.registers[phi]);
+    let block = &mut function.blocks[head];
+    block
+        .statements
+        .push(Statement::Assign { value, expression });
+    block.terminator = Terminator::Goto(join);
+
+    // Arms the head alone reached go, the phi takes the new value from the
+    // head and keeps the others.
+    for arm in arms {
+        if function.blocks[arm].predecessors == [head] {
+            function.blocks[arm].statements.clear();
+            function.blocks[arm].terminator = Terminator::Unreachable;
+        }
+    }
+    for phi_node in &mut function.blocks[join].phis {
+        let argument = match phi_node.value == phi {
+            true => value,
+            false => match phi_node
+                .arguments
+                .iter()
+                .find(|(predecessor, _)| *predecessor == edges[0])
+            {
+                Some((_, argument)) => *argument,
+                None => continue,
+            },
+        };
+        phi_node
+            .arguments
+            .retain(|(predecessor, _)| *predecessor != head);
+        phi_node.arguments.push((head, argument));
+    }
+    true
+}
+
+/// The join `block` passes on to when it is an arm of a diamond headed by
+/// `head`: it has at most one statement and goes straight on. An arm
+/// shared with other branches, as the short circuit of a `?.` chain is,
+/// can only load a constant, which stays for the others.
+fn arm_join(function: &LiftedFunction, head: BlockId, block: BlockId) -> Option<BlockId> {
+    let arm = &function.blocks[block];
+    let join = match arm.terminator {
+        Terminator::Goto(join) => join,
+        _ => return None,
+    };
+    let is_arm = block != head
+        && join != block
+        && arm.phis.is_empty()
+        && arm.handler == function.blocks[head].handler
+        && match arm.statements.len() {
+            0 => true,
+            1 => {
+                arm.predecessors == [head]
+                    || matches!(
+                        arm.statements[0],
+                        Statement::Assign {
+                            expression: Expression::Literal(_),
+                            ..
+                        }
+                    )
+            }
+            _ => false,
+        };
+    is_arm.then_some(join)
+}
+
+/// The value of `left` when `skip` holds, of `right` otherwise.
+fn short_circuit(
+    skip: Expression,
+    left: ValueId,
+    right: Expression,
+    literals: &[Option<Literal>],
+) -> Expression {
+    let operator = match &skip {
+        Expression::Value(value) if *value == left => Some(LogicalOperator::Or),
+        Expression::Unary {
+            operator: UnaryOperator::LogicalNot,
+            argument,
+        } if matches!(argument.as_ref(), Expression::Value(value) if *value == left) => {
+            Some(LogicalOperator::And)
+        }
+        skip if nullish_check(skip, literals) == Some((left, false)) => {
+            Some(LogicalOperator::NullishCoalescing)
+        }
+        _ => None,
+    };
+    match operator {
+        Some(operator) => logical(operator, Expression::Value(left), right),
+        None => Expression::Conditional {
+            test: Box::new(skip),
+            consequent: Box::new(Expression::Value(left)),
+            alternate: Box::new(right),
+        },
+    }
+}
+
+fn conditional(
+    test: Expression,
+    consequent: Expression,
+    alternate: Expression,
+    literals: &[Option<Literal>],
+) -> Expression {
+    let (test, consequent, alternate) = match test {
+        Expression::Unary {
+            operator: UnaryOperator::LogicalNot,
+            argument,
+        } => (*argument, alternate, consequent),
+        test => (test, consequent, alternate),
+    };
+
+    let is_value = |expression: &Expression, value: ValueId| matches!(expression, Expression::Value(current) if *current == value);
+    if let Expression::Value(value) = test {
+        if is_value(&consequent, value) {
+            return logical(LogicalOperator::Or, test, alternate);
+        }
+        if is_value(&alternate, value) {
+            return logical(LogicalOperator::And, test, consequent);
+        }
+    }
+
+    if let Some((value, is_null)) = nullish_check(&test, literals) {
+        let (skipped, other) = match is_null {
+            true => (&consequent, &alternate),
+            false => (&alternate, &consequent),
+        };
+        // `value ?? other`, with `value` kept when it is not nullish.
+        if is_value(other, value) {
+            return logical(
+                LogicalOperator::NullishCoalescing,
+                Expression::Value(value),
+                skipped.clone(),
+            );
+        }
+        // `value?.rest`, undefined when it is.
+        if is_undefined(skipped, literals) {
+            let mut chain = other.clone();
+            if mark_optional(&mut chain, value) {
+                return chain;
+            }
+        }
+    }
+
+    Expression::Conditional {
+        test: Box::new(test),
+        consequent: Box::new(consequent),
+        alternate: Box::new(alternate),
+    }
+}
+
+/// `left operator right`, grouped to the left like the source would have
+/// it: the operators are associative.
+fn logical(operator: LogicalOperator, left: Expression, right: Expression) -> Expression {
+    match right {
+        Expression::Logical {
+            operator: inner,
+            left: middle,
+            right,
+        } if inner == operator => Expression::Logical {
+            operator,
+            left: Box::new(logical(operator, left, *middle)),
+            right,
+        },
+        right => Expression::Logical {
+            operator,
+            left: Box::new(left),
+            right: Box::new(right),
+        },
+    }
+}
+
+/// The value `condition` compares loosely to null or undefined, and whether
+/// it holds when the value is nullish.
+fn nullish_check(condition: &Expression, literals: &[Option<Literal>]) -> Option<(ValueId, bool)> {
+    match condition {
+        Expression::Unary {
+            operator: UnaryOperator::LogicalNot,
+            argument,
+        } => nullish_check(argument, literals).map(|(value, is_null)| (value, !is_null)),
+        Expression::Binary {
+            operator: operator @ (BinaryOperator::Equality | BinaryOperator::InEquality),
+            left,
+            right,
+        } => {
+            let value = match (left.as_ref(), right.as_ref()) {
+                (Expression::Value(value), other) | (other, Expression::Value(value))
+                    if is_nullish(other, literals)
+                        && !is_nullish(&Expression::Value(*value), literals) =>
+                {
+                    *value
+                }
+                _ => return None,
+            };
+            Some((value, *operator == BinaryOperator::Equality))
+        }
+        _ => None,
+    }
+}
+
+fn is_nullish(expression: &Expression, literals: &[Option<Literal>]) -> bool {
+    matches!(
+        literal(expression, literals),
+        Some(Literal::Null | Literal::Undefined)
+    )
+}
+
+fn is_undefined(expression: &Expression, literals: &[Option<Literal>]) -> bool {
+    matches!(literal(expression, literals), Some(Literal::Undefined))
+}
+
+fn literal(expression: &Expression, literals: &[Option<Literal>]) -> Option<Literal> {
+    match expression {
+        Expression::Literal(literal) => Some(*literal),
+        Expression::Value(value) => *literals.get(*value)?,
+        _ => None,
+    }
+}
+
+/// Marks `value` as optional where it starts the member and call chain
+/// `expression`, if it does.
+fn mark_optional(expression: &mut Expression, value: ValueId) -> bool {
+    fn mark(expression: &mut Expression, value: ValueId) -> bool {
+        match expression {
+            Expression::Value(current) if *current == value => {
+                *expression = Expression::Optional(Box::new(Expression::Value(value)));
+                true
+            }
+            Expression::Member { object, .. } => mark(object, value),
+            Expression::Call { callee, .. } => mark(callee, value),
+            Expression::Optional(inner) => mark(inner, value),
+            _ => false,
+        }
+    }
+
+    matches!(
+        expression,
+        Expression::Member { .. } | Expression::Call { .. }
+    ) && mark(expression, value)
+}
+
+/// Joins `head` with the empty block it falls into when that block only
+/// branches again, with one of its targets shared: `if (a && b)`.
+fn merge_conditions(function: &mut LiftedFunction, head: BlockId) -> bool {
+    let (condition, target, fallthrough) = match &function.blocks[head].terminator {
+        Terminator::Branch {
+            condition,
+            target,
+            fallthrough,
+        } if target != fallthrough => (condition, *target, *fallthrough),
+        _ => return false,
+    };
+
+    // `head` goes to `exit` when `when` holds, to `next` otherwise.
+    for (next, exit, when) in [
+        (fallthrough, target, condition.clone()),
+        (target, fallthrough, negate(condition.clone())),
+    ] {
+        let block = &function.blocks[next];
+        let is_test = next != head
+            && block.predecessors == [head]
+            && block.phis.is_empty()
+            && block.statements.is_empty()
+            && block.handler == function.blocks[head].handler;
+        let (second, next_target, next_fallthrough) = match &block.terminator {
+            Terminator::Branch {
+                condition,
+                target,
+                fallthrough,
+            } if is_test && target != fallthrough => (condition.clone(), *target, *fallthrough),
+            _ => continue,
+        };
+        let (condition, other) = if next_target == exit {
+            (combine(LogicalOperator::Or, when, second), next_fallthrough)
+        } else if next_fallthrough == exit {
+            (
+                combine(LogicalOperator::And, negate(when), second),
+                next_target,
+            )
+        } else {
+            continue;
+        };
+        if other == head || other == next {
+            continue;
+        }
+
+        let agrees = function.blocks[exit].phis.iter().all(|phi| {
+            let argument = |edge: BlockId| {
+                phi.arguments
+                    .iter()
+                    .find(|(predecessor, _)| *predecessor == edge)
+                    .map(|(_, argument)| *argument)
+            };
+            argument(head) == argument(next)
+        });
+        if !agrees {
+            continue;
+        }
+
+        function.blocks[head].terminator = Terminator::Branch {
+            condition,
+            target: next_target,
+            fallthrough: next_fallthrough,
+        };
+        function.blocks[next].terminator = Terminator::Unreachable;
+        for phi in &mut function.blocks[other].phis {
+            for (predecessor, _) in &mut phi.arguments {
+                if *predecessor == next {
+                    *predecessor = head;
+                }
+            }
+        }
+        return true;
+    }
+    false
+}
+
+/// `left operator right` as a condition, written `!(a && b)` rather than
+/// `!a || !b`.
+fn combine(operator: LogicalOperator, left: Expression, right: Expression) -> Expression {
+    let not = |expression: &Expression| {
+        matches!(
+            expression,
+            Expression::Unary {
+                operator: UnaryOperator::LogicalNot,
+                ..
+            }
+        )
+    };
+    if not(&left) && not(&right) {
+        let operator = match operator {
+            LogicalOperator::And => LogicalOperator::Or,
+            _ => LogicalOperator::And,
+        };
+        return negate(logical(operator, negate(left), negate(right)));
+    }
+    logical(operator, left, right)
+}
+
+/// `!expression`, without stacking negations.
+fn negate(expression: Expression) -> Expression {
+    let flipped = |operator| match operator {
+        BinaryOperator::Equality => Some(BinaryOperator::InEquality),
+        BinaryOperator::InEquality => Some(BinaryOperator::Equality),
+        BinaryOperator::Identity => Some(BinaryOperator::NonIdentity),
+        BinaryOperator::NonIdentity => Some(BinaryOperator::Identity),
+        _ => None,
+    };
+    match expression {
+        Expression::Unary {
+            operator: UnaryOperator::LogicalNot,
+            argument,
+        } => *argument,
+        Expression::Binary {
+            operator,
+            left,
+            right,
+        } if flipped(operator).is_some() => Expression::Binary {
+            operator: flipped(operator).unwrap(),
+            left,
+            right,
+        },
+        expression => Expression::Unary {
+            operator: UnaryOperator::LogicalNot,
+            argument: Box::new(expression),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    /// `x = <value of r1 at join>`, with `branch` computing it.
+    fn assign_x(branch: &str) -> String {
+        let source = format!(
+            r#"
+                GetGlobalObject r0
+                GetById r1, r0, 1, "a"
+                {}
+                join:
+                GetGlobalObject r0
+                PutById r0, r1, 3, "x"
+                LoadConstUndefined r3
+                Ret r3
+                "#,
+            branch
+        );
+        testing::decompile(&[testing::function(source)])
+    }
+
+    #[test]
+    fn logical_and() {
+        let text = assign_x(
+            r#"
+            JmpFalse @join, r1
+            GetGlobalObject r0
+            GetById r1, r0, 2, "b"
+            "#,
+        );
+        assert_eq!(text, "x = a && b;\n");
+    }
+
+    #[test]
+    fn nullish_coalescing() {
+        let text = assign_x(
+            r#"
+            LoadConstNull r4
+            JNotEqual @join, r1, r4
+            GetGlobalObject r0
+            GetById r1, r0, 2, "b"
+            "#,
+        );
+        assert_eq!(text, "x = a ?? b;\n");
+    }
+
+    #[test]
+    fn conditional_expression() {
+        let text = assign_x(
+            r#"
+            JmpFalse @else, r1
+            LoadConstUInt8 r1, 1
+            Jmp @join
+            else:
+            LoadConstUInt8 r1, 2
+            "#,
+        );
+        assert_eq!(text, "x = a ? 1 : 2;\n");
+    }
+
+    #[test]
+    fn optional_member() {
+        let text = assign_x(
+            r#"
+            LoadConstNull r4
+            JEqual @short, r1, r4
+            GetById r1, r1, 2, "p"
+            Jmp @join
+            short:
+            LoadConstUndefined r1
+            "#,
+        );
+        assert_eq!(text, "x = a?.p;\n");
+    }
+
+    #[test]
+    fn arm_with_two_statements_stays_an_if() {
+        let text = assign_x(
+            r#"
+            JmpFalse @join, r1
+            GetGlobalObject r0
+            GetById r5, r0, 4, "f"
+            LoadConstUndefined r6
+            Call1 r7, r5, r6
+            GetGlobalObject r0
+            GetById r1, r0, 2, "b"
+            "#,
+        );
+        assert_eq!(
+            text,
+            "let a2;\na2 = a;\nif (a2) {\n  f();\n  a2 = b;\n}\nx = a2;\n"
+        );
+    }
+
+    #[test]
+    fn chained_tests_become_one_condition() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            GetGlobalObject r0
+            GetById r1, r0, 1, "a"
+            JmpFalse @end, r1
+            GetGlobalObject r0
+            GetById r2, r0, 2, "b"
+            JmpFalse @end, r2
+            GetGlobalObject r0
+            GetById r3, r0, 3, "f"
+            LoadConstUndefined r4
+            Call1 r5, r3, r4
+            end:
+            LoadConstUndefined r6
+            Ret r6
+            "#,
+        )]);
+        assert_eq!(text, "if (a && b) {\n  f();\n}\n");
+    }
+}
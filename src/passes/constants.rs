@@ -0,0 +1,345 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analysis::{BlockId, ValueId},
+    ast::{Block, Constant, Expression, LiftedFunction, LogicalOperator, Statement, Terminator},
+    bytecode_file_format::BytecodeFile,
+};
+
+use super::{eliminate_dead_code, propagate, remove_unreachable_blocks};
+
+/// What the propagation knows about a value.
+#[derive(Debug, Clone)]
+enum Cell {
+    /// No definition reaching it has been evaluated yet.
+    Pending,
+    Constant(Constant),
+    Varying,
+}
+
+impl Cell {
+    fn join(self, other: Self) -> Self {
+        match (self, other) {
+            (Cell::Pending, other) | (other, Cell::Pending) => other,
+            (Cell::Constant(first), Cell::Constant(second)) if first.is_same(&second) => {
+                Cell::Constant(first)
+            }
+            _ => Cell::Varying,
+        }
+    }
+
+    fn is_same(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Cell::Pending, Cell::Pending) | (Cell::Varying, Cell::Varying) => true,
+            (Cell::Constant(first), Cell::Constant(second)) => first.is_same(second),
+            _ => false,
+        }
+    }
+}
+
+/// Sparse conditional constant propagation. Values are evaluated with the
+/// semantics of JavaScript primitives, and only along edges a branch on a
+/// constant can take, so code behind an always-false test never makes a
+/// value vary. Constant operations are then folded into literals and the
+/// branches that cannot be taken removed.
+pub fn propagate_constants(function: &mut LiftedFunction, bytecode: &BytecodeFile) {
+    let cells = Solver::solve(function, bytecode);
+    let phis: HashSet<ValueId> = function
+        .blocks
+        .iter()
+        .flat_map(|block| block.phis.iter().map(|phi| phi.value))
+        .collect();
+    let mut folder = Folder {
+        cells: &cells,
+        phis: &phis,
+        bytecode,
+        strings: None,
+    };
+
+    let mut changed = false;
+    for block in &mut function.blocks {
+        let expressions = block
+            .statements
+            .iter_mut()
+            .flat_map(|statement| statement.expressions_mut())
+            .chain(block.terminator.expressions_mut());
+        for expression in expressions {
+            changed |= folder.fold(expression);
+        }
+
+        let target = match &block.terminator {
+            Terminator::Branch {
+                condition,
+                target,
+                fallthrough,
+            } => match evaluate(condition, &cells, bytecode) {
+                Cell::Constant(condition) if condition.to_boolean() => Some(*target),
+                Cell::Constant(_) => Some(*fallthrough),
+                _ => None,
+            },
+            Terminator::Switch {
+                value,
+                minimum,
+                targets,
+                default,
+            } => match evaluate(value, &cells, bytecode) {
+                Cell::Constant(value) => Some(switch_target(&value, *minimum, targets, *default)),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(target) = target {
+            block.terminator = Terminator::Goto(target);
+            changed = true;
+        }
+    }
+
+    if changed {
+        function.recompute_predecessors();
+        remove_unreachable_blocks(function);
+        function.remove_trivial_phis();
+        eliminate_dead_code(function);
+        propagate(function);
+    }
+}
+
+struct Solver<'a> {
+    bytecode: &'a BytecodeFile<'a>,
+    cells: Vec<Cell>,
+    executable: Vec<bool>,
+    edges: HashSet<(BlockId, BlockId)>,
+}
+
+impl Solver<'_> {
+    fn solve(function: &LiftedFunction, bytecode: &BytecodeFile) -> Vec<Cell> {
+        // Values from anything but an `Assign` or a phi could be anything.
+        let mut cells = vec![Cell::Varying; function.registers.len()];
+        for block in &function.blocks {
+            for phi in &block.phis {
+                cells[phi.value] = Cell::Pending;
+            }
+            for statement in &block.statements {
+                if let Statement::Assign { value, .. } = statement {
+                    cells[*value] = Cell::Pending;
+                }
+            }
+        }
+
+        let mut solver = Solver {
+            bytecode,
+            cells,
+            executable: vec![false; function.blocks.len()],
+            edges: HashSet::new(),
+        };
+        if let Some(entry) = solver.executable.first_mut() {
+            *entry = true;
+        }
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (index, block) in function.blocks.iter().enumerate() {
+                if solver.executable[index] {
+                    changed |= solver.visit(index, block);
+                }
+            }
+        }
+        solver.cells
+    }
+
+    /// Evaluates the block's definitions and marks the edges it can leave
+    /// along, returning whether anything changed.
+    fn visit(&mut self, index: BlockId, block: &Block) -> bool {
+        let mut changed = false;
+        for phi in &block.phis {
+            let joined = phi
+                .arguments
+                .iter()
+                .filter(|(predecessor, _)| self.edges.contains(&(*predecessor, index)))
+                .fold(Cell::Pending, |joined, (_, argument)| {
+                    joined.join(self.cells[*argument].clone())
+                });
+            changed |= self.update(phi.value, joined);
+        }
+
+        for statement in &block.statements {
+            if let Statement::Assign { value, expression } = statement {
+                let cell = evaluate(expression, &self.cells, self.bytecode);
+                changed |= self.update(*value, cell);
+            }
+        }
+
+        let mut successors = match &block.terminator {
+            Terminator::Branch {
+                condition,
+                target,
+                fallthrough,
+            } => match evaluate(condition, &self.cells, self.bytecode) {
+                Cell::Pending => vec![],
+                Cell::Constant(condition) if condition.to_boolean() => vec![*target],
+                Cell::Constant(_) => vec![*fallthrough],
+                Cell::Varying => vec![*target, *fallthrough],
+            },
+            Terminator::Switch {
+                value,
+                minimum,
+                targets,
+                default,
+            } => match evaluate(value, &self.cells, self.bytecode) {
+                Cell::Pending => vec![],
+                Cell::Constant(value) => vec![switch_target(&value, *minimum, targets, *default)],
+                Cell::Varying => block.terminator.successors(),
+            },
+            terminator => terminator.successors(),
+        };
+        successors.extend(block.handler);
+        for statement in &block.statements {
+            if let Statement::SaveGenerator { resume } = statement {
+                successors.push(*resume);
+            }
+        }
+
+        for successor in successors {
+            if self.edges.insert((index, successor)) {
+                self.executable[successor] = true;
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    fn update(&mut self, value: ValueId, cell: Cell) -> bool {
+        let joined = self.cells[value].clone().join(cell);
+        let changed = !joined.is_same(&self.cells[value]);
+        self.cells[value] = joined;
+        changed
+    }
+}
+
+/// SwitchImm takes `default` for anything but a number in the table.
+fn switch_target(value: &Constant, minimum: u32, targets: &[BlockId], default: BlockId) -> BlockId {
+    match value {
+        Constant::Number(number) if number.fract() == 0.0 && *number >= f64::from(minimum) => {
+            let offset = *number - f64::from(minimum);
+            match offset < targets.len() as f64 {
+                true => targets[offset as usize],
+                false => default,
+            }
+        }
+        _ => default,
+    }
+}
+
+/// The value of `expression` as far as `cells` knows.
+fn evaluate(expression: &Expression, cells: &[Cell], bytecode: &BytecodeFile) -> Cell {
+    let evaluate = |expression: &Expression| evaluate(expression, cells, bytecode);
+    let (first, second) = match expression {
+        Expression::Value(value) => return cells[*value].clone(),
+        Expression::Literal(literal) => {
+            return match Constant::from_literal(literal, bytecode) {
+                Some(constant) => Cell::Constant(constant),
+                None => Cell::Varying,
+            }
+        }
+        Expression::Unary { argument, .. } | Expression::Conversion { argument, .. } => {
+            (evaluate(argument), Cell::Constant(Constant::Undefined))
+        }
+        Expression::Binary { left, right, .. } => (evaluate(left), evaluate(right)),
+        Expression::Logical {
+            operator,
+            left,
+            right,
+        } => {
+            return match evaluate(left) {
+                Cell::Constant(left) => {
+                    let short_circuits = match operator {
+                        LogicalOperator::And => !left.to_boolean(),
+                        LogicalOperator::Or => left.to_boolean(),
+                        LogicalOperator::NullishCoalescing => {
+                            !matches!(left, Constant::Undefined | Constant::Null)
+                        }
+                    };
+                    match short_circuits {
+                        true => Cell::Constant(left),
+                        false => evaluate(right),
+                    }
+                }
+                cell => cell,
+            }
+        }
+        Expression::Conditional {
+            test,
+            consequent,
+            alternate,
+        } => {
+            return match evaluate(test) {
+                Cell::Constant(test) if test.to_boolean() => evaluate(consequent),
+                Cell::Constant(_) => evaluate(alternate),
+                Cell::Pending => Cell::Pending,
+                Cell::Varying => evaluate(consequent).join(evaluate(alternate)),
+            }
+        }
+        _ => return Cell::Varying,
+    };
+
+    let (first, second) = match (first, second) {
+        (Cell::Varying, _) | (_, Cell::Varying) => return Cell::Varying,
+        (Cell::Pending, _) | (_, Cell::Pending) => return Cell::Pending,
+        (Cell::Constant(first), Cell::Constant(second)) => (first, second),
+    };
+    let constant = match expression {
+        Expression::Unary { operator, .. } => Some(Constant::unary(*operator, &first)),
+        Expression::Conversion { conversion, .. } => Some(Constant::convert(*conversion, &first)),
+        Expression::Binary { operator, .. } => Constant::binary(*operator, &first, &second),
+        _ => None,
+    };
+    match constant {
+        Some(constant) => Cell::Constant(constant),
+        None => Cell::Varying,
+    }
+}
+
+struct Folder<'a> {
+    cells: &'a [Cell],
+    phis: &'a HashSet<ValueId>,
+    bytecode: &'a BytecodeFile<'a>,
+    /// The string table by contents, built on first use.
+    strings: Option<HashMap<Vec<u16>, u32>>,
+}
+
+impl Folder<'_> {
+    /// Replaces the outermost constant operations in `expression` with their
+    /// value, returning whether anything was replaced. Code with side effects
+    /// stays, and so do strings the string table has no entry for.
+    fn fold(&mut self, expression: &mut Expression) -> bool {
+        let foldable = match expression {
+            Expression::Value(value) => self.phis.contains(value),
+            Expression::Unary { .. }
+            | Expression::Binary { .. }
+            | Expression::Conversion { .. }
+            | Expression::Logical { .. }
+            | Expression::Conditional { .. } => !expression.has_side_effects(),
+            _ => false,
+        };
+        if foldable {
+            if let Cell::Constant(constant) = evaluate(expression, self.cells, self.bytecode) {
+                let bytecode = self.bytecode;
+                let strings = self.strings.get_or_insert_with(|| {
+                    (0..bytecode.header.string_count)
+                        .filter_map(|index| Some((bytecode.string_units(index)?, index)))
+                        .collect()
+                });
+                if let Some(literal) = constant.to_literal(|units| strings.get(units).copied()) {
+                    *expression = Expression::Literal(literal);
+                    return true;
+                }
+            }
+        }
+
+        let mut changed = false;
+        for child in expression.children_mut() {
+            changed |= self.fold(child);
+        }
+        changed
+    }
+}
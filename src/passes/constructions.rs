@@ -0,0 +1,79 @@
+use crate::{
+    ast::{Expression, LiftedFunction},
+    ir::Literal,
+};
+
+use super::eliminate_dead_code;
+
+/// Folds what `new` compiles to back into it. SelectObject picks the object
+/// a constructor returned over the one CreateThis made, which `new` already
+/// does, and the object CreateThis made is `this` to the constructor alone.
+pub fn restore_constructions(function: &mut LiftedFunction) {
+    let constructs: Vec<bool> = function
+        .definitions()
+        .iter()
+        .map(|definition| matches!(definition, Some(Expression::Construct { .. })))
+        .collect();
+    for block in &mut function.blocks {
+        let expressions = block
+            .statements
+            .iter_mut()
+            .flat_map(|statement| statement.expressions_mut())
+            .chain(block.terminator.expressions_mut());
+        for expression in expressions {
+            fold(expression, &constructs);
+        }
+    }
+    eliminate_dead_code(function);
+}
+
+fn fold(expression: &mut Expression, constructs: &[bool]) {
+    for child in expression.children_mut() {
+        fold(child, constructs);
+    }
+
+    match expression {
+        Expression::SelectObject { result, .. } => {
+            let is_construct = match result.as_ref() {
+                Expression::Construct { .. } => true,
+                Expression::Value(value) => constructs[*value],
+                _ => false,
+            };
+            if is_construct {
+                *expression = std::mem::replace(result, Expression::Literal(Literal::Undefined));
+            }
+        }
+        Expression::Construct { this, .. } => {
+            **this = Expression::Literal(Literal::Undefined);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    #[test]
+    fn new_drops_the_object_create_this_made() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            GetGlobalObject r0
+            GetById r1, r0, 1, "F"
+            GetById r2, r1, 2, "prototype"
+            CreateThis r3, r2, r1
+            Mov r31, r3
+            LoadConstUInt8 r30, 1
+            Construct r4, r1, 2
+            SelectObject r5, r3, r4
+            PutById r0, r5, 3, "x"
+            LoadConstUndefined r6
+            Ret r6
+            "#,
+        )]);
+        assert_eq!(
+            text,
+            "let globalObject;\nglobalObject = globalThis;\nglobalObject.x = new globalObject.F(1);\n"
+        );
+    }
+}
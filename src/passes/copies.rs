@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+
+use crate::{
+    analysis::{BlockId, ValueId, Variables},
+    ast::{Expression, LiftedFunction, Statement},
+};
+
+/// Keeps the function in conventional SSA form, where `Variables` can give
+/// every value a phi joins one variable. Passes that forward or fold values
+/// can leave a phi's arguments live at the same time, and such a phi gets
+/// a copy of each argument at the end of its predecessor and a copy of its
+/// result at the start of its block.
+pub fn insert_phi_copies(function: &mut LiftedFunction) {
+    let mut isolated = HashSet::new();
+    loop {
+        let variables = function.variables();
+        let interfering: HashSet<usize> = interferences(function)
+            .into_iter()
+            .map(|(value, _)| variables.of(value))
+            .collect();
+        let phis: Vec<(BlockId, usize)> = function
+            .blocks
+            .iter()
+            .enumerate()
+            .flat_map(|(block, basic_block)| {
+                (0..basic_block.phis.len()).map(move |position| (block, position))
+            })
+            .filter(|(block, position)| {
+                let phi = &function.blocks[*block].phis[*position];
+                interfering.contains(&variables.of(phi.value)) && !isolated.contains(&phi.value)
+            })
+            .collect();
+        if phis.is_empty() {
+            return;
+        }
+        for (block, position) in phis {
+            let value = isolate(function, block, position);
+            isolated.insert(value);
+        }
+    }
+}
+
+/// Moves the phi onto fresh values joined by copies, and returns its new
+/// value. Arguments on exception edges are read where they are thrown, so
+/// they keep their values.
+fn isolate(function: &mut LiftedFunction, block: BlockId, position: usize) -> ValueId {
+    let phi = function.blocks[block].phis[position].clone();
+    let register = function.registers[phi.value];
+
+    for (index, (predecessor, argument)) in phi.arguments.iter().enumerate() {
+        if is_exception_edge(function, *predecessor, block) {
+            continue;
+        }
+        let copy = function.new_value(register);
+        function.types[copy] = function.types[*argument];
+        function.blocks[*predecessor]
+            .statements
+            .push(Statement::Assign {
+                value: copy,
+                expression: Expression::Value(*argument),
+            });
+        function.blocks[block].phis[position].arguments[index].1 = copy;
+    }
+
+    let result = function.new_value(register);
+    function.types[result] = function.types[phi.value];
+    function.blocks[block].phis[position].value = result;
+    let statements = &mut function.blocks[block].statements;
+    let start = statements
+        .iter()
+        .take_while(|statement| matches!(statement, Statement::Catch(_)))
+        .count();
+    statements.insert(
+        start,
+        Statement::Assign {
+            value: phi.value,
+            expression: Expression::Value(result),
+        },
+    );
+    result
+}
+
+fn is_exception_edge(function: &LiftedFunction, predecessor: BlockId, block: BlockId) -> bool {
+    let basic_block = &function.blocks[predecessor];
+    basic_block.handler == Some(block) && !basic_block.terminator.successors().contains(&block)
+}
+
+/// Pairs of values sharing a variable, the first defined while the second
+/// is live. Empty when the function is in conventional SSA form.
+pub(super) fn interferences(function: &LiftedFunction) -> Vec<(ValueId, ValueId)> {
+    let variables = function.variables();
+    let live_out = live_out(function);
+    let mut pairs = Vec::new();
+
+    let mut define = |value: ValueId, live: &mut HashSet<ValueId>, variables: &Variables| {
+        live.remove(&value);
+        for other in live.iter() {
+            if variables.of(*other) == variables.of(value) {
+                pairs.push((value, *other));
+            }
+        }
+    };
+
+    for (block, basic_block) in function.blocks.iter().enumerate() {
+        let mut live = live_out[block].clone();
+        for value in basic_block.terminator.defs() {
+            define(value, &mut live, &variables);
+        }
+        reads(&basic_block.terminator.expressions(), &mut live);
+        for statement in basic_block.statements.iter().rev() {
+            for value in statement.defs() {
+                define(value, &mut live, &variables);
+            }
+            reads(&statement.expressions(), &mut live);
+        }
+        for phi in &basic_block.phis {
+            live.remove(&phi.value);
+        }
+        for phi in &basic_block.phis {
+            define(phi.value, &mut live, &variables);
+            live.insert(phi.value);
+        }
+    }
+    pairs
+}
+
+fn reads(expressions: &[&Expression], live: &mut HashSet<ValueId>) {
+    for expression in expressions {
+        live.extend(expression.values());
+    }
+}
+
+/// The values live at the end of each block, phi arguments counting as
+/// read on their edge.
+fn live_out(function: &LiftedFunction) -> Vec<HashSet<ValueId>> {
+    let count = function.blocks.len();
+    let mut defined = vec![HashSet::new(); count];
+    let mut exposed = vec![HashSet::new(); count];
+    for (block, basic_block) in function.blocks.iter().enumerate() {
+        let mut live = HashSet::new();
+        reads(&basic_block.terminator.expressions(), &mut live);
+        for value in basic_block.terminator.defs() {
+            defined[block].insert(value);
+        }
+        for statement in basic_block.statements.iter().rev() {
+            for value in statement.defs() {
+                live.remove(&value);
+                defined[block].insert(value);
+            }
+            reads(&statement.expressions(), &mut live);
+        }
+        for phi in &basic_block.phis {
+            live.remove(&phi.value);
+            defined[block].insert(phi.value);
+        }
+        exposed[block] = live;
+    }
+
+    let mut live_out: Vec<HashSet<ValueId>> = vec![HashSet::new(); count];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in (0..count).rev() {
+            let mut live = HashSet::new();
+            for successor in function.blocks[block].successors() {
+                for phi in &function.blocks[successor].phis {
+                    for (predecessor, argument) in &phi.arguments {
+                        if *predecessor == block && !is_exception_edge(function, block, successor) {
+                            live.insert(*argument);
+                        }
+                    }
+                }
+                live.extend(exposed[successor].iter().copied());
+                live.extend(
+                    live_out[successor]
+                        .iter()
+                        .filter(|value| !defined[successor].contains(value)),
+                );
+            }
+            if live.len() != live_out[block].len() {
+                live_out[block] = live;
+                changed = true;
+            }
+        }
+    }
+    live_out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// Swaps r0 and r1 on every iteration through r2.
+    const SWAP: &str = r#"
+        LoadConstUInt8 r0, 1
+        LoadConstUInt8 r1, 2
+        LoadConstZero r3
+        LoadConstUInt8 r4, 10
+        loop:
+        Mov r2, r0
+        Mov r0, r1
+        Mov r1, r2
+        LoadConstUInt8 r5, 1
+        Add r3, r3, r5
+        JLess @loop, r3, r4
+        Sub r0, r0, r1
+        GetGlobalObject r6
+        PutById r6, r0, 1, "result"
+        LoadConstUndefined r7
+        Ret r7
+    "#;
+
+    /// Forwards every copy into the phis reading it, which leaves SSA
+    /// form valid but no longer conventional.
+    fn forward_copies(function: &mut LiftedFunction) {
+        let definitions: Vec<Option<ValueId>> = function
+            .definitions()
+            .into_iter()
+            .map(|definition| match definition {
+                Some(Expression::Value(source)) => Some(*source),
+                _ => None,
+            })
+            .collect();
+        for phi in function.blocks.iter_mut().flat_map(|block| &mut block.phis) {
+            for (_, argument) in &mut phi.arguments {
+                while let Some(source) = definitions[*argument] {
+                    *argument = source;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn conventional_form_is_left_alone() {
+        let mut function = testing::lift(testing::function(SWAP));
+        assert!(interferences(&function).is_empty());
+
+        let statements = function.statement_count();
+        insert_phi_copies(&mut function);
+        assert_eq!(function.statement_count(), statements);
+    }
+
+    #[test]
+    fn forwarded_swap_gets_copies() {
+        let mut function = testing::lift(testing::function(SWAP));
+        forward_copies(&mut function);
+        // The two phis now read each other, and coalescing them would lose
+        // one of the values.
+        assert!(!interferences(&function).is_empty());
+
+        insert_phi_copies(&mut function);
+        assert!(interferences(&function).is_empty());
+        let variables = function.variables();
+        let header = function
+            .blocks
+            .iter()
+            .find(|block| block.phis.len() == 3)
+            .expect("loop header");
+        let swapped: Vec<usize> = header
+            .phis
+            .iter()
+            .filter(|phi| function.registers[phi.value] < 2)
+            .map(|phi| variables.of(phi.value))
+            .collect();
+        assert_eq!(swapped.len(), 2);
+        assert_ne!(swapped[0], swapped[1]);
+    }
+
+    #[test]
+    fn swap_loop_keeps_both_values() {
+        let text = testing::decompile(&[testing::function(SWAP)]);
+        assert!(
+            text.contains("  n4 = n;\n  n = n2;\n  n2 = n4;\n"),
+            "{}",
+            text
+        );
+        assert!(text.ends_with("}\nresult = n - n2;\n"), "{}", text);
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::HashSet;
+
+use crate::{
+    analysis::{BlockId, ValueId},
+    ast::{Block, Expression, LiftedFunction, Statement, Terminator},
+};
+
+/// Drops blocks the entry cannot reach and renumbers the rest, keeping
+/// their order.
+pub fn remove_unreachable_blocks(function: &mut LiftedFunction) {
+    if function.blocks.is_empty() {
+        return;
+    }
+
+    let mut reachable = vec![false; function.blocks.len()];
+    let mut worklist = vec![0];
+    reachable[0] = true;
+    while let Some(block) = worklist.pop() {
+        for successor in function.blocks[block].successors() {
+            if !reachable[successor] {
+                reachable[successor] = true;
+                worklist.push(successor);
+            }
+        }
+    }
+
+    if reachable.iter().all(|is_reachable| *is_reachable) {
+        return;
+    }
+
+    let mut renumbered: Vec<Option<BlockId>> = Vec::with_capacity(reachable.len());
+    let mut next = 0;
+    for is_reachable in &reachable {
+        renumbered.push(match is_reachable {
+            true => {
+                next += 1;
+                Some(next - 1)
+            }
+            false => None,
+        });
+    }
+    let map = |block: &mut BlockId| *block = renumbered[*block].unwrap();
+
+    let blocks = std::mem::take(&mut function.blocks);
+    for (mut block, is_reachable) in blocks.into_iter().zip(&reachable) {
+        if !is_reachable {
+            continue;
+        }
+
+        block
+            .predecessors
+            .retain(|predecessor| reachable[*predecessor]);
+        block.predecessors.iter_mut().for_each(map);
+
+        for phi in &mut block.phis {
+            phi.arguments
+                .retain(|(predecessor, _)| reachable[*predecessor]);
+            phi.arguments
+                .iter_mut()
+                .for_each(|(predecessor, _)| map(predecessor));
+        }
+
+        for statement in &mut block.statements {
+            if let Statement::SaveGenerator { resume } = statement {
+                map(resume);
+            }
+        }
+        block.handler.iter_mut().for_each(map);
+        block.terminator.successors_mut().into_iter().for_each(map);
+
+        function.blocks.push(block);
+    }
+
+    let keep = |blocks: &mut Vec<BlockId>| {
+        blocks.retain(|block| reachable[*block]);
+        blocks.iter_mut().for_each(map);
+    };
+    for statement in &mut function.try_statements {
+        keep(&mut statement.body);
+        if let Some(catch) = &mut statement.catch {
+            keep(&mut catch.blocks);
+        }
+        if let Some(finally) = &mut statement.finally {
+            keep(&mut finally.blocks);
+        }
+    }
+    function
+        .try_statements
+        .retain(|statement| !statement.body.is_empty());
+}
+
+/// Appends each block only entered by a jump from another to that block.
+/// Blocks a `try` statement or one of its clauses starts with stay apart.
+pub fn merge_blocks(function: &mut LiftedFunction) {
+    let mut starts: HashSet<BlockId> = HashSet::new();
+    for statement in &function.try_statements {
+        starts.extend(statement.body.first());
+        if let Some(catch) = &statement.catch {
+            starts.extend(catch.blocks.first());
+        }
+        if let Some(finally) = &statement.finally {
+            starts.extend(finally.blocks.first());
+        }
+    }
+
+    let mut merged = false;
+    for block in 0..function.blocks.len() {
+        while let Terminator::Goto(next) = function.blocks[block].terminator {
+            let entered = &function.blocks[next];
+            if next == block
+                || next == 0
+                || entered.predecessors != [block]
+                || !entered.phis.is_empty()
+                || entered.handler != function.blocks[block].handler
+                || starts.contains(&next)
+            {
+                break;
+            }
+
+            let entered = std::mem::replace(
+                &mut function.blocks[next],
+                Block {
+                    phis: vec![],
+                    statements: vec![],
+                    terminator: Terminator::Unreachable,
+                    predecessors: vec![],
+                    handler: None,
+                },
+            );
+            function.blocks[block].statements.extend(entered.statements);
+            function.blocks[block].terminator = entered.terminator;
+            for successor in function.blocks[block].successors() {
+                let successor = &mut function.blocks[successor];
+                for predecessor in &mut successor.predecessors {
+                    if *predecessor == next {
+                        *predecessor = block;
+                    }
+                }
+                for (predecessor, _) in successor
+                    .phis
+                    .iter_mut()
+                    .flat_map(|phi| phi.arguments.iter_mut())
+                {
+                    if *predecessor == next {
+                        *predecessor = block;
+                    }
+                }
+            }
+            merged = true;
+        }
+    }
+
+    if merged {
+        function.recompute_predecessors();
+        remove_unreachable_blocks(function);
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Definition {
+    Phi(BlockId, usize),
+    Statement(BlockId, usize),
+}
+
+/// Removes values nothing observable depends on. Statements with side effects
+/// stay, losing only their destination when it is never read.
+pub fn eliminate_dead_code(function: &mut LiftedFunction) {
+    let mut definitions = vec![None; function.registers.len()];
+    for (block_id, block) in function.blocks.iter().enumerate() {
+        for (position, phi) in block.phis.iter().enumerate() {
+            definitions[phi.value] = Some(Definition::Phi(block_id, position));
+        }
+        for (position, statement) in block.statements.iter().enumerate() {
+            for value in statement.defs() {
+                definitions[value] = Some(Definition::Statement(block_id, position));
+            }
+        }
+    }
+
+    let mut live = vec![false; function.registers.len()];
+    let mut worklist: Vec<ValueId> = Vec::new();
+    let mut mark = |value: ValueId, worklist: &mut Vec<ValueId>| {
+        if !live[value] {
+            live[value] = true;
+            worklist.push(value);
+        }
+    };
+
+    // Everything read by code that has to run anyway is live.
+    for block in &function.blocks {
+        for statement in &block.statements {
+            if is_removable(statement) {
+                continue;
+            }
+            for expression in statement.expressions() {
+                for value in expression.values() {
+                    mark(value, &mut worklist);
+                }
+            }
+        }
+        for expression in block.terminator.expressions() {
+            for value in expression.values() {
+                mark(value, &mut worklist);
+            }
+        }
+    }
+
+    while let Some(value) = worklist.pop() {
+        match definitions[value] {
+            Some(Definition::Phi(block, position)) => {
+                for (_, argument) in &function.blocks[block].phis[position].arguments {
+                    mark(*argument, &mut worklist);
+                }
+            }
+            Some(Definition::Statement(block, position)) => {
+                let statement = &function.blocks[block].statements[position];
+                if !is_removable(statement) {
+                    continue;
+                }
+                for expression in statement.expressions() {
+                    for value in expression.values() {
+                        mark(value, &mut worklist);
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    for block in &mut function.blocks {
+        block.phis.retain(|phi| live[phi.value]);
+
+        let statements = std::mem::take(&mut block.statements);
+        for statement in statements {
+            match statement {
+                Statement::Assign { value, expression }
+                    if !live[value] && !is_declaration(&expression) =>
+                {
+                    if expression.has_side_effects() {
+                        block.statements.push(Statement::Expression(expression));
+                    }
+                }
+                Statement::Expression(expression) if !expression.has_side_effects() => {}
+                statement => block.statements.push(statement),
+            }
+        }
+    }
+}
+
+/// An unused closure or class still declares something, so it is kept.
+fn is_declaration(expression: &Expression) -> bool {
+    matches!(
+        expression,
+        Expression::Closure { .. } | Expression::Class { .. }
+    )
+}
+
+/// Whether the statement only matters through the value it defines.
+fn is_removable(statement: &Statement) -> bool {
+    match statement {
+        Statement::Assign { expression, .. } if is_declaration(expression) => false,
+        Statement::Assign { expression, .. } | Statement::Expression(expression) => {
+            !expression.has_side_effects()
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn unused_values_go_but_effects_stay() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            LoadConstUInt8 r0, 1
+            GetGlobalObject r1
+            GetById r2, r1, 1, "o"
+            GetById r3, r2, 2, "a"
+            GetById r4, r1, 3, "f"
+            LoadConstUndefined r5
+            Call1 r6, r4, r5
+            Add r7, r0, r6
+            LoadConstUndefined r8
+            Ret r8
+            "#,
+        )]);
+        assert_eq!(text, "f();\n");
+    }
+
+    #[test]
+    fn unused_closure_is_kept() {
+        let mut function = testing::lift(testing::function(
+            r#"
+            CreateEnvironment r0
+            CreateClosure r1, r0, 1
+            LoadConstUndefined r2
+            Ret r2
+            "#,
+        ));
+        eliminate_dead_code(&mut function);
+
+        let closures = function.blocks[0]
+            .statements
+            .iter()
+            .filter(|statement| {
+                matches!(
+                    statement,
+                    Statement::Assign {
+                        expression: Expression::Closure { function: 1, .. },
+                        ..
+                    }
+                )
+            })
+            .count();
+        assert_eq!(closures, 1);
+    }
+
+    #[test]
+    fn counter_nothing_reads_goes_with_its_loop_phi() {
+        let mut function = testing::lift(testing::function(
+            r#"
+            LoadConstZero r0
+            LoadConstZero r1
+            LoadConstUInt8 r2, 10
+            loop:
+            LoadConstUInt8 r3, 1
+            Add r0, r0, r3
+            Add r1, r1, r3
+            JLess @loop, r0, r2
+            Ret r2
+            "#,
+        ));
+        eliminate_dead_code(&mut function);
+
+        let registers: Vec<u32> = function
+            .blocks
+            .iter()
+            .flat_map(|block| block.phis.iter())
+            .map(|phi| function.registers[phi.value])
+            .collect();
+        assert_eq!(registers, [0]);
+        let defined: Vec<u32> = function
+            .blocks
+            .iter()
+            .flat_map(|block| block.statements.iter())
+            .flat_map(Statement::defs)
+            .map(|value| function.registers[value])
+            .collect();
+        assert!(!defined.contains(&1), "{:?}", defined);
+    }
+
+    #[test]
+    fn unreachable_blocks_are_dropped() {
+        let mut function = testing::lift(testing::function(
+            r#"
+            LoadConstUndefined r0
+            Ret r0
+            LoadConstUInt8 r1, 1
+            Ret r1
+            "#,
+        ));
+        assert_eq!(function.blocks.len(), 2);
+        remove_unreachable_blocks(&mut function);
+        assert_eq!(function.blocks.len(), 1);
+    }
+}
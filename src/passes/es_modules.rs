@@ -0,0 +1,595 @@
+use crate::{
+    analysis::ValueId,
+    ast::{
+        CapturedVariable, Expression, LiftedFunction, MemberProperty, ModuleGraph, Nesting,
+        Statement,
+    },
+    builtins::Builtins,
+    bytecode_file_format::BytecodeFile,
+    ir::{AssignmentOperator, Literal, StringIndex},
+};
+
+use super::{eliminate_dead_code, propagate};
+
+/// Turns the CommonJS Babel compiles an ES module to back into imports and
+/// exports, in the module run by `factory` and the closures nested in it.
+/// Only modules marked with `__esModule` are touched. A required module
+/// whose exports are only ever read by name becomes `import { a } from`,
+/// one passed through `_interopRequireDefault` and only read as `.default`
+/// becomes `import x from`, and one passed through `_interopRequireWildcard`
+/// becomes `import * as x from`. `exports.default = value` becomes
+/// `export default value` and `exportAll` becomes `export * from`.
+pub fn restore_es_modules(
+    functions: &mut [LiftedFunction],
+    nesting: &Nesting,
+    bytecode: &BytecodeFile,
+    modules: &ModuleGraph,
+    factory: u32,
+) {
+    let exports = match functions
+        .get_mut(factory as usize)
+        .and_then(|function| remove_es_module_marker(function, bytecode))
+    {
+        Some(exports) => exports,
+        None => return,
+    };
+
+    let mut scope = vec![factory];
+    let mut position = 0;
+    while position < scope.len() {
+        scope.extend_from_slice(nesting.children(scope[position]));
+        position += 1;
+    }
+
+    restore_exports(&mut functions[factory as usize], &exports, bytecode);
+    for import in find_imports(functions, &scope, bytecode, modules) {
+        apply_import(functions, &scope, &import, bytecode);
+    }
+    for index in scope {
+        let function = &mut functions[index as usize];
+        propagate(function);
+        eliminate_dead_code(function);
+    }
+}
+
+/// Removes `Object.defineProperty(exports, "__esModule", { value: true })`
+/// or `exports.__esModule = true`, returning the exports object it marks.
+fn remove_es_module_marker(
+    function: &mut LiftedFunction,
+    bytecode: &BytecodeFile,
+) -> Option<Expression> {
+    let is_named = |property: &MemberProperty, expected: &str| match property {
+        MemberProperty::Named(name) => bytecode.string(name.index()).as_deref() == Some(expected),
+        _ => false,
+    };
+    let is_string = |expression: &Expression, expected: &str| match expression {
+        Expression::Literal(Literal::String(name)) => {
+            bytecode.string(name.index()).as_deref() == Some(expected)
+        }
+        _ => false,
+    };
+
+    for block in &mut function.blocks {
+        let found = block
+            .statements
+            .iter()
+            .enumerate()
+            .find_map(|(index, statement)| {
+                let exports = match statement {
+                    Statement::Expression(Expression::Call {
+                        callee, arguments, ..
+                    }) => match (callee.as_ref(), arguments.as_slice()) {
+                        (Expression::Member { property, .. }, [exports, name, ..])
+                            if is_named(property, "defineProperty")
+                                && is_string(name, "__esModule") =>
+                        {
+                            exports
+                        }
+                        _ => return None,
+                    },
+                    Statement::Expression(Expression::Assignment {
+                        operator: AssignmentOperator::Assignment,
+                        target,
+                        ..
+                    }) => match target.as_ref() {
+                        Expression::Member { object, property }
+                            if is_named(property, "__esModule") =>
+                        {
+                            object
+                        }
+                        _ => return None,
+                    },
+                    _ => return None,
+                };
+                Some((index, exports.clone()))
+            });
+        if let Some((index, exports)) = found {
+            block.statements.remove(index);
+            return Some(exports);
+        }
+    }
+    None
+}
+
+/// Whether two expressions name the same object, as far as the function's
+/// `definitions` tell.
+fn is_same_object<'a>(
+    first: &'a Expression,
+    second: &'a Expression,
+    definitions: &[Option<&'a Expression>],
+) -> bool {
+    let resolve = |expression: &'a Expression| match expression {
+        Expression::Value(value) => definitions[*value].unwrap_or(expression),
+        expression => expression,
+    };
+    match (resolve(first), resolve(second)) {
+        (Expression::Parameter(first), Expression::Parameter(second)) => first == second,
+        (Expression::Value(first), Expression::Value(second)) => first == second,
+        (Expression::Captured(first), Expression::Captured(second)) => first == second,
+        _ => false,
+    }
+}
+
+fn restore_exports(function: &mut LiftedFunction, exports: &Expression, bytecode: &BytecodeFile) {
+    let definitions: Vec<Option<Expression>> = function
+        .definitions()
+        .into_iter()
+        .map(|definition| definition.cloned())
+        .collect();
+    let definitions: Vec<Option<&Expression>> = definitions.iter().map(Option::as_ref).collect();
+    let is_exports = |object: &Expression| is_same_object(object, exports, &definitions);
+    let module_of = |expression: &Expression| match expression {
+        Expression::Require(module) => Some(*module),
+        Expression::Value(value) => match definitions[*value] {
+            Some(Expression::Require(module)) => Some(*module),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    // Babel declares `exports.default = void 0` up front when the default
+    // export is assigned later on.
+    let default_export = |statement: &Statement| match statement {
+        Statement::Expression(Expression::Assignment {
+            operator: AssignmentOperator::Assignment,
+            target,
+            value,
+        }) => match target.as_ref() {
+            Expression::Member {
+                object,
+                property: MemberProperty::Named(name),
+            } if is_exports(object)
+                && bytecode.string(name.index()).as_deref() == Some("default") =>
+            {
+                let value = match value.as_ref() {
+                    Expression::Value(value) => definitions[*value],
+                    value => Some(value),
+                };
+                Some(matches!(
+                    value,
+                    Some(Expression::Literal(Literal::Undefined))
+                ))
+            }
+            _ => None,
+        },
+        _ => None,
+    };
+    let assignments: Vec<bool> = function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.statements)
+        .filter_map(default_export)
+        .collect();
+    let restore_default = assignments
+        .iter()
+        .filter(|declaration| !**declaration)
+        .count()
+        == 1;
+
+    let mut rewritten = Vec::new();
+    for (block, basic_block) in function.blocks.iter().enumerate() {
+        for (index, statement) in basic_block.statements.iter().enumerate() {
+            let replacement = match statement {
+                Statement::Expression(Expression::Call {
+                    callee, arguments, ..
+                }) if matches!(
+                    callee.as_ref(),
+                    Expression::Builtin(Builtins::HermesBuiltinExportAll)
+                ) =>
+                {
+                    match arguments.as_slice() {
+                        [object, source] if is_exports(object) => {
+                            module_of(source).map(|module| Some(Statement::ExportAll(module)))
+                        }
+                        _ => None,
+                    }
+                }
+                statement if restore_default => match default_export(statement) {
+                    Some(true) => Some(None),
+                    Some(false) => match statement {
+                        Statement::Expression(Expression::Assignment { value, .. }) => {
+                            Some(Some(Statement::ExportDefault(value.as_ref().clone())))
+                        }
+                        _ => None,
+                    },
+                    None => None,
+                },
+                _ => None,
+            };
+            if let Some(replacement) = replacement {
+                rewritten.push((block, index, replacement));
+            }
+        }
+    }
+
+    for (block, index, replacement) in rewritten.into_iter().rev() {
+        let statements = &mut function.blocks[block].statements;
+        match replacement {
+            Some(statement) => statements[index] = statement,
+            None => {
+                statements.remove(index);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ImportKind {
+    /// `require(module)` itself, read by name.
+    Named,
+    /// Through `_interopRequireDefault`, read as `.default`.
+    Default,
+    /// Through `_interopRequireWildcard`.
+    Namespace,
+    /// Through a helper required from a module with no known path, which is
+    /// taken as a default import when only `.default` is read.
+    Unknown,
+}
+
+struct Import {
+    value: Option<ValueId>,
+    module: u32,
+    kind: ImportKind,
+    /// The captured variable the factory stores the value in, when closures
+    /// use it.
+    slot: Option<CapturedVariable>,
+    names: Vec<StringIndex>,
+}
+
+/// The imports among the factory's top-level `require`s, and how each is
+/// read. A required module read in any other way stays a `require`.
+fn find_imports(
+    functions: &[LiftedFunction],
+    scope: &[u32],
+    bytecode: &BytecodeFile,
+    modules: &ModuleGraph,
+) -> Vec<Import> {
+    let factory = &functions[scope[0] as usize];
+    let definitions = factory.definitions();
+    let helper_kind = |callee: &Expression| {
+        let callee = match callee {
+            Expression::Value(value) => definitions[*value]?,
+            callee => callee,
+        };
+        let name = match callee {
+            Expression::Closure { function, .. } => functions
+                .get(*function as usize)
+                .and_then(|function| function.name(bytecode)),
+            Expression::Member {
+                property: MemberProperty::Named(name),
+                ..
+            } => bytecode.string(name.index()),
+            Expression::Require(module) => match modules.module(*module)?.path {
+                Some(ref path) => Some(path.clone()),
+                None => return Some(ImportKind::Unknown),
+            },
+            _ => None,
+        }?;
+        if name.contains("interopRequireDefault") {
+            Some(ImportKind::Default)
+        } else if name.contains("interopRequireWildcard") {
+            Some(ImportKind::Namespace)
+        } else {
+            None
+        }
+    };
+
+    let mut imports = Vec::new();
+    for statement in factory.blocks.iter().flat_map(|block| &block.statements) {
+        let (value, expression) = match statement {
+            Statement::Assign { value, expression } => (Some(*value), expression),
+            Statement::Expression(expression @ Expression::Require(_)) => (None, expression),
+            _ => continue,
+        };
+        let (module, kind) = match expression {
+            Expression::Require(module) => (*module, ImportKind::Named),
+            Expression::Call {
+                callee, arguments, ..
+            } => match (arguments.as_slice(), helper_kind(callee)) {
+                ([Expression::Require(module)], Some(kind)) => (*module, kind),
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let value = match value {
+            Some(value) => value,
+            None => {
+                imports.push(Import {
+                    value: None,
+                    module,
+                    kind,
+                    slot: None,
+                    names: Vec::new(),
+                });
+                continue;
+            }
+        };
+
+        let uses = match Uses::of(functions, scope, value) {
+            Some(uses) => uses,
+            None => continue,
+        };
+        let read_as_default = uses
+            .names
+            .iter()
+            .all(|name| bytecode.string(name.index()).as_deref() == Some("default"));
+        let kind = match kind {
+            ImportKind::Named | ImportKind::Namespace => kind,
+            ImportKind::Default | ImportKind::Unknown if read_as_default => ImportKind::Default,
+            _ => continue,
+        };
+        if kind != ImportKind::Namespace && uses.other {
+            continue;
+        }
+        let mut names = Vec::new();
+        if kind == ImportKind::Named {
+            for name in uses.names {
+                if !names
+                    .iter()
+                    .any(|known: &StringIndex| known.index() == name.index())
+                {
+                    names.push(name);
+                }
+            }
+        }
+        imports.push(Import {
+            value: Some(value),
+            module,
+            kind,
+            slot: uses.slot,
+            names,
+        });
+    }
+    imports
+}
+
+/// How a value of the factory is read across the module.
+struct Uses {
+    slot: Option<CapturedVariable>,
+    /// The properties read by name.
+    names: Vec<StringIndex>,
+    /// Whether it is read in any other way.
+    other: bool,
+}
+
+impl Uses {
+    /// `None` when the value flows into a phi, or into a captured variable
+    /// that is assigned more than once.
+    fn of(functions: &[LiftedFunction], scope: &[u32], value: ValueId) -> Option<Self> {
+        let factory = &functions[scope[0] as usize];
+        let flows_into_phi = factory.blocks.iter().any(|block| {
+            block
+                .phis
+                .iter()
+                .any(|phi| phi.arguments.iter().any(|(_, argument)| *argument == value))
+        });
+        if flows_into_phi {
+            return None;
+        }
+
+        let mut uses = Uses {
+            slot: factory
+                .blocks
+                .iter()
+                .flat_map(|block| &block.statements)
+                .find_map(|statement| store_of(statement, value)),
+            names: Vec::new(),
+            other: false,
+        };
+        let mut stores = 0;
+        for index in scope {
+            let function = &functions[*index as usize];
+            let aliases = Aliases {
+                value: (*index == scope[0]).then_some(value),
+                slot: uses.slot,
+            };
+            for block in &function.blocks {
+                for statement in &block.statements {
+                    if *index == scope[0] && store_of(statement, value).is_some() {
+                        stores += 1;
+                        continue;
+                    }
+                    for expression in statement.expressions() {
+                        uses.visit(expression, &aliases, &mut stores);
+                    }
+                }
+                for expression in block.terminator.expressions() {
+                    uses.visit(expression, &aliases, &mut stores);
+                }
+            }
+        }
+        match uses.slot.is_none() || stores == 1 {
+            true => Some(uses),
+            false => None,
+        }
+    }
+
+    fn visit(&mut self, expression: &Expression, aliases: &Aliases, stores: &mut usize) {
+        match expression {
+            Expression::Member {
+                object,
+                property: MemberProperty::Named(name),
+            } if aliases.contains(object) => self.names.push(*name),
+            expression if aliases.contains(expression) => self.other = true,
+            Expression::Assignment { target, value, .. } => {
+                match target.as_ref() {
+                    Expression::Member { object, .. } if aliases.contains(object) => {
+                        self.other = true
+                    }
+                    target if aliases.contains(target) => *stores += 1,
+                    target => {
+                        for child in target.children() {
+                            self.visit(child, aliases, stores);
+                        }
+                    }
+                }
+                self.visit(value, aliases, stores);
+            }
+            Expression::Update { argument, .. } => match argument.as_ref() {
+                Expression::Member { object, .. } if aliases.contains(object) => self.other = true,
+                argument if aliases.contains(argument) => *stores += 1,
+                argument => {
+                    for child in argument.children() {
+                        self.visit(child, aliases, stores);
+                    }
+                }
+            },
+            Expression::Delete { object, .. } if aliases.contains(object) => self.other = true,
+            expression => {
+                for child in expression.children() {
+                    self.visit(child, aliases, stores);
+                }
+            }
+        }
+    }
+}
+
+/// The ways a function refers to an imported value.
+struct Aliases {
+    value: Option<ValueId>,
+    slot: Option<CapturedVariable>,
+}
+
+impl Aliases {
+    fn contains(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::Value(value) => self.value == Some(*value),
+            Expression::Captured(variable) => self.slot == Some(*variable),
+            _ => false,
+        }
+    }
+}
+
+/// The captured variable `statement` stores `value` in.
+fn store_of(statement: &Statement, value: ValueId) -> Option<CapturedVariable> {
+    match statement {
+        Statement::Expression(Expression::Assignment {
+            operator: AssignmentOperator::Assignment,
+            target,
+            value: stored,
+        }) => match (target.as_ref(), stored.as_ref()) {
+            (Expression::Captured(variable), Expression::Value(stored)) if *stored == value => {
+                Some(*variable)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn apply_import(
+    functions: &mut [LiftedFunction],
+    scope: &[u32],
+    import: &Import,
+    bytecode: &BytecodeFile,
+) {
+    let is_default =
+        |name: &StringIndex| bytecode.string(name.index()).as_deref() == Some("default");
+
+    for index in scope {
+        let function = &mut functions[*index as usize];
+        let aliases = Aliases {
+            value: match *index == scope[0] {
+                true => import.value,
+                false => None,
+            },
+            slot: import.slot,
+        };
+        for block in &mut function.blocks {
+            if import.kind == ImportKind::Named {
+                if let Some(value) = import.value {
+                    block
+                        .statements
+                        .retain(|statement| store_of(statement, value).is_none());
+                }
+            }
+
+            for statement in &mut block.statements {
+                let replacement = match statement {
+                    Statement::Assign { value, .. } if Some(*value) == import.value => {
+                        Some(import.statement())
+                    }
+                    Statement::Expression(Expression::Require(module))
+                        if import.value.is_none() && *module == import.module =>
+                    {
+                        Some(import.statement())
+                    }
+                    _ => None,
+                };
+                if let Some(replacement) = replacement {
+                    *statement = replacement;
+                    continue;
+                }
+                for expression in statement.expressions_mut() {
+                    rewrite_reads(expression, &aliases, import, &is_default);
+                }
+            }
+            for expression in block.terminator.expressions_mut() {
+                rewrite_reads(expression, &aliases, import, &is_default);
+            }
+        }
+    }
+}
+
+impl Import {
+    fn statement(&self) -> Statement {
+        Statement::Import {
+            module: self.module,
+            default: self.value.filter(|_| self.kind == ImportKind::Default),
+            namespace: self.value.filter(|_| self.kind == ImportKind::Namespace),
+            names: self.names.clone(),
+        }
+    }
+}
+
+/// Reads `x.a` of a named import as the binding `a`, and `x.default` of a
+/// default import as `x` itself.
+fn rewrite_reads(
+    expression: &mut Expression,
+    aliases: &Aliases,
+    import: &Import,
+    is_default: &dyn Fn(&StringIndex) -> bool,
+) {
+    let replacement = match expression {
+        Expression::Member {
+            object,
+            property: MemberProperty::Named(name),
+        } if aliases.contains(object) => match import.kind {
+            ImportKind::Named => Some(Expression::Imported {
+                module: import.module,
+                name: *name,
+            }),
+            ImportKind::Default if is_default(name) => Some(object.as_ref().clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+    match replacement {
+        Some(replacement) => *expression = replacement,
+        None => {
+            for child in expression.children_mut() {
+                rewrite_reads(child, aliases, import, is_default);
+            }
+        }
+    }
+}
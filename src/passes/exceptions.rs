@@ -0,0 +1,515 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    analysis::{BlockId, ValueId},
+    ast::{
+        Block, CatchClause, Expression, FinallyClause, LiftedFunction, Statement, Terminator,
+        TryStatement,
+    },
+};
+
+use super::remove_unreachable_blocks;
+
+/// Rebuilds `try` statements from the handlers protecting each block. A
+/// handler that catches and rethrows the same exception runs a `finally`
+/// when the copies of its body the compiler put on every other exit of the
+/// protected blocks can all be found, and those copies are removed.
+pub fn restore_try_statements(function: &mut LiftedFunction) {
+    function.recompute_predecessors();
+
+    let mut handlers: Vec<BlockId> = Vec::new();
+    for block in &function.blocks {
+        if let Some(handler) = block.handler {
+            if !handlers.contains(&handler) {
+                handlers.push(handler);
+            }
+        }
+    }
+    // Inner handlers first, so a `finally` sees the `catch` it wraps. A try
+    // nested in another protects a strict subset of its blocks, so it has
+    // fewer of them; the order of siblings does not matter.
+    handlers.sort_by_cached_key(|handler| protected_blocks(function, *handler).len());
+
+    let mut statements: Vec<TryStatement> = Vec::new();
+    let mut merged = HashSet::new();
+    for handler in handlers {
+        let exception = match function.blocks[handler].statements.first() {
+            Some(Statement::Catch(exception)) => *exception,
+            _ => continue,
+        };
+
+        let body = protected_blocks(function, handler);
+        let exits: Vec<(BlockId, BlockId)> = body
+            .iter()
+            .flat_map(|block| {
+                function.blocks[*block]
+                    .terminator
+                    .successors()
+                    .into_iter()
+                    .map(move |successor| (*block, successor))
+            })
+            .filter(|(_, successor)| *successor != handler && !body.contains(successor))
+            .collect();
+        let clause = handler_blocks(function, handler, &body, &exits);
+
+        if !rethrows(function, &clause, exception) {
+            let parameter =
+                Some(exception).filter(|exception| function.use_counts()[*exception] > 0);
+            statements.push(TryStatement {
+                body,
+                catch: Some(CatchClause {
+                    parameter,
+                    blocks: clause,
+                }),
+                finally: None,
+            });
+            continue;
+        }
+
+        // Every exit must run a copy that can go, or the code would run
+        // twice; otherwise the handler stays a `catch` that rethrows.
+        let mut copies: Vec<(BlockId, BlockId, usize)> = Vec::new();
+        let mut complete = true;
+        for (_, to) in exits {
+            if merged.contains(&to) || copies.iter().any(|(other, _, _)| *other == to) {
+                continue;
+            }
+            match find_copy(function, handler, exception, &body, to) {
+                Some((end, length)) if copies.iter().all(|(_, other, _)| *other != end) => {
+                    copies.push((to, end, length))
+                }
+                _ => complete = false,
+            }
+        }
+        if !complete {
+            statements.push(TryStatement {
+                body,
+                catch: Some(CatchClause {
+                    parameter: Some(exception),
+                    blocks: clause,
+                }),
+                finally: None,
+            });
+            continue;
+        }
+        for (to, end, length) in copies {
+            remove_copy(function, to, end, length);
+            merged.insert(to);
+        }
+
+        let finally = FinallyClause {
+            exception,
+            blocks: clause,
+        };
+
+        // `try {} catch {} finally {}` protects the try and its catch with a
+        // second handler, which becomes the same statement.
+        let wrapped = statements
+            .iter_mut()
+            .find(|statement| match &statement.catch {
+                Some(catch) if statement.finally.is_none() => {
+                    statement.body.iter().all(|block| body.contains(block))
+                        && catch
+                            .blocks
+                            .first()
+                            .is_some_and(|block| body.contains(block))
+                        && body.iter().all(|block| {
+                            statement.body.contains(block)
+                                || catch.blocks.contains(block)
+                                || is_jump(&function.blocks[*block])
+                        })
+                }
+                _ => false,
+            });
+        match wrapped {
+            Some(statement) => statement.finally = Some(finally),
+            None => statements.push(TryStatement {
+                body,
+                catch: None,
+                finally: Some(finally),
+            }),
+        }
+    }
+
+    function.try_statements = statements;
+    function.recompute_predecessors();
+    remove_unreachable_blocks(function);
+}
+
+/// A block that only passes control on, like the jump over a catch clause.
+fn is_jump(block: &Block) -> bool {
+    block.statements.is_empty() && matches!(block.terminator, Terminator::Goto(_))
+}
+
+/// The handlers an exception escaping `handler`'s own block passes through.
+fn handler_chain(function: &LiftedFunction, handler: BlockId) -> Vec<BlockId> {
+    let mut chain = Vec::new();
+    let mut current = function.blocks[handler].handler;
+    while let Some(block) = current {
+        if chain.contains(&block) {
+            break;
+        }
+        chain.push(block);
+        current = function.blocks[block].handler;
+    }
+    chain
+}
+
+/// Blocks whose exceptions reach `handler`, directly or through the handlers
+/// of try statements nested inside. The entry comes first.
+fn protected_blocks(function: &LiftedFunction, handler: BlockId) -> Vec<BlockId> {
+    let mut body: Vec<BlockId> = (0..function.blocks.len())
+        .filter(|block| match function.blocks[*block].handler {
+            Some(inner) => inner == handler || handler_chain(function, inner).contains(&handler),
+            None => false,
+        })
+        .collect();
+
+    let entry = body.iter().position(|block| {
+        function.blocks[*block]
+            .predecessors
+            .iter()
+            .any(|predecessor| !body.contains(predecessor))
+    });
+    if let Some(entry) = entry {
+        let block = body.remove(entry);
+        body.insert(0, block);
+    }
+    body
+}
+
+/// Blocks reachable from `handler` that the protected blocks do not reach
+/// without it, the handler first.
+fn handler_blocks(
+    function: &LiftedFunction,
+    handler: BlockId,
+    body: &[BlockId],
+    exits: &[(BlockId, BlockId)],
+) -> Vec<BlockId> {
+    let reach = |starts: Vec<BlockId>, avoid: Option<BlockId>| {
+        let mut seen: HashSet<BlockId> = HashSet::new();
+        let mut order = Vec::new();
+        let mut worklist = starts;
+        while let Some(block) = worklist.pop() {
+            if Some(block) == avoid || body.contains(&block) {
+                continue;
+            }
+            if seen.insert(block) {
+                order.push(block);
+                worklist.extend(function.blocks[block].terminator.successors());
+            }
+        }
+        order
+    };
+
+    let continuation: HashSet<BlockId> =
+        reach(exits.iter().map(|(_, to)| *to).collect(), Some(handler))
+            .into_iter()
+            .collect();
+    let mut blocks: Vec<BlockId> = reach(vec![handler], None)
+        .into_iter()
+        .filter(|block| *block == handler || !continuation.contains(block))
+        .collect();
+    blocks[1..].sort_unstable();
+    blocks
+}
+
+/// Whether every path out of the handler ends rethrowing `exception`, and
+/// nothing else looks at it.
+fn rethrows(function: &LiftedFunction, clause: &[BlockId], exception: ValueId) -> bool {
+    let mut rethrows = 0;
+    for block in clause {
+        let block = &function.blocks[*block];
+        if block
+            .terminator
+            .successors()
+            .iter()
+            .any(|successor| !clause.contains(successor))
+        {
+            return false;
+        }
+        if matches!(block.terminator, Terminator::Throw(Expression::Value(value)) if value == exception)
+        {
+            rethrows += 1;
+        }
+    }
+    rethrows > 0 && function.use_counts()[exception] == rethrows
+}
+
+/// Matches the code run on leaving the protected blocks through `to` against
+/// the finally handler. A copy ends in the middle of the block where the
+/// handler rethrows, which is returned with the length of the copy in it.
+fn find_copy(
+    function: &LiftedFunction,
+    handler: BlockId,
+    exception: ValueId,
+    body: &[BlockId],
+    to: BlockId,
+) -> Option<(BlockId, usize)> {
+    let mut finally_values = Canonical::default();
+    let mut copy_values = Canonical::default();
+
+    let mut pairs: HashMap<BlockId, BlockId> = HashMap::new();
+    let mut end: Option<(BlockId, usize)> = None;
+    let mut worklist = vec![(handler, to)];
+
+    while let Some((original, copy)) = worklist.pop() {
+        match pairs.get(&original) {
+            Some(paired) if *paired == copy => continue,
+            Some(_) => return None,
+            None => pairs.insert(original, copy),
+        };
+
+        let original_block = &function.blocks[original];
+        let copy_block = &function.blocks[copy];
+        let skip = usize::from(original == handler);
+        let statements = &original_block.statements[skip..];
+
+        let is_end = matches!(
+            original_block.terminator,
+            Terminator::Throw(Expression::Value(value)) if value == exception
+        );
+        let length_matches = match is_end {
+            true => copy_block.statements.len() >= statements.len(),
+            false => copy_block.statements.len() == statements.len(),
+        };
+        if !length_matches
+            || !statements
+                .iter()
+                .zip(&copy_block.statements)
+                .all(|(original, copy)| {
+                    finally_values.statement(original) == copy_values.statement(copy)
+                })
+        {
+            return None;
+        }
+
+        if is_end {
+            if end.is_some_and(|(block, _)| block != copy) {
+                return None;
+            }
+            end = Some((copy, statements.len()));
+            continue;
+        }
+
+        match (&original_block.terminator, &copy_block.terminator) {
+            (Terminator::Goto(original), Terminator::Goto(copy)) => {
+                worklist.push((*original, *copy))
+            }
+            (
+                Terminator::Branch {
+                    condition: original_condition,
+                    target: original_target,
+                    fallthrough: original_fallthrough,
+                },
+                Terminator::Branch {
+                    condition: copy_condition,
+                    target: copy_target,
+                    fallthrough: copy_fallthrough,
+                },
+            ) if finally_values.expression(original_condition)
+                == copy_values.expression(copy_condition) =>
+            {
+                worklist.push((*original_target, *copy_target));
+                worklist.push((*original_fallthrough, *copy_fallthrough));
+            }
+            _ => return None,
+        }
+    }
+
+    let (end, length) = end?;
+
+    // The copy can only go if nothing but the protected blocks runs it.
+    let copies: HashSet<BlockId> = pairs.values().copied().collect();
+    let is_private = copies.iter().all(|copy| {
+        function.blocks[*copy]
+            .predecessors
+            .iter()
+            .all(|predecessor| copies.contains(predecessor) || body.contains(predecessor))
+    });
+    match is_private {
+        true => Some((end, length)),
+        false => None,
+    }
+}
+
+/// Removes the copy `find_copy` found, sending the exit to where it ends.
+fn remove_copy(function: &mut LiftedFunction, to: BlockId, end: BlockId, length: usize) {
+    function.blocks[end].statements.drain(..length);
+    if to != end {
+        for from in function.blocks[to].predecessors.clone() {
+            for target in function.blocks[from].terminator.successors_mut() {
+                if *target == to {
+                    *target = end;
+                }
+            }
+        }
+        function.recompute_predecessors();
+    }
+}
+
+/// Rewrites statements with their values numbered in order of appearance, so
+/// two copies of the same code compare equal.
+#[derive(Default)]
+struct Canonical {
+    numbers: HashMap<ValueId, usize>,
+}
+
+impl Canonical {
+    /// Far above any real value, so renaming one never clobbers another.
+    const BASE: ValueId = usize::MAX / 2;
+
+    fn number(&mut self, value: ValueId) -> ValueId {
+        let next = self.numbers.len();
+        Self::BASE + *self.numbers.entry(value).or_insert(next)
+    }
+
+    fn expression(&mut self, expression: &Expression) -> Expression {
+        let mut expression = expression.clone();
+        for value in expression.values() {
+            let number = self.number(value);
+            expression.rename_value(value, number);
+        }
+        expression
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Statement {
+        let mut statement = statement.clone();
+        for expression in statement.expressions_mut() {
+            for value in expression.values() {
+                let number = self.number(value);
+                expression.rename_value(value, number);
+            }
+        }
+        for value in statement.defs_mut() {
+            *value = self.number(*value);
+        }
+        statement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    /// Assembly for `name()`, through r0 to r2.
+    fn call(name: &str) -> String {
+        format!(
+            r#"
+            GetGlobalObject r0
+            GetById r1, r0, 1, "{}"
+            LoadConstUndefined r2
+            Call1 r1, r1, r2
+            "#,
+            name
+        )
+    }
+
+    #[test]
+    fn finally_copy_is_removed() {
+        let source = format!(
+            r#"
+            start:
+            {}
+            end:
+            {}
+            Jmp @done
+            handler:
+            Catch r3
+            {}
+            Throw r3
+            done:
+            LoadConstUndefined r4
+            Ret r4
+            "#,
+            call("a"),
+            call("f"),
+            call("f")
+        );
+        let text =
+            testing::decompile(&[testing::function(source).handler("start", "end", "handler")]);
+        assert_eq!(text, "try {\n  a();\n} finally {\n  f();\n}\n");
+    }
+
+    #[test]
+    fn mismatched_finally_copy_stays_a_catch() {
+        let source = format!(
+            r#"
+            start:
+            {}
+            end:
+            {}
+            Jmp @done
+            handler:
+            Catch r3
+            {}
+            Throw r3
+            done:
+            LoadConstUndefined r4
+            Ret r4
+            "#,
+            call("a"),
+            call("g"),
+            call("f")
+        );
+        let text =
+            testing::decompile(&[testing::function(source).handler("start", "end", "handler")]);
+        assert_eq!(
+            text,
+            "try {\n  a();\n  g();\n} catch (e) {\n  f();\n  throw e;\n}\n"
+        );
+    }
+
+    #[test]
+    fn sibling_catches_inside_a_finally() {
+        let source = format!(
+            r#"
+            first:
+            {}
+            first_end:
+            Jmp @second
+            first_handler:
+            Catch r3
+            {}
+            second:
+            {}
+            second_end:
+            Jmp @after
+            second_handler:
+            Catch r3
+            {}
+            after:
+            outer_end:
+            {}
+            Jmp @done
+            outer_handler:
+            Catch r4
+            {}
+            Throw r4
+            done:
+            LoadConstUndefined r5
+            Ret r5
+            "#,
+            call("a"),
+            call("b"),
+            call("c"),
+            call("d"),
+            call("f"),
+            call("f")
+        );
+        let function = testing::function(source)
+            .handler("first", "first_end", "first_handler")
+            .handler("second", "second_end", "second_handler")
+            .handler("first", "outer_end", "outer_handler");
+        let text = testing::decompile(&[function]);
+        assert_eq!(
+            text,
+            concat!(
+                "try {\n",
+                "  try {\n    a();\n  } catch {\n    b();\n  }\n",
+                "  try {\n    c();\n  } catch {\n    d();\n  }\n",
+                "} finally {\n  f();\n}\n"
+            )
+        );
+    }
+}
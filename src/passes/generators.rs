@@ -0,0 +1,985 @@
+use std::collections::HashMap;
+
+use crate::{
+    analysis::{BlockId, ValueId},
+    ast::{Expression, FunctionKind, LiftedFunction, MemberProperty, Statement, Terminator},
+    builtins::Builtins,
+    bytecode_file_format::BytecodeFile,
+    ir::{ClosureKind, Literal},
+};
+
+use super::{eliminate_dead_code, helpers::Helper, remove_unreachable_blocks};
+
+/// Finds `function*` wrappers, the functions made by CreateGeneratorClosure
+/// whose only job is to CreateGenerator their real body, and turns the state
+/// machine of that body back into `yield`. `functions` is indexed by
+/// function index.
+pub fn restore_generators(functions: &mut [LiftedFunction], bytecode: &BytecodeFile) {
+    for wrapper in 0..functions.len() {
+        let body = functions[wrapper]
+            .blocks
+            .iter()
+            .flat_map(|block| {
+                block
+                    .statements
+                    .iter()
+                    .flat_map(|statement| statement.expressions())
+                    .chain(block.terminator.expressions())
+            })
+            .find_map(created_generator)
+            .map(|function| function as usize);
+
+        if let Some(body) = body.filter(|body| *body < functions.len() && *body != wrapper) {
+            functions[wrapper].kind = FunctionKind::Generator;
+            functions[wrapper].generator = Some(body as u32);
+            restore_yields(&mut functions[body], bytecode);
+        }
+    }
+}
+
+fn created_generator(expression: &Expression) -> Option<u32> {
+    match expression {
+        Expression::Closure {
+            kind: ClosureKind::Generator,
+            function,
+            ..
+        } => Some(*function),
+        expression => expression
+            .children()
+            .into_iter()
+            .find_map(created_generator),
+    }
+}
+
+/// A yield saves the generator, returns the yielded value and is resumed in
+/// the block the save names. Resuming with `.return()` sets `is_return`,
+/// and where the branch it takes only returns the value passed in, which
+/// `yield` does on its own, every resume becomes a plain `yield`. A branch
+/// doing more, like a `finally` the try statements had to keep, cannot be
+/// written that way, and the body is left as it is. `yield*` loops
+/// collapse first.
+fn restore_yields(function: &mut LiftedFunction, bytecode: &BytecodeFile) {
+    let delegated: Vec<BlockId> = (0..function.blocks.len())
+        .filter_map(|save| Delegation::find(function, save, bytecode))
+        .flat_map(|delegation| delegation.region)
+        .collect();
+    let restorable = (0..function.blocks.len())
+        .filter(|block| !delegated.contains(block))
+        .all(|block| return_branch(function, block).is_some());
+    if !restorable {
+        return;
+    }
+
+    while let Some(delegation) =
+        (0..function.blocks.len()).find_map(|save| Delegation::find(function, save, bytecode))
+    {
+        delegation.collapse(function, bytecode);
+        function.remove_trivial_phis();
+        function.recompute_predecessors();
+        remove_unreachable_blocks(function);
+    }
+
+    let mut saves: HashMap<BlockId, BlockId> = HashMap::new();
+    for (block, basic_block) in function.blocks.iter().enumerate() {
+        for statement in &basic_block.statements {
+            if let Statement::SaveGenerator { resume } = statement {
+                saves.insert(*resume, block);
+            }
+        }
+    }
+
+    for block in 0..function.blocks.len() {
+        let branch = match return_branch(function, block) {
+            Some(branch) => branch,
+            None => continue,
+        };
+        let resume = function.blocks[block]
+            .statements
+            .iter()
+            .position(|statement| matches!(statement, Statement::ResumeGenerator { .. }));
+        let result = match resume.map(|position| function.blocks[block].statements.remove(position))
+        {
+            Some(Statement::ResumeGenerator { result, .. }) => result,
+            _ => continue,
+        };
+        if let Some(branch) = branch {
+            if let Terminator::Branch { fallthrough, .. } = function.blocks[branch].terminator {
+                function.blocks[branch].terminator = Terminator::Goto(fallthrough);
+            }
+        }
+
+        match saves.get(&block) {
+            Some(&save) => {
+                let saving = &mut function.blocks[save];
+                saving
+                    .statements
+                    .retain(|statement| !matches!(statement, Statement::SaveGenerator { .. }));
+
+                let argument =
+                    match std::mem::replace(&mut saving.terminator, Terminator::Goto(block)) {
+                        Terminator::Return(argument) => argument,
+                        _ => Expression::Literal(Literal::Undefined),
+                    };
+
+                // A delegated yield outside a loop `Delegation` recognises
+                // passes on one inner result, which is all a `yield` does.
+                if let Some(Statement::Expression(Expression::Call { callee, .. })) =
+                    saving.statements.last()
+                {
+                    if matches!(
+                        callee.as_ref(),
+                        Expression::Builtin(Builtins::HermesBuiltinGeneratorSetDelegated)
+                    ) {
+                        saving.statements.pop();
+                    }
+                }
+
+                saving.statements.push(Statement::Assign {
+                    value: result,
+                    expression: Expression::Yield {
+                        argument: Box::new(argument),
+                        delegate: false,
+                    },
+                });
+            }
+            // The first resume only starts the body, what it receives is dropped.
+            None => function.replace_uses(result, &Expression::Literal(Literal::Undefined)),
+        }
+    }
+
+    for basic_block in &mut function.blocks {
+        basic_block.statements.retain(|statement| {
+            !matches!(
+                statement,
+                Statement::StartGenerator | Statement::CompleteGenerator
+            )
+        });
+    }
+
+    function.recompute_predecessors();
+    remove_unreachable_blocks(function);
+    eliminate_dead_code(function);
+}
+
+/// For a block resuming the generator, the block branching on `is_return`,
+/// or `Some(None)` when nothing does, as for blocks that do not resume.
+/// `None` when that branch does more than return the value the generator
+/// was resumed with.
+fn return_branch(function: &LiftedFunction, block: BlockId) -> Option<Option<BlockId>> {
+    let resume = function.blocks[block]
+        .statements
+        .iter()
+        .find_map(|statement| match statement {
+            Statement::ResumeGenerator { result, is_return } => Some((*result, *is_return)),
+            _ => None,
+        });
+    let (result, is_return) = match resume {
+        Some(resume) => resume,
+        None => return Some(None),
+    };
+
+    let use_counts = function.use_counts();
+    let branch = match use_counts[is_return] {
+        0 => return Some(None),
+        1 => function.blocks.iter().position(|basic_block| {
+            matches!(
+                basic_block.terminator,
+                Terminator::Branch {
+                    condition: Expression::Value(condition),
+                    ..
+                } if condition == is_return
+            )
+        })?,
+        _ => return None,
+    };
+    let returning = match function.blocks[branch].terminator {
+        Terminator::Branch { target, .. } => &function.blocks[target],
+        _ => return None,
+    };
+
+    let returned = match returning.terminator {
+        Terminator::Return(Expression::Value(returned)) => returned,
+        _ => return None,
+    };
+    let returns_result = returned == result
+        || returning
+            .phis
+            .iter()
+            .any(|phi| phi.value == returned && phi.arguments.contains(&(branch, result)));
+    let only_completes = returning
+        .statements
+        .iter()
+        .all(|statement| matches!(statement, Statement::CompleteGenerator));
+    (returns_result && only_completes).then_some(Some(branch))
+}
+
+/// The loop `yield* iterable` compiles to around a delegated yield:
+/// `preheader` gets the iterator and enters the loop, which passes what
+/// the generator is resumed with on to `next`, `return` or `throw` and
+/// yields their results until one is done. It then leaves for `exit`,
+/// which reads the final result's `value` through `results`.
+struct Delegation {
+    preheader: BlockId,
+    exit: BlockId,
+    iterable: Expression,
+    /// Every block of the loop.
+    region: Vec<BlockId>,
+    /// The statements of `preheader` getting the iterator and its `next`.
+    acquisition: Vec<usize>,
+    results: Vec<ValueId>,
+}
+
+impl Delegation {
+    fn find(function: &LiftedFunction, save: BlockId, bytecode: &BytecodeFile) -> Option<Self> {
+        let named = |property: &MemberProperty, name: &str| {
+            matches!(property, MemberProperty::Named(index)
+                if bytecode.string(index.index()).as_deref() == Some(name))
+        };
+        let definitions = function.definitions();
+        let member = |expression: &Expression, name: &str| match resolve(&definitions, expression) {
+            Expression::Member { object, property } if named(property, name) => {
+                Some(object.as_ref().clone())
+            }
+            _ => None,
+        };
+
+        let saving = &function.blocks[save];
+        match saving.statements.as_slice() {
+            [.., Statement::Expression(Expression::Call { callee, .. }), Statement::SaveGenerator { .. }]
+                if matches!(
+                    callee.as_ref(),
+                    Expression::Builtin(Builtins::HermesBuiltinGeneratorSetDelegated)
+                ) => {}
+            _ => return None,
+        }
+
+        // The head calls `next` and leaves once its result is done.
+        let (head, exit, next_result) = saving.predecessors.iter().find_map(|predecessor| {
+            match &function.blocks[*predecessor].terminator {
+                Terminator::Branch {
+                    condition,
+                    target,
+                    fallthrough,
+                } if *fallthrough == save => match member(condition, "done")? {
+                    Expression::Value(next_result) => Some((*predecessor, *target, next_result)),
+                    _ => None,
+                },
+                _ => None,
+            }
+        })?;
+        let defined_in_head = function.blocks[head].statements.iter().any(|statement| {
+            matches!(statement, Statement::Assign { value, .. } if *value == next_result)
+        });
+        let (iterator, received) = match definitions[next_result]? {
+            Expression::Call {
+                callee,
+                this,
+                arguments,
+            } if defined_in_head => match (this.as_ref(), arguments.as_slice()) {
+                (Expression::Value(iterator), [Expression::Value(received)])
+                    if member(callee, "next") == Some(Expression::Value(*iterator)) =>
+                {
+                    (*iterator, *received)
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        // `iterable[Symbol.iterator]()`
+        let iterable = match definitions[iterator]? {
+            Expression::Call {
+                callee,
+                this,
+                arguments,
+            } if arguments.is_empty() => match resolve(&definitions, callee) {
+                Expression::Member {
+                    object,
+                    property: MemberProperty::Computed(key),
+                } if object == this
+                    && member(key, "iterator").is_some_and(|symbol| {
+                        member(&symbol, "Symbol") == Some(Expression::Global)
+                    }) =>
+                {
+                    this.as_ref().clone()
+                }
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        let mut region = vec![head];
+        let mut position = 0;
+        while position < region.len() {
+            for successor in function.blocks[region[position]].successors() {
+                if successor != exit && !region.contains(&successor) {
+                    region.push(successor);
+                }
+            }
+            position += 1;
+        }
+        let preheader = match function.blocks[head]
+            .predecessors
+            .iter()
+            .filter(|predecessor| !region.contains(predecessor))
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [preheader] => **preheader,
+            _ => return None,
+        };
+        let closed = region.iter().all(|block| {
+            let basic_block = &function.blocks[*block];
+            basic_block
+                .successors()
+                .iter()
+                .all(|successor| *successor == exit || region.contains(successor))
+                && basic_block.predecessors.iter().all(|predecessor| {
+                    region.contains(predecessor) || (*block == head && *predecessor == preheader)
+                })
+        }) && function.blocks[exit]
+            .predecessors
+            .iter()
+            .all(|predecessor| region.contains(predecessor))
+            && matches!(function.blocks[preheader].terminator, Terminator::Goto(next) if next == head);
+        if !closed {
+            return None;
+        }
+
+        // The first `next` is passed undefined.
+        let starts_undefined = function.blocks[head].phis.iter().any(|phi| {
+            phi.value == received
+                && phi.arguments.iter().any(|(predecessor, argument)| {
+                    *predecessor == preheader
+                        && matches!(
+                            definitions[*argument],
+                            Some(Expression::Literal(Literal::Undefined))
+                        )
+                })
+        });
+        if !starts_undefined {
+            return None;
+        }
+
+        let mut loop_values: Vec<ValueId> = vec![iterator];
+        let acquisition: Vec<usize> = function.blocks[preheader]
+            .statements
+            .iter()
+            .enumerate()
+            .filter(|(_, statement)| match statement {
+                Statement::Assign { value, expression } => {
+                    let acquires = *value == iterator
+                        || expression == &Expression::Literal(Literal::Undefined)
+                            && function.blocks[head]
+                                .phis
+                                .iter()
+                                .any(|phi| phi.arguments.contains(&(preheader, *value)))
+                        || member(expression, "next") == Some(Expression::Value(iterator))
+                        || Some(*value)
+                            == match definitions[iterator] {
+                                Some(Expression::Call { callee, .. }) => match callee.as_ref() {
+                                    Expression::Value(method) => Some(*method),
+                                    _ => None,
+                                },
+                                _ => None,
+                            };
+                    if acquires {
+                        loop_values.push(*value);
+                    }
+                    acquires
+                }
+                Statement::Expression(Expression::Call {
+                    callee, arguments, ..
+                }) => {
+                    matches!(
+                        callee.as_ref(),
+                        Expression::Builtin(Builtins::HermesBuiltinEnsureObject)
+                    ) && arguments.first() == Some(&Expression::Value(iterator))
+                }
+                _ => false,
+            })
+            .map(|(index, _)| index)
+            .collect();
+        let defines_iterator = acquisition.iter().any(|index| {
+            matches!(&function.blocks[preheader].statements[*index],
+                Statement::Assign { value, .. } if *value == iterator)
+        });
+        if !defines_iterator {
+            return None;
+        }
+
+        for block in &region {
+            let basic_block = &function.blocks[*block];
+            loop_values.extend(basic_block.phis.iter().map(|phi| phi.value));
+            loop_values.extend(basic_block.statements.iter().flat_map(Statement::defs));
+            loop_values.extend(basic_block.terminator.defs());
+        }
+
+        // The exit's phis merge results, or pass on a value from before.
+        let mut results = Vec::new();
+        for phi in &function.blocks[exit].phis {
+            let arguments: Vec<ValueId> = phi
+                .arguments
+                .iter()
+                .map(|(_, argument)| *argument)
+                .collect();
+            if arguments
+                .iter()
+                .all(|argument| loop_values.contains(argument))
+            {
+                results.push(phi.value);
+            } else if arguments
+                .iter()
+                .any(|argument| *argument != arguments[0] || loop_values.contains(argument))
+            {
+                return None;
+            }
+        }
+
+        // Nothing else may see the loop's values, only the final `value`.
+        let escapes = |expression: &Expression| {
+            fn escapes(
+                expression: &Expression,
+                loop_values: &[ValueId],
+                results: &[ValueId],
+                is_value: &dyn Fn(&MemberProperty) -> bool,
+            ) -> bool {
+                match expression {
+                    Expression::Member { object, property } if is_value(property) => {
+                        match object.as_ref() {
+                            Expression::Value(value)
+                                if results.contains(value) || loop_values.contains(value) =>
+                            {
+                                false
+                            }
+                            object => escapes(object, loop_values, results, is_value),
+                        }
+                    }
+                    Expression::Value(value) => {
+                        results.contains(value) || loop_values.contains(value)
+                    }
+                    expression => expression
+                        .children()
+                        .into_iter()
+                        .any(|child| escapes(child, loop_values, results, is_value)),
+                }
+            }
+            escapes(expression, &loop_values, &results, &|property| {
+                named(property, "value")
+            })
+        };
+        for (block, basic_block) in function.blocks.iter().enumerate() {
+            if region.contains(&block) {
+                continue;
+            }
+            let phi_escapes = block != exit
+                && basic_block.phis.iter().any(|phi| {
+                    phi.arguments.iter().any(|(_, argument)| {
+                        loop_values.contains(argument) || results.contains(argument)
+                    })
+                });
+            let statement_escapes = basic_block
+                .statements
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| block != preheader || !acquisition.contains(index))
+                .flat_map(|(_, statement)| statement.expressions())
+                .chain(basic_block.terminator.expressions())
+                .any(escapes);
+            if phi_escapes || statement_escapes {
+                return None;
+            }
+        }
+
+        Some(Self {
+            preheader,
+            exit,
+            iterable,
+            region,
+            acquisition,
+            results,
+        })
+    }
+
+    /// Replaces the loop with `yield* iterable` at the end of the preheader.
+    fn collapse(self, function: &mut LiftedFunction, bytecode: &BytecodeFile) {
+        let Self {
+            preheader,
+            exit,
+            iterable,
+            region,
+            acquisition,
+            results,
+        } = self;
+        let register = function.registers[results.first().copied().unwrap_or(0)];
+        let result = function.new_value(register);
+
+        let entering = &mut function.blocks[preheader];
+        for index in acquisition.iter().rev() {
+            entering.statements.remove(*index);
+        }
+        entering.statements.push(Statement::Assign {
+            value: result,
+            expression: Expression::Yield {
+                argument: Box::new(iterable),
+                delegate: true,
+            },
+        });
+        entering.terminator = Terminator::Goto(exit);
+
+        let leaving = &mut function.blocks[exit];
+        leaving.phis.retain(|phi| !results.contains(&phi.value));
+        for phi in &mut leaving.phis {
+            let passed = phi.arguments[0].1;
+            phi.arguments = vec![(preheader, passed)];
+        }
+
+        let mut loop_values: Vec<ValueId> = results.clone();
+        for block in &region {
+            let basic_block = &function.blocks[*block];
+            loop_values.extend(basic_block.phis.iter().map(|phi| phi.value));
+            loop_values.extend(basic_block.statements.iter().flat_map(Statement::defs));
+        }
+        let read_value = |expression: &mut Expression| {
+            fn read_value(
+                expression: &mut Expression,
+                loop_values: &[ValueId],
+                result: ValueId,
+                bytecode: &BytecodeFile,
+            ) {
+                if let Expression::Member {
+                    object,
+                    property: MemberProperty::Named(name),
+                } = expression
+                {
+                    if matches!(object.as_ref(), Expression::Value(value) if loop_values.contains(value))
+                        && bytecode.string(name.index()).as_deref() == Some("value")
+                    {
+                        *expression = Expression::Value(result);
+                        return;
+                    }
+                }
+                for child in expression.children_mut() {
+                    read_value(child, loop_values, result, bytecode);
+                }
+            }
+            read_value(expression, &loop_values, result, bytecode)
+        };
+        for (block, basic_block) in function.blocks.iter_mut().enumerate() {
+            if region.contains(&block) {
+                continue;
+            }
+            basic_block
+                .statements
+                .iter_mut()
+                .flat_map(|statement| statement.expressions_mut())
+                .chain(basic_block.terminator.expressions_mut())
+                .for_each(read_value);
+        }
+    }
+}
+
+/// Recognises Babel's `asyncToGenerator(function* () {})` and turns it back
+/// into an `async function`, with its yields becoming awaits.
+pub fn restore_async_functions(functions: &mut [LiftedFunction], bytecode: &BytecodeFile) {
+    let helpers = Helper::with_shape(functions, bytecode, "asyncToGenerator", |function| {
+        is_async_to_generator(functions, function, bytecode)
+    });
+
+    let mut asynchronous = Vec::new();
+    for function in functions.iter_mut() {
+        let values = AsyncValues::new(function, &helpers);
+        for block in &mut function.blocks {
+            let expressions = block
+                .statements
+                .iter_mut()
+                .flat_map(|statement| statement.expressions_mut())
+                .chain(block.terminator.expressions_mut());
+            for expression in expressions {
+                unwrap_async_calls(expression, &helpers, &values, &mut asynchronous);
+            }
+        }
+    }
+
+    for wrapper in asynchronous {
+        let body = match functions.get_mut(wrapper as usize) {
+            Some(function) => {
+                function.kind = FunctionKind::Async;
+                function.generator
+            }
+            None => continue,
+        };
+        if let Some(body) = body.and_then(|body| functions.get_mut(body as usize)) {
+            for block in &mut body.blocks {
+                let expressions = block
+                    .statements
+                    .iter_mut()
+                    .flat_map(|statement| statement.expressions_mut())
+                    .chain(block.terminator.expressions_mut());
+                for expression in expressions {
+                    yields_to_awaits(expression);
+                }
+            }
+        }
+    }
+}
+
+/// Whether `function` has the shape of Babel's helper: it returns a function
+/// that returns `new Promise(executor)`.
+fn is_async_to_generator(
+    functions: &[LiftedFunction],
+    function: &LiftedFunction,
+    bytecode: &BytecodeFile,
+) -> bool {
+    let wrapper = match returned(function) {
+        Some(Expression::Closure { function, .. }) => functions.get(*function as usize),
+        _ => None,
+    };
+    let promise = match wrapper.and_then(returned) {
+        Some(Expression::Construct {
+            callee, arguments, ..
+        }) => Some((wrapper, callee, arguments)),
+        _ => None,
+    };
+    match promise {
+        Some((Some(wrapper), callee, arguments)) => {
+            let definitions = wrapper.definitions();
+            let is_promise = matches!(
+                resolve(&definitions, callee),
+                Expression::Member {
+                    object,
+                    property: MemberProperty::Named(name),
+                } if matches!(resolve(&definitions, object), Expression::Global)
+                    && bytecode.string(name.index()).as_deref() == Some("Promise")
+            );
+            is_promise
+                && matches!(
+                    arguments
+                        .first()
+                        .map(|executor| resolve(&definitions, executor)),
+                    Some(Expression::Closure { .. })
+                )
+        }
+        _ => false,
+    }
+}
+
+/// What every `return` of `function` with a value returns, when that is
+/// the same expression.
+fn returned(function: &LiftedFunction) -> Option<&Expression> {
+    let definitions = function.definitions();
+    let mut returned: Option<&Expression> = None;
+    for block in &function.blocks {
+        if let Terminator::Return(expression) = &block.terminator {
+            let expression = resolve(&definitions, expression);
+            if matches!(expression, Expression::Literal(Literal::Undefined)) {
+                continue;
+            }
+            match returned {
+                Some(other) if other != expression => return None,
+                _ => returned = Some(expression),
+            }
+        }
+    }
+    returned
+}
+
+/// `expression`, looking through the values `definitions` assigns.
+fn resolve<'a>(
+    definitions: &[Option<&'a Expression>],
+    expression: &'a Expression,
+) -> &'a Expression {
+    match expression {
+        Expression::Value(value) => match definitions.get(*value) {
+            Some(Some(definition)) => resolve(definitions, definition),
+            _ => expression,
+        },
+        expression => expression,
+    }
+}
+
+fn unwrap_async_calls(
+    expression: &mut Expression,
+    helpers: &Helper,
+    values: &AsyncValues,
+    asynchronous: &mut Vec<u32>,
+) {
+    for child in expression.children_mut() {
+        unwrap_async_calls(child, helpers, values, asynchronous);
+    }
+
+    let wrapped = match expression {
+        Expression::Call {
+            callee, arguments, ..
+        } if arguments.len() == 1 && values.is_helper(helpers, callee) => {
+            match values.generator_function(&arguments[0]) {
+                Some(function) => {
+                    asynchronous.push(function);
+                    arguments.remove(0)
+                }
+                None => return,
+            }
+        }
+        _ => return,
+    };
+    *expression = wrapped;
+}
+
+fn yields_to_awaits(expression: &mut Expression) {
+    for child in expression.children_mut() {
+        yields_to_awaits(child);
+    }
+    if let Expression::Yield {
+        argument,
+        delegate: false,
+    } = expression
+    {
+        let argument =
+            std::mem::replace(argument.as_mut(), Expression::Literal(Literal::Undefined));
+        *expression = Expression::Await(Box::new(argument));
+    }
+}
+
+/// The values of one function that hold an `asyncToGenerator` helper or a
+/// `function*` closure, worked out before its expressions are rewritten.
+struct AsyncValues {
+    helpers: Vec<bool>,
+    generator_functions: Vec<Option<u32>>,
+}
+
+impl AsyncValues {
+    fn new(function: &LiftedFunction, helpers: &Helper) -> Self {
+        let definitions = function.definitions();
+        Self {
+            helpers: definitions
+                .iter()
+                .map(|definition| {
+                    definition.is_some_and(|definition| helpers.resolves(&definitions, definition))
+                })
+                .collect(),
+            generator_functions: definitions
+                .iter()
+                .map(|definition| match definition {
+                    Some(Expression::Closure {
+                        kind: ClosureKind::GeneratorFunction,
+                        function,
+                        ..
+                    }) => Some(*function),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    fn is_helper(&self, helpers: &Helper, callee: &Expression) -> bool {
+        match callee {
+            Expression::Value(value) => self.helpers.get(*value).copied().unwrap_or(false),
+            callee => helpers.resolves(&[], callee),
+        }
+    }
+
+    fn generator_function(&self, expression: &Expression) -> Option<u32> {
+        match expression {
+            Expression::Closure {
+                kind: ClosureKind::GeneratorFunction,
+                function,
+                ..
+            } => Some(*function),
+            Expression::Value(value) => *self.generator_functions.get(*value)?,
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    /// `g = function* () { <body> }`, the body running as function 2.
+    fn generator(body: testing::Function) -> String {
+        testing::decompile(&[
+            testing::function(
+                r#"
+                CreateEnvironment r0
+                CreateGeneratorClosure r1, r0, 1
+                GetGlobalObject r2
+                PutById r2, r1, 1, "g"
+                LoadConstUndefined r3
+                Ret r3
+                "#,
+            ),
+            testing::function(
+                r#"
+                CreateEnvironment r0
+                CreateGenerator r1, r0, 2
+                Ret r1
+                "#,
+            ),
+            body,
+        ])
+    }
+
+    /// `ensureObject(r<register>)` through the outgoing registers.
+    fn ensure_object(register: u8) -> String {
+        format!(
+            r#"
+            LoadConstUndefined r31
+            Mov r30, r{}
+            LoadConstString r29, "not an object"
+            CallBuiltin r8, 43, 3
+            "#,
+            register
+        )
+    }
+
+    /// `yield* h()` the way Hermes compiles it, followed by `exit`, which
+    /// can read the final result from r11.
+    fn delegation(exit: &str) -> testing::Function {
+        let source = format!(
+            r#"
+            StartGenerator
+            ResumeGenerator r0, r1
+            JmpTrue @done, r1
+            GetGlobalObject r2
+            GetById r3, r2, 1, "h"
+            LoadConstUndefined r4
+            Call1 r5, r3, r4
+            GetGlobalObject r2
+            GetById r6, r2, 2, "Symbol"
+            GetById r6, r6, 3, "iterator"
+            GetByVal r6, r5, r6
+            Call1 r7, r6, r5
+            {iterator}
+            GetById r9, r7, 4, "next"
+            LoadConstUndefined r10
+            loop:
+            Call2 r11, r9, r7, r10
+            {result}
+            GetById r12, r11, 5, "done"
+            JmpTrue @exit, r12
+            yield:
+            LoadConstUndefined r31
+            CallBuiltin r8, 45, 1
+            SaveGenerator @resume
+            Ret r11
+            resume:
+            ResumeGenerator r10, r1
+            resumed:
+            JmpTrue @return, r1
+            Jmp @loop
+            return:
+            GetById r13, r7, 6, "return"
+            JmpUndefined @plain, r13
+            Call2 r11, r13, r7, r10
+            {result}
+            GetById r12, r11, 5, "done"
+            JmpFalse @yield, r12
+            GetById r14, r11, 7, "value"
+            CompleteGenerator
+            Ret r14
+            plain:
+            CompleteGenerator
+            Ret r10
+            throw:
+            Catch r16
+            GetById r17, r7, 8, "throw"
+            JmpUndefined @missing, r17
+            Call2 r11, r17, r7, r16
+            {result}
+            GetById r12, r11, 5, "done"
+            JmpFalse @yield, r12
+            Jmp @exit
+            missing:
+            Throw r16
+            exit:
+            {exit}
+            done:
+            CompleteGenerator
+            Ret r0
+            "#,
+            iterator = ensure_object(7),
+            result = ensure_object(11),
+            exit = exit,
+        );
+        testing::function(source).handler("resume", "resumed", "throw")
+    }
+
+    #[test]
+    fn delegation_loop_is_one_yield_star() {
+        let text = generator(delegation(
+            r#"
+            CompleteGenerator
+            LoadConstUndefined r15
+            Ret r15
+            "#,
+        ));
+        assert_eq!(text, "g = function* /* g */ () {\n  yield* h();\n};\n");
+    }
+
+    #[test]
+    fn delegation_result_is_the_yield_star() {
+        let text = generator(delegation(
+            r#"
+            GetById r14, r11, 7, "value"
+            CompleteGenerator
+            Ret r14
+            "#,
+        ));
+        assert_eq!(
+            text,
+            "g = function* /* g */ () {\n  let v;\n  v = yield* h();\n  return v;\n};\n"
+        );
+    }
+
+    /// Assembly for `f()`, through r5 to r7.
+    fn call_f() -> &'static str {
+        r#"
+        GetGlobalObject r5
+        GetById r6, r5, 1, "f"
+        LoadConstUndefined r7
+        Call1 r6, r6, r7
+        "#
+    }
+
+    #[test]
+    fn return_through_finally_is_a_plain_yield() {
+        let source = format!(
+            r#"
+            StartGenerator
+            ResumeGenerator r0, r1
+            JmpTrue @done, r1
+            start:
+            LoadConstUInt8 r2, 1
+            SaveGenerator @resume
+            Ret r2
+            resume:
+            ResumeGenerator r3, r1
+            JmpTrue @return, r1
+            end:
+            {call}
+            Jmp @after
+            return:
+            {call}
+            CompleteGenerator
+            Ret r3
+            handler:
+            Catch r4
+            {call}
+            Throw r4
+            after:
+            CompleteGenerator
+            LoadConstUndefined r8
+            Ret r8
+            done:
+            CompleteGenerator
+            Ret r0
+            "#,
+            call = call_f()
+        );
+        let text = generator(testing::function(source).handler("start", "end", "handler"));
+        assert_eq!(
+            text,
+            "g = function* /* g */ () {\n  try {\n    yield 1;\n  } finally {\n    f();\n  }\n};\n"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use crate::{
+    ast::{CapturedVariable, Expression, LiftedFunction, MemberProperty, Statement},
+    bytecode_file_format::BytecodeFile,
+};
+
+/// What a Babel runtime helper can be called through: a property so named,
+/// as in `babelHelpers.asyncToGenerator`, or a closure of a function so
+/// named or shaped like the helper, directly or through the captured
+/// variable it was stored in.
+pub struct Helper<'a> {
+    name: &'static str,
+    bytecode: &'a BytecodeFile<'a>,
+    functions: Vec<bool>,
+    variables: HashMap<CapturedVariable, bool>,
+}
+
+impl<'a> Helper<'a> {
+    pub fn new(
+        functions: &[LiftedFunction],
+        bytecode: &'a BytecodeFile<'a>,
+        name: &'static str,
+    ) -> Self {
+        Self::with_shape(functions, bytecode, name, |function| {
+            function
+                .name(bytecode)
+                .is_some_and(|function_name| function_name.contains(name))
+        })
+    }
+
+    /// A helper whose functions are told by `is_helper` rather than by
+    /// their name, for names user code could well have too.
+    pub fn with_shape(
+        functions: &[LiftedFunction],
+        bytecode: &'a BytecodeFile<'a>,
+        name: &'static str,
+        is_helper: impl Fn(&LiftedFunction) -> bool,
+    ) -> Self {
+        let mut helper = Self {
+            name,
+            bytecode,
+            functions: functions.iter().map(is_helper).collect(),
+            variables: HashMap::new(),
+        };
+
+        for function in functions {
+            let definitions = function.definitions();
+            for statement in function
+                .blocks
+                .iter()
+                .flat_map(|block| block.statements.iter())
+            {
+                if let Statement::Expression(Expression::Assignment { target, value, .. }) =
+                    statement
+                {
+                    if let Expression::Captured(variable) = target.as_ref() {
+                        let assigns_helper = helper.resolves(&definitions, value);
+                        *helper.variables.entry(*variable).or_insert(assigns_helper) &=
+                            assigns_helper;
+                    }
+                }
+            }
+        }
+
+        helper
+    }
+
+    /// Whether `expression` evaluates to the helper, looking through the
+    /// values `definitions` assigns.
+    pub fn resolves(&self, definitions: &[Option<&Expression>], expression: &Expression) -> bool {
+        match expression {
+            Expression::Value(value) => match definitions.get(*value) {
+                Some(Some(definition)) => self.resolves(definitions, definition),
+                _ => false,
+            },
+            Expression::Member {
+                object,
+                property: MemberProperty::Named(name),
+            } => {
+                let name = self.bytecode.string(name.index()).unwrap_or_default();
+                name.trim_start_matches('_') == self.name
+                    || (name == "default" && self.resolves(definitions, object))
+            }
+            Expression::Closure { function, .. } => self
+                .functions
+                .get(*function as usize)
+                .copied()
+                .unwrap_or(false),
+            Expression::Captured(variable) => {
+                self.variables.get(variable).copied().unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+}
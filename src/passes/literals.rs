@@ -0,0 +1,518 @@
+use std::collections::HashMap;
+
+use crate::{
+    analysis::ValueId,
+    ast::{Expression, LiftedFunction, MemberProperty, ObjectProperty, Statement},
+    builtins::Builtins,
+    bytecode_file_format::BytecodeFile,
+    ir::{AssignmentOperator, BinaryOperator, Literal, Number},
+};
+
+/// Rebuilds object and array literals. The buffer of a `NewObject` or
+/// `NewArray` only holds the constant part, the stores, accessor definitions
+/// and spreads right after it add the rest. `NewObjectWithParent` sets
+/// `__proto__`.
+pub fn restore_literals(function: &mut LiftedFunction, bytecode: &BytecodeFile) {
+    for block in 0..function.blocks.len() {
+        let mut position = 0;
+        while position < function.blocks[block].statements.len() {
+            fold_literal(function, block, position, bytecode);
+            position += 1;
+        }
+    }
+
+    // Literals nothing is added to after all.
+    for block in &mut function.blocks {
+        let expressions = block
+            .statements
+            .iter_mut()
+            .flat_map(|statement| statement.expressions_mut())
+            .chain(block.terminator.expressions_mut());
+        for expression in expressions {
+            restore_constant_literals(expression, bytecode);
+        }
+    }
+}
+
+fn restore_constant_literals(expression: &mut Expression, bytecode: &BytecodeFile) {
+    for child in expression.children_mut() {
+        restore_constant_literals(child, bytecode);
+    }
+    if let Some(literal) = constant_literal(expression, bytecode) {
+        *expression = literal;
+    }
+}
+
+/// The literal a `NewObject` or `NewArray` makes from its buffer.
+fn constant_literal(expression: &Expression, bytecode: &BytecodeFile) -> Option<Expression> {
+    match expression {
+        Expression::NewArray { buffer, .. } => {
+            let elements = match buffer {
+                Some((length, offset)) => bytecode
+                    .array_literals(*offset, *length)?
+                    .into_iter()
+                    .map(|literal| Some(Expression::Literal(literal)))
+                    .collect(),
+                None => Vec::new(),
+            };
+            Some(Expression::ArrayLiteral(elements))
+        }
+        Expression::NewObject { parent, buffer } => {
+            let mut properties = Vec::new();
+            if let Some(parent) = parent {
+                properties.push(ObjectProperty::Proto(parent.as_ref().clone()));
+            }
+            if let Some((length, keys, values)) = buffer {
+                for (key, value) in bytecode.object_literals(*keys, *values, *length)? {
+                    properties.push(ObjectProperty::Value {
+                        key: MemberProperty::from_key(&Expression::Literal(key)),
+                        value: Expression::Literal(value),
+                    });
+                }
+            }
+            Some(Expression::ObjectLiteral(properties))
+        }
+        _ => None,
+    }
+}
+
+/// Folds what is added to the literal defined at `position` into it. The
+/// definition moves down to the last statement folded, so the scan stops at
+/// anything that reads the literal, or whose order with the folded parts
+/// would change.
+fn fold_literal(
+    function: &mut LiftedFunction,
+    block: usize,
+    position: usize,
+    bytecode: &BytecodeFile,
+) {
+    let definitions = function.definitions();
+    let statements = &function.blocks[block].statements;
+    let (literal, mut scan) = match &statements[position] {
+        Statement::Assign { value, expression } => {
+            match Scan::new(*value, expression, &definitions, bytecode) {
+                Some(scan) => (*value, scan),
+                None => return,
+            }
+        }
+        _ => return,
+    };
+
+    let mut consumed = Vec::new();
+    let (mut reads_memory, mut writes_memory) = (false, false);
+    for (index, statement) in statements.iter().enumerate().skip(position + 1) {
+        if let Some((reads, writes)) = scan.consume(statement) {
+            consumed.push(index);
+            reads_memory |= reads;
+            writes_memory |= writes;
+            continue;
+        }
+        let (reads, writes) = statement.touches_memory();
+        if self::reads(statement, literal)
+            || (writes && (reads_memory || writes_memory))
+            || (reads && writes_memory)
+        {
+            break;
+        }
+    }
+
+    let is_private = {
+        let use_counts = function.use_counts();
+        scan.owned().iter().all(|value| {
+            let reads = consumed
+                .iter()
+                .flat_map(|index| statements[*index].expressions())
+                .flat_map(|expression| expression.values())
+                .filter(|read| read == value)
+                .count();
+            use_counts[*value] <= reads
+        })
+    };
+    if is_private {
+        let literal = scan.literal();
+        replace_with_literal(function, block, position, consumed, literal);
+    }
+}
+
+enum Scan<'a> {
+    Array(ArrayScan),
+    Object(ObjectScan<'a>),
+}
+
+impl<'a> Scan<'a> {
+    fn new(
+        value: ValueId,
+        expression: &Expression,
+        definitions: &'a [Option<&'a Expression>],
+        bytecode: &BytecodeFile,
+    ) -> Option<Self> {
+        match constant_literal(expression, bytecode)? {
+            Expression::ArrayLiteral(elements) => Some(Scan::Array(ArrayScan {
+                array: value,
+                elements,
+                is_constant: true,
+                indices: HashMap::new(),
+                stored: HashMap::new(),
+            })),
+            Expression::ObjectLiteral(properties) => Some(Scan::Object(ObjectScan {
+                object: value,
+                properties,
+                has_spread: false,
+                definitions,
+            })),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Folds `statement` into the literal, returning whether what it adds
+    /// reads and writes memory.
+    fn consume(&mut self, statement: &Statement) -> Option<(bool, bool)> {
+        match self {
+            Scan::Array(scan) => scan.consume(statement),
+            Scan::Object(scan) => scan.consume(statement),
+        }
+    }
+
+    /// Values the folded statements define for their own use.
+    fn owned(&self) -> Vec<ValueId> {
+        match self {
+            Scan::Array(scan) => scan.indices.keys().copied().collect(),
+            Scan::Object(_) => Vec::new(),
+        }
+    }
+
+    fn literal(self) -> Expression {
+        match self {
+            Scan::Array(scan) => Expression::ArrayLiteral(scan.elements),
+            Scan::Object(scan) => Expression::ObjectLiteral(scan.properties),
+        }
+    }
+}
+
+/// Elements up to the first spread are stored at constant indices, with
+/// holes where none is. `arraySpread(array, source, index)` returns the index
+/// after the copied elements, and later elements are stored at, or skip as
+/// holes, an index counted up from it.
+struct ArrayScan {
+    array: ValueId,
+    elements: Vec<Option<Expression>>,
+    /// Whether no spread came yet, so indices are still constants.
+    is_constant: bool,
+    /// The elements before each index value.
+    indices: HashMap<ValueId, Vec<Option<Expression>>>,
+    /// What was stored at each index value.
+    stored: HashMap<ValueId, Expression>,
+}
+
+impl ArrayScan {
+    fn consume(&mut self, statement: &Statement) -> Option<(bool, bool)> {
+        match statement {
+            Statement::Expression(Expression::Assignment {
+                operator: AssignmentOperator::Assignment,
+                target,
+                value,
+            }) => {
+                let property = match target.as_ref() {
+                    Expression::Member { object, property } if matches!(object.as_ref(), Expression::Value(array) if *array == self.array) => {
+                        property
+                    }
+                    _ => return None,
+                };
+                if value.values().contains(&self.array) {
+                    return None;
+                }
+                match property {
+                    MemberProperty::Index(index) if self.is_constant => {
+                        let index = *index as usize;
+                        if self.elements.len() <= index {
+                            self.elements.resize(index + 1, None);
+                        }
+                        self.elements[index] = Some(value.as_ref().clone());
+                    }
+                    MemberProperty::Computed(index) => {
+                        let mut elements = self.elements_before(index)?;
+                        if let Expression::Value(index) = index.as_ref() {
+                            self.stored.insert(*index, value.as_ref().clone());
+                        }
+                        elements.push(Some(value.as_ref().clone()));
+                        self.elements = elements;
+                        self.is_constant = false;
+                    }
+                    _ => return None,
+                }
+                Some((value.reads_memory(), value.has_side_effects()))
+            }
+            Statement::Assign { value, expression } if self.is_index(expression) => {
+                let elements = self.elements_before(expression)?;
+                self.indices.insert(*value, elements.clone());
+                self.elements = elements;
+                self.is_constant = false;
+                Some((true, true))
+            }
+            Statement::Expression(expression) if self.is_index(expression) => {
+                self.elements = self.elements_before(expression)?;
+                self.is_constant = false;
+                Some((true, true))
+            }
+            _ => None,
+        }
+    }
+
+    fn is_index(&self, expression: &Expression) -> bool {
+        matches!(
+            expression,
+            Expression::Call { callee, .. }
+                if matches!(callee.as_ref(), Expression::Builtin(Builtins::HermesBuiltinArraySpread))
+        ) || matches!(
+            expression,
+            Expression::Binary {
+                operator: BinaryOperator::Addition,
+                ..
+            }
+        )
+    }
+
+    /// The elements in front of the position `index` evaluates to.
+    fn elements_before(&self, index: &Expression) -> Option<Vec<Option<Expression>>> {
+        match index {
+            Expression::Literal(_) => {
+                let index = literal_index(index)?;
+                (self.is_constant && index == self.elements.len()).then(|| self.elements.clone())
+            }
+            Expression::Value(value) => self.indices.get(value).cloned(),
+            Expression::Call {
+                callee, arguments, ..
+            } if matches!(
+                callee.as_ref(),
+                Expression::Builtin(Builtins::HermesBuiltinArraySpread)
+            ) && arguments.len() == 3
+                && matches!(&arguments[0], Expression::Value(array) if *array == self.array)
+                && !arguments[1].values().contains(&self.array) =>
+            {
+                let mut elements = self.elements_before(&arguments[2])?;
+                elements.push(Some(Expression::Spread(Box::new(arguments[1].clone()))));
+                Some(elements)
+            }
+            Expression::Binary {
+                operator: BinaryOperator::Addition,
+                left,
+                right,
+            } if literal_index(right) == Some(1)
+                && !matches!(left.as_ref(), Expression::Literal(_)) =>
+            {
+                let mut elements = self.elements_before(left)?;
+                let element = match left.as_ref() {
+                    Expression::Value(index) => self.stored.get(index).cloned(),
+                    _ => None,
+                };
+                elements.push(element);
+                Some(elements)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Properties are stored one by one after the buffer, which holds
+/// placeholders for the ones coming later so the order is kept.
+/// `copyDataProperties(object, source)` spreads `source` in between.
+struct ObjectScan<'a> {
+    object: ValueId,
+    properties: Vec<ObjectProperty>,
+    has_spread: bool,
+    definitions: &'a [Option<&'a Expression>],
+}
+
+impl<'a> ObjectScan<'a> {
+    fn consume(&mut self, statement: &Statement) -> Option<(bool, bool)> {
+        let expression = match statement {
+            Statement::Expression(expression) => expression,
+            _ => return None,
+        };
+        match expression {
+            Expression::Assignment {
+                operator: AssignmentOperator::Assignment,
+                target,
+                value,
+            } => {
+                let key = match target.as_ref() {
+                    Expression::Member { object, property } if self.is_object(object) => property,
+                    _ => return None,
+                };
+                if self.reads_object(value) || self.reads_object_in_key(key) {
+                    return None;
+                }
+                let property = ObjectProperty::Value {
+                    key: key.clone(),
+                    value: value.as_ref().clone(),
+                };
+                self.add(property, None);
+                let key_effects = match key {
+                    MemberProperty::Computed(key) => (key.reads_memory(), key.has_side_effects()),
+                    _ => (false, false),
+                };
+                Some((
+                    value.reads_memory() || key_effects.0,
+                    value.has_side_effects() || key_effects.1,
+                ))
+            }
+            Expression::DefineAccessor {
+                object,
+                property,
+                getter,
+                setter,
+                enumerable: true,
+            } if self.is_object(object) => {
+                if [property, getter, setter]
+                    .iter()
+                    .any(|expression| self.reads_object(expression))
+                {
+                    return None;
+                }
+                let key = MemberProperty::from_key(property);
+                let getter = (!self.is_undefined(getter)).then(|| ObjectProperty::Getter {
+                    key: key.clone(),
+                    function: getter.as_ref().clone(),
+                });
+                let setter = (!self.is_undefined(setter)).then(|| ObjectProperty::Setter {
+                    key,
+                    function: setter.as_ref().clone(),
+                });
+                match (getter, setter) {
+                    (Some(getter), setter) => self.add(getter, setter),
+                    (None, Some(setter)) => self.add(setter, None),
+                    (None, None) => return None,
+                }
+                Some((property.reads_memory(), property.has_side_effects()))
+            }
+            Expression::Call {
+                callee, arguments, ..
+            } if matches!(
+                callee.as_ref(),
+                Expression::Builtin(Builtins::HermesBuiltinCopyDataProperties)
+            ) && arguments.len() == 2
+                && self.is_object(&arguments[0])
+                && !self.reads_object(&arguments[1]) =>
+            {
+                self.properties
+                    .push(ObjectProperty::Spread(arguments[1].clone()));
+                self.has_spread = true;
+                Some((true, true))
+            }
+            _ => None,
+        }
+    }
+
+    /// Adds `property`, and `next` right after it, in place of the
+    /// placeholder for its key, unless a spread came in between.
+    fn add(&mut self, property: ObjectProperty, next: Option<ObjectProperty>) {
+        let key = match &property {
+            ObjectProperty::Value { key, .. }
+            | ObjectProperty::Getter { key, .. }
+            | ObjectProperty::Setter { key, .. } => key,
+            _ => unreachable!(),
+        };
+        let placeholder = self.properties.iter().position(|existing| {
+            !self.has_spread
+                && matches!(existing, ObjectProperty::Value { key: existing, .. }
+                    if same_key(existing, key))
+        });
+        match placeholder {
+            Some(index) => {
+                self.properties[index] = property;
+                if let Some(next) = next {
+                    self.properties.insert(index + 1, next);
+                }
+            }
+            None => {
+                self.properties.push(property);
+                self.properties.extend(next);
+            }
+        }
+    }
+
+    fn is_object(&self, expression: &Expression) -> bool {
+        matches!(expression, Expression::Value(value) if *value == self.object)
+    }
+
+    fn reads_object(&self, expression: &Expression) -> bool {
+        expression.values().contains(&self.object)
+    }
+
+    fn reads_object_in_key(&self, key: &MemberProperty) -> bool {
+        matches!(key, MemberProperty::Computed(key) if self.reads_object(key))
+    }
+
+    fn is_undefined(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::Literal(Literal::Undefined) => true,
+            Expression::Value(value) => matches!(
+                self.definitions.get(*value),
+                Some(Some(definition)) if self.is_undefined(definition)
+            ),
+            _ => false,
+        }
+    }
+}
+
+fn same_key(first: &MemberProperty, second: &MemberProperty) -> bool {
+    match (first, second) {
+        (MemberProperty::Named(first), MemberProperty::Named(second)) => {
+            first.index() == second.index()
+        }
+        (MemberProperty::Index(first), MemberProperty::Index(second)) => first == second,
+        _ => false,
+    }
+}
+
+/// Defines `value` as `literal` where the last consumed statement was, and
+/// removes the statements building it.
+fn replace_with_literal(
+    function: &mut LiftedFunction,
+    block: usize,
+    position: usize,
+    consumed: Vec<usize>,
+    literal: Expression,
+) {
+    let statements = std::mem::take(&mut function.blocks[block].statements);
+    let value = match &statements[position] {
+        Statement::Assign { value, .. } => *value,
+        _ => unreachable!(),
+    };
+    let last = consumed.last().copied().unwrap_or(position);
+
+    let mut definition = Some(Statement::Assign {
+        value,
+        expression: literal,
+    });
+    for (index, statement) in statements.into_iter().enumerate() {
+        if index != position && !consumed.contains(&index) {
+            function.blocks[block].statements.push(statement);
+        }
+        if index == last {
+            function.blocks[block].statements.extend(definition.take());
+        }
+    }
+}
+
+fn reads(statement: &Statement, value: ValueId) -> bool {
+    statement
+        .expressions()
+        .iter()
+        .any(|expression| expression.values().contains(&value))
+}
+
+pub(super) fn literal_index(expression: &Expression) -> Option<usize> {
+    match expression {
+        Expression::Literal(Literal::Number(Number::UInt(number))) => Some(usize::from(*number)),
+        Expression::Literal(Literal::Number(Number::Int(number))) => {
+            (*number >= 0).then_some(*number as usize)
+        }
+        Expression::Literal(Literal::Number(Number::Double(number)))
+            if number.fract() == 0.0 && *number >= 0.0 =>
+        {
+            Some(*number as usize)
+        }
+        _ => None,
+    }
+}
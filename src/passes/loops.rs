@@ -0,0 +1,773 @@
+use std::collections::HashSet;
+
+use crate::{
+    analysis::{BlockId, ValueId},
+    ast::{Expression, LiftedFunction, MemberProperty, Statement, Terminator},
+    ir::{BinaryOperator, Boolean, Literal, UnaryOperator},
+};
+
+use super::{eliminate_dead_code, remove_unreachable_blocks};
+
+/// Turns the GetPNameList / GetNextPName idiom back into `for (k in obj)`.
+/// The name list, index and size it keeps in registers disappear with it.
+pub fn restore_for_in_loops(function: &mut LiftedFunction) {
+    for header in 0..function.blocks.len() {
+        let (property, names, object) = match function.blocks[header].statements.last() {
+            Some(Statement::NextPropertyName {
+                property,
+                names: Expression::Value(names),
+                object,
+                ..
+            }) => (*property, *names, object.clone()),
+            _ => continue,
+        };
+        let (body, exit) = match &function.blocks[header].terminator {
+            Terminator::Branch {
+                condition,
+                target,
+                fallthrough,
+            } if is_undefined_check(condition, property) => (*fallthrough, *target),
+            _ => continue,
+        };
+
+        // The names are fetched once before the loop, which is skipped
+        // outright when there are none.
+        let preheader = function.blocks[header]
+            .predecessors
+            .iter()
+            .copied()
+            .find(|predecessor| {
+                function.blocks[*predecessor]
+                    .statements
+                    .last()
+                    .is_some_and(|statement| {
+                        matches!(statement, Statement::PropertyNames { names: defined, .. } if *defined == names)
+                    })
+            });
+        let preheader = match preheader {
+            Some(preheader) => preheader,
+            None => continue,
+        };
+        match &function.blocks[preheader].terminator {
+            Terminator::Branch {
+                condition,
+                target,
+                fallthrough,
+            } if *target == exit
+                && *fallthrough == header
+                && is_undefined_check(condition, names) => {}
+            _ => continue,
+        }
+
+        let preheader = &mut function.blocks[preheader];
+        preheader.statements.pop();
+        preheader.terminator = Terminator::Goto(header);
+
+        let header = &mut function.blocks[header];
+        header.statements.pop();
+        header.terminator = Terminator::ForIn {
+            property,
+            object,
+            body,
+            exit,
+        };
+    }
+
+    function.recompute_predecessors();
+    eliminate_dead_code(function);
+}
+
+/// `value === undefined`, what JmpUndefined lifts to.
+fn is_undefined_check(condition: &Expression, value: ValueId) -> bool {
+    matches!(
+        condition,
+        Expression::Binary {
+            operator: BinaryOperator::Identity,
+            left,
+            right,
+        } if matches!(left.as_ref(), Expression::Value(left) if *left == value)
+            && matches!(right.as_ref(), Expression::Literal(Literal::Undefined))
+    )
+}
+
+/// Rebuilds `for (x of xs)` and array destructuring from the iteration
+/// protocol. The iterator, its next method and every check of whether it is
+/// done disappear, along with the IteratorClose on the way out and the
+/// handlers that only close it before rethrowing.
+pub fn restore_iteration(function: &mut LiftedFunction) {
+    let mut skipped = 0;
+    loop {
+        let begin = iterator_begins(function).nth(skipped);
+        let (block, position) = match begin {
+            Some(begin) => begin,
+            None => break,
+        };
+        let restored = match &function.blocks[block].statements[position] {
+            Statement::IteratorBegin { iterator, next, .. } => {
+                let chain = IteratorChain::new(function, *iterator, *next);
+                match chain.loop_step(function) {
+                    Some(step) if chain.rest_array(function, step).is_none() => {
+                        restore_for_of(function, &chain, (block, position), step)
+                    }
+                    _ => restore_destructuring(function, &chain, (block, position)),
+                }
+            }
+            _ => false,
+        };
+        if !restored {
+            skipped += 1;
+        }
+    }
+}
+
+fn iterator_begins(function: &LiftedFunction) -> impl Iterator<Item = (BlockId, usize)> + '_ {
+    function
+        .blocks
+        .iter()
+        .enumerate()
+        .flat_map(|(block, basic_block)| {
+            basic_block
+                .statements
+                .iter()
+                .enumerate()
+                .filter(|(_, statement)| matches!(statement, Statement::IteratorBegin { .. }))
+                .map(move |(position, _)| (block, position))
+        })
+}
+
+/// One IteratorNext of a chain.
+#[derive(Debug, Copy, Clone)]
+struct Step {
+    block: BlockId,
+    result: ValueId,
+    iterator: ValueId,
+    previous: ValueId,
+}
+
+/// Everything one IteratorBegin sets in motion.
+struct IteratorChain {
+    /// Each value the iterator register takes, phis included. Once done the
+    /// iterator becomes undefined, so until then they are all objects.
+    iterators: HashSet<ValueId>,
+    steps: Vec<Step>,
+}
+
+impl IteratorChain {
+    fn new(function: &LiftedFunction, iterator: ValueId, next: ValueId) -> Self {
+        let mut chain = Self {
+            iterators: HashSet::from([iterator]),
+            steps: Vec::new(),
+        };
+
+        let advanced: HashSet<ValueId> = function
+            .blocks
+            .iter()
+            .flat_map(|block| block.statements.iter())
+            .filter_map(|statement| match statement {
+                Statement::IteratorNext {
+                    iterator,
+                    next: Expression::Value(method),
+                    ..
+                } if *method == next => Some(*iterator),
+                _ => None,
+            })
+            .collect();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (block, basic_block) in function.blocks.iter().enumerate() {
+                // A loop's phi is fed back by a step that is only found
+                // through the phi, so steps of the same method count too.
+                for phi in &basic_block.phis {
+                    let mut arguments = phi.arguments.iter().map(|(_, argument)| argument);
+                    let is_iterator = arguments
+                        .clone()
+                        .any(|argument| chain.iterators.contains(argument))
+                        && arguments.all(|argument| {
+                            chain.iterators.contains(argument)
+                                || advanced.contains(argument)
+                                || *argument == phi.value
+                        });
+                    if is_iterator && chain.iterators.insert(phi.value) {
+                        changed = true;
+                    }
+                }
+
+                for statement in &basic_block.statements {
+                    if let Statement::IteratorNext {
+                        result,
+                        iterator,
+                        previous: Expression::Value(previous),
+                        next: Expression::Value(method),
+                    } = statement
+                    {
+                        if *method == next
+                            && chain.iterators.contains(previous)
+                            && chain.iterators.insert(*iterator)
+                        {
+                            chain.steps.push(Step {
+                                block,
+                                result: *result,
+                                iterator: *iterator,
+                                previous: *previous,
+                            });
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        chain.steps.sort_by_key(|step| step.block);
+        chain
+    }
+
+    /// The step that runs once per iteration of a loop: its iterator comes
+    /// from a phi of its own block fed back by the step itself.
+    fn loop_step(&self, function: &LiftedFunction) -> Option<Step> {
+        self.steps.iter().copied().find(|step| {
+            function.blocks[step.block].phis.iter().any(|phi| {
+                phi.value == step.previous
+                    && phi
+                        .arguments
+                        .iter()
+                        .any(|(_, argument)| *argument == step.iterator)
+            })
+        })
+    }
+
+    /// For a loop that only appends each value to a new array, that array:
+    /// the `...rest` of a destructuring.
+    fn rest_array(&self, function: &LiftedFunction, step: Step) -> Option<ValueId> {
+        let (body, _) = self.exits(function, step)?;
+        let block = &function.blocks[body];
+        if !matches!(block.terminator, Terminator::Goto(target) if target == step.block) {
+            return None;
+        }
+
+        let mut array = None;
+        for statement in &block.statements {
+            match statement {
+                Statement::Expression(Expression::Assignment { target, value, .. }) => {
+                    match (target.as_ref(), value.as_ref()) {
+                        (
+                            Expression::Member {
+                                object,
+                                property: MemberProperty::Computed(_),
+                            },
+                            Expression::Value(value),
+                        ) if *value == step.result && array.is_none() => match object.as_ref() {
+                            Expression::Value(object) => array = Some(*object),
+                            _ => return None,
+                        },
+                        _ => return None,
+                    }
+                }
+                Statement::Assign { expression, .. } if !expression.has_side_effects() => {}
+                _ => return None,
+            }
+        }
+
+        let definitions = function.definitions();
+        array.filter(|array| {
+            matches!(
+                definitions[*array],
+                Some(Expression::NewArray { buffer: None, .. })
+            )
+        })
+    }
+
+    /// Where the step's block goes while the iterator runs and once it is
+    /// done, when the step ends the block.
+    fn exits(&self, function: &LiftedFunction, step: Step) -> Option<(BlockId, BlockId)> {
+        let block = &function.blocks[step.block];
+        let is_last = matches!(
+            block.statements.last(),
+            Some(Statement::IteratorNext { iterator, .. }) if *iterator == step.iterator
+        );
+        if !is_last {
+            return None;
+        }
+        match &block.terminator {
+            Terminator::Branch {
+                condition,
+                target,
+                fallthrough,
+            } => match self.truthiness(function, condition, &mut Vec::new())? {
+                true => Some((*target, *fallthrough)),
+                false => Some((*fallthrough, *target)),
+            },
+            _ => None,
+        }
+    }
+
+    /// What `expression` evaluates to as a condition while the iterator is
+    /// not done, when that does not depend on anything else.
+    fn truthiness(
+        &self,
+        function: &LiftedFunction,
+        expression: &Expression,
+        visiting: &mut Vec<ValueId>,
+    ) -> Option<bool> {
+        match expression {
+            Expression::Literal(Literal::Undefined | Literal::Null) => Some(false),
+            Expression::Literal(Literal::Boolean(boolean)) => {
+                Some(matches!(boolean, Boolean::True))
+            }
+            Expression::Value(value) if self.iterators.contains(value) => Some(true),
+            Expression::Value(value) => {
+                if visiting.contains(value) {
+                    return None;
+                }
+                visiting.push(*value);
+                let truthiness = self.value_truthiness(function, *value, visiting);
+                visiting.pop();
+                truthiness
+            }
+            Expression::Unary {
+                operator: UnaryOperator::LogicalNot,
+                argument,
+            } => self
+                .truthiness(function, argument, visiting)
+                .map(|truthy| !truthy),
+            Expression::Binary {
+                operator,
+                left,
+                right,
+            } => {
+                let is_done_check = match (left.as_ref(), right.as_ref()) {
+                    (Expression::Value(value), Expression::Literal(Literal::Undefined))
+                    | (Expression::Literal(Literal::Undefined), Expression::Value(value)) => {
+                        self.iterators.contains(value)
+                    }
+                    _ => false,
+                };
+                match operator {
+                    BinaryOperator::Identity | BinaryOperator::Equality if is_done_check => {
+                        Some(false)
+                    }
+                    BinaryOperator::NonIdentity | BinaryOperator::InEquality if is_done_check => {
+                        Some(true)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn value_truthiness(
+        &self,
+        function: &LiftedFunction,
+        value: ValueId,
+        visiting: &mut Vec<ValueId>,
+    ) -> Option<bool> {
+        for block in &function.blocks {
+            if let Some(phi) = block.phis.iter().find(|phi| phi.value == value) {
+                let mut truthiness = None;
+                for (_, argument) in &phi.arguments {
+                    if visiting.contains(argument) {
+                        continue;
+                    }
+                    let argument =
+                        self.truthiness(function, &Expression::Value(*argument), visiting)?;
+                    if truthiness.is_some_and(|truthiness| truthiness != argument) {
+                        return None;
+                    }
+                    truthiness = Some(argument);
+                }
+                return truthiness;
+            }
+            for statement in &block.statements {
+                if let Statement::Assign {
+                    value: defined,
+                    expression,
+                } = statement
+                {
+                    if *defined == value {
+                        return self.truthiness(function, expression, visiting);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn closes(&self, statement: &Statement) -> bool {
+        matches!(
+            statement,
+            Statement::IteratorClose {
+                iterator: Expression::Value(iterator),
+                ..
+            } if self.iterators.contains(iterator)
+        )
+    }
+}
+
+fn restore_for_of(
+    function: &mut LiftedFunction,
+    chain: &IteratorChain,
+    (begin, position): (BlockId, usize),
+    step: Step,
+) -> bool {
+    let (body, exit) = match chain.exits(function, step) {
+        Some(exits) => exits,
+        None => return false,
+    };
+
+    let source = match function.blocks[begin].statements.remove(position) {
+        Statement::IteratorBegin {
+            iterator, source, ..
+        } => {
+            let enters_loop = position == function.blocks[begin].statements.len()
+                && matches!(function.blocks[begin].terminator, Terminator::Goto(target) if target == step.block);
+            match enters_loop {
+                true => source,
+                // Something runs in between, so the iterable is evaluated where
+                // the iterator was taken from it.
+                false => {
+                    let value = function.new_value(function.registers[iterator]);
+                    function.blocks[begin].statements.insert(
+                        position,
+                        Statement::Assign {
+                            value,
+                            expression: source,
+                        },
+                    );
+                    Expression::Value(value)
+                }
+            }
+        }
+        _ => return false,
+    };
+
+    let header = &mut function.blocks[step.block];
+    header.statements.pop();
+    header.terminator = Terminator::ForOf {
+        value: step.result,
+        iterable: source,
+        body,
+        exit,
+    };
+
+    forget_iterator(function, chain);
+    true
+}
+
+fn restore_destructuring(
+    function: &mut LiftedFunction,
+    chain: &IteratorChain,
+    (begin, position): (BlockId, usize),
+) -> bool {
+    let rest = match chain.loop_step(function) {
+        Some(step) => match (
+            chain.rest_array(function, step),
+            chain.exits(function, step),
+        ) {
+            (Some(array), Some((_, exit))) => Some((step, array, exit)),
+            _ => return false,
+        },
+        None => None,
+    };
+    let is_last = |step: &Step| rest.is_none_or(|(rest, ..)| step.block <= rest.block);
+    if !chain.steps.iter().all(is_last) {
+        return false;
+    }
+
+    // Taking every branch as if the iterator never ran out leaves one path
+    // through the elements; running out early only makes the rest undefined.
+    for block in 0..function.blocks.len() {
+        if rest.is_some_and(|(step, ..)| step.block == block) {
+            continue;
+        }
+        if let Terminator::Branch {
+            condition,
+            target,
+            fallthrough,
+        } = &function.blocks[block].terminator
+        {
+            if let Some(truthy) = chain.truthiness(function, condition, &mut Vec::new()) {
+                let taken = if truthy { *target } else { *fallthrough };
+                function.blocks[block].terminator = Terminator::Goto(taken);
+            }
+        }
+    }
+
+    let mut elements = Vec::new();
+    for step in &chain.steps {
+        if rest.is_some_and(|(rest, ..)| rest.block == step.block) {
+            continue;
+        }
+        elements.push(step.result);
+        function.blocks[step.block].statements.retain(|statement| {
+            !matches!(statement, Statement::IteratorNext { iterator, .. } if *iterator == step.iterator)
+        });
+    }
+
+    if let Some((step, array, exit)) = rest {
+        let header = &mut function.blocks[step.block];
+        header.statements.pop();
+        header.terminator = Terminator::Goto(exit);
+        for block in &mut function.blocks {
+            block.statements.retain(|statement| {
+                !matches!(statement, Statement::Assign { value, .. } if *value == array)
+            });
+        }
+    }
+
+    let position = function.blocks[begin]
+        .statements
+        .iter()
+        .position(|statement| matches!(statement, Statement::IteratorBegin { .. }))
+        .filter(|found| *found <= position);
+    let source = match position.map(|position| function.blocks[begin].statements.remove(position)) {
+        Some(Statement::IteratorBegin { source, .. }) => source,
+        _ => return false,
+    };
+    function.blocks[begin].statements.insert(
+        position.unwrap_or_default(),
+        Statement::Destructure {
+            elements,
+            rest: rest.map(|(_, array, _)| array),
+            source,
+        },
+    );
+
+    forget_iterator(function, chain);
+    true
+}
+
+/// Drops what only kept the iterator alive: closing it on the way out of the
+/// loop or pattern, and handlers that close it and rethrow.
+fn forget_iterator(function: &mut LiftedFunction, chain: &IteratorChain) {
+    let closing_handlers: Vec<(BlockId, Option<BlockId>)> = function
+        .blocks
+        .iter()
+        .enumerate()
+        .filter(
+            |(_, block)| match (block.statements.as_slice(), &block.terminator) {
+                (
+                    [Statement::Catch(exception), close],
+                    Terminator::Throw(Expression::Value(thrown)),
+                ) => exception == thrown && chain.closes(close),
+                _ => false,
+            },
+        )
+        .map(|(handler, block)| (handler, block.handler))
+        .collect();
+
+    for block in &mut function.blocks {
+        block
+            .statements
+            .retain(|statement| !chain.closes(statement));
+        for (handler, outer) in &closing_handlers {
+            if block.handler == Some(*handler) {
+                block.handler = *outer;
+            }
+        }
+    }
+
+    function.recompute_predecessors();
+    remove_unreachable_blocks(function);
+    function.remove_trivial_phis();
+    eliminate_dead_code(function);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    #[test]
+    fn for_in_over_a_property_reassigned_in_the_body() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            GetGlobalObject r0
+            GetById r1, r0, 1, "o"
+            GetPNameList r2, r1, r3, r4
+            JmpUndefined @exit, r2
+            header:
+            GetNextPName r5, r2, r1, r3, r4
+            JmpUndefined @exit, r5
+            LoadConstString r6, "!"
+            Add r5, r5, r6
+            GetGlobalObject r0
+            GetById r7, r0, 2, "g"
+            LoadConstUndefined r8
+            Call2 r10, r7, r8, r5
+            GetGlobalObject r0
+            GetById r7, r0, 3, "h"
+            LoadConstUndefined r8
+            Call2 r10, r7, r8, r5
+            Jmp @header
+            exit:
+            LoadConstUndefined r9
+            Ret r9
+            "#,
+        )]);
+        assert_eq!(
+            text,
+            concat!(
+                "let o2, key, str;\n",
+                "o2 = o;\n",
+                "for (key in o2) {\n",
+                "  str = key + \"!\";\n",
+                "  g(str);\n",
+                "  h(str);\n",
+                "}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn for_in_over_an_object_reassigned_in_the_body() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            GetGlobalObject r0
+            GetById r1, r0, 1, "o"
+            GetPNameList r2, r1, r3, r4
+            JmpUndefined @exit, r2
+            header:
+            GetNextPName r5, r2, r1, r3, r4
+            JmpUndefined @exit, r5
+            GetById r1, r1, 2, "next"
+            Jmp @header
+            exit:
+            GetGlobalObject r0
+            PutById r0, r1, 3, "last"
+            LoadConstUndefined r9
+            Ret r9
+            "#,
+        )]);
+        // The names are taken once, so the loop reads the object it started
+        // with even though the variable moves on.
+        assert_eq!(
+            text,
+            "let next, key;\nnext = o;\nfor (key in next) {\n  next = next.next;\n}\nlast = next;\n"
+        );
+    }
+
+    #[test]
+    fn for_of_with_break_drops_the_closing_handler() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            GetGlobalObject r0
+            GetById r1, r0, 1, "xs"
+            IteratorBegin r2, r1
+            loop:
+            IteratorNext r3, r2, r1
+            LoadConstUndefined r4
+            JStrictEqual @end, r2, r4
+            body:
+            GetById r5, r0, 2, "log"
+            Call2 r6, r5, r0, r3
+            JmpFalse @loop, r3
+            IteratorClose r2, 0
+            body_end:
+            Jmp @end
+            handler:
+            Catch r7
+            IteratorClose r2, 1
+            Throw r7
+            end:
+            LoadConstUndefined r4
+            Ret r4
+            "#,
+        )
+        .handler("body", "body_end", "handler")]);
+        assert_eq!(
+            text,
+            concat!(
+                "let globalObject, item;\n",
+                "globalObject = globalThis;\n",
+                "for (item of globalObject.xs) {\n",
+                "  globalObject.log(item);\n",
+                "  if (item) {\n",
+                "    break;\n",
+                "  }\n",
+                "}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn array_pattern_closes_an_unfinished_iterator() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            GetGlobalObject r0
+            GetById r1, r0, 1, "ys"
+            IteratorBegin r2, r1
+            LoadConstUndefined r3
+            LoadConstUndefined r4
+            JmpTrue @first, r3
+            IteratorNext r5, r2, r1
+            LoadConstUndefined r6
+            StrictEq r3, r2, r6
+            JmpTrue @first, r3
+            Mov r4, r5
+            first:
+            Mov r8, r4
+            LoadConstUndefined r4
+            JmpTrue @second, r3
+            IteratorNext r5, r2, r1
+            LoadConstUndefined r6
+            StrictEq r3, r2, r6
+            JmpTrue @second, r3
+            Mov r4, r5
+            second:
+            Mov r9, r4
+            JmpTrue @done, r3
+            IteratorClose r2, 0
+            done:
+            GetById r5, r0, 2, "log"
+            Call3 r6, r5, r0, r8, r9
+            LoadConstUndefined r4
+            Ret r4
+            "#,
+        )]);
+        assert_eq!(
+            text,
+            "let globalObject, v, v2;\nglobalObject = globalThis;\n[v, v2] = globalObject.ys;\nglobalObject.log(v, v2);\n"
+        );
+    }
+
+    #[test]
+    fn array_pattern_with_a_rest_element() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            GetGlobalObject r0
+            GetById r1, r0, 1, "ys"
+            IteratorBegin r2, r1
+            LoadConstUndefined r3
+            LoadConstUndefined r4
+            JmpTrue @first, r3
+            IteratorNext r5, r2, r1
+            LoadConstUndefined r6
+            StrictEq r3, r2, r6
+            JmpTrue @first, r3
+            Mov r4, r5
+            first:
+            Mov r8, r4
+            NewArray r9, 0
+            LoadConstZero r10
+            JmpTrue @done, r3
+            loop:
+            IteratorNext r5, r2, r1
+            JmpUndefined @done, r2
+            PutByVal r9, r10, r5
+            LoadConstUInt8 r6, 1
+            Add r10, r10, r6
+            Jmp @loop
+            done:
+            GetById r5, r0, 2, "log"
+            Call3 r6, r5, r0, r8, r9
+            LoadConstUndefined r4
+            Ret r4
+            "#,
+        )]);
+        assert_eq!(
+            text,
+            "let globalObject, v, object;\nglobalObject = globalThis;\n[v, ...object] = globalObject.ys;\nglobalObject.log(v, object);\n"
+        );
+    }
+}
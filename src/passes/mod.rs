@@ -0,0 +1,40 @@
+mod classes;
+mod closures;
+mod conditionals;
+mod constants;
+mod constructions;
+mod copies;
+mod dead_code;
+mod es_modules;
+mod exceptions;
+mod generators;
+mod helpers;
+mod literals;
+mod loops;
+mod modules;
+mod pipeline;
+mod propagation;
+mod spread;
+mod templates;
+mod types;
+mod updates;
+
+pub use classes::*;
+pub use closures::*;
+pub use conditionals::*;
+pub use constants::*;
+pub use constructions::*;
+pub use copies::*;
+pub use dead_code::*;
+pub use es_modules::*;
+pub use exceptions::*;
+pub use generators::*;
+pub use literals::*;
+pub use loops::*;
+pub use modules::*;
+pub use pipeline::*;
+pub use propagation::*;
+pub use spread::*;
+pub use templates::*;
+pub use types::*;
+pub use updates::*;
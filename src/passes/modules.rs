@@ -0,0 +1,200 @@
+use std::convert::TryFrom;
+
+use crate::{
+    ast::{
+        CapturedVariable, Expression, LiftedFunction, MemberProperty, MetroModule, Nesting,
+        Statement,
+    },
+    builtins::Builtins,
+    ir::AssignmentOperator,
+};
+
+use super::{eliminate_dead_code, literals::literal_index};
+
+/// Rewrites `HermesBuiltin.requireFast(id)`, which files whose CommonJS
+/// modules the compiler resolved use instead of `require`, to a `Require`.
+pub fn restore_cjs_requires(function: &mut LiftedFunction) {
+    for block in &mut function.blocks {
+        let expressions = block
+            .statements
+            .iter_mut()
+            .flat_map(|statement| statement.expressions_mut())
+            .chain(block.terminator.expressions_mut());
+        for expression in expressions {
+            restore_require_fast(expression);
+        }
+    }
+}
+
+fn restore_require_fast(expression: &mut Expression) {
+    for child in expression.children_mut() {
+        restore_require_fast(child);
+    }
+
+    let module = match expression {
+        Expression::Call {
+            callee, arguments, ..
+        } if matches!(
+            callee.as_ref(),
+            Expression::Builtin(Builtins::HermesBuiltinRequireFast)
+        ) =>
+        {
+            arguments.first().and_then(literal_index)
+        }
+        _ => None,
+    };
+    if let Some(module) = module.and_then(|module| u32::try_from(module).ok()) {
+        *expression = Expression::Require(module);
+    }
+}
+
+/// Rewrites `_$$_REQUIRE(_dependencyMap[n])` in a Metro module to a
+/// `Require` of its `n`th dependency, in the factory and in every closure
+/// nested in it, which reach both parameters through captured variables.
+pub fn restore_metro_requires(
+    functions: &mut [LiftedFunction],
+    nesting: &Nesting,
+    module: &MetroModule,
+) {
+    let factory = match functions.get(module.factory as usize) {
+        Some(factory) if factory.header.param_count >= 3 => factory,
+        _ => return,
+    };
+    let require = Binding::new(factory, 2);
+    let dependency_map = Binding::new(factory, factory.header.param_count - 1);
+
+    let mut pending = vec![module.factory];
+    while let Some(index) = pending.pop() {
+        pending.extend_from_slice(nesting.children(index));
+
+        let function = &mut functions[index as usize];
+        let definitions: Vec<Option<Expression>> = function
+            .definitions()
+            .into_iter()
+            .map(|definition| definition.cloned())
+            .collect();
+        let rewriter = Rewriter {
+            require: &require,
+            dependency_map: &dependency_map,
+            dependencies: &module.dependencies,
+            definitions: &definitions,
+            in_factory: index == module.factory,
+        };
+
+        let mut changed = false;
+        for block in &mut function.blocks {
+            let expressions = block
+                .statements
+                .iter_mut()
+                .flat_map(|statement| statement.expressions_mut())
+                .chain(block.terminator.expressions_mut());
+            for expression in expressions {
+                changed |= rewriter.rewrite(expression);
+            }
+        }
+        if changed {
+            eliminate_dead_code(function);
+        }
+    }
+}
+
+/// A parameter of the factory, and the captured variable it is stored in
+/// when closures use it.
+struct Binding {
+    parameter: u32,
+    slot: Option<CapturedVariable>,
+}
+
+impl Binding {
+    fn new(factory: &LiftedFunction, parameter: u32) -> Self {
+        let definitions = factory.definitions();
+        let slot = factory
+            .blocks
+            .iter()
+            .flat_map(|block| &block.statements)
+            .find_map(|statement| match statement {
+                Statement::Expression(Expression::Assignment {
+                    operator: AssignmentOperator::Assignment,
+                    target,
+                    value,
+                }) => {
+                    let value = match value.as_ref() {
+                        Expression::Value(value) => definitions[*value]?,
+                        value => value,
+                    };
+                    match (target.as_ref(), value) {
+                        (Expression::Captured(variable), Expression::Parameter(stored))
+                            if *stored == parameter =>
+                        {
+                            Some(*variable)
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            });
+        Self { parameter, slot }
+    }
+}
+
+struct Rewriter<'a> {
+    require: &'a Binding,
+    dependency_map: &'a Binding,
+    dependencies: &'a [u32],
+    definitions: &'a [Option<Expression>],
+    in_factory: bool,
+}
+
+impl Rewriter<'_> {
+    fn rewrite(&self, expression: &mut Expression) -> bool {
+        let mut changed = false;
+        for child in expression.children_mut() {
+            changed |= self.rewrite(child);
+        }
+
+        let module = match expression {
+            Expression::Call {
+                callee, arguments, ..
+            } if self.refers_to(callee, self.require) => arguments
+                .first()
+                .and_then(|argument| self.dependency(argument)),
+            _ => None,
+        };
+        match module {
+            Some(module) => {
+                *expression = Expression::Require(module);
+                true
+            }
+            None => changed,
+        }
+    }
+
+    /// The module `_dependencyMap[n]` names.
+    fn dependency(&self, expression: &Expression) -> Option<u32> {
+        let (object, property) = match expression {
+            Expression::Member { object, property } => (object, property),
+            _ => return None,
+        };
+        if !self.refers_to(object, self.dependency_map) {
+            return None;
+        }
+        let index = match property {
+            MemberProperty::Index(index) => *index as usize,
+            MemberProperty::Computed(index) => literal_index(index)?,
+            MemberProperty::Named(_) => return None,
+        };
+        self.dependencies.get(index).copied()
+    }
+
+    fn refers_to(&self, expression: &Expression, binding: &Binding) -> bool {
+        match expression {
+            Expression::Parameter(parameter) => self.in_factory && *parameter == binding.parameter,
+            Expression::Captured(variable) => binding.slot == Some(*variable),
+            Expression::Value(value) => match &self.definitions[*value] {
+                Some(definition) => self.refers_to(definition, binding),
+                None => false,
+            },
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use crate::{
+    analysis::{ControlFlowGraph, Dominators, SsaForm},
+    ast::{LiftedFunction, MetroBundle, ModuleGraph, Names, Nesting, Scopes},
+    ir::Program,
+};
+
+use super::{
+    capture_variables, eliminate_dead_code, infer_types, insert_phi_copies, propagate,
+    propagate_constants, remove_unreachable_blocks, resolve_scopes, restore_async_functions,
+    restore_cjs_requires, restore_classes, restore_conditionals, restore_constructions,
+    restore_es_modules, restore_for_in_loops, restore_generators, restore_iteration,
+    restore_literals, restore_metro_requires, restore_spread, restore_templates,
+    restore_try_statements, restore_updates, simplify_conversions,
+};
+
+/// A program's functions after every pass, and what the passes learned
+/// about how they nest, capture and name things.
+pub struct Restored {
+    pub functions: Vec<LiftedFunction>,
+    pub nesting: Nesting,
+    pub scopes: Scopes,
+    pub bundle: MetroBundle,
+    pub modules: ModuleGraph,
+    pub names: Names,
+}
+
+/// Lifts every function of `program` and runs the passes over them, in the
+/// order they build on each other. With `esm`, module factories are
+/// restored to ES modules.
+pub fn restore_program(program: &Program, esm: bool) -> Restored {
+    let mut functions: Vec<LiftedFunction> = program
+        .functions
+        .iter()
+        .map(|function| {
+            let cfg = ControlFlowGraph::new(function);
+            let dominators = Dominators::new(&cfg);
+            let ssa = SsaForm::new(function, &cfg, &dominators);
+
+            let mut lifted = LiftedFunction::new(function, &cfg, &ssa);
+            remove_unreachable_blocks(&mut lifted);
+            eliminate_dead_code(&mut lifted);
+            propagate(&mut lifted);
+            propagate_constants(&mut lifted, &program.bytecode);
+            insert_phi_copies(&mut lifted);
+            infer_types(&mut lifted);
+            restore_iteration(&mut lifted);
+            restore_try_statements(&mut lifted);
+            restore_for_in_loops(&mut lifted);
+            lifted
+        })
+        .collect();
+
+    let nesting = Nesting::new(&functions, program.bytecode.header.global_code_index);
+    for function in &mut functions {
+        resolve_scopes(function, &nesting);
+        eliminate_dead_code(function);
+    }
+
+    let scopes = Scopes::new(&functions);
+    for function in &mut functions {
+        capture_variables(function, &scopes);
+    }
+
+    restore_generators(&mut functions, &program.bytecode);
+    restore_async_functions(&mut functions, &program.bytecode);
+    restore_classes(&mut functions, &program.bytecode);
+    for function in &mut functions {
+        restore_conditionals(function);
+        restore_literals(function, &program.bytecode);
+        restore_spread(function);
+        restore_constructions(function);
+        restore_cjs_requires(function);
+        restore_updates(function);
+        simplify_conversions(function);
+        restore_templates(function);
+        propagate(function);
+        insert_phi_copies(function);
+    }
+
+    let root = program.bytecode.header.global_code_index;
+    let bundle = functions
+        .get(root as usize)
+        .map(|global| MetroBundle::find(global, &program.bytecode))
+        .unwrap_or_default();
+    for module in &bundle.modules {
+        restore_metro_requires(&mut functions, &nesting, module);
+    }
+    let mut modules = match bundle.is_empty() {
+        true => ModuleGraph::from_cjs_table(&program.bytecode),
+        false => ModuleGraph::from_metro_bundle(&bundle),
+    };
+    if esm {
+        let factories: Vec<u32> = modules
+            .modules()
+            .iter()
+            .map(|module| module.factory)
+            .collect();
+        for factory in factories {
+            restore_es_modules(
+                &mut functions,
+                &nesting,
+                &program.bytecode,
+                &modules,
+                factory,
+            );
+        }
+    }
+    modules.add_imports(&functions, &nesting);
+
+    let names = Names::new(&functions, program, &nesting, &scopes, &modules);
+    Restored {
+        functions,
+        nesting,
+        scopes,
+        bundle,
+        modules,
+        names,
+    }
+}
@@ -0,0 +1,346 @@
+use crate::{
+    analysis::{ValueId, Variables},
+    ast::{Expression, LiftedFunction, Statement},
+};
+
+/// Removes register shuffling: copies are forwarded to their readers and
+/// values read exactly once are folded into the expression that reads them.
+pub fn propagate(function: &mut LiftedFunction) {
+    eliminate_moves(function);
+    inline_single_uses(function);
+}
+
+/// Rewrites the readers of `a = b` to read `b` directly. A copy that feeds a
+/// phi is kept, as is one whose source shares its variable with other values:
+/// moving that read could cross a redefinition once SSA is left.
+fn eliminate_moves(function: &mut LiftedFunction) {
+    let variables = function.variables();
+    let phi_arguments = function.phi_arguments();
+
+    let mut values_per_variable = vec![0; variables.len()];
+    for value in 0..function.registers.len() {
+        values_per_variable[variables.of(value)] += 1;
+    }
+
+    let mut renames: Vec<(ValueId, ValueId)> = Vec::new();
+    for block in &mut function.blocks {
+        block.statements.retain(|statement| match statement {
+            Statement::Assign {
+                value,
+                expression: Expression::Value(source),
+            } if !phi_arguments[*value] && values_per_variable[variables.of(*source)] == 1 => {
+                renames.push((*value, *source));
+                false
+            }
+            _ => true,
+        });
+    }
+
+    if renames.is_empty() {
+        return;
+    }
+
+    // Chains of copies resolve to their first source.
+    let mut replacement: Vec<ValueId> = (0..function.registers.len()).collect();
+    for (value, source) in &renames {
+        replacement[*value] = *source;
+    }
+    let resolve = |mut value: ValueId| {
+        while replacement[value] != value {
+            value = replacement[value];
+        }
+        value
+    };
+
+    for block in &mut function.blocks {
+        let expressions = block
+            .statements
+            .iter_mut()
+            .flat_map(|statement| statement.expressions_mut())
+            .chain(block.terminator.expressions_mut());
+        for expression in expressions {
+            for (value, _) in &renames {
+                expression.rename_value(*value, resolve(*value));
+            }
+        }
+    }
+}
+
+/// Folds `v = e` into the single later statement of the same block reading
+/// `v`, unless something evaluated in between could tell the difference.
+fn inline_single_uses(function: &mut LiftedFunction) {
+    let variables = function.variables();
+    let use_counts = function.use_counts();
+    let phi_arguments = function.phi_arguments();
+
+    for block in &mut function.blocks {
+        let mut position = 0;
+        while position < block.statements.len() {
+            let value = match &block.statements[position] {
+                Statement::Assign { value, .. }
+                    if use_counts[*value] == 1 && !phi_arguments[*value] =>
+                {
+                    *value
+                }
+                _ => {
+                    position += 1;
+                    continue;
+                }
+            };
+
+            let expression = match &block.statements[position] {
+                Statement::Assign { expression, .. } => expression,
+                _ => unreachable!(),
+            };
+            let effects = Effects::of(expression, &variables);
+
+            // Find the reader, stopping at the first statement in the way.
+            let mut reader = None;
+            for (later, statement) in block.statements.iter().enumerate().skip(position + 1) {
+                let expressions = statement.expressions();
+                if let Some(before) = Effects::before(&expressions, value, &variables) {
+                    if !effects.conflicts(&before) {
+                        reader = Some(later);
+                    }
+                    break;
+                }
+                if effects.conflicts(&Effects::of_statement(statement, &variables)) {
+                    break;
+                }
+            }
+
+            let terminator_reads = block
+                .terminator
+                .expressions()
+                .iter()
+                .any(|expression| expression.values().contains(&value));
+            let reaches_terminator = reader.is_none()
+                && terminator_reads
+                && block.statements[position + 1..].iter().all(|statement| {
+                    !effects.conflicts(&Effects::of_statement(statement, &variables))
+                })
+                && Effects::before(&block.terminator.expressions(), value, &variables)
+                    .is_some_and(|before| !effects.conflicts(&before));
+
+            if reader.is_none() && !reaches_terminator {
+                position += 1;
+                continue;
+            }
+
+            let expression = match block.statements.remove(position) {
+                Statement::Assign { expression, .. } => expression,
+                _ => unreachable!(),
+            };
+            let mut replacement = Some(expression);
+            let targets = match reader {
+                Some(reader) => block.statements[reader - 1].expressions_mut(),
+                None => block.terminator.expressions_mut(),
+            };
+            for target in targets {
+                if target.replace_value(value, &mut replacement) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// What evaluating some code can observe or change.
+#[derive(Default)]
+struct Effects {
+    reads_memory: bool,
+    writes_memory: bool,
+    reads: Vec<usize>,
+    defines: Vec<usize>,
+}
+
+impl Effects {
+    fn of(expression: &Expression, variables: &Variables) -> Self {
+        Self {
+            reads_memory: expression.reads_memory(),
+            writes_memory: expression.has_side_effects(),
+            reads: expression
+                .values()
+                .into_iter()
+                .map(|value| variables.of(value))
+                .collect(),
+            defines: vec![],
+        }
+    }
+
+    fn of_statement(statement: &Statement, variables: &Variables) -> Self {
+        let (reads_memory, writes_memory) = statement.touches_memory();
+        Self {
+            reads_memory,
+            writes_memory,
+            reads: vec![],
+            defines: statement
+                .defs()
+                .into_iter()
+                .map(|value| variables.of(value))
+                .collect(),
+        }
+    }
+
+    /// Effects of everything `expressions` evaluate before reading `value`,
+    /// or `None` when `value` is not read.
+    fn before(expressions: &[&Expression], value: ValueId, variables: &Variables) -> Option<Self> {
+        let mut effects = Self::default();
+        for expression in expressions {
+            if effects.accumulate_before(expression, value, variables) {
+                return Some(effects);
+            }
+        }
+        None
+    }
+
+    /// Returns whether `value` was found; until then every subtree walked
+    /// is added to `self`.
+    fn accumulate_before(
+        &mut self,
+        expression: &Expression,
+        value: ValueId,
+        variables: &Variables,
+    ) -> bool {
+        if let Expression::Value(current) = expression {
+            return *current == value;
+        }
+        if !expression.values().contains(&value) {
+            let effects = Self::of(expression, variables);
+            self.reads_memory |= effects.reads_memory;
+            self.writes_memory |= effects.writes_memory;
+            return false;
+        }
+        for (index, child) in expression.children().into_iter().enumerate() {
+            // Moving code where only some paths evaluate it is only safe for
+            // code that cannot tell.
+            if index > 0 && expression.short_circuits() && child.values().contains(&value) {
+                self.reads_memory = true;
+                self.writes_memory = true;
+            }
+            if self.accumulate_before(child, value, variables) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Whether code with effects `self` can be moved past `other`.
+    fn conflicts(&self, other: &Self) -> bool {
+        (self.writes_memory && (other.reads_memory || other.writes_memory))
+            || (self.reads_memory && other.writes_memory)
+            || self
+                .reads
+                .iter()
+                .any(|variable| other.defines.contains(variable))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    /// `g(o.a)` with `between` evaluated after the read.
+    fn read_then(between: &str) -> String {
+        let source = format!(
+            r#"
+                GetGlobalObject r0
+                GetById r1, r0, 1, "o"
+                GetById r2, r1, 2, "a"
+                {}
+                GetGlobalObject r0
+                GetById r5, r0, 4, "g"
+                LoadConstUndefined r7
+                Call2 r6, r5, r7, r2
+                LoadConstUndefined r8
+                Ret r8
+                "#,
+            between
+        );
+        testing::decompile(&[testing::function(source)])
+    }
+
+    #[test]
+    fn read_is_folded_into_its_reader() {
+        let text = read_then("");
+        assert_eq!(text, "g(o.a);\n");
+    }
+
+    #[test]
+    fn read_stays_before_a_call() {
+        let text = read_then(
+            r#"
+            GetGlobalObject r0
+            GetById r3, r0, 3, "f"
+            LoadConstUndefined r7
+            Call1 r4, r3, r7
+            "#,
+        );
+        assert_eq!(text, "let a;\na = o.a;\nf();\ng(a);\n");
+    }
+
+    #[test]
+    fn read_stays_before_a_property_store() {
+        let text = read_then(
+            r#"
+            LoadConstUInt8 r3, 1
+            PutById r1, r3, 3, "a"
+            "#,
+        );
+        assert_eq!(text, "let o2, a;\no2 = o;\na = o2.a;\no2.a = 1;\ng(a);\n");
+    }
+
+    #[test]
+    fn read_stays_outside_a_try() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            GetGlobalObject r0
+            GetById r1, r0, 1, "o"
+            GetById r2, r1, 2, "a"
+            start:
+            GetGlobalObject r0
+            GetById r5, r0, 4, "g"
+            LoadConstUndefined r7
+            Call2 r6, r5, r7, r2
+            end:
+            Jmp @done
+            handler:
+            Catch r3
+            done:
+            LoadConstUndefined r8
+            Ret r8
+            "#,
+        )
+        .handler("start", "end", "handler")]);
+        assert_eq!(text, "let a;\na = o.a;\ntry {\n  g(a);\n} catch {\n}\n");
+    }
+
+    #[test]
+    fn read_stays_inside_a_try() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            start:
+            GetGlobalObject r0
+            GetById r1, r0, 1, "o"
+            GetById r2, r1, 2, "a"
+            end:
+            Jmp @done
+            handler:
+            Catch r3
+            LoadConstNull r2
+            done:
+            GetGlobalObject r0
+            GetById r5, r0, 4, "g"
+            LoadConstUndefined r7
+            Call2 r6, r5, r7, r2
+            LoadConstUndefined r8
+            Ret r8
+            "#,
+        )
+        .handler("start", "end", "handler")]);
+        assert_eq!(
+            text,
+            "let a;\ntry {\n  a = o.a;\n} catch {\n  a = null;\n}\ng(a);\n"
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use crate::{
+    ast::{Expression, LiftedFunction, MemberProperty, ObjectProperty, Statement},
+    builtins::Builtins,
+    ir::Literal,
+};
+
+use super::{eliminate_dead_code, literals::literal_index, propagate};
+
+/// Folds the builtins behind spread and rest syntax back into it:
+/// `copyRestArgs` past the last parameter is a rest parameter,
+/// `copyDataProperties` into an empty object destructures an object rest,
+/// and `apply` is a call with spread arguments. Runs after
+/// `restore_literals`, which takes care of spreads in literals.
+pub fn restore_spread(function: &mut LiftedFunction) {
+    let param_count = function.header.param_count;
+    let mut rest = false;
+    for_each_expression(function, |expression| {
+        restore_rest(expression, param_count, &mut rest)
+    });
+    function.rest |= rest;
+    restore_object_rests(function);
+    for_each_expression(function, restore_apply);
+    eliminate_dead_code(function);
+    propagate(function);
+    for_each_expression(function, flatten_spreads);
+}
+
+fn for_each_expression(function: &mut LiftedFunction, mut rewrite: impl FnMut(&mut Expression)) {
+    for block in &mut function.blocks {
+        let expressions = block
+            .statements
+            .iter_mut()
+            .flat_map(|statement| statement.expressions_mut())
+            .chain(block.terminator.expressions_mut());
+        for expression in expressions {
+            rewrite(expression);
+        }
+    }
+}
+
+/// `copyRestArgs(n)` collects the arguments after the first `n` parameters,
+/// which when `n` is all of them is reading the trailing parameter `rest`
+/// adds.
+fn restore_rest(expression: &mut Expression, param_count: u32, rest: &mut bool) {
+    for child in expression.children_mut() {
+        restore_rest(child, param_count, rest);
+    }
+
+    let arguments = match expression {
+        Expression::Call {
+            callee, arguments, ..
+        } if matches!(
+            callee.as_ref(),
+            Expression::Builtin(Builtins::HermesBuiltinCopyRestArgs)
+        ) =>
+        {
+            arguments
+        }
+        _ => return,
+    };
+    *expression = match arguments.first().and_then(literal_index) {
+        // `this` is parameter 0 but not one of the `n`.
+        Some(count) if count as u32 + 1 == param_count => {
+            *rest = true;
+            Expression::Parameter(param_count)
+        }
+        Some(count) => Expression::RestParameter(count as u32 + 1),
+        None => return,
+    };
+}
+
+/// `copyDataProperties({}, source, { a: 0, b: 0 })` collects the properties
+/// of `source` besides `a` and `b`, the `rest` of
+/// `({ a, b, ...rest } = source)`. The call becomes that statement, moved
+/// ahead of the one it stood in when nothing evaluated before it there has
+/// effects, and is left alone otherwise.
+fn restore_object_rests(function: &mut LiftedFunction) {
+    let definitions: Vec<Option<Expression>> = function
+        .definitions()
+        .into_iter()
+        .map(|definition| definition.cloned())
+        .collect();
+
+    for block in 0..function.blocks.len() {
+        let mut position = 0;
+        while position < function.blocks[block].statements.len() {
+            let statement = &function.blocks[block].statements[position];
+            let found = statement
+                .expressions()
+                .into_iter()
+                .map(|expression| find_object_rest(expression, &definitions))
+                .enumerate()
+                .find(|(_, found)| *found != Err(false));
+            let (index, path) = match found {
+                Some((index, Ok(path))) => (index, path),
+                _ => {
+                    position += 1;
+                    continue;
+                }
+            };
+            let register = statement.expressions()[index]
+                .values()
+                .first()
+                .map_or(0, |value| function.registers[*value]);
+
+            let (call, rest) = match &mut function.blocks[block].statements[position] {
+                Statement::Assign { value, expression } if path.is_empty() => {
+                    let rest = *value;
+                    let call = std::mem::replace(expression, Expression::Value(rest));
+                    function.blocks[block].statements.remove(position);
+                    (call, rest)
+                }
+                _ => {
+                    let rest = function.new_value(register);
+                    let statement = &mut function.blocks[block].statements[position];
+                    let expression = statement.expressions_mut().swap_remove(index);
+                    let call =
+                        std::mem::replace(descendant(expression, &path), Expression::Value(rest));
+                    (call, rest)
+                }
+            };
+            let (source, excluded) = match object_rest(&call, &definitions) {
+                Some(parts) => parts,
+                None => unreachable!(),
+            };
+            let properties = excluded
+                .into_iter()
+                .map(|key| (key, function.new_value(register)))
+                .collect();
+            function.blocks[block].statements.insert(
+                position,
+                Statement::DestructureObject {
+                    properties,
+                    rest,
+                    source,
+                },
+            );
+            position += 1;
+        }
+    }
+}
+
+/// The child indices leading to the first object rest `expression`
+/// evaluates, `Err(true)` when something with effects is evaluated before
+/// it and `Err(false)` when there is none.
+fn find_object_rest(
+    expression: &Expression,
+    definitions: &[Option<Expression>],
+) -> Result<Vec<usize>, bool> {
+    if object_rest(expression, definitions).is_some() {
+        return Ok(Vec::new());
+    }
+    for (index, child) in expression.children().into_iter().enumerate() {
+        // What only some paths evaluate cannot be moved ahead of them.
+        if index > 0 && expression.short_circuits() {
+            return Err(true);
+        }
+        match find_object_rest(child, definitions) {
+            Ok(mut path) => {
+                path.insert(0, index);
+                return Ok(path);
+            }
+            Err(false) => {}
+            Err(true) => return Err(true),
+        }
+    }
+    Err(expression.has_side_effects() || expression.reads_memory())
+}
+
+fn descendant<'e>(expression: &'e mut Expression, path: &[usize]) -> &'e mut Expression {
+    match path.split_first() {
+        Some((index, path)) => descendant(expression.children_mut().swap_remove(*index), path),
+        None => expression,
+    }
+}
+
+/// The source and excluded keys of `copyDataProperties({}, source,
+/// excluded)`, where `excluded` is an object literal.
+fn object_rest(
+    expression: &Expression,
+    definitions: &[Option<Expression>],
+) -> Option<(Expression, Vec<MemberProperty>)> {
+    let arguments = match expression {
+        Expression::Call {
+            callee, arguments, ..
+        } if matches!(
+            callee.as_ref(),
+            Expression::Builtin(Builtins::HermesBuiltinCopyDataProperties)
+        ) =>
+        {
+            arguments
+        }
+        _ => return None,
+    };
+    let literal = |expression: &Expression| -> Option<Vec<ObjectProperty>> {
+        match expression {
+            Expression::ObjectLiteral(properties) => Some(properties.clone()),
+            Expression::Value(value) => match definitions.get(*value) {
+                Some(Some(Expression::ObjectLiteral(properties))) => Some(properties.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    };
+    match arguments.as_slice() {
+        [target, source, excluded] if literal(target)?.is_empty() => {
+            let excluded = literal(excluded)?
+                .into_iter()
+                .map(|property| match property {
+                    ObjectProperty::Value { key, .. } => Some(key),
+                    _ => None,
+                })
+                .collect::<Option<Vec<MemberProperty>>>()?;
+            Some((source.clone(), excluded))
+        }
+        _ => None,
+    }
+}
+
+/// `HermesBuiltin.apply(callee, arguments, this)` calls with the arguments
+/// spread, and constructs when there is no `this`.
+fn restore_apply(expression: &mut Expression) {
+    for child in expression.children_mut() {
+        restore_apply(child);
+    }
+
+    let arguments = match expression {
+        Expression::Call {
+            callee, arguments, ..
+        } if matches!(
+            callee.as_ref(),
+            Expression::Builtin(Builtins::HermesBuiltinApply)
+        ) && (2..=3).contains(&arguments.len()) =>
+        {
+            std::mem::take(arguments)
+        }
+        _ => return,
+    };
+    let mut arguments = arguments.into_iter();
+    let (callee, spread, this) = match (arguments.next(), arguments.next(), arguments.next()) {
+        (Some(callee), Some(spread), this) => (callee, spread, this),
+        _ => unreachable!(),
+    };
+
+    let callee = Box::new(callee);
+    let arguments = vec![Expression::Spread(Box::new(spread))];
+    *expression = match this {
+        Some(this) => Expression::Call {
+            callee,
+            this: Box::new(this),
+            arguments,
+        },
+        None => Expression::Construct {
+            callee,
+            this: Box::new(Expression::Literal(Literal::Undefined)),
+            arguments,
+        },
+    };
+}
+
+/// Spreading an array literal is the same as listing its elements.
+fn flatten_spreads(expression: &mut Expression) {
+    for child in expression.children_mut() {
+        flatten_spreads(child);
+    }
+
+    let spread_elements = |expression: &Expression| match expression {
+        Expression::Spread(argument) => match argument.as_ref() {
+            Expression::ArrayLiteral(elements) => Some(
+                elements
+                    .iter()
+                    .map(|element| {
+                        element
+                            .clone()
+                            .unwrap_or(Expression::Literal(Literal::Undefined))
+                    })
+                    .collect::<Vec<Expression>>(),
+            ),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    match expression {
+        Expression::Call { arguments, .. } | Expression::Construct { arguments, .. } => {
+            *arguments = std::mem::take(arguments)
+                .into_iter()
+                .flat_map(|argument| spread_elements(&argument).unwrap_or(vec![argument]))
+                .collect();
+        }
+        Expression::ArrayLiteral(elements) => {
+            *elements = std::mem::take(elements)
+                .into_iter()
+                .flat_map(|element| match element.as_ref().and_then(spread_elements) {
+                    Some(spread) => spread.into_iter().map(Some).collect(),
+                    None => vec![element],
+                })
+                .collect();
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    /// `function (a) { g(<copyRestArgs(from)>); }` stored in `f`.
+    fn rest_from(from: u8) -> String {
+        let body = format!(
+            r#"
+                LoadConstUInt8 r30, {}
+                LoadConstUndefined r31
+                CallBuiltin r0, 47, 2
+                GetGlobalObject r1
+                GetById r2, r1, 1, "g"
+                LoadConstUndefined r3
+                Call2 r4, r2, r3, r0
+                LoadConstUndefined r5
+                Ret r5
+                "#,
+            from
+        );
+        testing::decompile(&[
+            testing::function(
+                r#"
+                CreateEnvironment r0
+                CreateClosure r1, r0, 1
+                GetGlobalObject r2
+                PutById r2, r1, 2, "f"
+                LoadConstUndefined r3
+                Ret r3
+                "#,
+            ),
+            testing::function(body).params(2),
+        ])
+    }
+
+    #[test]
+    fn tail_slice_is_a_rest_parameter() {
+        assert_eq!(
+            rest_from(1),
+            "f = function /* f */ (a0, ...rest) {\n  g(rest);\n};\n"
+        );
+    }
+
+    #[test]
+    fn slice_past_the_parameters_stays_a_call() {
+        assert_eq!(
+            rest_from(2),
+            "f = function /* f */ (a0) {\n  g(Array.prototype.slice.call(arguments, 2));\n};\n"
+        );
+    }
+
+    #[test]
+    fn object_rest_is_destructured() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            GetGlobalObject r0
+            GetById r1, r0, 1, "a"
+            NewObject r2
+            NewObject r3
+            LoadConstUInt8 r4, 1
+            PutNewOwnById r3, r4, "b"
+            LoadConstUndefined r31
+            Mov r30, r2
+            Mov r29, r1
+            Mov r28, r3
+            CallBuiltin r6, 46, 4
+            PutById r0, r6, 2, "rest"
+            LoadConstUndefined r5
+            Ret r5
+            "#,
+        )]);
+        assert_eq!(
+            text,
+            "let globalObject, b, rest;\nglobalObject = globalThis;\n({ b, ...rest } = globalObject.a);\nglobalObject.rest = rest;\n"
+        );
+    }
+
+    #[test]
+    fn object_rest_on_one_path() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            GetGlobalObject r0
+            GetById r1, r0, 1, "a"
+            Mov r6, r1
+            JmpFalse @done, r1
+            NewObject r2
+            NewObject r3
+            LoadConstUInt8 r4, 1
+            PutNewOwnById r3, r4, "b"
+            LoadConstUndefined r31
+            Mov r30, r2
+            Mov r29, r1
+            Mov r28, r3
+            CallBuiltin r6, 46, 4
+            done:
+            PutById r0, r6, 2, "rest"
+            LoadConstUndefined r5
+            Ret r5
+            "#,
+        )]);
+        assert_eq!(
+            text,
+            "let globalObject, a, rest, b;\nglobalObject = globalThis;\na = globalObject.a;\nrest = a;\nif (a) {\n  ({ b, ...rest } = a);\n}\nglobalObject.rest = rest;\n"
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{
+    ast::{Expression, LiftedFunction, Statement},
+    builtins::Builtins,
+    ir::{BinaryOperator, Boolean, Conversion, Literal, StringIndex},
+};
+
+/// Rebuilds template literals. A tag is called with the object
+/// `HermesBuiltin.getTemplateObject` makes from the raw text, followed by the
+/// substitutions. Untagged templates are compiled to string concatenation,
+/// which becomes a template again when it starts from `"" + x` or alternates
+/// text and substitutions; a lone `"text" + x` is left alone.
+pub fn restore_templates(function: &mut LiftedFunction) {
+    let literals: Vec<Option<Literal>> = function
+        .definitions()
+        .iter()
+        .map(|definition| match definition {
+            Some(Expression::Literal(literal)) => Some(*literal),
+            _ => None,
+        })
+        .collect();
+    let mut values = TemplateValues {
+        objects: Vec::new(),
+        literals,
+    };
+    values.objects = function
+        .definitions()
+        .iter()
+        .map(|definition| definition.and_then(|definition| values.template_object(definition)))
+        .collect();
+
+    for block in &mut function.blocks {
+        let expressions = block
+            .statements
+            .iter_mut()
+            .flat_map(|statement| statement.expressions_mut())
+            .chain(block.terminator.expressions_mut());
+        for expression in expressions {
+            values.rewrite(expression);
+        }
+    }
+
+    // The template objects tags no longer read.
+    let use_counts = function.use_counts();
+    for block in &mut function.blocks {
+        block.statements.retain(|statement| match statement {
+            Statement::Assign { value, .. } => {
+                values.objects[*value].is_none() || use_counts[*value] > 0
+            }
+            _ => true,
+        });
+    }
+}
+
+/// Template objects and string literals held in values of one function.
+struct TemplateValues {
+    objects: Vec<Option<Vec<StringIndex>>>,
+    literals: Vec<Option<Literal>>,
+}
+
+impl TemplateValues {
+    fn rewrite(&self, expression: &mut Expression) {
+        if let Some(template) = self.template(expression) {
+            *expression = template;
+        }
+        for child in expression.children_mut() {
+            self.rewrite(child);
+        }
+    }
+
+    fn template(&self, expression: &Expression) -> Option<Expression> {
+        match expression {
+            Expression::Call {
+                callee,
+                this,
+                arguments,
+            } => {
+                let quasis = match arguments.first()? {
+                    Expression::Value(value) => self.objects.get(*value)?.clone()?,
+                    object => self.template_object(object)?,
+                };
+                if quasis.len() != arguments.len() {
+                    return None;
+                }
+                Some(Expression::TaggedTemplate {
+                    tag: callee.clone(),
+                    this: this.clone(),
+                    quasis,
+                    expressions: arguments[1..].to_vec(),
+                })
+            }
+            Expression::Binary {
+                operator: BinaryOperator::Addition,
+                ..
+            }
+            | Expression::Conversion {
+                conversion: Conversion::ToString,
+                ..
+            } => self.concatenation(expression),
+            _ => None,
+        }
+    }
+
+    /// The template a chain of `+` spells out, if it looks like one.
+    fn concatenation(&self, expression: &Expression) -> Option<Expression> {
+        let mut operands = Vec::new();
+        let mut current = expression;
+        let starts_empty = loop {
+            match current {
+                Expression::Binary {
+                    operator: BinaryOperator::Addition,
+                    left,
+                    right,
+                } => {
+                    operands.push(right.as_ref());
+                    current = left;
+                }
+                Expression::Conversion {
+                    conversion: Conversion::ToString,
+                    argument,
+                } => {
+                    operands.push(argument);
+                    break true;
+                }
+                operand => {
+                    operands.push(operand);
+                    break false;
+                }
+            }
+        };
+        operands.reverse();
+
+        let texts: Vec<Option<StringIndex>> = operands
+            .iter()
+            .map(|operand| self.string(operand))
+            .collect();
+        let alternates = texts.first().is_some_and(|text| text.is_some())
+            && texts
+                .iter()
+                .enumerate()
+                .all(|(index, text)| text.is_some() == (index % 2 == 0));
+        let is_template = starts_empty || (operands.len() >= 3 && alternates);
+        if !is_template {
+            return None;
+        }
+
+        let mut quasis = Vec::new();
+        let mut expressions = Vec::new();
+        let mut text = None;
+        for (operand, string) in operands.into_iter().zip(texts) {
+            match string {
+                // Adjacent strings were folded by the compiler, or were never
+                // part of a template.
+                Some(_) if text.is_some() => return None,
+                Some(string) => text = Some(string),
+                None => {
+                    quasis.push(text.take());
+                    expressions.push(operand.clone());
+                }
+            }
+        }
+        quasis.push(text);
+
+        Some(Expression::Template {
+            quasis,
+            expressions,
+        })
+    }
+
+    fn string(&self, expression: &Expression) -> Option<StringIndex> {
+        match self.literal(expression)? {
+            Literal::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    fn literal(&self, expression: &Expression) -> Option<Literal> {
+        match expression {
+            Expression::Literal(literal) => Some(*literal),
+            Expression::Value(value) => *self.literals.get(*value)?,
+            _ => None,
+        }
+    }
+
+    /// The raw strings of a `getTemplateObject(id, dup, ...raw, ...cooked)`
+    /// call made of literals. `dup` says the cooked strings equal the raw
+    /// ones and were left out.
+    fn template_object(&self, expression: &Expression) -> Option<Vec<StringIndex>> {
+        let arguments = match expression {
+            Expression::Call {
+                callee, arguments, ..
+            } if matches!(
+                callee.as_ref(),
+                Expression::Builtin(Builtins::HermesBuiltinGetTemplateObject)
+            ) =>
+            {
+                arguments
+            }
+            _ => return None,
+        };
+
+        let strings = arguments
+            .get(2..)?
+            .iter()
+            .map(|argument| match self.literal(argument)? {
+                Literal::String(string) => Some(string),
+                _ => None,
+            })
+            .collect::<Option<Vec<StringIndex>>>()?;
+
+        let raw_count = match self.literal(arguments.get(1)?)? {
+            Literal::Boolean(Boolean::True) => strings.len(),
+            Literal::Boolean(Boolean::False) if strings.len() % 2 == 0 => strings.len() / 2,
+            _ => return None,
+        };
+        match raw_count {
+            0 => None,
+            count => Some(strings[..count].to_vec()),
+        }
+    }
+}
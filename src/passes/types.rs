@@ -0,0 +1,103 @@
+use crate::{
+    ast::{Expression, LiftedFunction, Statement, Type},
+    ir::{Conversion, Literal},
+};
+
+/// Works out what every value holds, starting from what lifting recorded
+/// about the operands of numeric opcodes. Phis join their arguments and
+/// loops are iterated until nothing changes; values defined by anything
+/// but an `Assign` or a phi stay `Unknown`.
+pub fn infer_types(function: &mut LiftedFunction) {
+    let known = function.types.clone();
+    let mut inferred = vec![Type::Unknown; known.len()];
+    for block in &function.blocks {
+        for phi in &block.phis {
+            inferred[phi.value] = Type::Never;
+        }
+        for statement in &block.statements {
+            if let Statement::Assign { value, .. } = statement {
+                inferred[*value] = Type::Never;
+            }
+        }
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in &function.blocks {
+            for phi in &block.phis {
+                let joined = phi
+                    .arguments
+                    .iter()
+                    .fold(Type::Never, |joined, (_, argument)| {
+                        joined.join(inferred[*argument].meet(known[*argument]))
+                    });
+                changed |= update(&mut inferred, phi.value, joined);
+            }
+
+            for statement in &block.statements {
+                if let Statement::Assign { value, expression } = statement {
+                    let current = Type::of(expression, &|value| inferred[value].meet(known[value]));
+                    changed |= update(&mut inferred, *value, current);
+                }
+            }
+        }
+    }
+
+    for (value, inferred) in inferred.into_iter().enumerate() {
+        function.types[value] = inferred.meet(known[value]);
+    }
+}
+
+fn update(inferred: &mut [Type], value: usize, current: Type) -> bool {
+    let joined = inferred[value].join(current);
+    let changed = joined != inferred[value];
+    inferred[value] = joined;
+    changed
+}
+
+/// Drops conversions of values already of the type converted to, like the
+/// AddEmptyString of `"" + name` once `name` is known to be a string.
+/// Runs after `restore_updates`, which looks for the `ToNumber` of `x++`,
+/// and before `restore_templates`, which reads AddEmptyString as `""`.
+pub fn simplify_conversions(function: &mut LiftedFunction) {
+    let types = function.types.clone();
+    for block in &mut function.blocks {
+        let expressions = block
+            .statements
+            .iter_mut()
+            .flat_map(|statement| statement.expressions_mut())
+            .chain(block.terminator.expressions_mut());
+        for expression in expressions {
+            simplify(expression, &types);
+        }
+    }
+}
+
+fn simplify(expression: &mut Expression, types: &[Type]) {
+    for child in expression.children_mut() {
+        simplify(child, types);
+    }
+
+    let argument = match expression {
+        Expression::Conversion {
+            conversion,
+            argument,
+        } => {
+            let converted = Type::of(argument, &|value| types[value]);
+            let redundant = match conversion {
+                Conversion::ToNumber => converted.is_number(),
+                Conversion::ToInt32 => converted == Type::Int32,
+                Conversion::ToString => converted == Type::String,
+            };
+            match redundant {
+                true => {
+                    std::mem::replace(argument.as_mut(), Expression::Literal(Literal::Undefined))
+                }
+                false => return,
+            }
+        }
+        _ => return,
+    };
+    *expression = argument;
+}
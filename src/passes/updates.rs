@@ -0,0 +1,387 @@
+use std::collections::HashSet;
+
+use crate::{
+    analysis::{ValueId, Variables},
+    ast::{Expression, LiftedFunction, MemberProperty, Statement},
+    ir::{AssignmentOperator, BinaryOperator, Conversion, Literal, Number, UpdateOperator},
+};
+
+/// Turns a store of `x op y` back into `x` into `x op= y`, and adding or
+/// subtracting 1 into `x++` or `--x`. `++` converts `x` to a number first,
+/// so `x + 1` without that conversion stays `x += 1`. Which of the old and
+/// new value the code goes on to read decides between `x++` and `++x`.
+/// Registers only count as the same variable across a phi, the loop
+/// counters and accumulators this is for.
+pub fn restore_updates(function: &mut LiftedFunction) {
+    let variables = function.variables();
+    let phis: HashSet<ValueId> = function
+        .blocks
+        .iter()
+        .flat_map(|block| block.phis.iter().map(|phi| phi.value))
+        .collect();
+    let ones: HashSet<ValueId> = function
+        .definitions()
+        .into_iter()
+        .enumerate()
+        .filter(|(_, definition)| definition.is_some_and(is_one_literal))
+        .map(|(value, _)| value)
+        .collect();
+    let is_one = |expression: &Expression| match expression {
+        Expression::Value(value) => ones.contains(value),
+        expression => is_one_literal(expression),
+    };
+
+    for block in &mut function.blocks {
+        let statements = std::mem::take(&mut block.statements);
+        let mut statements = statements.into_iter().peekable();
+        while let Some(statement) = statements.next() {
+            let statement = match (statement, statements.peek()) {
+                (
+                    Statement::Assign { value, expression },
+                    Some(Statement::Expression(Expression::Assignment {
+                        operator: AssignmentOperator::Assignment,
+                        target,
+                        value: stored,
+                    })),
+                ) if is_target(target) => {
+                    match stored_update(value, &expression, target, stored, &is_one) {
+                        Some(update) => {
+                            statements.next();
+                            Statement::Assign {
+                                value,
+                                expression: update,
+                            }
+                        }
+                        None => Statement::Assign { value, expression },
+                    }
+                }
+                (
+                    Statement::Expression(Expression::Assignment {
+                        operator: AssignmentOperator::Assignment,
+                        target,
+                        value,
+                    }),
+                    _,
+                ) if is_target(&target) => Statement::Expression(
+                    update(*target.clone(), &value, false, &is_one).unwrap_or(
+                        Expression::Assignment {
+                            operator: AssignmentOperator::Assignment,
+                            target,
+                            value,
+                        },
+                    ),
+                ),
+                (Statement::Assign { value, expression }, _) => {
+                    let update = register_update(value, &expression, &variables, &phis, &is_one);
+                    Statement::Assign {
+                        value,
+                        expression: update.unwrap_or(expression),
+                    }
+                }
+                (statement, _) => statement,
+            };
+            block.statements.push(statement);
+        }
+    }
+}
+
+/// `value = expression` right before `target = stored`: `++target` when
+/// `value` is the new value, `target++` when it is the old one.
+fn stored_update(
+    value: ValueId,
+    expression: &Expression,
+    target: &Expression,
+    stored: &Expression,
+    is_one: &dyn Fn(&Expression) -> bool,
+) -> Option<Expression> {
+    match stored {
+        Expression::Value(stored) if *stored == value => {
+            update(target.clone(), expression, true, is_one)
+        }
+        Expression::Binary {
+            operator,
+            left,
+            right,
+        } if matches!(left.as_ref(), Expression::Value(left) if *left == value)
+            && is_one(right) =>
+        {
+            let operator = update_operator(*operator)?;
+            match expression {
+                Expression::Conversion {
+                    conversion: Conversion::ToNumber,
+                    argument,
+                } if same_target(argument, target) => Some(Expression::Update {
+                    operator,
+                    prefix: false,
+                    argument: Box::new(target.clone()),
+                }),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The update or compound assignment of `target` to `value`.
+fn update(
+    target: Expression,
+    value: &Expression,
+    prefix: bool,
+    is_one: &dyn Fn(&Expression) -> bool,
+) -> Option<Expression> {
+    let (operator, left, right) = match value {
+        Expression::Binary {
+            operator,
+            left,
+            right,
+        } => (*operator, left.as_ref(), right.as_ref()),
+        _ => return None,
+    };
+
+    let converted = match left {
+        Expression::Conversion {
+            conversion: Conversion::ToNumber,
+            argument,
+        } if same_target(argument, &target) => true,
+        left if same_target(left, &target) => false,
+        _ => return None,
+    };
+    let is_update = is_one(right)
+        && (operator == BinaryOperator::Subtraction
+            || (operator == BinaryOperator::Addition && converted));
+    if is_update {
+        return Some(Expression::Update {
+            operator: update_operator(operator)?,
+            prefix,
+            argument: Box::new(target),
+        });
+    }
+    if converted {
+        return None;
+    }
+    Some(Expression::Assignment {
+        operator: AssignmentOperator::compound(operator)?,
+        target: Box::new(target),
+        value: Box::new(right.clone()),
+    })
+}
+
+/// `new = old op y` where both are the same variable, `old` merged at a phi.
+fn register_update(
+    value: ValueId,
+    expression: &Expression,
+    variables: &Variables,
+    phis: &HashSet<ValueId>,
+    is_one: &dyn Fn(&Expression) -> bool,
+) -> Option<Expression> {
+    let left = match expression {
+        Expression::Binary { left, .. } => match left.as_ref() {
+            Expression::Conversion {
+                conversion: Conversion::ToNumber,
+                argument,
+            } => argument.as_ref(),
+            left => left,
+        },
+        _ => return None,
+    };
+    match left {
+        Expression::Value(old)
+            if phis.contains(old) && variables.of(*old) == variables.of(value) =>
+        {
+            update(Expression::Value(*old), expression, true, is_one)
+        }
+        _ => None,
+    }
+}
+
+fn update_operator(operator: BinaryOperator) -> Option<UpdateOperator> {
+    match operator {
+        BinaryOperator::Addition => Some(UpdateOperator::Increment),
+        BinaryOperator::Subtraction => Some(UpdateOperator::Decrement),
+        _ => None,
+    }
+}
+
+fn is_one_literal(expression: &Expression) -> bool {
+    match expression {
+        Expression::Literal(Literal::Number(Number::UInt(number))) => *number == 1,
+        Expression::Literal(Literal::Number(Number::Int(number))) => *number == 1,
+        Expression::Literal(Literal::Number(Number::Double(number))) => *number == 1.0,
+        _ => false,
+    }
+}
+
+/// Whether writing `expression` only evaluates something already computed,
+/// so writing it once where it was read and written is the same.
+fn is_target(expression: &Expression) -> bool {
+    match expression {
+        Expression::Member { object, property } => {
+            is_simple(object)
+                && match property {
+                    MemberProperty::Computed(key) => is_simple(key),
+                    _ => true,
+                }
+        }
+        Expression::EnvironmentSlot { environment, .. } => is_simple(environment),
+        Expression::Captured(_) => true,
+        _ => false,
+    }
+}
+
+fn is_simple(expression: &Expression) -> bool {
+    matches!(
+        expression,
+        Expression::Value(_)
+            | Expression::Literal(_)
+            | Expression::Parameter(_)
+            | Expression::This
+            | Expression::Global
+            | Expression::Scope(_)
+    )
+}
+
+fn same_target(first: &Expression, second: &Expression) -> bool {
+    match (first, second) {
+        (
+            Expression::Member {
+                object: first_object,
+                property: first_property,
+            },
+            Expression::Member {
+                object: second_object,
+                property: second_property,
+            },
+        ) => {
+            same_simple(first_object, second_object)
+                && match (first_property, second_property) {
+                    (MemberProperty::Named(first), MemberProperty::Named(second)) => {
+                        first.index() == second.index()
+                    }
+                    (MemberProperty::Index(first), MemberProperty::Index(second)) => {
+                        first == second
+                    }
+                    (MemberProperty::Computed(first), MemberProperty::Computed(second)) => {
+                        same_simple(first, second)
+                    }
+                    _ => false,
+                }
+        }
+        (
+            Expression::EnvironmentSlot {
+                environment: first_environment,
+                slot: first_slot,
+            },
+            Expression::EnvironmentSlot {
+                environment: second_environment,
+                slot: second_slot,
+            },
+        ) => first_slot == second_slot && same_simple(first_environment, second_environment),
+        (Expression::Captured(first), Expression::Captured(second)) => first == second,
+        (Expression::Value(first), Expression::Value(second)) => first == second,
+        _ => false,
+    }
+}
+
+fn same_simple(first: &Expression, second: &Expression) -> bool {
+    match (first, second) {
+        (Expression::Value(first), Expression::Value(second)) => first == second,
+        (Expression::Parameter(first), Expression::Parameter(second)) => first == second,
+        (Expression::Scope(first), Expression::Scope(second)) => first == second,
+        (Expression::This, Expression::This) | (Expression::Global, Expression::Global) => true,
+        (
+            Expression::Literal(Literal::String(first)),
+            Expression::Literal(Literal::String(second)),
+        ) => first.index() == second.index(),
+        (
+            Expression::Literal(Literal::Number(Number::UInt(first))),
+            Expression::Literal(Literal::Number(Number::UInt(second))),
+        ) => first == second,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing;
+
+    /// `y = o.x++` written out, with `between` evaluated after the read.
+    fn increment(between: &str) -> String {
+        let source = format!(
+            r#"
+                GetGlobalObject r0
+                GetById r1, r0, 1, "o"
+                GetById r2, r1, 2, "x"
+                ToNumber r3, r2
+                {}
+                LoadConstUInt8 r5, 1
+                Add r4, r3, r5
+                PutById r1, r4, 3, "x"
+                GetGlobalObject r0
+                PutById r0, r3, 4, "y"
+                LoadConstUndefined r6
+                Ret r6
+                "#,
+            between
+        );
+        testing::decompile(&[testing::function(source)])
+    }
+
+    #[test]
+    fn postfix_increment_reads_the_member_once() {
+        let text = increment("");
+        assert_eq!(text, "y = o.x++;\n");
+    }
+
+    #[test]
+    fn read_before_a_call_is_not_an_increment() {
+        let text = increment(
+            r#"
+            GetGlobalObject r0
+            GetById r7, r0, 5, "f"
+            LoadConstUndefined r8
+            Call1 r9, r7, r8
+            "#,
+        );
+        assert_eq!(
+            text,
+            "let o2, y2;\no2 = o;\ny2 = +o2.x;\nf();\no2.x = y2 + 1;\ny = y2;\n"
+        );
+    }
+
+    #[test]
+    fn prefix_increment_of_a_member() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            GetGlobalObject r0
+            GetById r1, r0, 1, "o"
+            GetById r2, r1, 2, "x"
+            ToNumber r3, r2
+            LoadConstUInt8 r5, 1
+            Add r4, r3, r5
+            PutById r1, r4, 3, "x"
+            GetGlobalObject r0
+            PutById r0, r4, 4, "y"
+            LoadConstUndefined r6
+            Ret r6
+            "#,
+        )]);
+        assert_eq!(text, "y = ++o.x;\n");
+    }
+
+    #[test]
+    fn compound_assignment_without_conversion() {
+        let text = testing::decompile(&[testing::function(
+            r#"
+            GetGlobalObject r0
+            GetById r1, r0, 1, "o"
+            GetById r2, r1, 2, "x"
+            LoadConstUInt8 r5, 2
+            Sub r4, r2, r5
+            PutById r1, r4, 3, "x"
+            LoadConstUndefined r6
+            Ret r6
+            "#,
+        )]);
+        assert_eq!(text, "o.x -= 2;\n");
+    }
+}
@@ -0,0 +1,406 @@
+//! Builds bytecode files from assembly text, for tests.
+//!
+//! A function is written one instruction per line, as `Opcode operand, ...`,
+//! with `label:` lines in between. Registers are written `r3`, jump targets
+//! `@label`, and strings `"text"`, which are added to the string table.
+
+use std::convert::TryFrom;
+
+use crate::{
+    analysis::{ControlFlowGraph, Dominators, SsaForm},
+    ast::LiftedFunction,
+    codegen::{Decompiled, Printer},
+    opcodes::Opcode,
+    parsers::program_parser,
+    passes::restore_program,
+};
+
+/// The layout of an operand after the opcode byte.
+#[derive(Clone, Copy)]
+enum Operand {
+    U8,
+    U16,
+    U32,
+    I32,
+    F64,
+    Jump8,
+    Jump32,
+}
+
+fn operands(opcode: &str) -> Vec<Operand> {
+    use Operand::*;
+    let r = U8;
+    match opcode {
+        "NewObjectWithBuffer" => vec![r, U16, U16, U16, U16],
+        "NewObjectWithBufferLong" => vec![r, U16, U16, U32, U32],
+        "NewObject"
+        | "GetGlobalObject"
+        | "GetNewTarget"
+        | "CreateEnvironment"
+        | "Ret"
+        | "Catch"
+        | "Throw"
+        | "ThrowIfUndefindedInst"
+        | "LoadConstUndefined"
+        | "LoadConstNull"
+        | "LoadConstTrue"
+        | "LoadConstFalse"
+        | "LoadConstZero"
+        | "LoadThisNS" => vec![r],
+        "NewObjectWithParent"
+        | "Mov"
+        | "DirectEval"
+        | "CoerceThisNS"
+        | "ToNumber"
+        | "ToInt32"
+        | "AddEmptyString"
+        | "GetArgumentsLength"
+        | "ResumeGenerator"
+        | "IteratorBegin"
+        | "Negate"
+        | "Not"
+        | "BitNot"
+        | "TypeOf" => vec![r, r],
+        "NewArrayWithBuffer" => vec![r, U16, U16, U16],
+        "NewArray" => vec![r, U16],
+        "GetEnvironment" | "LoadParam" | "LoadConstUInt8" | "IteratorClose" => vec![r, U8],
+        "StoreToEnvironment" | "StoreNPToEnvironment" | "PutOwnByIndex" => vec![r, U8, r],
+        "LoadFromEnvironment" => vec![r, r, U8],
+        "DeclareGlobalVar" => vec![U32],
+        "GetByIdShort" => vec![r, r, U8, U8],
+        "GetById" | "TryGetById" | "PutById" | "TryPutById" => vec![r, r, U8, U16],
+        "PutNewOwnByIdShort" => vec![r, r, U8],
+        "PutNewOwnById"
+        | "PutNewOwnNEById"
+        | "DelById"
+        | "CreateClosure"
+        | "CreateGeneratorClosure"
+        | "CreateGenerator" => {
+            vec![r, r, U16]
+        }
+        "PutOwnByVal" => vec![r, r, r, U8],
+        "GetByVal"
+        | "PutByVal"
+        | "DelByVal"
+        | "Call1"
+        | "CreateThis"
+        | "SelectObject"
+        | "GetArgumentsPropByVal"
+        | "IteratorNext" => vec![r, r, r],
+        "PutOwnGetterSetterByVal" => vec![r, r, r, r, U8],
+        "GetPNameList" | "Call2" => vec![r, r, r, r],
+        "GetNextPName" | "Call3" => vec![r, r, r, r, r],
+        "Call4" => vec![r, r, r, r, r, r],
+        "Call" | "Construct" => vec![r, r, U8],
+        "CallDirect" => vec![r, U8, U16],
+        "CallBuiltin" => vec![r, U8, U8],
+        "LoadConstInt" => vec![r, I32],
+        "LoadConstDouble" => vec![r, F64],
+        "LoadConstString" => vec![r, U16],
+        "CreateRegExp" => vec![r, U32, U32, U32],
+        "Jmp" | "SaveGenerator" => vec![Jump8],
+        "JmpLong" | "SaveGeneratorLong" => vec![Jump32],
+        "JmpTrue" | "JmpFalse" | "JmpUndefined" => vec![Jump8, r],
+        opcode if opcode.starts_with('J') && opcode.ends_with("Long") => vec![Jump32, r, r],
+        opcode if opcode.starts_with('J') => vec![Jump8, r, r],
+        "Eq" | "StrictEq" | "Neq" | "StrictNeq" | "Less" | "LessEq" | "Greater" | "GreaterEq"
+        | "Add" | "AddN" | "Mul" | "MulN" | "Div" | "DivN" | "Mod" | "Sub" | "SubN" | "LShift"
+        | "RShift" | "URShift" | "BitAnd" | "BitXor" | "BitOr" | "InstanceOf" | "IsIn" => {
+            vec![r, r, r]
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn opcode(name: &str) -> u8 {
+    (0..=u8::MAX)
+        .find(|&byte| Opcode::try_from(byte).is_ok_and(|op| format!("{:?}", op) == name))
+        .unwrap_or_else(|| panic!("unknown opcode {}", name))
+}
+
+fn size(operands: &[Operand]) -> usize {
+    1 + operands
+        .iter()
+        .map(|operand| match operand {
+            Operand::U8 | Operand::Jump8 => 1,
+            Operand::U16 => 2,
+            Operand::U32 | Operand::I32 | Operand::Jump32 => 4,
+            Operand::F64 => 8,
+        })
+        .sum::<usize>()
+}
+
+/// A function to assemble, with its header's fields.
+pub(crate) struct Function {
+    source: String,
+    name: &'static str,
+    params: u32,
+    environment: u32,
+    handlers: Vec<[&'static str; 3]>,
+}
+
+pub(crate) fn function(source: impl Into<String>) -> Function {
+    Function {
+        source: source.into(),
+        name: "",
+        params: 1,
+        environment: 0,
+        handlers: Vec::new(),
+    }
+}
+
+impl Function {
+    pub(crate) fn name(mut self, name: &'static str) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// The number of parameters, `this` included.
+    pub(crate) fn params(mut self, params: u32) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// A handler protecting `start..end`, which jumps to `target`.
+    pub(crate) fn handler(
+        mut self,
+        start: &'static str,
+        end: &'static str,
+        target: &'static str,
+    ) -> Self {
+        self.handlers.push([start, end, target]);
+        self
+    }
+}
+
+#[derive(Default)]
+struct Strings {
+    strings: Vec<String>,
+}
+
+impl Strings {
+    fn intern(&mut self, text: &str) -> u32 {
+        let index = match self.strings.iter().position(|string| string == text) {
+            Some(index) => index,
+            None => {
+                self.strings.push(String::from(text));
+                self.strings.len() - 1
+            }
+        };
+        index as u32
+    }
+}
+
+/// A function's bytecode, its handlers as offsets, and a frame just big
+/// enough for the registers it names.
+struct Assembled {
+    code: Vec<u8>,
+    handlers: Vec<[u32; 3]>,
+    frame: u32,
+}
+
+fn assemble(function: &Function, strings: &mut Strings) -> Assembled {
+    let mut labels = Vec::new();
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    for line in function.source.lines().map(str::trim) {
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(label) = line.strip_suffix(':') {
+            labels.push((label, offset));
+            continue;
+        }
+        let (name, arguments) = line.split_at(line.find(' ').unwrap_or(line.len()));
+        let arguments: Vec<&str> = arguments
+            .split(',')
+            .map(str::trim)
+            .filter(|argument| !argument.is_empty())
+            .collect();
+        let layout = operands(name);
+        assert_eq!(layout.len(), arguments.len(), "operands of {}", line);
+        instructions.push((offset, name, layout.clone(), arguments));
+        offset += size(&layout);
+    }
+    let label = |name: &str| {
+        labels
+            .iter()
+            .find(|(label, _)| *label == name)
+            .map(|(_, offset)| *offset as i64)
+            .unwrap_or_else(|| panic!("unknown label {}", name))
+    };
+
+    let mut code = Vec::new();
+    let mut frame = 1;
+    for (offset, name, layout, arguments) in instructions {
+        code.push(opcode(name));
+        for (operand, argument) in layout.into_iter().zip(arguments) {
+            let number: f64 = if let Some(target) = argument.strip_prefix('@') {
+                (label(target) - offset as i64) as f64
+            } else if let Some(register) = argument.strip_prefix('r') {
+                let register: u32 = register.parse().expect("register");
+                frame = frame.max(register + 1);
+                f64::from(register)
+            } else if argument.starts_with('"') {
+                strings.intern(&argument[1..argument.len() - 1]) as f64
+            } else {
+                argument.parse().expect("number")
+            };
+            match operand {
+                Operand::U8 => code.push(number as u8),
+                Operand::Jump8 => code.push(number as i8 as u8),
+                Operand::U16 => code.extend_from_slice(&(number as u16).to_le_bytes()),
+                Operand::U32 => code.extend_from_slice(&(number as u32).to_le_bytes()),
+                Operand::I32 | Operand::Jump32 => {
+                    code.extend_from_slice(&(number as i32).to_le_bytes())
+                }
+                Operand::F64 => code.extend_from_slice(&number.to_le_bytes()),
+            }
+        }
+    }
+    let handlers = function
+        .handlers
+        .iter()
+        .map(|handler| {
+            let [start, end, target] = handler;
+            [label(start) as u32, label(end) as u32, label(target) as u32]
+        })
+        .collect();
+    Assembled {
+        code,
+        handlers,
+        frame,
+    }
+}
+
+fn align(bytes: &mut Vec<u8>) {
+    while !bytes.len().is_multiple_of(4) {
+        bytes.push(0);
+    }
+}
+
+fn words(bytes: &mut Vec<u8>, words: &[u32]) {
+    for word in words {
+        bytes.extend_from_slice(&word.to_le_bytes());
+    }
+}
+
+/// A bytecode file holding `functions`, the first being the global one.
+pub(crate) fn build(functions: &[Function]) -> Vec<u8> {
+    const HEADER: usize = 128;
+    let mut strings = Strings::default();
+    let assembled: Vec<_> = functions
+        .iter()
+        .map(|function| {
+            let name = strings.intern(function.name);
+            (name, assemble(function, &mut strings))
+        })
+        .collect();
+
+    let mut storage = Vec::new();
+    let mut table = Vec::new();
+    for string in &strings.strings {
+        table.push(((storage.len() as u32) << 1) | ((string.len() as u32) << 24));
+        storage.extend_from_slice(string.as_bytes());
+    }
+    let kinds: Vec<u32> = match strings.strings.len() {
+        0 => Vec::new(),
+        count => vec![count as u32],
+    };
+
+    let mut sections = vec![0; 16 * functions.len()];
+    words(&mut sections, &kinds);
+    words(&mut sections, &table);
+    sections.extend_from_slice(&storage);
+    align(&mut sections);
+
+    let code_start = HEADER + sections.len();
+    let mut code = Vec::new();
+    let mut headers = Vec::new();
+    for (function, (name, assembled)) in functions.iter().zip(&assembled) {
+        let Assembled {
+            code: bytecode,
+            handlers,
+            frame,
+        } = assembled;
+        let offset = (code_start + code.len()) as u32;
+        code.extend_from_slice(bytecode);
+        align(&mut code);
+        let (mut info, mut flags) = (0, 0);
+        if !handlers.is_empty() {
+            info = (code_start + code.len()) as u32;
+            flags |= 8;
+            words(&mut code, &[handlers.len() as u32]);
+            for handler in handlers {
+                words(&mut code, handler);
+            }
+        }
+        headers.extend_from_slice(&[
+            offset | (function.params << 25),
+            bytecode.len() as u32 | (name << 15),
+            info | (frame << 25),
+            function.environment | (flags << 24),
+        ]);
+    }
+    let mut header_bytes = Vec::new();
+    words(&mut header_bytes, &headers);
+    sections[..header_bytes.len()].copy_from_slice(&header_bytes);
+
+    let length = HEADER + sections.len() + code.len();
+    let mut file = Vec::with_capacity(length);
+    file.extend_from_slice(&0x1F19_03C1_03BC_1FC6u64.to_le_bytes());
+    words(&mut file, &[74]);
+    file.extend_from_slice(&[0; 20]);
+    words(
+        &mut file,
+        &[
+            length as u32,
+            0,
+            functions.len() as u32,
+            kinds.len() as u32,
+            0,
+            strings.strings.len() as u32,
+            0,
+            storage.len() as u32,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ],
+    );
+    file.extend_from_slice(&[0; 32]);
+    file.extend_from_slice(&sections);
+    file.extend_from_slice(&code);
+    file
+}
+
+/// `functions` decompiled to JavaScript.
+pub(crate) fn decompile(functions: &[Function]) -> String {
+    let bytes = build(functions);
+    let program = program_parser(&bytes).expect("assembled file parses");
+    let restored = restore_program(&program, false);
+    let decompiled = Decompiled {
+        functions: &restored.functions,
+        bytecode: &program.bytecode,
+        names: &restored.names,
+        scopes: &restored.scopes,
+        nesting: &restored.nesting,
+        modules: &restored.modules,
+    };
+    Printer::new(&decompiled).program(0)
+}
+
+/// A single function lifted to SSA values, before any pass.
+pub(crate) fn lift(function: Function) -> LiftedFunction {
+    let bytes = build(&[function]);
+    let program = program_parser(&bytes).expect("assembled file parses");
+    let function = &program.functions[0];
+    let cfg = ControlFlowGraph::new(function);
+    let dominators = Dominators::new(&cfg);
+    let ssa = SsaForm::new(function, &cfg, &dominators);
+    LiftedFunction::new(function, &cfg, &ssa)
+}